@@ -0,0 +1,52 @@
+/*
+ * C interface of the enigma library.
+ *
+ * Build the library with the `ffi` feature to get the shared library:
+ *
+ *     cargo build --release --features ffi
+ *
+ * See src/ffi.rs for the documentation of the functions.
+ */
+
+#ifndef ENIGMA_H
+#define ENIGMA_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An Enigma machine. */
+typedef struct Enigma Enigma;
+
+/* Create a new machine from a configuration in JSON format, or return NULL on failure. */
+Enigma *enigma_new_from_json(const char *json);
+
+/* Create a copy of a machine in its current state. */
+Enigma *enigma_clone(const Enigma *machine);
+
+/* Release a machine. NULL is ignored. */
+void enigma_free(Enigma *machine);
+
+/* Map the ASCII letters of a buffer in place, and return the number of letters mapped. */
+ptrdiff_t enigma_map_buffer(Enigma *machine, uint8_t *buf, size_t len);
+
+/* Map an ASCII character. Characters that are not letters are returned unchanged. */
+char enigma_map_char(Enigma *machine, char ch);
+
+/* Write the offsets of the 3 rotators to positions[0..3]. Returns 0 on success. */
+int enigma_get_positions(const Enigma *machine, uint8_t *positions);
+
+/* Set the offsets of the 3 rotators from positions[0..3]. Returns 0 on success. */
+int enigma_set_positions(Enigma *machine, const uint8_t *positions);
+
+/* Get the reason of the last failure on the calling thread, or NULL. */
+const char *enigma_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ENIGMA_H */
@@ -0,0 +1,439 @@
+//! This module implements Banburismus, the sequential Bayesian procedure used at Bletchley Park to
+//! reduce the number of rotor orders and positions to be tried on the naval Enigma.
+//!
+//! # Principle
+//!
+//! The messages of a day are enciphered with message keys that are sent as trigram indicators.
+//! The indicator letters are not the rotator positions themselves, but each indicator letter
+//! stands for a fixed rotator position throughout the day. If two messages have indicators that
+//! agree in the first two letters, they were enciphered with the same left and middle rotator
+//! positions, and their right rotator positions differ by some unknown distance `d`. When the two
+//! ciphertexts are written one below the other with an offset of `d` letters, they are enciphered
+//! by exactly the same machine states along the overlap. Therefore letters repeat along the
+//! overlap as often as in two plaintexts, e.g. with probability of about `1/17` for German naval
+//! texts, instead of `1/26` for random texts.
+//!
+//! Every repeat within the overlap is evidence for the offset, and every non-repeat is evidence
+//! against it. The weight of the evidence is measured in decibans, e.g. tenths of base-10
+//! logarithms of the likelihood ratio. Evidence from different message pairs about the same
+//! hypothesis is added up, which is Bayes' rule applied sequentially.
+//!
+//! The `rank_right` function ranks the hypotheses about the distances between the right rotator
+//! positions of the indicator letters. The best hypotheses are chained together by the
+//! `right_positions` function, which assigns relative right rotator positions to the indicator
+//! letters. With the right rotator positions known, the `rank_middle` function ranks the
+//! hypotheses about the middle rotator in the same way.
+//!
+//! ```
+//! # use enigma::Rune;
+//! # use enigma::analysis::banburismus;
+//! #
+//! let runes = |s: &str| -> Vec<Rune> {
+//!     s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+//! };
+//! let overlap = banburismus::overlap(&runes("xxabcdefgh"), &runes("abcdwxyz"), 2);
+//! assert_eq!(overlap.length, 8);
+//! assert_eq!(overlap.repeats, 4);
+//! assert!(overlap.decibans(banburismus::DEFAULT_PLAIN_KAPPA) > 0.0);
+//! ```
+//!
+
+use std::collections::HashMap;
+
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// The probability that two letters at the same position of two German naval plaintexts are the
+/// same, as used at Bletchley Park.
+pub const DEFAULT_PLAIN_KAPPA: f64 = 1.0 / 17.0;
+
+/// A message intercepted with its trigram indicator.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Message {
+    /// The indicator letters of the left, middle and right rotators.
+    pub indicator: [Rune; 3],
+
+    /// The ciphertext.
+    pub ciphertext: Vec<Rune>,
+}
+
+impl Message {
+    /// Create a new `Message` object.
+    pub fn new(indicator: [Rune; 3], ciphertext: Vec<Rune>) -> Self {
+        Self { indicator, ciphertext }
+    }
+}
+
+/// The overlap of two ciphertexts written one below the other.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Overlap {
+    /// The number of letter pairs within the overlap.
+    pub length: usize,
+
+    /// The number of letter pairs within the overlap that are the same letter.
+    pub repeats: usize,
+}
+
+impl Overlap {
+    /// Get the weight of evidence, in decibans, that the two ciphertexts are enciphered by the
+    /// same machine states along the overlap. `plain_kappa` is the probability that two letters at
+    /// the same position of two plaintexts are the same.
+    pub fn decibans(&self, plain_kappa: f64) -> f64 {
+        let random_kappa = 1.0 / RUNE_SET_SIZE as f64;
+        let hit = 10.0 * (plain_kappa / random_kappa).log10();
+        let miss = 10.0 * ((1.0 - plain_kappa) / (1.0 - random_kappa)).log10();
+        self.repeats as f64 * hit + (self.length - self.repeats) as f64 * miss
+    }
+}
+
+/// Get the overlap of two ciphertexts when the `j`-th letter of `second` is written below the
+/// `j + offset`-th letter of `first`.
+pub fn overlap(first: &[Rune], second: &[Rune], offset: usize) -> Overlap {
+    let first = first.get(offset..).unwrap_or(&[]);
+    let length = first.len().min(second.len());
+    let repeats = first.iter().zip(second).filter(|(a, b)| a == b).count();
+    Overlap { length, repeats }
+}
+
+/// A hypothesis about the distance between the rotator positions of two indicator letters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hypothesis {
+    /// The two indicator letters, ordered.
+    pub letters: (Rune, Rune),
+
+    /// The number of steps from the position of the first letter to the position of the second
+    /// letter.
+    pub distance: u8,
+
+    /// The total weight of evidence for the hypothesis, in decibans.
+    pub decibans: f64,
+
+    /// The number of message pairs contributing to the evidence.
+    pub pairs: usize,
+}
+
+impl Hypothesis {
+    /// Get the odds in favour of the hypothesis, relative to the prior odds.
+    pub fn odds(&self) -> f64 {
+        10f64.powf(self.decibans / 10.0)
+    }
+}
+
+/// Rank the hypotheses about the right rotator positions of the indicator letters, the best
+/// first.
+///
+/// Only the pairs of messages whose indicators agree in the first two letters and differ in the
+/// third letter contribute to the evidence.
+pub fn rank_right(messages: &[Message], plain_kappa: f64) -> Vec<Hypothesis> {
+    let n = RUNE_SET_SIZE as isize;
+    let mut evidence = Evidence::new();
+
+    for (i, first) in messages.iter().enumerate() {
+        for second in &messages[i + 1..] {
+            if first.indicator[..2] != second.indicator[..2] ||
+                first.indicator[2] == second.indicator[2] {
+                continue;
+            }
+
+            let (first, second) = ordered(first, second, 2);
+            for distance in 1..n {
+                // The right rotator either reaches the second position without passing its
+                // turnover, or it passes its turnover and the second message starts earlier.
+                let offsets = [distance, distance - n];
+                let letters = (first.indicator[2], second.indicator[2]);
+                evidence.add(letters, distance as u8, first, second, &offsets, plain_kappa);
+            }
+        }
+    }
+
+    evidence.into_ranking()
+}
+
+/// Chain the hypotheses with at least the specified weight of evidence together, and return the
+/// relative rotator positions of the indicator letters indexed by rune values.
+///
+/// The hypotheses are taken in the given order. A hypothesis that contradicts the positions
+/// already assigned is skipped. The first assigned letter gets the position `0`.
+pub fn right_positions(hypotheses: &[Hypothesis], min_decibans: f64)
+    -> [Option<u8>; RUNE_SET_SIZE as usize] {
+    let mut positions = [None; RUNE_SET_SIZE as usize];
+    let accepted: Vec<&Hypothesis> = hypotheses.iter()
+        .filter(|h| h.decibans >= min_decibans)
+        .collect();
+
+    let mut used = vec![false; accepted.len()];
+    loop {
+        let mut changed = false;
+        for (hypothesis, used) in accepted.iter().zip(used.iter_mut()) {
+            if *used {
+                continue;
+            }
+
+            let a = hypothesis.letters.0.value() as usize;
+            let b = hypothesis.letters.1.value() as usize;
+            let d = hypothesis.distance;
+            match (positions[a], positions[b]) {
+                (Some(_), Some(_)) => (),
+                (Some(pa), None) => positions[b] = Some((pa + d) % RUNE_SET_SIZE),
+                (None, Some(pb)) => positions[a] = Some((pb + RUNE_SET_SIZE - d) % RUNE_SET_SIZE),
+                (None, None) => {
+                    if positions.iter().any(Option::is_some) {
+                        continue;
+                    }
+                    positions[a] = Some(0);
+                    positions[b] = Some(d);
+                },
+            }
+
+            *used = true;
+            changed = true;
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    positions
+}
+
+/// Rank the hypotheses about the middle rotator positions of the indicator letters, the best
+/// first.
+///
+/// `right_positions` gives the right rotator positions of the indicator letters, counted from the
+/// turnover of the right rotator. Only the pairs of messages whose indicators agree in the first
+/// letter, differ in the second letter, and whose third letters have known right rotator
+/// positions contribute to the evidence.
+pub fn rank_middle(messages: &[Message], right_positions: &[Option<u8>; RUNE_SET_SIZE as usize],
+    plain_kappa: f64) -> Vec<Hypothesis> {
+    let n = RUNE_SET_SIZE as isize;
+    let mut evidence = Evidence::new();
+
+    for (i, first) in messages.iter().enumerate() {
+        for second in &messages[i + 1..] {
+            if first.indicator[0] != second.indicator[0] ||
+                first.indicator[1] == second.indicator[1] {
+                continue;
+            }
+
+            let (first, second) = ordered(first, second, 1);
+            let (right_first, right_second) = match (
+                right_positions[first.indicator[2].value() as usize],
+                right_positions[second.indicator[2].value() as usize]) {
+                (Some(a), Some(b)) => (a as isize, b as isize),
+                _ => continue,
+            };
+
+            for distance in 1..n {
+                let offset = distance * n + right_second - right_first;
+                let offsets = [offset, offset - n * n];
+                let letters = (first.indicator[1], second.indicator[1]);
+                evidence.add(letters, distance as u8, first, second, &offsets, plain_kappa);
+            }
+        }
+    }
+
+    evidence.into_ranking()
+}
+
+/// Order the two messages such that the indicator letter at the specified index of the first
+/// message is less than that of the second message.
+fn ordered<'a>(first: &'a Message, second: &'a Message, index: usize)
+    -> (&'a Message, &'a Message) {
+    if first.indicator[index] < second.indicator[index] {
+        (first, second)
+    } else {
+        (second, first)
+    }
+}
+
+/// The accumulated evidence of hypotheses.
+struct Evidence {
+    hypotheses: HashMap<(u8, u8, u8), (f64, usize)>,
+}
+
+impl Evidence {
+    fn new() -> Self {
+        Self { hypotheses: HashMap::new() }
+    }
+
+    /// Add the evidence of a message pair to a hypothesis. The hypothesis allows any of the
+    /// specified offsets between the two messages with equal prior probabilities, where a negative
+    /// offset means that the second message starts before the first one.
+    fn add(&mut self, letters: (Rune, Rune), distance: u8, first: &Message, second: &Message,
+        offsets: &[isize], plain_kappa: f64) {
+        let overlaps: Vec<Overlap> = offsets.iter()
+            .map(|offset| {
+                if *offset >= 0 {
+                    overlap(&first.ciphertext, &second.ciphertext, *offset as usize)
+                } else {
+                    overlap(&second.ciphertext, &first.ciphertext, (-*offset) as usize)
+                }
+            })
+            .collect();
+        if overlaps.iter().all(|overlap| overlap.length == 0) {
+            return;
+        }
+
+        let mean_odds = overlaps.iter()
+            .map(|overlap| 10f64.powf(overlap.decibans(plain_kappa) / 10.0))
+            .sum::<f64>() / overlaps.len() as f64;
+
+        let key = (letters.0.value(), letters.1.value(), distance);
+        let entry = self.hypotheses.entry(key).or_insert((0.0, 0));
+        entry.0 += 10.0 * mean_odds.log10();
+        entry.1 += 1;
+    }
+
+    fn into_ranking(self) -> Vec<Hypothesis> {
+        let mut ranking: Vec<Hypothesis> = self.hypotheses.into_iter()
+            .map(|((a, b, distance), (decibans, pairs))| {
+                let letters = (Rune::from_value(a).unwrap(), Rune::from_value(b).unwrap());
+                Hypothesis { letters, distance, decibans, pairs }
+            })
+            .collect();
+        ranking.sort_by(|lhs, rhs| {
+            rhs.decibans.partial_cmp(&lhs.decibans).unwrap()
+                .then(lhs.letters.cmp(&rhs.letters))
+                .then(lhs.distance.cmp(&rhs.distance))
+        });
+        ranking
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Settings, SteppingMode};
+    use crate::analysis::fitness;
+    use crate::math::Permutation;
+
+    fn wiring(s: &str) -> Permutation {
+        Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+    }
+
+    fn runes(s: &str) -> Vec<Rune> {
+        s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+    }
+
+    /// Get the indicator letter standing for the specified rotator position.
+    fn code(position: u8) -> Rune {
+        Rune::from_value((position * 7 + 3) % RUNE_SET_SIZE).unwrap()
+    }
+
+    /// Encipher disjoint pieces of English text at the specified middle and right rotator
+    /// positions.
+    fn create_test_messages(positions: &[(u8, u8)]) -> Vec<Message> {
+        let corpus: String = include_str!("data/english.txt").chars()
+            .filter(char::is_ascii_alphabetic)
+            .map(|ch| ch.to_ascii_lowercase())
+            .collect();
+        let corpus = runes(&corpus);
+
+        positions.iter().enumerate()
+            .map(|(i, (middle, right))| {
+                let settings = Settings {
+                    rotator_wirings: [
+                        wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+                        wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+                        wiring("ekmflgdqvzntowyhxuspaibrcj"),
+                    ],
+                    positions: [*right, *middle, 0],
+                    rings: [0, 0, 0],
+                    notches: Default::default(),
+                    stepping: SteppingMode::Odometer,
+                    reflector: wiring("yruhqsldpxngokmiebfzcwvjat"),
+                    plug_pairs: Vec::new(),
+                };
+                let mut machine = settings.create_enigma().unwrap();
+                let plaintext = &corpus[i * 250..(i + 1) * 250];
+                let ciphertext = plaintext.iter().map(|r| machine.map_rune(*r)).collect();
+                Message::new([code(0), code(*middle), code(*right)], ciphertext)
+            })
+            .collect()
+    }
+
+    const TEST_POSITIONS: [(u8, u8); 9] =
+        [(0, 2), (0, 9), (0, 15), (3, 2), (3, 9), (3, 15), (6, 2), (6, 9), (6, 15)];
+
+    mod overlap_tests {
+        use super::*;
+
+        #[test]
+        fn test_overlap() {
+            let overlap = overlap(&runes("abcde"), &runes("cdxyz"), 2);
+            assert_eq!(overlap, Overlap { length: 3, repeats: 2 });
+        }
+
+        #[test]
+        fn test_overlap_out_of_range() {
+            let overlap = overlap(&runes("abc"), &runes("abc"), 5);
+            assert_eq!(overlap, Overlap { length: 0, repeats: 0 });
+        }
+
+        #[test]
+        fn test_decibans() {
+            let random = Overlap { length: 26, repeats: 1 };
+            assert!(random.decibans(DEFAULT_PLAIN_KAPPA) < 0.0);
+
+            let plain = Overlap { length: 17, repeats: 1 };
+            assert!(plain.decibans(DEFAULT_PLAIN_KAPPA).abs() < 0.5);
+
+            let hit = Overlap { length: 1, repeats: 1 };
+            assert!((hit.decibans(1.0 / 13.0) - 10.0 * 2f64.log10()).abs() < 1e-9);
+        }
+    }
+
+    mod rank_tests {
+        use super::*;
+
+        fn true_distance(hypothesis: &Hypothesis) -> u8 {
+            let position = |letter: Rune| (0..RUNE_SET_SIZE).find(|p| code(*p) == letter).unwrap();
+            let (a, b) = (position(hypothesis.letters.0), position(hypothesis.letters.1));
+            (b + RUNE_SET_SIZE - a) % RUNE_SET_SIZE
+        }
+
+        #[test]
+        fn test_rank_right() {
+            let messages = create_test_messages(&TEST_POSITIONS);
+            let hypotheses = rank_right(&messages, fitness::ENGLISH_IOC);
+            assert!(!hypotheses.is_empty());
+            assert!(hypotheses.iter().all(|h| h.pairs == 3));
+
+            let best = &hypotheses[0];
+            assert_eq!(best.distance, true_distance(best));
+            assert!(best.odds() > 1.0);
+        }
+
+        #[test]
+        fn test_right_positions() {
+            let hypotheses = vec![
+                Hypothesis { letters: (code(2), code(9)), distance: 7, decibans: 20.0, pairs: 1 },
+                Hypothesis { letters: (code(9), code(15)), distance: 6, decibans: 15.0, pairs: 1 },
+                Hypothesis { letters: (code(2), code(15)), distance: 1, decibans: 10.0, pairs: 1 },
+                Hypothesis { letters: (code(1), code(9)), distance: 8, decibans: -5.0, pairs: 1 },
+            ];
+            let positions = right_positions(&hypotheses, 0.0);
+
+            assert_eq!(positions[code(2).value() as usize], Some(0));
+            assert_eq!(positions[code(9).value() as usize], Some(7));
+            assert_eq!(positions[code(15).value() as usize], Some(13));
+            assert_eq!(positions[code(1).value() as usize], None);
+            assert_eq!(positions.iter().filter(|p| p.is_some()).count(), 3);
+        }
+
+        #[test]
+        fn test_rank_middle() {
+            let messages = create_test_messages(&TEST_POSITIONS);
+            let mut positions = [None; RUNE_SET_SIZE as usize];
+            for (_, right) in &TEST_POSITIONS {
+                positions[code(*right).value() as usize] = Some(*right);
+            }
+
+            let hypotheses = rank_middle(&messages, &positions, fitness::ENGLISH_IOC);
+            let best = &hypotheses[0];
+            assert_eq!(best.letters, (code(0), code(3)));
+            assert_eq!(best.distance, 3);
+            assert_eq!(best.pairs, 9);
+        }
+    }
+}
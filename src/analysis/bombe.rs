@@ -0,0 +1,352 @@
+//! This module implements a simulation of the Turing-Welchman Bombe.
+//!
+//! The Bombe searches for the rotator positions at which a crib, e.g. a guessed piece of
+//! plaintext, could have been enciphered into the corresponding ciphertext. The plug board is
+//! unknown during the search.
+//!
+//! # Principle
+//!
+//! Let `S` be the unknown plug board permutation and `E_i` be the permutation realized by the
+//! rotators and the reflector at the `i`-th letter of the crib. If the `i`-th crib letter `p` is
+//! enciphered to `c`, then `S(c) = E_i(S(p))`. Therefore the hypothesis "`p` is steckered to
+//! `x`" implies "`c` is steckered to `E_i(x)`".
+//!
+//! The Bombe models each hypothesis "`a` is steckered to `b`" as a wire. The crib letters connect
+//! the wires as described above, and the diagonal board connects the wire `(a, b)` to the wire
+//! `(b, a)` since steckering is symmetric. At each rotator position, a test letter `T` from the
+//! crib is chosen. All wires connected to the wire `(T, x)` are implied by the hypothesis "`T` is
+//! steckered to `x`". If the connected wires include another wire `(T, y)`, the hypothesis is
+//! contradictory. Otherwise the Bombe stops, and the connected wires are the stecker pairs deduced
+//! from the hypothesis.
+//!
+//! The crib is given to the Bombe as a [`Menu`], which is built by the `menu::build` function.
+//!
+//! A stop is not necessarily the right setting. Stops should be further examined, e.g. by checking
+//! whether the deduced stecker pairs are consistent with each other.
+//!
+//! [`Menu`]: ../menu/struct.Menu.html
+//!
+
+use crate::{Enigma, PlugBoard, Reflector, RotatorGroup};
+use crate::analysis::menu::Menu;
+use crate::analysis::progress::Progress;
+use crate::analysis::states::{self, NUM_STATES};
+use crate::math::Permutation;
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+const NUM_WIRES: usize = (RUNE_SET_SIZE as usize) * (RUNE_SET_SIZE as usize);
+
+/// A stop of the Bombe, e.g. a rotator position at which the crib is not contradictory.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Stop {
+    offsets: [u8; 3],
+    test_letter: Rune,
+    steckers: Vec<(Rune, Rune)>,
+}
+
+impl Stop {
+    /// Get the offsets of the 3 rotators when the first letter of the crib is enciphered.
+    pub fn offsets(&self) -> [u8; 3] {
+        self.offsets
+    }
+
+    /// Get the test letter used at this stop.
+    pub fn test_letter(&self) -> Rune {
+        self.test_letter
+    }
+
+    /// Get the stecker pairs deduced at this stop, including the hypothesis of the test letter.
+    /// Each pair is ordered and the pairs are sorted. A pair consisting of the same two letters
+    /// means that the letter is not steckered.
+    pub fn steckers(&self) -> &[(Rune, Rune)] {
+        &self.steckers
+    }
+
+    /// Get the letter that the test letter is steckered to at this stop.
+    pub fn test_stecker(&self) -> Rune {
+        self.steckers.iter()
+            .find_map(|(a, b)| {
+                if *a == self.test_letter {
+                    Some(*b)
+                } else if *b == self.test_letter {
+                    Some(*a)
+                } else {
+                    None
+                }
+            })
+            .unwrap()
+    }
+
+    /// Determine whether the deduced stecker pairs are consistent, e.g. no letter is steckered to
+    /// two different letters.
+    pub fn is_consistent(&self) -> bool {
+        let mut seen = [false; RUNE_SET_SIZE as usize];
+        for (a, b) in &self.steckers {
+            if seen[a.value() as usize] || seen[b.value() as usize] {
+                return false;
+            }
+            seen[a.value() as usize] = true;
+            seen[b.value() as usize] = true;
+        }
+        true
+    }
+}
+
+/// A Bombe loaded with a rotator order and a reflector.
+#[derive(Clone, Debug)]
+pub struct Bombe {
+    /// The permutations realized by the rotators and the reflector at every state.
+    scramblers: Vec<Permutation>,
+
+    /// The index of the state following each state.
+    next: Vec<usize>,
+
+    /// The index of the state at which the search starts.
+    first: usize,
+}
+
+impl Bombe {
+    /// Create a new Bombe that tests every position of the specified rotators, starting from their
+    /// current offsets and following the odometer order of the offsets. The crib is enciphered
+    /// with the stepping mechanism of the rotator group, so the rotator orders of the Wehrmacht
+    /// Enigma are tested with their double stepping.
+    pub fn new(rotators: RotatorGroup, reflector: Reflector) -> Self {
+        let plug_board = PlugBoard::from_perm(Permutation::identity(RUNE_SET_SIZE)).unwrap();
+        let machine = Enigma::new(plug_board, rotators, reflector);
+
+        let (scramblers, next) = states::scan(&machine, 0..NUM_STATES).into_iter().unzip();
+        Self { scramblers, next, first: states::current(&machine) }
+    }
+
+    /// Run the Bombe wired up with the specified menu, and return all stops. The most connected
+    /// letter of the menu is used as the test letter.
+    pub fn run(&self, menu: &Menu) -> Vec<Stop> {
+        let test_letter = match menu.most_connected_letter() {
+            Some(letter) => letter,
+            None => return Vec::new(),
+        };
+
+        let mut progress = Progress::start("bombe");
+        let stops = (0..NUM_STATES)
+            .flat_map(|i| self.test_state((self.first + i) % NUM_STATES, menu, test_letter))
+            .collect();
+        progress.tested(NUM_STATES as u64);
+        progress.finish();
+        stops
+    }
+
+    fn test_state(&self, state: usize, menu: &Menu, test_letter: Rune) -> Vec<Stop> {
+        let mut wires = DisjointSet::new(NUM_WIRES);
+
+        // The diagonal board.
+        for a in 0..RUNE_SET_SIZE {
+            for b in a + 1..RUNE_SET_SIZE {
+                wires.union(wire(a, b), wire(b, a));
+            }
+        }
+
+        // The edges are ordered by their positions, so the states of the crib letters are found by
+        // stepping forward from the state of the previous edge.
+        let mut current = state;
+        let mut position = 0;
+        for edge in menu.edges() {
+            while position < edge.position {
+                current = self.next[current];
+                position += 1;
+            }
+
+            let scrambler = &self.scramblers[current];
+            for x in 0..RUNE_SET_SIZE {
+                wires.union(wire(edge.plain.value(), x),
+                    wire(edge.cipher.value(), scrambler.map(x)));
+            }
+        }
+
+        let t = test_letter.value();
+        let roots: Vec<usize> = (0..RUNE_SET_SIZE).map(|x| wires.find(wire(t, x))).collect();
+
+        let mut stops = Vec::new();
+        for (x, root) in roots.iter().enumerate() {
+            if roots.iter().filter(|r| *r == root).count() != 1 {
+                continue;
+            }
+
+            let mut steckers = Vec::new();
+            for a in 0..RUNE_SET_SIZE {
+                for b in a..RUNE_SET_SIZE {
+                    if wires.find(wire(a, b)) == *root {
+                        steckers.push((Rune::from_value(a).unwrap(), Rune::from_value(b).unwrap()));
+                    }
+                }
+            }
+
+            debug_assert!(steckers.iter().any(|(a, b)| {
+                (a.value(), b.value()) == (t, x as u8) || (a.value(), b.value()) == (x as u8, t)
+            }));
+
+            stops.push(Stop {
+                offsets: states::offsets(state),
+                test_letter,
+                steckers,
+            });
+        }
+
+        stops
+    }
+}
+
+fn wire(a: u8, b: u8) -> usize {
+    a as usize * RUNE_SET_SIZE as usize + b as usize
+}
+
+/// A disjoint-set forest with path compression.
+struct DisjointSet {
+    parents: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(n: usize) -> Self {
+        Self { parents: (0..n).collect() }
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parents[x] != x {
+            self.parents[x] = self.parents[self.parents[x]];
+            x = self.parents[x];
+        }
+        x
+    }
+
+    fn union(&mut self, x: usize, y: usize) {
+        let x = self.find(x);
+        let y = self.find(y);
+        if x != y {
+            self.parents[x] = y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Rotator;
+    use crate::analysis::menu;
+    use crate::components::catalog::HISTORICAL_ROTATORS;
+    use crate::components::stepping::SteppingMode;
+    use crate::math::PermutationBuilder;
+
+    fn create_rotator(wiring: &str, offset: u8) -> Rotator {
+        let perm = wiring.bytes().map(|b| b - b'a').collect();
+        Rotator::new(Permutation::from_perm(perm).unwrap(), offset).unwrap()
+    }
+
+    fn create_test_rotators(offsets: [u8; 3]) -> RotatorGroup {
+        RotatorGroup::new([
+            create_rotator("ekmflgdqvzntowyhxuspaibrcj", offsets[0]),
+            create_rotator("ajdksiruxblhwtmcqgznpyfvoe", offsets[1]),
+            create_rotator("bdfhjlcprtxvznyeiwgakmusqo", offsets[2]),
+        ])
+    }
+
+    fn create_test_reflector() -> Reflector {
+        Reflector::from_perm(PermutationBuilder::new(RUNE_SET_SIZE)
+            .swap(0, 24).swap(1, 17).swap(2, 20).swap(3, 7).swap(4, 16)
+            .swap(5, 18).swap(6, 11).swap(8, 15).swap(9, 23).swap(10, 13)
+            .swap(12, 14).swap(19, 25).swap(21, 22)
+            .build()).unwrap()
+    }
+
+    fn to_runes(s: &str) -> Vec<Rune> {
+        s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_disjoint_set() {
+        let mut set = DisjointSet::new(4);
+        set.union(0, 1);
+        set.union(2, 3);
+        assert_eq!(set.find(0), set.find(1));
+        assert_ne!(set.find(1), set.find(2));
+
+        set.union(1, 3);
+        assert_eq!(set.find(0), set.find(2));
+    }
+
+    #[test]
+    fn test_run_finds_setting() {
+        let plug_board = PlugBoard::from_perm(PermutationBuilder::new(RUNE_SET_SIZE)
+            .swap(0, 12).swap(3, 21).swap(7, 19).swap(9, 25).swap(14, 17)
+            .swap(2, 11).swap(4, 23).swap(6, 15)
+            .build()).unwrap();
+        let mut machine = Enigma::new(plug_board, create_test_rotators([5, 9, 2]),
+            create_test_reflector());
+
+        let crib = to_runes("wettervorhersagebiskaya");
+        let ciphertext: Vec<Rune> = crib.iter().map(|r| machine.map_rune(*r)).collect();
+
+        let menu = menu::build(&crib, &ciphertext).unwrap();
+        let bombe = Bombe::new(create_test_rotators([0, 0, 0]), create_test_reflector());
+        let stops = bombe.run(&menu);
+
+        let stop = stops.iter()
+            .find(|stop| stop.offsets() == [5, 9, 2])
+            .expect("the right setting should produce a stop");
+        assert!(stop.is_consistent());
+        assert_eq!(stop.test_letter(), 'e');
+        assert_eq!(stop.test_stecker(), 'x');
+        assert!(stop.steckers().contains(
+            &(Rune::from_char('a').unwrap(), Rune::from_char('m').unwrap())));
+
+        assert!(stops.iter().filter(|stop| stop.is_consistent()).count() < 20);
+    }
+
+    /// Create the rotators III, II and I at the specified offsets, stepped in the specified mode.
+    fn create_historical_rotators(offsets: [u8; 3], mode: SteppingMode) -> RotatorGroup {
+        let mut rotators = [2, 1, 0].map(|i| HISTORICAL_ROTATORS[i].1.clone());
+        for (r, offset) in rotators.iter_mut().zip(&offsets) {
+            r.advance_by(*offset as u64);
+        }
+        RotatorGroup::new(rotators).with_stepping_mode(mode)
+    }
+
+    /// Build the menu of a crib enciphered at the ground setting `[5, 4, 9]` of the rotators
+    /// created by `create_historical_rotators` with the Wehrmacht stepping. The ground setting can
+    /// only be set by hand, since the middle rotator at its notch double steps at the first letter
+    /// of the crib.
+    fn create_lever_menu() -> Menu {
+        let plug_board = PlugBoard::from_perm(PermutationBuilder::new(RUNE_SET_SIZE)
+            .swap(0, 12).swap(3, 21).swap(7, 19).swap(9, 25).swap(14, 17)
+            .swap(2, 11).swap(4, 23).swap(6, 15)
+            .build()).unwrap();
+        let rotators = create_historical_rotators([5, 4, 9], SteppingMode::WehrmachtLever);
+        let mut machine = Enigma::new(plug_board, rotators, create_test_reflector());
+
+        let crib = to_runes("wettervorhersagebiskaya");
+        let ciphertext: Vec<Rune> = crib.iter().map(|r| machine.map_rune(*r)).collect();
+        menu::build(&crib, &ciphertext).unwrap()
+    }
+
+    #[test]
+    fn test_run_lever() {
+        let rotators = create_historical_rotators([0, 0, 0], SteppingMode::WehrmachtLever);
+        let stops = Bombe::new(rotators, create_test_reflector()).run(&create_lever_menu());
+
+        let stop = stops.iter()
+            .find(|stop| stop.offsets() == [5, 4, 9])
+            .expect("the right setting should produce a stop");
+        assert!(stop.is_consistent());
+        assert!(stop.steckers().contains(
+            &(Rune::from_char('a').unwrap(), Rune::from_char('m').unwrap())));
+        assert!(stops.iter().all(|stop| stop.offsets() != [6, 4, 9]));
+    }
+
+    #[test]
+    fn test_run_rejects_false_stop() {
+        // The rotators stepped like an odometer do not encipher the crib like the Wehrmacht
+        // stepping does, so the Bombe rejects the setting.
+        let rotators = create_historical_rotators([0, 0, 0], SteppingMode::Odometer);
+        let stops = Bombe::new(rotators, create_test_reflector()).run(&create_lever_menu());
+        assert!(stops.iter().all(|stop| stop.offsets() != [5, 4, 9]));
+    }
+}
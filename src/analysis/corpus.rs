@@ -0,0 +1,227 @@
+//! This module builds n-gram statistics from user corpora, so that decryptions can be scored
+//! against languages other than English.
+//!
+//! The `build_ngrams` function counts the n-grams of a corpus read from any reader. German
+//! umlauts and the sharp s are transliterated the way Enigma operators wrote them, e.g. `ä`
+//! becomes `AE` and `ß` becomes `SS`. All other characters separate words, and n-grams never span
+//! them.
+//!
+//! Since counting a large corpus takes a while, tables can be saved in a compact binary format
+//! with the `save` function and loaded back with the `load` function:
+//!
+//! ```
+//! # use enigma::Rune;
+//! # use enigma::analysis::corpus;
+//! #
+//! let corpus = "Der Kommandant befiehlt: Angriff im Morgengrauen. Die Brücke ist zu sichern.";
+//! let table = corpus::build_ngrams(corpus.as_bytes(), 2).unwrap();
+//!
+//! let mut bytes = Vec::new();
+//! corpus::save(&table, &mut bytes).unwrap();
+//! let loaded = corpus::load(bytes.as_slice()).unwrap();
+//!
+//! let ngram = [Rune::from_char('e').unwrap(), Rune::from_char('r').unwrap()];
+//! assert_eq!(loaded.n(), 2);
+//! assert!((loaded.log_prob(&ngram) - table.log_prob(&ngram)).abs() < 1e-6);
+//! ```
+//!
+//! The binary format consists of the magic bytes `NGRM`, a format version byte, a byte holding
+//! the length `n` of the n-grams, and the log-probabilities of all `26^n` n-grams in index order,
+//! each stored as a little-endian 32-bit float.
+//!
+
+use std::io::{BufRead, Read, Write};
+
+use crate::analysis::fitness::{NgramTable, NgramTableError, MAX_NGRAM_LEN};
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// The magic bytes at the start of a binary n-gram table.
+const MAGIC: &[u8; 4] = b"NGRM";
+
+/// The version of the binary format written by `save`.
+const FORMAT_VERSION: u8 = 1;
+
+/// Count the n-grams of length `n` within the corpus read from the specified reader and create a
+/// table from the counts.
+pub fn build_ngrams<R: BufRead>(reader: R, n: usize) -> Result<NgramTable, NgramTableError> {
+    if n == 0 || n > MAX_NGRAM_LEN {
+        return Err(NgramTableError::InvalidLength);
+    }
+
+    let size = (RUNE_SET_SIZE as usize).pow(n as u32);
+    let mut counts = vec![0u64; size];
+    let mut index = 0usize;
+    let mut run = 0usize;
+    for line in reader.lines() {
+        let line = line?;
+        for ch in line.chars() {
+            let letters = transliterate(ch);
+            if letters.is_empty() {
+                run = 0;
+                continue;
+            }
+
+            for letter in letters.chars() {
+                let rune = Rune::from_char(letter).unwrap();
+                index = (index * RUNE_SET_SIZE as usize + rune.value() as usize) % size;
+                run += 1;
+                if run >= n {
+                    counts[index] += 1;
+                }
+            }
+        }
+        run = 0;
+    }
+
+    NgramTable::from_counts(n, &counts)
+}
+
+/// Get the English letters standing for the specified character, or an empty string if the
+/// character is a separator.
+fn transliterate(ch: char) -> &'static str {
+    match ch.to_ascii_lowercase() {
+        'ä' | 'Ä' => "ae",
+        'ö' | 'Ö' => "oe",
+        'ü' | 'Ü' => "ue",
+        'ß' => "ss",
+        ch if ch.is_ascii_lowercase() => {
+            const LETTERS: &str = "abcdefghijklmnopqrstuvwxyz";
+            let i = (ch as u8 - b'a') as usize;
+            &LETTERS[i..i + 1]
+        },
+        _ => "",
+    }
+}
+
+/// Save the specified table in the binary format.
+pub fn save<W: Write>(table: &NgramTable, mut writer: W) -> std::io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[FORMAT_VERSION, table.n() as u8])?;
+    for log_prob in table.log_probs() {
+        writer.write_all(&(*log_prob as f32).to_le_bytes())?;
+    }
+    writer.flush()
+}
+
+/// Load a table saved in the binary format.
+pub fn load<R: Read>(mut reader: R) -> Result<NgramTable, NgramTableError> {
+    let mut header = [0u8; 6];
+    read_exact(&mut reader, &mut header)?;
+    if &header[..4] != MAGIC || header[4] != FORMAT_VERSION {
+        return Err(NgramTableError::Format);
+    }
+
+    let n = header[5] as usize;
+    if n == 0 || n > MAX_NGRAM_LEN {
+        return Err(NgramTableError::InvalidLength);
+    }
+
+    let mut bytes = vec![0u8; (RUNE_SET_SIZE as usize).pow(n as u32) * 4];
+    read_exact(&mut reader, &mut bytes)?;
+    if reader.read(&mut [0u8])? != 0 {
+        return Err(NgramTableError::Format);
+    }
+
+    let log_probs = bytes.chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as f64)
+        .collect();
+    Ok(NgramTable::from_log_probs(n, log_probs))
+}
+
+/// Fill the specified buffer from the reader, reporting a truncated input as a format error.
+fn read_exact<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), NgramTableError> {
+    reader.read_exact(buf).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => NgramTableError::Format,
+        _ => NgramTableError::Io(e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::fitness;
+
+    fn to_runes(s: &str) -> Vec<Rune> {
+        s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_transliterate() {
+        assert_eq!(transliterate('a'), "a");
+        assert_eq!(transliterate('Z'), "z");
+        assert_eq!(transliterate('Ü'), "ue");
+        assert_eq!(transliterate('ß'), "ss");
+        assert_eq!(transliterate(' '), "");
+        assert_eq!(transliterate('é'), "");
+    }
+
+    mod build_ngrams_tests {
+        use super::*;
+
+        #[test]
+        fn test_build_ngrams() {
+            let table = build_ngrams("ab ab,\nba".as_bytes(), 2).unwrap();
+            let expected = fitness::NgramTable::from_text(2, "ab ab, ba").unwrap();
+            for ngram in &["ab", "ba", "bb"] {
+                assert_eq!(table.log_prob(&to_runes(ngram)), expected.log_prob(&to_runes(ngram)));
+            }
+        }
+
+        #[test]
+        fn test_build_ngrams_lines() {
+            let table = build_ngrams("a\nb".as_bytes(), 2);
+            assert!(matches!(table, Err(NgramTableError::Empty)));
+        }
+
+        #[test]
+        fn test_build_ngrams_umlauts() {
+            let table = build_ngrams("Brücke".as_bytes(), 3).unwrap();
+            let expected = NgramTable::from_text(3, "bruecke").unwrap();
+            assert_eq!(table.log_prob(&to_runes("uec")), expected.log_prob(&to_runes("uec")));
+            assert_eq!(table.log_prob(&to_runes("ruc")), expected.log_prob(&to_runes("ruc")));
+        }
+
+        #[test]
+        fn test_build_ngrams_invalid() {
+            assert!(matches!(build_ngrams("abc".as_bytes(), 0),
+                Err(NgramTableError::InvalidLength)));
+            assert!(matches!(build_ngrams("abc".as_bytes(), 5),
+                Err(NgramTableError::InvalidLength)));
+            assert!(matches!(build_ngrams("".as_bytes(), 1), Err(NgramTableError::Empty)));
+        }
+    }
+
+    mod binary_tests {
+        use super::*;
+
+        #[test]
+        fn test_round_trip() {
+            let table = fitness::english_trigrams();
+            let mut bytes = Vec::new();
+            save(&table, &mut bytes).unwrap();
+            assert_eq!(bytes.len(), 6 + 26 * 26 * 26 * 4);
+
+            let loaded = load(bytes.as_slice()).unwrap();
+            assert_eq!(loaded.n(), 3);
+            let text = to_runes("attackthebridgeatdawn");
+            assert!((loaded.score(&text) - table.score(&text)).abs() < 1e-3);
+        }
+
+        #[test]
+        fn test_load_invalid() {
+            let mut bytes = Vec::new();
+            save(&NgramTable::from_text(1, "abc").unwrap(), &mut bytes).unwrap();
+
+            assert!(matches!(load(&bytes[..bytes.len() - 1]), Err(NgramTableError::Format)));
+            let mut extended = bytes.clone();
+            extended.push(0);
+            assert!(matches!(load(extended.as_slice()), Err(NgramTableError::Format)));
+            let mut corrupted = bytes.clone();
+            corrupted[0] = b'X';
+            assert!(matches!(load(corrupted.as_slice()), Err(NgramTableError::Format)));
+            let mut corrupted = bytes;
+            corrupted[5] = 7;
+            assert!(matches!(load(corrupted.as_slice()), Err(NgramTableError::InvalidLength)));
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! This module implements crib dragging, the first step of every crib attack.
+//!
+//! A crib is a guessed piece of plaintext whose position within the ciphertext is unknown. Since
+//! the Enigma machine never enciphers a letter to itself, the crib cannot be placed at a position
+//! where one of its letters equals the ciphertext letter below it. Dragging the crib along the
+//! ciphertext therefore rules out many positions at once, and the remaining positions are the
+//! candidates for the Bombe:
+//!
+//! ```
+//! # use enigma::Rune;
+//! # use enigma::analysis::crib;
+//! #
+//! # fn runes(s: &str) -> Vec<Rune> {
+//! #     s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+//! # }
+//! #
+//! let crib = runes("wetter");
+//! let ciphertext = runes("qwertzuiopwetter");
+//! assert_eq!(crib::positions(&crib, &ciphertext), vec![0, 3, 4, 5, 6, 8]);
+//!
+//! for (offset, menu) in crib::menus(&crib, &ciphertext) {
+//!     assert_eq!(menu.len(), crib.len());
+//!     assert_eq!(menu.edges()[0].cipher, ciphertext[offset]);
+//! }
+//! ```
+//!
+
+use crate::analysis::menu::{self, Menu};
+use crate::utils::Rune;
+
+/// Determine whether the specified crib can be placed above the specified ciphertext window, e.g.
+/// whether they have the same length and no crib letter equals the ciphertext letter below it.
+pub fn can_align(crib: &[Rune], ciphertext_window: &[Rune]) -> bool {
+    crib.len() == ciphertext_window.len() &&
+        crib.iter().zip(ciphertext_window).all(|(plain, cipher)| plain != cipher)
+}
+
+/// Get every offset within the ciphertext at which the specified crib can be placed.
+pub fn positions(crib: &[Rune], ciphertext: &[Rune]) -> Vec<usize> {
+    offsets(crib, ciphertext).collect()
+}
+
+/// Drag the specified crib along the ciphertext and yield the offset of the crib within the
+/// ciphertext and the menu at every position where the crib can be placed.
+pub fn menus<'a>(crib: &'a [Rune], ciphertext: &'a [Rune])
+    -> impl Iterator<Item = (usize, Menu)> + 'a {
+    offsets(crib, ciphertext).map(move |offset| {
+        let menu = menu::build(crib, &ciphertext[offset..offset + crib.len()])
+            .expect("the crib can be placed at the offset");
+        (offset, menu)
+    })
+}
+
+fn offsets<'a>(crib: &'a [Rune], ciphertext: &'a [Rune]) -> impl Iterator<Item = usize> + 'a {
+    let num_offsets = (ciphertext.len() + 1).saturating_sub(crib.len());
+    (0..num_offsets)
+        .filter(move |offset| can_align(crib, &ciphertext[*offset..*offset + crib.len()]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_runes(s: &str) -> Vec<Rune> {
+        s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_can_align() {
+        assert!(can_align(&to_runes("abc"), &to_runes("bca")));
+        assert!(!can_align(&to_runes("abc"), &to_runes("bba")));
+        assert!(!can_align(&to_runes("abc"), &to_runes("bc")));
+    }
+
+    #[test]
+    fn test_positions() {
+        assert_eq!(positions(&to_runes("ab"), &to_runes("abcab")), vec![1, 2]);
+        assert_eq!(positions(&to_runes("ab"), &to_runes("ba")), vec![0]);
+        assert!(positions(&to_runes("abc"), &to_runes("xy")).is_empty());
+        assert_eq!(positions(&[], &to_runes("xy")), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_menus() {
+        let crib = to_runes("wetter");
+        let ciphertext = to_runes("wetterewrwtzwetter");
+        let offsets: Vec<usize> = menus(&crib, &ciphertext).map(|(offset, _)| offset).collect();
+        assert_eq!(offsets, positions(&crib, &ciphertext));
+
+        let (_, menu) = menus(&crib, &ciphertext).find(|(offset, _)| *offset == 6).unwrap();
+        assert_eq!(menu, menu::build(&crib, &to_runes("ewrwtz")).unwrap());
+        assert_eq!(menu.closures(), 2);
+    }
+}
@@ -0,0 +1,478 @@
+//! This module implements Monte Carlo experiments measuring how much ciphertext the hill-climbing
+//! attack needs to recover the key of a machine, which makes the crate usable for reproducible
+//! research.
+//!
+//! # Unicity Distance
+//!
+//! The unicity distance is the length of ciphertext beyond which a single key is expected to
+//! decrypt the ciphertext to meaningful text. It is the number of bits of the key divided by the
+//! redundancy of the language, e.g. `log2(26)` minus the entropy rate of the language in bits per
+//! letter. The `entropy_rate` function estimates the entropy rate of a language from an n-gram
+//! table, and the `unicity_distance` function calculates the unicity distance:
+//!
+//! ```
+//! # use enigma::analysis::experiments;
+//! # use enigma::analysis::fitness;
+//! #
+//! let entropy = experiments::entropy_rate(&fitness::english_trigrams());
+//! assert!(entropy < 26f64.log2());
+//!
+//! // 60 rotor orders, 26^3 positions and about 2^47 plug boards of 10 plug pairs.
+//! let distance = experiments::unicity_distance(67.1, &fitness::english_trigrams());
+//! assert!(distance > 10.0 && distance < 100.0);
+//! ```
+//!
+//! The unicity distance is a lower bound only: attacks need much more ciphertext in practice.
+//!
+//! # Experiments
+//!
+//! An [`Experiment`] runs a number of trials at each of a list of ciphertext lengths. Each trial
+//! draws a random key of the machine variant given by the `hillclimb::Constraints` and a random
+//! plaintext sampled from a language model given as an `NgramTable`, encrypts the plaintext and
+//! lets `hillclimb::recover` attack the ciphertext. A trial succeeds if the best candidate key
+//! decrypts at least `RECOVERY_ACCURACY` of the letters correctly. All random choices are derived
+//! from the seed of the experiment, so running the same experiment again gives the same results:
+//!
+//! ```
+//! # use enigma::analysis::experiments::{self, Experiment};
+//! # use enigma::analysis::fitness;
+//! # use enigma::analysis::hillclimb::Constraints;
+//! # use enigma::components::RotorCatalog;
+//! # use enigma::math::Permutation;
+//! #
+//! let catalog = RotorCatalog::historical();
+//! let rotators = ["I", "II", "III"].iter()
+//!     .map(|name| catalog.wiring(name).unwrap().clone())
+//!     .collect();
+//! let reflector = Permutation::from_perm(
+//!     b"yruhqsldpxngokmiebfzcwvjat".iter().map(|b| b - b'a').collect()).unwrap();
+//!
+//! let experiment = Experiment::new(Constraints::new(rotators, reflector).candidates(3),
+//!     fitness::english_trigrams())
+//!     .plug_pairs(2)
+//!     .lengths(vec![20])
+//!     .trials(1)
+//!     .seed(42);
+//! let trials = experiment.run();
+//! assert_eq!(trials.len(), 1);
+//!
+//! let mut csv = Vec::new();
+//! experiments::write_csv(&trials, &mut csv).unwrap();
+//! println!("{}", String::from_utf8(csv).unwrap());
+//! ```
+//!
+//! [`Experiment`]: struct.Experiment.html
+//!
+
+use std::io::Write;
+
+use crate::{Settings, SteppingMode};
+use crate::analysis::fitness::{self, NgramTable};
+use crate::analysis::hillclimb::{self, Constraints};
+use crate::analysis::stecker::DEFAULT_MAX_PLUG_PAIRS;
+use crate::settings::Keyspace;
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// The minimal rate of correctly decrypted letters for a trial to succeed.
+pub const RECOVERY_ACCURACY: f64 = 0.9;
+
+/// Estimate the entropy rate of the language of the specified n-gram table in bits per letter,
+/// e.g. the entropy of a letter given the `n - 1` letters preceding it.
+pub fn entropy_rate(model: &NgramTable) -> f64 {
+    let probs = probabilities(model);
+    let total: f64 = probs.iter().sum();
+    let ngram_entropy = entropy(probs.iter().copied(), total);
+    if model.n() == 1 {
+        return ngram_entropy;
+    }
+
+    // The entropy of the last letter of an n-gram given its prefix is the entropy of the n-grams
+    // minus the entropy of their prefixes.
+    let prefix_entropy = entropy(probs.chunks(RUNE_SET_SIZE as usize).map(|c| c.iter().sum()),
+        total);
+    ngram_entropy - prefix_entropy
+}
+
+/// Calculate the entropy in bits of the distribution with the specified weights, which sum up to
+/// the specified total.
+fn entropy<I>(weights: I, total: f64) -> f64
+    where I: Iterator<Item=f64> {
+    weights.map(|w| w / total)
+        .filter(|p| *p > 0.0)
+        .map(|p| -p * p.log2())
+        .sum()
+}
+
+/// Calculate the unicity distance of a cipher with keys of the specified number of bits on texts
+/// of the language of the specified n-gram table.
+pub fn unicity_distance(key_bits: f64, model: &NgramTable) -> f64 {
+    key_bits / ((RUNE_SET_SIZE as f64).log2() - entropy_rate(model))
+}
+
+/// The result of a trial of an experiment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trial {
+    /// The length of the ciphertext.
+    pub length: usize,
+
+    /// The index of the trial among the trials of the same length.
+    pub index: usize,
+
+    /// Whether the best candidate key has the right rotor order.
+    pub rotor_order_recovered: bool,
+
+    /// The rate of letters decrypted correctly by the best candidate key.
+    pub accuracy: f64,
+
+    /// The n-gram score of the best candidate key.
+    pub score: f64,
+}
+
+impl Trial {
+    /// Determine whether this trial succeeded, e.g. whether the best candidate key decrypts at
+    /// least `RECOVERY_ACCURACY` of the letters correctly.
+    pub fn is_success(&self) -> bool {
+        self.accuracy >= RECOVERY_ACCURACY
+    }
+}
+
+/// A Monte Carlo experiment of the hill-climbing attack.
+#[derive(Clone, Debug)]
+pub struct Experiment {
+    constraints: Constraints,
+    model: NgramTable,
+    plug_pairs: usize,
+    lengths: Vec<usize>,
+    trials: usize,
+    seed: u64,
+}
+
+impl Experiment {
+    /// Create a new experiment attacking machines built from the rotators and the reflector of the
+    /// specified constraints, with plaintexts sampled from the specified n-gram table.
+    ///
+    /// By default, the keys have 10 plug pairs, 10 trials are run at each of the lengths 100, 200,
+    /// 300 and 400, and the seed is `0`.
+    pub fn new(constraints: Constraints, model: NgramTable) -> Self {
+        Self {
+            constraints,
+            model,
+            plug_pairs: DEFAULT_MAX_PLUG_PAIRS,
+            lengths: vec![100, 200, 300, 400],
+            trials: 10,
+            seed: 0,
+        }
+    }
+
+    /// Set the number of plug pairs of the random keys.
+    ///
+    /// This function panics if the number of plug pairs is greater than `RUNE_SET_SIZE / 2`.
+    pub fn plug_pairs(mut self, plug_pairs: usize) -> Self {
+        assert!(plug_pairs <= RUNE_SET_SIZE as usize / 2, "too many plug pairs");
+        self.plug_pairs = plug_pairs;
+        self
+    }
+
+    /// Set the lengths of the ciphertexts to attack.
+    pub fn lengths(mut self, lengths: Vec<usize>) -> Self {
+        self.lengths = lengths;
+        self
+    }
+
+    /// Set the number of trials at each length.
+    pub fn trials(mut self, trials: usize) -> Self {
+        self.trials = trials;
+        self
+    }
+
+    /// Set the seed from which the random keys and plaintexts are derived.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Get the number of bits of the keys of this experiment, e.g. the base-2 logarithm of the
+    /// number of rotor orders, positions and plug boards.
+    pub fn key_bits(&self) -> f64 {
+        let n = RUNE_SET_SIZE as usize;
+        let p = self.plug_pairs;
+        let log2_sum = |range: std::ops::RangeInclusive<usize>| -> f64 {
+            range.map(|i| (i as f64).log2()).sum()
+        };
+
+        // There are 26! / ((26 - 2p)! p! 2^p) plug boards of p plug pairs.
+        let plug_bits = log2_sum(n - 2 * p + 1..=n) - log2_sum(1..=p) - p as f64;
+        let keyspace = Keyspace::new(self.constraints.rotators().len());
+        (keyspace.len() as f64).log2() + plug_bits
+    }
+
+    /// Get the unicity distance of the keys of this experiment on texts of its language model.
+    pub fn unicity_distance(&self) -> f64 {
+        unicity_distance(self.key_bits(), &self.model)
+    }
+
+    /// Run all trials of this experiment, length by length.
+    ///
+    /// This function panics if less than 3 rotators are available.
+    pub fn run(&self) -> Vec<Trial> {
+        let keyspace = Keyspace::new(self.constraints.rotators().len());
+        assert!(!keyspace.is_empty(), "at least 3 rotators are needed");
+
+        let probs = probabilities(&self.model);
+        let mut rng = Rng(self.seed);
+        let mut trials = Vec::with_capacity(self.lengths.len() * self.trials);
+        for length in &self.lengths {
+            for index in 0..self.trials {
+                let (settings, rotor_order) = self.random_key(&keyspace, &mut rng);
+                let plaintext = sample_text(&probs, self.model.n(), *length, &mut rng);
+                trials.push(self.run_trial(&settings, rotor_order, &plaintext, index));
+            }
+        }
+        trials
+    }
+
+    /// Draw a random key, and return its settings and rotor order.
+    fn random_key(&self, keyspace: &Keyspace, rng: &mut Rng) -> (Settings, [usize; 3]) {
+        let (rotor_order, positions) = keyspace.unrank(rng.below(keyspace.len())).unwrap();
+
+        let mut letters: Vec<u8> = (0..RUNE_SET_SIZE).collect();
+        for i in (1..letters.len()).rev() {
+            letters.swap(i, rng.below(i as u64 + 1) as usize);
+        }
+        let plug_pairs = letters.chunks_exact(2)
+            .take(self.plug_pairs)
+            .map(|pair| (Rune::from_value(pair[0]).unwrap(), Rune::from_value(pair[1]).unwrap()))
+            .collect();
+
+        let rotators = self.constraints.rotators();
+        let settings = Settings {
+            rotator_wirings: [
+                rotators[rotor_order[0]].clone(),
+                rotators[rotor_order[1]].clone(),
+                rotators[rotor_order[2]].clone(),
+            ],
+            positions,
+            rings: [0, 0, 0],
+            notches: Default::default(),
+            stepping: SteppingMode::Odometer,
+            reflector: self.constraints.reflector().clone(),
+            plug_pairs,
+        };
+        (settings, rotor_order)
+    }
+
+    /// Encrypt the specified plaintext under the specified key, and attack the ciphertext.
+    fn run_trial(&self, settings: &Settings, rotor_order: [usize; 3], plaintext: &[Rune],
+        index: usize) -> Trial {
+        let mut machine = settings.create_enigma()
+            .expect("invalid rotator or reflector wiring in constraints");
+        let ciphertext: Vec<Rune> = plaintext.iter().map(|r| machine.map_rune(*r)).collect();
+
+        let candidates = hillclimb::recover(&ciphertext, &self.constraints);
+        let mut trial = Trial {
+            length: plaintext.len(),
+            index,
+            rotor_order_recovered: false,
+            accuracy: 0.0,
+            score: f64::NEG_INFINITY,
+        };
+
+        if let Some(best) = candidates.first() {
+            let mut machine = best.settings.create_enigma().unwrap();
+            let num_correct = ciphertext.iter()
+                .zip(plaintext)
+                .filter(|(c, p)| machine.map_rune(**c) == **p)
+                .count();
+            trial.rotor_order_recovered = best.rotor_order == rotor_order;
+            if !plaintext.is_empty() {
+                trial.accuracy = num_correct as f64 / plaintext.len() as f64;
+            }
+            trial.score = best.score;
+        }
+        trial
+    }
+}
+
+/// Get the rate of successful trials at each length, in the order in which the lengths first
+/// appear within the specified trials.
+pub fn success_rates(trials: &[Trial]) -> Vec<(usize, f64)> {
+    let mut counts: Vec<(usize, usize, usize)> = Vec::new();
+    for trial in trials {
+        let index = match counts.iter().position(|(length, _, _)| *length == trial.length) {
+            Some(index) => index,
+            None => {
+                counts.push((trial.length, 0, 0));
+                counts.len() - 1
+            },
+        };
+        counts[index].1 += trial.is_success() as usize;
+        counts[index].2 += 1;
+    }
+
+    counts.into_iter()
+        .map(|(length, successes, total)| (length, successes as f64 / total as f64))
+        .collect()
+}
+
+/// Write the specified trials as CSV, with a header line and a line for each trial.
+pub fn write_csv<W: Write>(trials: &[Trial], mut writer: W) -> std::io::Result<()> {
+    writeln!(writer, "length,trial,rotor_order_recovered,accuracy,score,success")?;
+    for trial in trials {
+        writeln!(writer, "{},{},{},{},{},{}", trial.length, trial.index,
+            trial.rotor_order_recovered, trial.accuracy, trial.score, trial.is_success())?;
+    }
+    Ok(())
+}
+
+/// Get the probabilities of all n-grams of the specified table, indexed by n-gram. The
+/// probabilities are not normalized.
+fn probabilities(model: &NgramTable) -> Vec<f64> {
+    model.log_probs().iter().map(|log_prob| 10f64.powf(*log_prob)).collect()
+}
+
+/// Sample a text of the specified length from the n-grams of length `n` with the specified
+/// probabilities, e.g. each letter is drawn given the `n - 1` letters preceding it.
+fn sample_text(probs: &[f64], n: usize, len: usize, rng: &mut Rng) -> Vec<Rune> {
+    let size = RUNE_SET_SIZE as usize;
+    let mut text = Vec::with_capacity(len.max(n));
+
+    let mut index = rng.choose(probs);
+    for i in (0..n).rev() {
+        text.push(Rune::from_value((index / size.pow(i as u32) % size) as u8).unwrap());
+    }
+
+    while text.len() < len {
+        let prefix = fitness::ngram_index(&text[text.len() + 1 - n..]);
+        index = rng.choose(&probs[prefix * size..(prefix + 1) * size]);
+        text.push(Rune::from_value(index as u8).unwrap());
+    }
+
+    text.truncate(len);
+    text
+}
+
+/// A SplitMix64 pseudo-random number generator, which derives the random choices of an
+/// experiment from its seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Draw an integer uniformly from `0..n`, ignoring the negligible modulo bias.
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// Draw an index of the specified weights with a probability proportional to its weight.
+    fn choose(&mut self, weights: &[f64]) -> usize {
+        let total: f64 = weights.iter().sum();
+        let mut x = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 * total;
+        for (i, weight) in weights.iter().enumerate() {
+            if x < *weight {
+                return i;
+            }
+            x -= weight;
+        }
+        weights.len() - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::analysis::fitness::index_of_coincidence;
+    use crate::math::Permutation;
+
+    fn wiring(s: &str) -> Permutation {
+        Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+    }
+
+    fn create_test_constraints() -> Constraints {
+        let rotators = vec![
+            wiring("ekmflgdqvzntowyhxuspaibrcj"),
+            wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+            wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+        ];
+        Constraints::new(rotators, wiring("yruhqsldpxngokmiebfzcwvjat")).candidates(3)
+    }
+
+    fn create_test_trial(length: usize, index: usize, accuracy: f64) -> Trial {
+        Trial { length, index, rotor_order_recovered: true, accuracy, score: -10.5 }
+    }
+
+    #[test]
+    fn test_entropy_rate() {
+        let uniform = NgramTable::from_counts(2, &vec![1; 26 * 26]).unwrap();
+        assert!((entropy_rate(&uniform) - 26f64.log2()).abs() < 1e-9);
+
+        let english = entropy_rate(&fitness::english_trigrams());
+        assert!(english > 1.0 && english < entropy_rate(&fitness::english_bigrams()));
+    }
+
+    #[test]
+    fn test_key_bits() {
+        let experiment = Experiment::new(create_test_constraints(), fitness::english_trigrams());
+        let expected = (6.0 * 26f64.powi(3)).log2() + 150738274937250f64.log2();
+        assert!((experiment.key_bits() - expected).abs() < 1e-9);
+
+        let experiment = experiment.plug_pairs(0);
+        assert!((experiment.key_bits() - (6.0 * 26f64.powi(3)).log2()).abs() < 1e-9);
+        assert!(experiment.unicity_distance() > 0.0);
+    }
+
+    #[test]
+    fn test_sample_text() {
+        let model = fitness::english_trigrams();
+        let probs = probabilities(&model);
+        let text = sample_text(&probs, 3, 2000, &mut Rng(1));
+        assert_eq!(text.len(), 2000);
+        assert!(index_of_coincidence(&text) > 0.055);
+        assert_eq!(text, sample_text(&probs, 3, 2000, &mut Rng(1)));
+
+        assert_eq!(sample_text(&probs, 3, 2, &mut Rng(1)).len(), 2);
+    }
+
+    #[test]
+    fn test_run() {
+        let experiment = Experiment::new(create_test_constraints(), fitness::english_trigrams())
+            .plug_pairs(2)
+            .lengths(vec![300])
+            .trials(1)
+            .seed(7);
+        let trials = experiment.run();
+        assert_eq!(trials.len(), 1);
+        assert_eq!(trials[0].length, 300);
+        assert!(trials[0].rotor_order_recovered);
+        assert!(trials[0].is_success());
+        assert_eq!(experiment.run(), trials);
+    }
+
+    #[test]
+    fn test_success_rates() {
+        let trials = vec![
+            create_test_trial(100, 0, 0.5),
+            create_test_trial(100, 1, 0.95),
+            create_test_trial(50, 0, 0.1),
+        ];
+        assert_eq!(success_rates(&trials), vec![(100, 0.5), (50, 0.0)]);
+        assert!(success_rates(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let trials = vec![create_test_trial(100, 0, 0.5), create_test_trial(100, 1, 1.0)];
+        let mut csv = Vec::new();
+        write_csv(&trials, &mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(),
+            "length,trial,rotor_order_recovered,accuracy,score,success\n\
+             100,0,true,0.5,-10.5,false\n\
+             100,1,true,1,-10.5,true\n");
+    }
+}
@@ -0,0 +1,367 @@
+//! This module implements statistical scoring of texts, which tells how closely a candidate
+//! decryption resembles natural language.
+//!
+//! # Index of Coincidence
+//!
+//! The index of coincidence is the probability that two letters drawn at random from a text are
+//! the same. It is about `0.066` for English texts and `1/26` (about `0.038`) for uniformly random
+//! texts. Since the plug board does not change the frequencies of letters, the index of
+//! coincidence can be used to score rotator settings when the plug board is unknown:
+//!
+//! ```
+//! # use enigma::Rune;
+//! # use enigma::analysis::fitness::index_of_coincidence;
+//! #
+//! let text: Vec<Rune> = "attackatdawn".chars().map(|ch| Rune::from_char(ch).unwrap()).collect();
+//! assert!(index_of_coincidence(&text) > 0.1);
+//! ```
+//!
+//! # N-gram Scores
+//!
+//! The n-gram score of a text is the sum of the base-10 logarithms of the probabilities of all
+//! n-grams within the text, as given by an [`NgramTable`]. A higher score means that the text is
+//! more likely to be natural language. Built-in English tables of bigrams, trigrams and quadgrams
+//! are provided by the `english_bigrams`, `english_trigrams` and `english_quadgrams` functions.
+//! Statistics of other languages can be loaded with the `NgramTable::load` function, or built from
+//! a corpus with the [`corpus`] module.
+//!
+//! ```
+//! # use enigma::Rune;
+//! # use enigma::analysis::fitness;
+//! #
+//! let to_runes = |s: &str| -> Vec<Rune> {
+//!     s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+//! };
+//!
+//! let table = fitness::english_trigrams();
+//! assert!(table.score(&to_runes("thereportofthecommittee")) >
+//!     table.score(&to_runes("qzxjvkwpfmqzxjvkwpfmqzx")));
+//! ```
+//!
+//! [`NgramTable`]: struct.NgramTable.html
+//! [`corpus`]: ../corpus/index.html
+//!
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::BufRead;
+
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// The expected index of coincidence of English texts.
+pub const ENGLISH_IOC: f64 = 0.0667;
+
+/// The expected index of coincidence of uniformly random texts.
+pub const RANDOM_IOC: f64 = 1.0 / RUNE_SET_SIZE as f64;
+
+/// The maximal length of n-grams supported by `NgramTable`.
+pub const MAX_NGRAM_LEN: usize = 4;
+
+/// The English text from which the built-in English statistics are derived.
+const ENGLISH_CORPUS: &str = include_str!("data/english.txt");
+
+/// Calculate the index of coincidence of the specified text.
+///
+/// Returns `0` if the text has less than 2 runes.
+pub fn index_of_coincidence(text: &[Rune]) -> f64 {
+    if text.len() < 2 {
+        return 0.0;
+    }
+
+    let mut counts = [0usize; RUNE_SET_SIZE as usize];
+    for rune in text {
+        counts[rune.value() as usize] += 1;
+    }
+
+    let coincidences: usize = counts.iter().map(|c| c * c.saturating_sub(1)).sum();
+    coincidences as f64 / (text.len() * (text.len() - 1)) as f64
+}
+
+/// Error indicating that an n-gram table cannot be created or loaded.
+#[derive(Debug)]
+pub enum NgramTableError {
+    /// The length of the n-grams is 0 or greater than `MAX_NGRAM_LEN`.
+    InvalidLength,
+
+    /// The table does not contain any n-grams.
+    Empty,
+
+    /// A line of the statistics file is malformed.
+    Parse {
+        /// The line number, starting from 1.
+        line: usize,
+    },
+
+    /// A binary n-gram table is malformed.
+    Format,
+
+    /// The statistics file cannot be read.
+    Io(std::io::Error),
+}
+
+impl Display for NgramTableError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NgramTableError::InvalidLength =>
+                write!(f, "n-gram length should be between 1 and {}", MAX_NGRAM_LEN),
+            NgramTableError::Empty => f.write_str("n-gram table is empty"),
+            NgramTableError::Parse { line } => write!(f, "malformed n-gram at line {}", line),
+            NgramTableError::Format => f.write_str("malformed binary n-gram table"),
+            NgramTableError::Io(e) => write!(f, "cannot read n-gram statistics: {}", e),
+        }
+    }
+}
+
+impl Error for NgramTableError { }
+
+impl From<std::io::Error> for NgramTableError {
+    fn from(e: std::io::Error) -> Self {
+        NgramTableError::Io(e)
+    }
+}
+
+/// A table of the log-probabilities of all n-grams of a language.
+#[derive(Clone, Debug)]
+pub struct NgramTable {
+    n: usize,
+    log_probs: Vec<f64>,
+}
+
+impl NgramTable {
+    /// Create a table from the occurrence counts of n-grams of length `n`. The counts are indexed
+    /// by n-gram, where the index of an n-gram is the value of its runes read as a base-26
+    /// number, e.g. the index of `"BA"` is `1 * 26 + 0`. The n-grams that never occur are assigned
+    /// a small floor probability.
+    pub fn from_counts(n: usize, counts: &[u64]) -> Result<Self, NgramTableError> {
+        if n == 0 || n > MAX_NGRAM_LEN {
+            return Err(NgramTableError::InvalidLength);
+        }
+        if counts.len() != (RUNE_SET_SIZE as usize).pow(n as u32) {
+            return Err(NgramTableError::InvalidLength);
+        }
+
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return Err(NgramTableError::Empty);
+        }
+
+        let total = total as f64;
+        let floor = (0.01 / total).log10();
+        let log_probs = counts.iter()
+            .map(|c| if *c == 0 { floor } else { (*c as f64 / total).log10() })
+            .collect();
+
+        Ok(Self { n, log_probs })
+    }
+
+    /// Count the n-grams of length `n` within the specified text and create a table from the
+    /// counts. Characters other than English letters are skipped, and n-grams never span them.
+    pub fn from_text(n: usize, text: &str) -> Result<Self, NgramTableError> {
+        if n == 0 || n > MAX_NGRAM_LEN {
+            return Err(NgramTableError::InvalidLength);
+        }
+
+        let mut counts = vec![0u64; (RUNE_SET_SIZE as usize).pow(n as u32)];
+        for word in text.split(|ch: char| !ch.is_ascii_alphabetic()) {
+            let runes: Vec<Rune> = word.chars().map(|ch| Rune::from_char(ch).unwrap()).collect();
+            for ngram in runes.windows(n) {
+                counts[ngram_index(ngram)] += 1;
+            }
+        }
+
+        Self::from_counts(n, &counts)
+    }
+
+    /// Load a table from a statistics file. Each line of the file consists of an n-gram and its
+    /// occurrence count, separated by whitespace, e.g. `TION 13168375`. All n-grams should have the
+    /// same length. Empty lines and lines starting with `#` are ignored.
+    pub fn load<R: BufRead>(reader: R) -> Result<Self, NgramTableError> {
+        let mut n = 0usize;
+        let mut counts = Vec::new();
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parse_error = NgramTableError::Parse { line: i + 1 };
+            let mut fields = line.split_whitespace();
+            let (ngram, count) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(ngram), Some(count), None) => (ngram, count),
+                _ => return Err(parse_error),
+            };
+
+            let ngram: Vec<Rune> = match ngram.chars().map(Rune::from_char).collect() {
+                Ok(ngram) => ngram,
+                Err(_) => return Err(parse_error),
+            };
+            let count: u64 = count.parse().map_err(|_| NgramTableError::Parse { line: i + 1 })?;
+
+            if n == 0 {
+                n = ngram.len();
+                if n > MAX_NGRAM_LEN {
+                    return Err(NgramTableError::InvalidLength);
+                }
+                counts = vec![0u64; (RUNE_SET_SIZE as usize).pow(n as u32)];
+            } else if ngram.len() != n {
+                return Err(parse_error);
+            }
+
+            counts[ngram_index(&ngram)] += count;
+        }
+
+        if n == 0 {
+            return Err(NgramTableError::Empty);
+        }
+
+        Self::from_counts(n, &counts)
+    }
+
+    /// Create a table from the log-probabilities of all n-grams of length `n`, indexed by n-gram.
+    pub(crate) fn from_log_probs(n: usize, log_probs: Vec<f64>) -> Self {
+        debug_assert_eq!(log_probs.len(), (RUNE_SET_SIZE as usize).pow(n as u32));
+        Self { n, log_probs }
+    }
+
+    /// Get the log-probabilities of all n-grams, indexed by n-gram.
+    pub(crate) fn log_probs(&self) -> &[f64] {
+        &self.log_probs
+    }
+
+    /// Get the length of the n-grams within this table.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// Get the log-probability of the specified n-gram.
+    ///
+    /// This function panics if the length of the specified n-gram is not `n()`.
+    pub fn log_prob(&self, ngram: &[Rune]) -> f64 {
+        assert_eq!(ngram.len(), self.n, "n-gram length mismatch");
+        self.log_probs[ngram_index(ngram)]
+    }
+
+    /// Calculate the n-gram score of the specified text.
+    pub fn score(&self, text: &[Rune]) -> f64 {
+        text.windows(self.n).map(|ngram| self.log_probs[ngram_index(ngram)]).sum()
+    }
+}
+
+/// Get the index of the specified n-gram within an n-gram table.
+pub(crate) fn ngram_index(ngram: &[Rune]) -> usize {
+    ngram.iter().fold(0usize, |index, rune| {
+        index * RUNE_SET_SIZE as usize + rune.value() as usize
+    })
+}
+
+/// Create the built-in English n-gram table of n-grams of length `n`.
+///
+/// This function panics if `n` is 0 or greater than `MAX_NGRAM_LEN`.
+pub fn english_ngrams(n: usize) -> NgramTable {
+    NgramTable::from_text(n, ENGLISH_CORPUS).unwrap()
+}
+
+/// Create the built-in English bigram table.
+pub fn english_bigrams() -> NgramTable {
+    english_ngrams(2)
+}
+
+/// Create the built-in English trigram table.
+pub fn english_trigrams() -> NgramTable {
+    english_ngrams(3)
+}
+
+/// Create the built-in English quadgram table.
+pub fn english_quadgrams() -> NgramTable {
+    english_ngrams(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_runes(s: &str) -> Vec<Rune> {
+        s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_index_of_coincidence() {
+        assert_eq!(index_of_coincidence(&to_runes("aaaa")), 1.0);
+        assert_eq!(index_of_coincidence(&to_runes("abcd")), 0.0);
+        assert_eq!(index_of_coincidence(&to_runes("aabb")), 4.0 / 12.0);
+        assert_eq!(index_of_coincidence(&to_runes("a")), 0.0);
+    }
+
+    #[test]
+    fn test_english_ioc() {
+        let text: Vec<Rune> = ENGLISH_CORPUS.chars().filter_map(|ch| Rune::from_char(ch).ok())
+            .collect();
+        let ioc = index_of_coincidence(&text);
+        assert!((ioc - ENGLISH_IOC).abs() < 0.005);
+    }
+
+    mod ngram_table_tests {
+        use super::*;
+
+        #[test]
+        fn test_ngram_index() {
+            assert_eq!(ngram_index(&to_runes("ba")), 26);
+            assert_eq!(ngram_index(&to_runes("zz")), 26 * 26 - 1);
+        }
+
+        #[test]
+        fn test_from_counts_invalid() {
+            assert!(NgramTable::from_counts(0, &[]).is_err());
+            assert!(NgramTable::from_counts(5, &[]).is_err());
+            assert!(NgramTable::from_counts(1, &[1, 2]).is_err());
+            assert!(NgramTable::from_counts(1, &[0; 26]).is_err());
+        }
+
+        #[test]
+        fn test_from_text() {
+            let table = NgramTable::from_text(2, "ab ab, ba").unwrap();
+            assert_eq!(table.n(), 2);
+            assert!((table.log_prob(&to_runes("ab")) - (2.0f64 / 3.0).log10()).abs() < 1e-9);
+            assert!((table.log_prob(&to_runes("ba")) - (1.0f64 / 3.0).log10()).abs() < 1e-9);
+            assert!(table.log_prob(&to_runes("bb")) < table.log_prob(&to_runes("ba")));
+        }
+
+        #[test]
+        fn test_load() {
+            let content = "# comment\nAB 2\n\nba 1\n";
+            let table = NgramTable::load(content.as_bytes()).unwrap();
+            assert_eq!(table.n(), 2);
+            assert!((table.log_prob(&to_runes("ab")) - (2.0f64 / 3.0).log10()).abs() < 1e-9);
+        }
+
+        #[test]
+        fn test_load_invalid() {
+            assert!(matches!(NgramTable::load("AB 2\nABC 1\n".as_bytes()),
+                Err(NgramTableError::Parse { line: 2 })));
+            assert!(matches!(NgramTable::load("AB x\n".as_bytes()),
+                Err(NgramTableError::Parse { line: 1 })));
+            assert!(matches!(NgramTable::load("A1 3\n".as_bytes()),
+                Err(NgramTableError::Parse { line: 1 })));
+            assert!(matches!(NgramTable::load("".as_bytes()), Err(NgramTableError::Empty)));
+        }
+
+        #[test]
+        fn test_score() {
+            let table = NgramTable::from_text(2, "abab").unwrap();
+            let expected = 2.0 * table.log_prob(&to_runes("ab")) + table.log_prob(&to_runes("ba"));
+            assert!((table.score(&to_runes("abab")) - expected).abs() < 1e-9);
+            assert_eq!(table.score(&to_runes("a")), 0.0);
+        }
+
+        #[test]
+        fn test_english_scores() {
+            let english = to_runes("attackthebridgeatdawn");
+            let random = to_runes("xqjzvkwpfmqzxjvkwpfmq");
+            for table in &[english_bigrams(), english_trigrams(), english_quadgrams()] {
+                assert!(table.score(&english) > table.score(&random));
+            }
+        }
+    }
+}
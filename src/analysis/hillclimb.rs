@@ -0,0 +1,257 @@
+//! This module implements a ciphertext-only attack in the style of James Gillogly's hill-climbing
+//! method.
+//!
+//! The attack does not need any crib or indicator. It works in two phases:
+//!
+//! 1. Every rotor order and every rotor position is tried with an empty plug board, and the
+//!    decryptions are scored by their index of coincidence. Since the plug board does not change
+//!    the letter frequencies much when only a few letters are steckered, the right rotor settings
+//!    tend to produce decryptions with a higher index of coincidence than the wrong ones.
+//! 2. For the best rotor settings, the plug board is recovered by hill climbing: plug pairs are
+//!    added, replaced or removed one at a time as long as the n-gram score of the decryption
+//!    improves.
+//!
+//! The search space and the parameters of the attack are given by [`Constraints`]. The result is
+//! a list of [`Candidate`]s ranked by their n-gram scores.
+//!
+//! The attack needs a few hundred letters of ciphertext to be reliable.
+//!
+//! [`Constraints`]: struct.Constraints.html
+//! [`Candidate`]: struct.Candidate.html
+//!
+
+use crate::{Enigma, Settings, SteppingMode};
+use crate::analysis::fitness::{self, NgramTable};
+use crate::analysis::progress::Progress;
+use crate::analysis::stecker::{self, DEFAULT_MAX_PLUG_PAIRS};
+use crate::math::Permutation;
+use crate::settings::Keyspace;
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// The constraints and parameters of the hill-climbing attack.
+#[derive(Clone, Debug)]
+pub struct Constraints {
+    rotators: Vec<Permutation>,
+    reflector: Permutation,
+    max_plug_pairs: usize,
+    candidates: usize,
+    table: NgramTable,
+}
+
+impl Constraints {
+    /// Create new constraints with the wirings of the available rotators and the reflector. Every
+    /// ordered choice of 3 distinct rotators from the available rotators is tried.
+    ///
+    /// By default, at most 10 plug pairs are recovered, the best 10 rotor settings are kept after
+    /// the first phase, and the plug board is scored with the built-in English trigrams.
+    pub fn new(rotators: Vec<Permutation>, reflector: Permutation) -> Self {
+        Self {
+            rotators,
+            reflector,
+            max_plug_pairs: DEFAULT_MAX_PLUG_PAIRS,
+            candidates: 10,
+            table: fitness::english_trigrams(),
+        }
+    }
+
+    /// Set the maximal number of plug pairs to recover.
+    pub fn max_plug_pairs(mut self, max_plug_pairs: usize) -> Self {
+        self.max_plug_pairs = max_plug_pairs;
+        self
+    }
+
+    /// Set the number of rotor settings kept after the first phase.
+    pub fn candidates(mut self, candidates: usize) -> Self {
+        self.candidates = candidates;
+        self
+    }
+
+    /// Set the n-gram table used to score the plug board.
+    pub fn ngram_table(mut self, table: NgramTable) -> Self {
+        self.table = table;
+        self
+    }
+
+    /// Get the wirings of the available rotators.
+    pub(crate) fn rotators(&self) -> &[Permutation] {
+        &self.rotators
+    }
+
+    /// Get the wiring of the reflector.
+    pub(crate) fn reflector(&self) -> &Permutation {
+        &self.reflector
+    }
+}
+
+/// A candidate key recovered by the hill-climbing attack.
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    /// The recovered settings.
+    pub settings: Settings,
+
+    /// The indices of the recovered rotators within the available rotators.
+    pub rotor_order: [usize; 3],
+
+    /// The n-gram score of the decryption under the recovered settings.
+    pub score: f64,
+}
+
+/// Recover the key of the specified ciphertext, and return the candidate keys ranked by their
+/// scores, the best first.
+pub fn recover(ciphertext: &[Rune], constraints: &Constraints) -> Vec<Candidate> {
+    let num_states = (RUNE_SET_SIZE as usize).pow(3);
+    let mut progress = Progress::start("hillclimb");
+
+    // Phase 1: score every rotor order and position by the index of coincidence.
+    let mut best_rotor_settings: Vec<(f64, [usize; 3], usize)> = Vec::new();
+    for rotor_order in Keyspace::new(constraints.rotators.len()).rotor_orders() {
+        let machine = create_machine(constraints, rotor_order, [0, 0, 0]);
+        let scramblers = stecker::scrambler_tables(&machine, num_states);
+
+        for state in 0..num_states {
+            let mut counts = [0usize; RUNE_SET_SIZE as usize];
+            for (i, c) in ciphertext.iter().enumerate() {
+                counts[scramblers[(state + i) % num_states][c.value() as usize] as usize] += 1;
+            }
+            let ioc = ioc_from_counts(&counts, ciphertext.len());
+
+            if best_rotor_settings.len() < constraints.candidates ||
+                ioc > best_rotor_settings.last().unwrap().0 {
+                let index = best_rotor_settings.iter()
+                    .position(|(score, _, _)| ioc > *score)
+                    .unwrap_or(best_rotor_settings.len());
+                best_rotor_settings.insert(index, (ioc, rotor_order, state));
+                best_rotor_settings.truncate(constraints.candidates);
+            }
+        }
+        progress.tested(num_states as u64);
+    }
+
+    // Phase 2: recover the plug board of the best rotor settings by hill climbing.
+    let mut candidates: Vec<Candidate> = best_rotor_settings.into_iter()
+        .map(|(_, rotor_order, state)| {
+            let positions = state_to_offsets(state);
+            let machine = create_machine(constraints, rotor_order, positions);
+            let scramblers = stecker::scrambler_tables(&machine, ciphertext.len());
+            let (plug, score) = stecker::climb_plug_board(ciphertext, &scramblers,
+                stecker::identity_plug(), constraints.max_plug_pairs, &constraints.table);
+
+            let settings = Settings {
+                rotator_wirings: [
+                    constraints.rotators[rotor_order[0]].clone(),
+                    constraints.rotators[rotor_order[1]].clone(),
+                    constraints.rotators[rotor_order[2]].clone(),
+                ],
+                positions,
+                rings: [0, 0, 0],
+                notches: Default::default(),
+                stepping: SteppingMode::Odometer,
+                reflector: constraints.reflector.clone(),
+                plug_pairs: stecker::plug_to_pairs(&plug),
+            };
+            Candidate { settings, rotor_order, score }
+        })
+        .collect();
+
+    progress.finish();
+
+    candidates.sort_by(|lhs, rhs| rhs.score.partial_cmp(&lhs.score).unwrap());
+    candidates
+}
+
+/// Get the rotator offsets after advancing a rotator group from offsets `[0, 0, 0]` by the
+/// specified number of steps.
+fn state_to_offsets(state: usize) -> [u8; 3] {
+    let n = RUNE_SET_SIZE as usize;
+    [(state % n) as u8, (state / n % n) as u8, (state / n / n % n) as u8]
+}
+
+fn create_machine(constraints: &Constraints, rotor_order: [usize; 3], positions: [u8; 3])
+    -> Enigma {
+    let settings = Settings {
+        rotator_wirings: [
+            constraints.rotators[rotor_order[0]].clone(),
+            constraints.rotators[rotor_order[1]].clone(),
+            constraints.rotators[rotor_order[2]].clone(),
+        ],
+        positions,
+        rings: [0, 0, 0],
+        notches: Default::default(),
+        stepping: SteppingMode::Odometer,
+        reflector: constraints.reflector.clone(),
+        plug_pairs: Vec::new(),
+    };
+    settings.create_enigma().expect("invalid rotator or reflector wiring in constraints")
+}
+
+fn ioc_from_counts(counts: &[usize; RUNE_SET_SIZE as usize], len: usize) -> f64 {
+    if len < 2 {
+        return 0.0;
+    }
+    let coincidences: usize = counts.iter().map(|c| c * c.saturating_sub(1)).sum();
+    coincidences as f64 / (len * (len - 1)) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wiring(s: &str) -> Permutation {
+        Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+    }
+
+    fn rune(ch: char) -> Rune {
+        Rune::from_char(ch).unwrap()
+    }
+
+    #[test]
+    fn test_state_to_offsets() {
+        assert_eq!(state_to_offsets(0), [0, 0, 0]);
+        assert_eq!(state_to_offsets(26 * 26 * 3 + 26 * 2 + 1), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_recover() {
+        let rotators = vec![
+            wiring("ekmflgdqvzntowyhxuspaibrcj"),
+            wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+            wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+        ];
+        let reflector = wiring("yruhqsldpxngokmiebfzcwvjat");
+
+        let settings = Settings {
+            rotator_wirings: [rotators[2].clone(), rotators[0].clone(), rotators[1].clone()],
+            positions: [12, 4, 20],
+            rings: [0, 0, 0],
+            notches: Default::default(),
+            stepping: SteppingMode::Odometer,
+            reflector: reflector.clone(),
+            plug_pairs: vec![
+                (rune('a'), rune('q')), (rune('e'), rune('n')), (rune('t'), rune('x')),
+                (rune('o'), rune('w')),
+            ],
+        };
+
+        let plaintext = "theconvoywillleavetheharbouratfirstlightandproceednorthalongthe\
+            coastuntilitreachestheislandwhereitwillmeettheescortshipsallcaptainsaretokeep\
+            strictradiosilenceandtoreportanysightingofenemyaircraftorsubmarinesbysignallamp\
+            onlytheweatherisexpectedtoremaingoodforthenextthreedaysbutastormisforecastforthe\
+            endoftheweekandthecommanderwillconsiderreturningtoportifthewindincreases";
+        let plaintext: Vec<Rune> = plaintext.chars().map(rune).collect();
+        let mut machine = settings.create_enigma().unwrap();
+        let ciphertext: Vec<Rune> = plaintext.iter().map(|r| machine.map_rune(*r)).collect();
+
+        let constraints = Constraints::new(rotators, reflector).candidates(5);
+        let candidates = recover(&ciphertext, &constraints);
+        assert_eq!(candidates.len(), 5);
+
+        let best = &candidates[0];
+        assert_eq!(best.rotor_order, [2, 0, 1]);
+        assert_eq!(best.settings.positions, [12, 4, 20]);
+
+        let mut machine = best.settings.create_enigma().unwrap();
+        let decrypted: Vec<Rune> = ciphertext.iter().map(|r| machine.map_rune(*r)).collect();
+        let num_correct = decrypted.iter().zip(&plaintext).filter(|(a, b)| a == b).count();
+        assert!(num_correct * 10 > plaintext.len() * 9);
+    }
+}
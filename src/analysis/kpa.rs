@@ -0,0 +1,459 @@
+//! This module implements an exact known-plaintext attack, which recovers every key consistent
+//! with a whole pair of plaintext and ciphertext.
+//!
+//! The known parts of the key are given by [`KnownParts`]. The unknown rotor order, positions and
+//! ring settings are brute-forced. If the plug board is unknown as well, it is deduced at each
+//! rotor order, position and ring setting from the plaintext and the ciphertext in the way of the
+//! Bombe checking machine: a hypothesis is made about the partner of a letter, and its
+//! consequences are propagated along the text until either a contradiction is found or all
+//! letters of the text are steckered.
+//!
+//! Every returned key enciphers the plaintext into the ciphertext exactly. Letters that occur in
+//! neither text are not constrained, and they are left unplugged in the returned keys.
+//!
+//! The positions of the returned keys are the offsets of the rotators, and the ring settings only
+//! matter as far as they move the notches. With the odometer stepping mode, which ignores the
+//! notches, the ring settings do not change the mapping of a machine: they cannot be recovered and
+//! are left at `0`. With the Wehrmacht lever stepping mode, the ring settings of the first two
+//! rotators are tried in all `26 * 26` combinations, so the search takes that many times as long
+//! unless they are known. The notch of the third rotator never steps another rotator, so its ring
+//! setting is left at `0`.
+//!
+//! ```
+//! # use enigma::{Rune, Settings, SteppingMode};
+//! # use enigma::analysis::kpa::{self, KnownParts};
+//! # use enigma::math::Permutation;
+//! #
+//! # fn wiring(s: &str) -> Permutation {
+//! #     Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+//! # }
+//! # fn runes(s: &str) -> Vec<Rune> {
+//! #     s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+//! # }
+//! #
+//! let rotators = vec![
+//!     wiring("ekmflgdqvzntowyhxuspaibrcj"),
+//!     wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+//!     wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+//! ];
+//! let reflector = wiring("yruhqsldpxngokmiebfzcwvjat");
+//!
+//! let key = Settings {
+//!     rotator_wirings: [rotators[2].clone(), rotators[1].clone(), rotators[0].clone()],
+//!     positions: [5, 11, 23],
+//!     rings: [0, 0, 0],
+//!     notches: Default::default(),
+//!     stepping: SteppingMode::Odometer,
+//!     reflector: reflector.clone(),
+//!     plug_pairs: Vec::new(),
+//! };
+//! let plaintext = runes("theenemyisadvancingalongthecoastroad");
+//! let mut machine = key.create_enigma().unwrap();
+//! let ciphertext: Vec<Rune> = plaintext.iter().map(|r| machine.map_rune(*r)).collect();
+//!
+//! let known = KnownParts::new(rotators, reflector)
+//!     .rotor_order([2, 1, 0])
+//!     .plug_pairs(Vec::new());
+//! let keys = kpa::solve(&plaintext, &ciphertext, &known).unwrap();
+//! assert!(keys.iter().any(|k| k.positions == [5, 11, 23]));
+//! for key in &keys {
+//!     let mut machine = key.create_enigma().unwrap();
+//!     let decrypted: Vec<Rune> = ciphertext.iter().map(|r| machine.map_rune(*r)).collect();
+//!     assert_eq!(decrypted, plaintext);
+//! }
+//! ```
+//!
+//! [`KnownParts`]: struct.KnownParts.html
+//!
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::{Settings, SteppingMode};
+use crate::analysis::progress::Progress;
+use crate::analysis::states;
+use crate::analysis::stecker::{self, RuneTable, UNKNOWN};
+use crate::math::Permutation;
+use crate::settings::Keyspace;
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// Error indicating that the plaintext and the ciphertext have different lengths.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LengthMismatchError;
+
+impl Display for LengthMismatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the plaintext and the ciphertext have different lengths")
+    }
+}
+
+impl Error for LengthMismatchError { }
+
+/// The known parts of a key.
+#[derive(Clone, Debug)]
+pub struct KnownParts {
+    rotators: Vec<Permutation>,
+    notches: Vec<Vec<Rune>>,
+    stepping: SteppingMode,
+    reflector: Permutation,
+    rotor_order: Option<[usize; 3]>,
+    positions: Option<[u8; 3]>,
+    rings: Option<[u8; 3]>,
+    plug_pairs: Option<Vec<(Rune, Rune)>>,
+}
+
+impl KnownParts {
+    /// Create new known parts with the wirings of the available rotators and the reflector. The
+    /// available rotators have no notches and step with the odometer stepping mode. The rotor
+    /// order, the positions, the ring settings and the plug board are unknown.
+    pub fn new(rotators: Vec<Permutation>, reflector: Permutation) -> Self {
+        let notches = vec![Vec::new(); rotators.len()];
+        Self {
+            rotators,
+            notches,
+            stepping: SteppingMode::Odometer,
+            reflector,
+            rotor_order: None,
+            positions: None,
+            rings: None,
+            plug_pairs: None,
+        }
+    }
+
+    /// Set the notches of the available rotators, in the order of their wirings. A notch is given
+    /// by the letter shown in the window of its rotator when the rotator is at the notch, as in
+    /// `Settings`.
+    pub fn notches(mut self, notches: Vec<Vec<Rune>>) -> Self {
+        self.notches = notches;
+        self
+    }
+
+    /// Set the stepping mode of the rotators.
+    pub fn stepping(mut self, stepping: SteppingMode) -> Self {
+        self.stepping = stepping;
+        self
+    }
+
+    /// Set the known rotor order, given by the indices of the available rotators in the order of
+    /// the rotator group.
+    pub fn rotor_order(mut self, rotor_order: [usize; 3]) -> Self {
+        self.rotor_order = Some(rotor_order);
+        self
+    }
+
+    /// Set the known offsets of the 3 rotators.
+    pub fn positions(mut self, positions: [u8; 3]) -> Self {
+        self.positions = Some(positions);
+        self
+    }
+
+    /// Set the known ring settings of the 3 rotators.
+    pub fn rings(mut self, rings: [u8; 3]) -> Self {
+        self.rings = Some(rings);
+        self
+    }
+
+    /// Set the known plug pairs.
+    pub fn plug_pairs(mut self, plug_pairs: Vec<(Rune, Rune)>) -> Self {
+        self.plug_pairs = Some(plug_pairs);
+        self
+    }
+}
+
+/// Find all keys that encipher the specified plaintext into the specified ciphertext and agree
+/// with the known parts. The keys are ordered by the ranks of their rotor orders in the keyspace
+/// of the available rotators, then by their ring settings and their positions.
+///
+/// # Panics
+///
+/// This function panics if the known parts contain an invalid wiring, rotor order or plug board,
+/// or if the number of the notches differs from the number of the available rotators.
+pub fn solve(plaintext: &[Rune], ciphertext: &[Rune], known: &KnownParts)
+    -> Result<Vec<Settings>, LengthMismatchError> {
+    if plaintext.len() != ciphertext.len() {
+        return Err(LengthMismatchError);
+    }
+    assert_eq!(known.notches.len(), known.rotators.len(), "invalid notches");
+
+    let keyspace = Keyspace::new(known.rotators.len());
+    let orders = match known.rotor_order {
+        Some(order) => {
+            assert!(keyspace.rank(order, [0, 0, 0]).is_some(), "invalid rotor order");
+            vec![order]
+        },
+        None => keyspace.rotor_orders(),
+    };
+    let states: Vec<usize> = match known.positions {
+        Some(positions) => {
+            assert!(positions.iter().all(|x| *x < RUNE_SET_SIZE), "invalid positions");
+            vec![states::index(positions)]
+        },
+        None => (0..states::NUM_STATES).collect(),
+    };
+    let rings: Vec<[u8; 3]> = match (known.rings, known.stepping) {
+        (Some(rings), _) => vec![rings],
+        (None, SteppingMode::Odometer) => vec![[0, 0, 0]],
+        (None, SteppingMode::WehrmachtLever) => (0..RUNE_SET_SIZE)
+            .flat_map(|middle| (0..RUNE_SET_SIZE).map(move |right| [right, middle, 0]))
+            .collect(),
+    };
+    let known_plug = known.plug_pairs.as_ref().map(|pairs| {
+        let mut plug = [UNKNOWN; RUNE_SET_SIZE as usize];
+        for (a, b) in pairs {
+            assert!(stecker::connect(&mut plug, a.value(), b.value()), "invalid plug pairs");
+        }
+        for (i, x) in plug.iter_mut().enumerate() {
+            if *x == UNKNOWN {
+                *x = i as u8;
+            }
+        }
+        plug
+    });
+
+    let mut progress = Progress::start("kpa");
+    let mut keys = Vec::new();
+    for order in orders {
+        let mut settings = Settings {
+            rotator_wirings: [
+                known.rotators[order[0]].clone(),
+                known.rotators[order[1]].clone(),
+                known.rotators[order[2]].clone(),
+            ],
+            positions: [0, 0, 0],
+            rings: [0, 0, 0],
+            notches: order.map(|i| known.notches[i].clone()),
+            stepping: known.stepping,
+            reflector: known.reflector.clone(),
+            plug_pairs: Vec::new(),
+        };
+        let machine = settings.create_enigma().expect("invalid rotator or reflector wiring");
+
+        // The permutations realized at the states do not depend on the stepping mode, so they are
+        // listed in the order of the state indices by stepping like an odometer.
+        let scramblers = stecker::scrambler_tables(
+            &machine.clone().with_stepping_mode(SteppingMode::Odometer), states::NUM_STATES);
+
+        for ring_settings in &rings {
+            settings.rings = *ring_settings;
+            let mut successors = states::Successors::new(&settings.create_enigma().unwrap());
+
+            for state in &states {
+                let mut current = *state;
+                let mut state_scramblers: Vec<&RuneTable> = Vec::with_capacity(plaintext.len());
+                for _ in 0..plaintext.len() {
+                    state_scramblers.push(&scramblers[current]);
+                    current = successors.next(current);
+                }
+
+                let plugs = match &known_plug {
+                    Some(plug) => {
+                        if verify(plaintext, ciphertext, &state_scramblers, plug) {
+                            vec![*plug]
+                        } else {
+                            Vec::new()
+                        }
+                    },
+                    None => deduce_plugs(plaintext, ciphertext, &state_scramblers),
+                };
+
+                settings.positions = states::offsets(*state);
+                keys.extend(plugs.iter()
+                    .map(|plug| settings.clone().with_plug_pairs(stecker::plug_to_pairs(plug))));
+            }
+            progress.tested(states.len() as u64);
+        }
+    }
+    progress.finish();
+
+    Ok(keys)
+}
+
+/// Verify that the plaintext is enciphered into the ciphertext with the specified plug board.
+fn verify(plaintext: &[Rune], ciphertext: &[Rune], scramblers: &[&RuneTable], plug: &RuneTable)
+    -> bool {
+    plaintext.iter().zip(ciphertext).zip(scramblers)
+        .all(|((p, c), scrambler)| {
+            plug[scrambler[plug[p.value() as usize] as usize] as usize] == c.value()
+        })
+}
+
+/// Deduce all plug boards with which the plaintext is enciphered into the ciphertext. Letters not
+/// occurring in the texts are left unknown.
+fn deduce_plugs(plaintext: &[Rune], ciphertext: &[Rune], scramblers: &[&RuneTable])
+    -> Vec<RuneTable> {
+    let edges: Vec<(usize, usize, &RuneTable)> = plaintext.iter().zip(ciphertext).zip(scramblers)
+        .map(|((p, c), scrambler)| (p.value() as usize, c.value() as usize, *scrambler))
+        .collect();
+
+    let mut plugs = Vec::new();
+    branch([UNKNOWN; RUNE_SET_SIZE as usize], &edges, &mut plugs);
+    plugs
+}
+
+/// Extend the specified partial plug board until every letter of the edges has a partner, trying
+/// every partner of the first letter without one.
+fn branch(plug: RuneTable, edges: &[(usize, usize, &RuneTable)], plugs: &mut Vec<RuneTable>) {
+    let letter = match edges.iter().map(|(p, _, _)| *p).find(|p| plug[*p] == UNKNOWN) {
+        Some(letter) => letter as u8,
+        None => {
+            plugs.push(plug);
+            return;
+        },
+    };
+
+    for partner in 0..RUNE_SET_SIZE {
+        let mut trial = plug;
+        if stecker::connect(&mut trial, letter, partner) && stecker::propagate(&mut trial, edges) {
+            branch(trial, edges, plugs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wiring(s: &str) -> Permutation {
+        Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+    }
+
+    fn rune(ch: char) -> Rune {
+        Rune::from_char(ch).unwrap()
+    }
+
+    fn rotators() -> Vec<Permutation> {
+        vec![
+            wiring("ekmflgdqvzntowyhxuspaibrcj"),
+            wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+            wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+        ]
+    }
+
+    fn reflector() -> Permutation {
+        wiring("yruhqsldpxngokmiebfzcwvjat")
+    }
+
+    fn encrypt(settings: &Settings, plaintext: &str) -> (Vec<Rune>, Vec<Rune>) {
+        let plaintext: Vec<Rune> = plaintext.chars().map(rune).collect();
+        let mut machine = settings.create_enigma().unwrap();
+        let ciphertext = plaintext.iter().map(|r| machine.map_rune(*r)).collect();
+        (plaintext, ciphertext)
+    }
+
+    #[test]
+    fn test_solve_known_plug_board() {
+        let rotators = rotators();
+        let plug_pairs = vec![(rune('a'), rune('q')), (rune('e'), rune('n'))];
+        let settings = Settings {
+            rotator_wirings: [rotators[1].clone(), rotators[2].clone(), rotators[0].clone()],
+            positions: [7, 19, 3],
+            rings: [0, 0, 0],
+            notches: Default::default(),
+            stepping: SteppingMode::Odometer,
+            reflector: reflector(),
+            plug_pairs: plug_pairs.clone(),
+        };
+        let (plaintext, ciphertext) = encrypt(&settings, "weatherreportforthenorthsea");
+
+        let known = KnownParts::new(rotators, reflector()).plug_pairs(plug_pairs);
+        let keys = solve(&plaintext, &ciphertext, &known).unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].rotator_wirings, settings.rotator_wirings);
+        assert_eq!(keys[0].positions, [7, 19, 3]);
+        assert_eq!(keys[0].plug_pairs, settings.plug_pairs);
+    }
+
+    #[test]
+    fn test_solve_unknown_plug_board() {
+        let rotators = rotators();
+        let settings = Settings {
+            rotator_wirings: [rotators[2].clone(), rotators[0].clone(), rotators[1].clone()],
+            positions: [12, 4, 20],
+            rings: [0, 0, 0],
+            notches: Default::default(),
+            stepping: SteppingMode::Odometer,
+            reflector: reflector(),
+            plug_pairs: vec![
+                (rune('a'), rune('q')), (rune('e'), rune('n')), (rune('t'), rune('x')),
+                (rune('o'), rune('w')),
+            ],
+        };
+        let (plaintext, ciphertext) = encrypt(&settings,
+            "theconvoywillleavetheharbouratfirstlightandproceednorthalongthecoast");
+
+        let known = KnownParts::new(rotators, reflector()).rotor_order([2, 0, 1]);
+        let keys = solve(&plaintext, &ciphertext, &known).unwrap();
+        assert!(keys.iter().any(|key| key.positions == [12, 4, 20]));
+        for key in &keys {
+            let mut machine = key.create_enigma().unwrap();
+            let decrypted: Vec<Rune> = ciphertext.iter().map(|r| machine.map_rune(*r)).collect();
+            assert_eq!(decrypted, plaintext);
+        }
+    }
+
+    #[test]
+    fn test_solve_known_positions() {
+        let rotators = rotators();
+        let settings = Settings {
+            rotator_wirings: [rotators[0].clone(), rotators[1].clone(), rotators[2].clone()],
+            positions: [1, 2, 3],
+            rings: [0, 0, 0],
+            notches: Default::default(),
+            stepping: SteppingMode::Odometer,
+            reflector: reflector(),
+            plug_pairs: vec![(rune('b'), rune('z'))],
+        };
+        let (plaintext, ciphertext) = encrypt(&settings, "attackatdawnbythebridge");
+
+        let known = KnownParts::new(rotators, reflector())
+            .rotor_order([1, 0, 2])
+            .positions([1, 2, 3]);
+        assert!(solve(&plaintext, &ciphertext, &known).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_solve_lever_rings() {
+        // Rotors III, II and I with their historical notches.
+        let rotators = rotators();
+        let notches = vec![vec![rune('q')], vec![rune('e')], vec![rune('v')]];
+        let settings = Settings {
+            rotator_wirings: [rotators[2].clone(), rotators[1].clone(), rotators[0].clone()],
+            positions: [14, 12, 8],
+            rings: [4, 17, 9],
+            notches: [notches[2].clone(), notches[1].clone(), notches[0].clone()],
+            stepping: SteppingMode::WehrmachtLever,
+            reflector: reflector(),
+            plug_pairs: vec![(rune('a'), rune('q')), (rune('e'), rune('n'))],
+        };
+
+        // The first rotator steps the second one at the 4th key press, and the second rotator
+        // double steps at the 5th key press.
+        let (plaintext, ciphertext) = encrypt(&settings, "enemyquartersattheendofthevalley");
+
+        let known = KnownParts::new(rotators, reflector())
+            .notches(notches)
+            .stepping(SteppingMode::WehrmachtLever)
+            .rotor_order([2, 1, 0])
+            .positions([14, 12, 8]);
+        let keys = solve(&plaintext, &ciphertext, &known).unwrap();
+        let mut expected = settings.clone();
+        expected.rings[2] = 0;
+        assert!(keys.iter().any(|key| key.diff(&expected).is_empty()));
+        assert!(keys.iter().all(|key| key.rings == [4, 17, 0]));
+        for key in &keys {
+            let mut machine = key.create_enigma().unwrap();
+            let decrypted: Vec<Rune> = ciphertext.iter().map(|r| machine.map_rune(*r)).collect();
+            assert_eq!(decrypted, plaintext);
+        }
+
+        let keys = solve(&plaintext, &ciphertext, &known.rings([4, 17, 9])).unwrap();
+        assert!(keys.iter().any(|key| key.diff(&settings).is_empty()));
+    }
+
+    #[test]
+    fn test_solve_length_mismatch() {
+        let known = KnownParts::new(rotators(), reflector());
+        let plaintext = vec![rune('a'), rune('b')];
+        assert_eq!(solve(&plaintext, &plaintext[..1], &known).unwrap_err(), LengthMismatchError);
+    }
+}
@@ -0,0 +1,262 @@
+//! This module implements heuristics exploiting the laziness of Enigma operators, which reduce the
+//! settings to be tried by the other attacks to a small set of candidates.
+//!
+//! Two patterns are detected from the settings chosen by the operators of a day, e.g. the
+//! indicator settings sent in the clear:
+//!
+//! - The Herivel tip: after setting up the machine for the day, an operator often left the rotators
+//!   close to where they were when the machine was set up, and chose the first indicator setting
+//!   by turning each rotator a few steps only. The first indicator settings of different operators
+//!   therefore cluster around the setting of the day. The `herivel_clusters` function finds such
+//!   clusters, as John Herivel did with his square.
+//! - Cillies: operators tend to choose settings that are easy to type, such as repeated letters
+//!   (`AAA`), consecutive letters of the alphabet (`ABC`) or adjacent keys on the keyboard
+//!   (`QWE`). The `classify` function detects such settings, and the `lazy_settings` function
+//!   enumerates them.
+//!
+//! The `candidates` function combines the two heuristics. Settings are represented by the offsets
+//! of the 3 rotators, in the same order as the `positions` of [`Settings`] and the `offsets` of
+//! the catalog entries of the [`rejewski`] module, so that the candidates can be fed to the other
+//! attacks directly.
+//!
+//! ```
+//! # use enigma::analysis::laziness::{self, Laziness};
+//! #
+//! assert_eq!(laziness::classify([16, 22, 4]), Some(Laziness::Keyboard));
+//! assert_eq!(laziness::classify([0, 0, 0]), Some(Laziness::Repeated));
+//! assert_eq!(laziness::classify([3, 17, 9]), None);
+//! ```
+//!
+//! [`Settings`]: ../../settings/struct.Settings.html
+//! [`rejewski`]: ../rejewski/index.html
+//!
+
+use crate::utils::RUNE_SET_SIZE;
+
+/// The rows and columns of the German QWERTZ keyboard of the Enigma machine.
+const KEYBOARD_LINES: [&str; 10] = [
+    "qwertzuio", "asdfghjk", "pyxcvbnml",
+    "qap", "wsy", "edx", "rfc", "tgv", "zhb", "ujn",
+];
+
+/// A cluster of settings found by the Herivel tip.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cluster {
+    /// The offsets of the 3 rotators at the center of the cluster, e.g. the setting from which the
+    /// clustered settings were reached.
+    pub center: [u8; 3],
+
+    /// The number of settings within the cluster.
+    pub members: usize,
+}
+
+/// A pattern of lazily chosen settings.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Laziness {
+    /// The same letter repeated, such as `AAA`.
+    Repeated,
+
+    /// Consecutive letters of the alphabet, forwards or backwards, such as `ABC`.
+    Alphabetic,
+
+    /// Adjacent keys on the keyboard, forwards or backwards, such as `QWE`.
+    Keyboard,
+}
+
+/// Find the clusters of the specified settings, the biggest first.
+///
+/// A setting belongs to the cluster around `center` if each of its offsets can be reached from
+/// the offset of `center` by turning the rotator forward at most `radius` steps. Only clusters of
+/// at least 2 settings are returned. Clusters of the same size are ordered by their centers.
+pub fn herivel_clusters(settings: &[[u8; 3]], radius: u8) -> Vec<Cluster> {
+    let n = RUNE_SET_SIZE;
+    let mut clusters = Vec::new();
+
+    for state in 0..(n as usize).pow(3) {
+        let center = [
+            (state / (n as usize).pow(2)) as u8,
+            (state / n as usize % n as usize) as u8,
+            (state % n as usize) as u8,
+        ];
+        let members = settings.iter()
+            .filter(|setting| {
+                setting.iter().zip(&center).all(|(x, c)| (x + n - c) % n <= radius)
+            })
+            .count();
+
+        if members >= 2 {
+            clusters.push(Cluster { center, members });
+        }
+    }
+
+    clusters.sort_by(|lhs, rhs| rhs.members.cmp(&lhs.members).then(lhs.center.cmp(&rhs.center)));
+    clusters
+}
+
+/// Detect whether the specified setting is lazily chosen. A setting matching several patterns is
+/// classified by the first one in the order of declaration of `Laziness`.
+pub fn classify(setting: [u8; 3]) -> Option<Laziness> {
+    let [a, b, c] = setting;
+    if a == b && b == c {
+        return Some(Laziness::Repeated);
+    }
+
+    // Consecutive letters may wrap around the end of the alphabet, such as `YZA`.
+    let n = RUNE_SET_SIZE;
+    let forwards = (b + n - a) % n == 1 && (c + n - b) % n == 1;
+    let backwards = (a + n - b) % n == 1 && (b + n - c) % n == 1;
+    if forwards || backwards {
+        return Some(Laziness::Alphabetic);
+    }
+
+    if keyboard_sequences().contains(&setting) {
+        return Some(Laziness::Keyboard);
+    }
+
+    None
+}
+
+/// Enumerate the settings of the specified pattern.
+pub fn lazy_settings(laziness: Laziness) -> Vec<[u8; 3]> {
+    let n = RUNE_SET_SIZE;
+    match laziness {
+        Laziness::Repeated => (0..n).map(|x| [x, x, x]).collect(),
+        Laziness::Alphabetic => (0..n)
+            .flat_map(|x| vec![[x, (x + 1) % n, (x + 2) % n], [(x + 2) % n, (x + 1) % n, x]])
+            .collect(),
+        Laziness::Keyboard => keyboard_sequences(),
+    }
+}
+
+/// Get the candidate settings suggested by the specified settings of a day.
+///
+/// The centers of the biggest Herivel clusters come first. If any of the settings is lazily
+/// chosen, the lazy settings of the detected patterns follow, those seen among the specified
+/// settings first.
+pub fn candidates(settings: &[[u8; 3]], radius: u8) -> Vec<[u8; 3]> {
+    let mut candidates = Vec::new();
+
+    let clusters = herivel_clusters(settings, radius);
+    if let Some(biggest) = clusters.first() {
+        candidates.extend(clusters.iter()
+            .take_while(|cluster| cluster.members == biggest.members)
+            .map(|cluster| cluster.center));
+    }
+
+    let mut patterns = Vec::new();
+    for setting in settings {
+        if let Some(laziness) = classify(*setting) {
+            if !candidates.contains(setting) {
+                candidates.push(*setting);
+            }
+            if !patterns.contains(&laziness) {
+                patterns.push(laziness);
+            }
+        }
+    }
+
+    for laziness in patterns {
+        for setting in lazy_settings(laziness) {
+            if !candidates.contains(&setting) {
+                candidates.push(setting);
+            }
+        }
+    }
+
+    candidates
+}
+
+fn keyboard_sequences() -> Vec<[u8; 3]> {
+    let mut sequences = Vec::new();
+    for line in &KEYBOARD_LINES {
+        let keys: Vec<u8> = line.bytes().map(|b| b - b'a').collect();
+        for window in keys.windows(3) {
+            sequences.push([window[0], window[1], window[2]]);
+            sequences.push([window[2], window[1], window[0]]);
+        }
+    }
+    sequences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setting(s: &str) -> [u8; 3] {
+        let bytes = s.as_bytes();
+        [bytes[0] - b'a', bytes[1] - b'a', bytes[2] - b'a']
+    }
+
+    mod herivel_tests {
+        use super::*;
+
+        #[test]
+        fn test_herivel_clusters() {
+            let settings = [
+                setting("dhk"), setting("fhl"), setting("eim"), setting("dkk"),
+                setting("qzr"), setting("auc"),
+            ];
+            let clusters = herivel_clusters(&settings, 3);
+
+            let biggest = clusters[0];
+            assert_eq!(biggest.members, 4);
+            assert_eq!(biggest.center, setting("chj"));
+            assert!(clusters.iter().all(|cluster| cluster.members <= 4));
+        }
+
+        #[test]
+        fn test_herivel_clusters_wrap() {
+            let settings = [setting("zza"), setting("aaz")];
+            let clusters = herivel_clusters(&settings, 1);
+            assert_eq!(clusters, vec![Cluster { center: setting("zzz"), members: 2 }]);
+        }
+
+        #[test]
+        fn test_herivel_clusters_none() {
+            let settings = [setting("abc"), setting("nop")];
+            assert!(herivel_clusters(&settings, 2).is_empty());
+        }
+    }
+
+    mod cillies_tests {
+        use super::*;
+
+        #[test]
+        fn test_classify() {
+            assert_eq!(classify(setting("kkk")), Some(Laziness::Repeated));
+            assert_eq!(classify(setting("abc")), Some(Laziness::Alphabetic));
+            assert_eq!(classify(setting("zyx")), Some(Laziness::Alphabetic));
+            assert_eq!(classify(setting("yza")), Some(Laziness::Alphabetic));
+            assert_eq!(classify(setting("qwe")), Some(Laziness::Keyboard));
+            assert_eq!(classify(setting("mnb")), Some(Laziness::Keyboard));
+            assert_eq!(classify(setting("wsy")), Some(Laziness::Keyboard));
+            assert_eq!(classify(setting("qwz")), None);
+        }
+
+        #[test]
+        fn test_lazy_settings() {
+            assert_eq!(lazy_settings(Laziness::Repeated).len(), 26);
+            assert_eq!(lazy_settings(Laziness::Alphabetic).len(), 52);
+            for laziness in &[Laziness::Repeated, Laziness::Alphabetic] {
+                assert!(lazy_settings(*laziness).iter().all(|s| classify(*s) == Some(*laziness)));
+            }
+            // Some keyboard sequences, such as `FGH`, are also consecutive letters of the alphabet.
+            assert!(lazy_settings(Laziness::Keyboard).iter().all(|s| classify(*s).is_some()));
+        }
+    }
+
+    #[test]
+    fn test_candidates() {
+        let settings = [
+            setting("dhk"), setting("fhl"), setting("eim"), setting("asd"), setting("pqs"),
+        ];
+        let candidates = candidates(&settings, 3);
+
+        assert!(candidates.contains(&setting("chj")));
+        assert!(candidates.contains(&setting("asd")));
+        assert!(candidates.contains(&setting("qwe")));
+        assert!(!candidates.contains(&setting("aaa")));
+        assert!(!candidates.contains(&setting("xyz")));
+        assert!(candidates.len() < 100);
+    }
+}
@@ -0,0 +1,349 @@
+//! This module implements the construction of crib menus.
+//!
+//! A menu is built from a crib, e.g. a guessed piece of plaintext, and the ciphertext at the
+//! position where the crib is assumed to be. Each pair of crib letter and ciphertext letter forms
+//! an edge between the two letters, labeled with the position of the pair. The menu is the graph
+//! formed by these edges. It is used to wire up the Bombe.
+//!
+//! The strength of a menu mostly depends on the number of closures, e.g. independent loops, within
+//! the graph: each closure allows the Bombe to reject wrong hypotheses by itself. The
+//! [`Menu`] type provides the `closures` associate function to count them and the `loops`
+//! associate function to list them:
+//!
+//! ```
+//! # use enigma::Rune;
+//! # use enigma::analysis::menu;
+//! #
+//! let crib: Vec<Rune> = "wetter".chars().map(|ch| Rune::from_char(ch).unwrap()).collect();
+//! let ciphertext: Vec<Rune> = "ewrwtz".chars().map(|ch| Rune::from_char(ch).unwrap()).collect();
+//! let menu = menu::build(&crib, &ciphertext).unwrap();
+//! assert_eq!(menu.closures(), 2);
+//! ```
+//!
+//! Since the Enigma machine never enciphers a letter to itself, a crib cannot be placed at a
+//! position where one of its letters equals the ciphertext letter below it. The `slide` function
+//! slides a crib along a ciphertext and yields the menus at every possible position; see the
+//! [`crib`] module for more crib dragging utilities.
+//!
+//! [`Menu`]: struct.Menu.html
+//! [`crib`]: ../crib/index.html
+//!
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::analysis::crib;
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// Error indicating that a menu cannot be built from a crib and a ciphertext.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MenuError {
+    /// The crib and the ciphertext have different lengths.
+    LengthMismatch,
+
+    /// A crib letter equals the ciphertext letter at the same position, which cannot be produced
+    /// by an Enigma machine.
+    SelfEncryption {
+        /// The position of the letter within the crib.
+        position: usize,
+    },
+}
+
+impl Display for MenuError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MenuError::LengthMismatch => f.write_str("crib and ciphertext lengths differ"),
+            MenuError::SelfEncryption { position } =>
+                write!(f, "crib letter at position {} would encipher to itself", position),
+        }
+    }
+}
+
+impl Error for MenuError { }
+
+/// An edge within a menu.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MenuEdge {
+    /// The position of the letter pair, relative to the first letter of the crib.
+    pub position: usize,
+
+    /// The crib letter.
+    pub plain: Rune,
+
+    /// The ciphertext letter.
+    pub cipher: Rune,
+}
+
+/// A crib menu.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Menu {
+    edges: Vec<MenuEdge>,
+}
+
+impl Menu {
+    /// Get all edges within the menu, ordered by their positions.
+    pub fn edges(&self) -> &[MenuEdge] {
+        &self.edges
+    }
+
+    /// Get the number of edges within the menu, which equals the length of the crib.
+    pub fn len(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Determine whether the menu has no edges.
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+
+    /// Get the distinct letters within the menu, in alphabetical order.
+    pub fn letters(&self) -> Vec<Rune> {
+        let degrees = self.degrees();
+        Rune::ALPHABET.iter()
+            .copied()
+            .filter(|rune| degrees[rune.value() as usize] > 0)
+            .collect()
+    }
+
+    /// Get the letter with the most edges, which is the best choice of the test letter of the
+    /// Bombe. Returns `None` if the menu is empty.
+    pub fn most_connected_letter(&self) -> Option<Rune> {
+        let degrees = self.degrees();
+        let (value, degree) = degrees.iter().enumerate().max_by_key(|(_, degree)| **degree)?;
+        if *degree == 0 {
+            return None;
+        }
+
+        Some(Rune::from_value(value as u8).unwrap())
+    }
+
+    /// Count the closures within the menu, e.g. the number of independent loops. This is the
+    /// cyclomatic number of the letter graph.
+    pub fn closures(&self) -> usize {
+        let num_letters = self.letters().len();
+        let num_components = self.components().len();
+        self.edges.len() + num_components - num_letters
+    }
+
+    /// Find a set of independent loops within the menu. Each loop is given as the indices of its
+    /// edges within `edges()`, in the order they are walked. The number of loops equals
+    /// `closures()`.
+    pub fn loops(&self) -> Vec<Vec<usize>> {
+        // Build a spanning forest with breadth first search. Each edge outside of the forest
+        // closes exactly one loop with the path between its ends in the forest.
+        let n = RUNE_SET_SIZE as usize;
+        let mut parent_edge: Vec<Option<usize>> = vec![None; n];
+        let mut depth: Vec<Option<usize>> = vec![None; n];
+        let mut tree_edges = vec![false; self.edges.len()];
+
+        for root in self.letters() {
+            let root = root.value() as usize;
+            if depth[root].is_some() {
+                continue;
+            }
+
+            depth[root] = Some(0);
+            let mut queue = VecDeque::new();
+            queue.push_back(root);
+            while let Some(letter) = queue.pop_front() {
+                for (i, edge) in self.edges.iter().enumerate() {
+                    let other = match other_end(edge, letter) {
+                        Some(other) => other,
+                        None => continue,
+                    };
+                    if depth[other].is_none() {
+                        depth[other] = Some(depth[letter].unwrap() + 1);
+                        parent_edge[other] = Some(i);
+                        tree_edges[i] = true;
+                        queue.push_back(other);
+                    }
+                }
+            }
+        }
+
+        let mut loops = Vec::new();
+        for (i, edge) in self.edges.iter().enumerate() {
+            if tree_edges[i] {
+                continue;
+            }
+
+            // Walk up from both ends of the edge until the paths meet.
+            let mut lhs = edge.plain.value() as usize;
+            let mut rhs = edge.cipher.value() as usize;
+            let mut lhs_path = Vec::new();
+            let mut rhs_path = Vec::new();
+            while lhs != rhs {
+                if depth[lhs] >= depth[rhs] {
+                    let e = parent_edge[lhs].unwrap();
+                    lhs_path.push(e);
+                    lhs = other_end(&self.edges[e], lhs).unwrap();
+                } else {
+                    let e = parent_edge[rhs].unwrap();
+                    rhs_path.push(e);
+                    rhs = other_end(&self.edges[e], rhs).unwrap();
+                }
+            }
+
+            let mut walk = vec![i];
+            walk.extend(rhs_path);
+            walk.extend(lhs_path.into_iter().rev());
+            loops.push(walk);
+        }
+
+        loops
+    }
+
+    /// Get the connected components of the letter graph. Each component is given as its letters
+    /// in alphabetical order.
+    pub fn components(&self) -> Vec<Vec<Rune>> {
+        let n = RUNE_SET_SIZE as usize;
+        let mut component: Vec<Option<usize>> = vec![None; n];
+        let mut components: Vec<Vec<Rune>> = Vec::new();
+
+        for root in self.letters() {
+            if component[root.value() as usize].is_some() {
+                continue;
+            }
+
+            let id = components.len();
+            let mut stack = vec![root.value() as usize];
+            component[root.value() as usize] = Some(id);
+            while let Some(letter) = stack.pop() {
+                for edge in &self.edges {
+                    if let Some(other) = other_end(edge, letter) {
+                        if component[other].is_none() {
+                            component[other] = Some(id);
+                            stack.push(other);
+                        }
+                    }
+                }
+            }
+
+            components.push((0..n)
+                .filter(|i| component[*i] == Some(id))
+                .map(|i| Rune::from_value(i as u8).unwrap())
+                .collect());
+        }
+
+        components
+    }
+
+    fn degrees(&self) -> [usize; RUNE_SET_SIZE as usize] {
+        let mut degrees = [0usize; RUNE_SET_SIZE as usize];
+        for edge in &self.edges {
+            degrees[edge.plain.value() as usize] += 1;
+            degrees[edge.cipher.value() as usize] += 1;
+        }
+        degrees
+    }
+}
+
+/// Get the other end of the specified edge, if the specified letter is one of its ends.
+fn other_end(edge: &MenuEdge, letter: usize) -> Option<usize> {
+    if edge.plain.value() as usize == letter {
+        Some(edge.cipher.value() as usize)
+    } else if edge.cipher.value() as usize == letter {
+        Some(edge.plain.value() as usize)
+    } else {
+        None
+    }
+}
+
+/// Build the menu of the specified crib and the ciphertext below it.
+pub fn build(crib: &[Rune], ciphertext_window: &[Rune]) -> Result<Menu, MenuError> {
+    if crib.len() != ciphertext_window.len() {
+        return Err(MenuError::LengthMismatch);
+    }
+
+    let mut edges = Vec::with_capacity(crib.len());
+    for (position, (plain, cipher)) in crib.iter().zip(ciphertext_window).enumerate() {
+        if plain == cipher {
+            return Err(MenuError::SelfEncryption { position });
+        }
+        edges.push(MenuEdge { position, plain: *plain, cipher: *cipher });
+    }
+
+    Ok(Menu { edges })
+}
+
+/// Slide the specified crib along the ciphertext and yield the offset of the crib within the
+/// ciphertext and the menu at every position where no crib letter would encipher to itself.
+pub fn slide<'a>(crib: &'a [Rune], ciphertext: &'a [Rune])
+    -> impl Iterator<Item = (usize, Menu)> + 'a {
+    crib::menus(crib, ciphertext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_runes(s: &str) -> Vec<Rune> {
+        s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_build_valid() {
+        let menu = build(&to_runes("abc"), &to_runes("bcd")).unwrap();
+        assert_eq!(menu.len(), 3);
+        assert_eq!(menu.edges()[1], MenuEdge {
+            position: 1,
+            plain: Rune::from_char('b').unwrap(),
+            cipher: Rune::from_char('c').unwrap(),
+        });
+    }
+
+    #[test]
+    fn test_build_invalid() {
+        assert_eq!(build(&to_runes("abc"), &to_runes("bc")), Err(MenuError::LengthMismatch));
+        assert_eq!(build(&to_runes("abc"), &to_runes("bbd")),
+            Err(MenuError::SelfEncryption { position: 1 }));
+    }
+
+    #[test]
+    fn test_letters() {
+        let menu = build(&to_runes("ab"), &to_runes("za")).unwrap();
+        assert_eq!(menu.letters(), to_runes("abz"));
+    }
+
+    #[test]
+    fn test_most_connected_letter() {
+        let menu = build(&to_runes("wetter"), &to_runes("ewrwtz")).unwrap();
+        assert_eq!(menu.most_connected_letter().unwrap(), 'w');
+        assert!(build(&[], &[]).unwrap().most_connected_letter().is_none());
+    }
+
+    #[test]
+    fn test_closures_and_loops() {
+        // Edges: W-E, E-W, T-R, T-W, E-T, R-Z. Loops: W-E-W and W-E-T-W.
+        let menu = build(&to_runes("wetter"), &to_runes("ewrwtz")).unwrap();
+        assert_eq!(menu.closures(), 2);
+
+        let loops = menu.loops();
+        assert_eq!(loops.len(), 2);
+        for walk in &loops {
+            // Each loop should start and end at the same letter.
+            let mut counts = [0usize; RUNE_SET_SIZE as usize];
+            for i in walk {
+                counts[menu.edges()[*i].plain.value() as usize] += 1;
+                counts[menu.edges()[*i].cipher.value() as usize] += 1;
+            }
+            assert!(counts.iter().all(|c| c % 2 == 0));
+        }
+    }
+
+    #[test]
+    fn test_components() {
+        let menu = build(&to_runes("abxy"), &to_runes("bcyz")).unwrap();
+        assert_eq!(menu.components(), vec![to_runes("abc"), to_runes("xyz")]);
+        assert_eq!(menu.closures(), 0);
+    }
+
+    #[test]
+    fn test_slide() {
+        let offsets: Vec<usize> = slide(&to_runes("ab"), &to_runes("abcab"))
+            .map(|(offset, _)| offset)
+            .collect();
+        assert_eq!(offsets, vec![1, 2]);
+    }
+}
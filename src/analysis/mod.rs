@@ -0,0 +1,58 @@
+//! This module implements cryptanalysis procedures against the Enigma machine.
+//!
+//! The following attacks are available:
+//! - Marian Rejewski's characteristic method, in the [`rejewski`] submodule;
+//! - Marian Rejewski's recovery of unknown rotor wirings, in the [`wiring`] submodule;
+//! - Banburismus, in the [`banburismus`] submodule;
+//! - The Turing-Welchman Bombe, in the [`bombe`] submodule, wired up with crib menus built by the
+//!   [`menu`] submodule at the crib positions found by the [`crib`] submodule;
+//! - James Gillogly's ciphertext-only hill-climbing attack, in the [`hillclimb`] submodule;
+//! - The recovery of the plug board once the rotator settings are known, in the [`stecker`]
+//!   submodule;
+//! - The exact recovery of every key consistent with a known plaintext, in the [`kpa`] submodule.
+//!
+//! Statistical attacks score candidate decryptions with the [`fitness`] submodule, using n-gram
+//! statistics of any language built by the [`corpus`] submodule. Brute-force searches can be
+//! driven by the [`search`] submodule, which stops as soon as a decryption looks like natural
+//! language. The search space of the attacks can be reduced by exploiting the laziness of
+//! operators with the [`laziness`] submodule. The [`stats`] submodule quantifies how far a
+//! ciphertext deviates from uniformly random text, and the [`experiments`] submodule measures how
+//! much ciphertext the hill-climbing attack needs with Monte Carlo experiments.
+//!
+//! With the `tracing` feature, the search loops of the attacks report their progress, e.g. the
+//! number of keys tested per second, as `tracing` events.
+//!
+//! [`rejewski`]: rejewski/index.html
+//! [`banburismus`]: banburismus/index.html
+//! [`bombe`]: bombe/index.html
+//! [`corpus`]: corpus/index.html
+//! [`crib`]: crib/index.html
+//! [`experiments`]: experiments/index.html
+//! [`menu`]: menu/index.html
+//! [`fitness`]: fitness/index.html
+//! [`hillclimb`]: hillclimb/index.html
+//! [`kpa`]: kpa/index.html
+//! [`laziness`]: laziness/index.html
+//! [`search`]: search/index.html
+//! [`stats`]: stats/index.html
+//! [`stecker`]: stecker/index.html
+//! [`wiring`]: wiring/index.html
+//!
+
+pub mod banburismus;
+pub mod bombe;
+pub mod corpus;
+pub mod crib;
+pub mod experiments;
+pub mod fitness;
+pub mod hillclimb;
+pub mod kpa;
+pub mod laziness;
+pub mod menu;
+mod progress;
+pub mod rejewski;
+pub mod search;
+mod states;
+pub mod stats;
+pub mod stecker;
+pub mod wiring;
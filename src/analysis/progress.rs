@@ -0,0 +1,144 @@
+//! This module reports the progress of the search loops of the attacks as `tracing` events, so
+//! that long-running jobs can be observed with standard tooling. Without the `tracing` feature,
+//! the reports compile to nothing.
+//!
+//! A search emits an `INFO` event when it starts and when it finishes, and `DEBUG` events with the
+//! number of keys tested so far and the rate at most once per second in between. All events are
+//! emitted within a span named after the search.
+//!
+
+#[cfg(feature = "tracing")]
+use std::time::{Duration, Instant};
+
+/// The minimal interval between two progress reports.
+#[cfg(feature = "tracing")]
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The progress of a search loop.
+#[cfg(feature = "tracing")]
+pub(crate) struct Progress {
+    span: tracing::span::EnteredSpan,
+    start: Instant,
+    last_report: Instant,
+    tested: u64,
+}
+
+#[cfg(feature = "tracing")]
+impl Progress {
+    /// Start reporting the progress of the specified search.
+    pub(crate) fn start(search: &'static str) -> Self {
+        let span = tracing::info_span!("search", name = search).entered();
+        tracing::info!("search started");
+
+        let now = Instant::now();
+        Self { span, start: now, last_report: now, tested: 0 }
+    }
+
+    /// Record that the specified number of keys have been tested.
+    pub(crate) fn tested(&mut self, keys: u64) {
+        self.tested += keys;
+
+        let now = Instant::now();
+        if now.duration_since(self.last_report) >= REPORT_INTERVAL {
+            self.last_report = now;
+            tracing::debug!(keys_tested = self.tested, keys_per_sec = self.rate(), "progress");
+        }
+    }
+
+    /// Finish the search.
+    pub(crate) fn finish(self) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        tracing::info!(keys_tested = self.tested, keys_per_sec = self.rate(),
+            elapsed_secs = elapsed, "search finished");
+        drop(self.span);
+    }
+
+    fn rate(&self) -> f64 {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        if elapsed > 0.0 { self.tested as f64 / elapsed } else { 0.0 }
+    }
+}
+
+/// The progress of a search loop.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct Progress;
+
+#[cfg(not(feature = "tracing"))]
+impl Progress {
+    /// Start reporting the progress of the specified search.
+    #[inline]
+    pub(crate) fn start(_search: &'static str) -> Self {
+        Self
+    }
+
+    /// Record that the specified number of keys have been tested.
+    #[inline]
+    pub(crate) fn tested(&mut self, _keys: u64) { }
+
+    /// Finish the search.
+    #[inline]
+    pub(crate) fn finish(self) { }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{Event, Metadata};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::subscriber::Subscriber;
+
+    /// A subscriber that records the messages of all events.
+    struct Recorder {
+        messages: Arc<Mutex<Vec<String>>>,
+    }
+
+    struct MessageVisitor<'a>(&'a mut String);
+
+    impl tracing::field::Visit for MessageVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                *self.0 = format!("{:?}", value);
+            }
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _span: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) { }
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) { }
+
+        fn event(&self, event: &Event<'_>) {
+            let mut message = String::new();
+            event.record(&mut MessageVisitor(&mut message));
+            self.messages.lock().unwrap().push(message);
+        }
+
+        fn enter(&self, _span: &Id) { }
+
+        fn exit(&self, _span: &Id) { }
+    }
+
+    #[test]
+    fn test_progress() {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Recorder { messages: messages.clone() };
+        tracing::subscriber::with_default(recorder, || {
+            let mut progress = Progress::start("test");
+            progress.tested(10);
+            progress.finish();
+        });
+
+        assert_eq!(*messages.lock().unwrap(), vec!["search started", "search finished"]);
+    }
+}
@@ -0,0 +1,589 @@
+//! This module implements Marian Rejewski's characteristic method for recovering the ground
+//! setting of the rotators.
+//!
+//! Before 1938, each message sent with the Enigma machine began with an indicator: the operator
+//! set the rotators to the ground setting of the day and enciphered the message key twice, giving
+//! 6 letters. Let `A1, A2, ..., A6` be the rune permutations realized by the machine at these 6
+//! key presses. Since the first and the fourth letters of an indicator encipher the same letter of
+//! the message key, the product `A1A4` maps the first letter of every indicator to its fourth
+//! letter. The same applies to `A2A5` and `A3A6`. With enough indicators of a day, the three
+//! products can be fully recovered. They are called the *characteristic* of the day.
+//!
+//! The plug board only conjugates the three products, thus it does not change their cycle types.
+//! Therefore the cycle types of the characteristic only depend on the rotators, the reflector and
+//! the ground setting. By cataloging the cycle types produced by every ground setting in advance,
+//! the ground setting of a day can be narrowed down to a few candidates.
+//!
+//! The characteristic of a day is represented by the [`Characteristic`] type and the catalog is
+//! represented by the [`Catalog`] type:
+//!
+//! ```
+//! # use enigma::{Enigma, PlugBoard, Reflector, Rotator, RotatorGroup, Rune};
+//! # use enigma::analysis::rejewski::{Catalog, Characteristic, Indicator};
+//! # use enigma::math::Permutation;
+//! # use enigma::utils::RUNE_SET_SIZE;
+//! #
+//! # let create_rotator = |wiring: &str| {
+//! #     let perm = wiring.bytes().map(|b| b - b'a').collect();
+//! #     Rotator::new(Permutation::from_perm(perm).unwrap(), 0).unwrap()
+//! # };
+//! # let rotators = RotatorGroup::new([
+//! #     create_rotator("rcpdnugiozlmhetwsjxykvfqab"),
+//! #     create_rotator("uzqhaybgotivrknxjcwmdpsefl"),
+//! #     create_rotator("itaulnzmbeovckrhyxqdwgfsjp"),
+//! # ]);
+//! # let reflector = Reflector::from_perm(Permutation::first_with_cycle_type(&[2; 13]).unwrap())
+//! #     .unwrap();
+//! # let plug_board = PlugBoard::from_perm(Permutation::identity(RUNE_SET_SIZE)).unwrap();
+//! let machine: Enigma = // ...
+//! #     Enigma::new(plug_board, rotators, reflector);
+//! let catalog = Catalog::build(&machine);
+//!
+//! // Pretend that the day's ground setting is the initial state of `machine`.
+//! let characteristic = Characteristic::from_machine(&machine);
+//! let candidates = catalog.find(&characteristic);
+//! assert!(candidates.iter().any(|entry| entry.offsets() == [0, 0, 0]));
+//! ```
+//!
+//! In practice, the characteristic is recovered from the intercepted indicators with the
+//! `Characteristic::from_indicators` function.
+//!
+//! [`Characteristic`]: struct.Characteristic.html
+//! [`Catalog`]: struct.Catalog.html
+//!
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::Enigma;
+use crate::analysis::progress::Progress;
+use crate::analysis::states::{self, NUM_STATES};
+use crate::math::Permutation;
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// The number of runes within an indicator.
+pub const INDICATOR_LEN: usize = 6;
+
+/// Error indicating that an indicator is malformed.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidIndicatorError;
+
+impl Display for InvalidIndicatorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("indicator should consist of exactly 6 English letters")
+    }
+}
+
+impl Error for InvalidIndicatorError { }
+
+/// Error indicating that a characteristic cannot be recovered from a set of indicators.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CharacteristicError {
+    /// Two indicators disagree on the image of a rune under one of the products.
+    Conflict {
+        /// The index of the product, e.g. `0` for `A1A4`.
+        product: usize,
+
+        /// The rune whose image is in conflict.
+        rune: Rune,
+    },
+
+    /// The indicators do not determine the image of every rune under one of the products.
+    Incomplete {
+        /// The index of the product, e.g. `0` for `A1A4`.
+        product: usize,
+    },
+}
+
+impl Display for CharacteristicError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CharacteristicError::Conflict { product, rune } =>
+                write!(f, "indicators disagree on the image of {} under A{}A{}",
+                    rune, product + 1, product + 4),
+            CharacteristicError::Incomplete { product } =>
+                write!(f, "too few indicators to determine A{}A{}", product + 1, product + 4),
+        }
+    }
+}
+
+impl Error for CharacteristicError { }
+
+/// A message indicator, e.g. the message key enciphered twice at the ground setting.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Indicator {
+    runes: [Rune; INDICATOR_LEN],
+}
+
+impl Indicator {
+    /// Create a new indicator from the specified runes.
+    pub fn new(runes: [Rune; INDICATOR_LEN]) -> Self {
+        Self { runes }
+    }
+
+    /// Get the runes within this indicator.
+    pub fn runes(&self) -> &[Rune; INDICATOR_LEN] {
+        &self.runes
+    }
+}
+
+impl FromStr for Indicator {
+    type Err = InvalidIndicatorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut runes = [Rune::from_value(0).unwrap(); INDICATOR_LEN];
+        let mut len = 0usize;
+        for ch in s.trim().chars() {
+            if len == INDICATOR_LEN {
+                return Err(InvalidIndicatorError);
+            }
+            runes[len] = Rune::from_char(ch).map_err(|_| InvalidIndicatorError)?;
+            len += 1;
+        }
+
+        if len != INDICATOR_LEN {
+            return Err(InvalidIndicatorError);
+        }
+
+        Ok(Self { runes })
+    }
+}
+
+/// The characteristic of a day, e.g. the three products `A1A4`, `A2A5` and `A3A6`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Characteristic {
+    products: [Permutation; 3],
+}
+
+impl Characteristic {
+    /// Create a characteristic from the three products `A1A4`, `A2A5` and `A3A6`.
+    pub fn new(products: [Permutation; 3]) -> Self {
+        Self { products }
+    }
+
+    /// Recover the characteristic from the specified indicators, which are all enciphered at the
+    /// same ground setting.
+    pub fn from_indicators(indicators: &[Indicator]) -> Result<Self, CharacteristicError> {
+        let mut images = [[None; RUNE_SET_SIZE as usize]; 3];
+
+        for indicator in indicators {
+            for (product, product_images) in images.iter_mut().enumerate() {
+                let input = indicator.runes[product];
+                let output = indicator.runes[product + 3];
+
+                let image = &mut product_images[input.value() as usize];
+                match image {
+                    Some(existing) if *existing != output =>
+                        return Err(CharacteristicError::Conflict { product, rune: input }),
+                    _ => *image = Some(output),
+                }
+            }
+        }
+
+        let mut products = Vec::with_capacity(3);
+        for (product, product_images) in images.iter().enumerate() {
+            let perm: Option<Vec<u8>> = product_images.iter()
+                .map(|image| image.map(|rune| rune.value()))
+                .collect();
+            let perm = perm.ok_or(CharacteristicError::Incomplete { product })?;
+
+            // Two different runes mapping to the same image cannot be produced by a real machine.
+            let perm = Permutation::from_perm(perm).map_err(|_| {
+                let rune = Rune::from_value(product_images.iter()
+                    .position(|image| {
+                        product_images.iter().filter(|other| *other == image).count() > 1
+                    })
+                    .unwrap() as u8).unwrap();
+                CharacteristicError::Conflict { product, rune }
+            })?;
+            products.push(perm);
+        }
+
+        let mut products = products.into_iter();
+        Ok(Self::new([
+            products.next().unwrap(),
+            products.next().unwrap(),
+            products.next().unwrap(),
+        ]))
+    }
+
+    /// Calculate the characteristic produced by the specified machine when its current state is
+    /// used as the ground setting.
+    pub fn from_machine(machine: &Enigma) -> Self {
+        let mut machine = machine.clone();
+        let mut perms = Vec::with_capacity(INDICATOR_LEN);
+        for _ in 0..INDICATOR_LEN {
+            perms.push(machine.permutation());
+            machine.advance_rotators();
+        }
+
+        Self::new([
+            perms[0].compose(&perms[3]),
+            perms[1].compose(&perms[4]),
+            perms[2].compose(&perms[5]),
+        ])
+    }
+
+    /// Get the three products `A1A4`, `A2A5` and `A3A6`.
+    pub fn products(&self) -> &[Permutation; 3] {
+        &self.products
+    }
+
+    /// Get the cycle types of the three products.
+    pub fn cycle_types(&self) -> [Vec<usize>; 3] {
+        [
+            self.products[0].cycle_type(),
+            self.products[1].cycle_type(),
+            self.products[2].cycle_type(),
+        ]
+    }
+}
+
+impl Display for Characteristic {
+    /// Write the three products in cycle notation, one per line, e.g. `A1A4: (AB)(CD)...`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, product) in self.products.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "A{}A{}: {}", i + 1, i + 4, product.display_cycles().with_letters())?;
+        }
+        Ok(())
+    }
+}
+
+/// An entry in a characteristic catalog.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CatalogEntry {
+    offsets: [u8; 3],
+    cycle_types: [Vec<usize>; 3],
+}
+
+impl CatalogEntry {
+    /// Create the entry of the state with the specified index from the offsets, the successors
+    /// and the cycle types of the products `AiAi+3` of all states.
+    fn new(index: usize, offsets: &[[u8; 3]], next: &[usize], cycle_types: &[Vec<usize>]) -> Self {
+        Self {
+            offsets: offsets[index],
+            cycle_types: [
+                cycle_types[index].clone(),
+                cycle_types[next[index]].clone(),
+                cycle_types[next[next[index]]].clone(),
+            ],
+        }
+    }
+
+    /// Get the offsets of the 3 rotators at the ground setting of this entry.
+    pub fn offsets(&self) -> [u8; 3] {
+        self.offsets
+    }
+
+    /// Get the cycle types of the characteristic produced by the ground setting of this entry.
+    pub fn cycle_types(&self) -> &[Vec<usize>; 3] {
+        &self.cycle_types
+    }
+}
+
+/// A catalog of the cycle types of the characteristics produced by every ground setting of a
+/// machine.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Catalog {
+    entries: Vec<CatalogEntry>,
+}
+
+impl Catalog {
+    /// Build the catalog for the rotators and the reflector of the specified machine.
+    ///
+    /// The catalog holds an entry for every ground setting, starting at the current state of the
+    /// machine and following the odometer order of the offsets. The characteristic of each entry
+    /// is calculated with the stepping mechanism of the machine, so ground settings that the
+    /// stepping mechanism skips, e.g. after a double step of the Wehrmacht Enigma, are covered
+    /// as well.
+    pub fn build(machine: &Enigma) -> Self {
+        let mut progress = Progress::start("rejewski catalog");
+
+        let (perms, next): (Vec<Permutation>, Vec<usize>) = states::scan(machine, 0..NUM_STATES)
+            .into_iter()
+            .unzip();
+        let offsets: Vec<[u8; 3]> = (0..NUM_STATES).map(states::offsets).collect();
+
+        let cycle_types: Vec<Vec<usize>> = (0..NUM_STATES)
+            .map(|i| perms[i].compose(&perms[next[next[next[i]]]]).cycle_type())
+            .collect();
+
+        let first = states::current(machine);
+        let entries = (0..NUM_STATES)
+            .map(|i| CatalogEntry::new((first + i) % NUM_STATES, &offsets, &next, &cycle_types))
+            .collect();
+        progress.tested(NUM_STATES as u64);
+        progress.finish();
+
+        Self { entries }
+    }
+
+    /// Build the catalog like `build`, using the global rayon thread pool. The entries are in the
+    /// same order as those built by `build`.
+    #[cfg(feature = "parallel")]
+    pub fn build_parallel(machine: &Enigma) -> Self {
+        use rayon::prelude::*;
+
+        let chunk_size = (RUNE_SET_SIZE as usize).pow(2);
+        let mut progress = Progress::start("rejewski catalog");
+
+        let (perms, next): (Vec<Permutation>, Vec<usize>) = (0..NUM_STATES / chunk_size)
+            .into_par_iter()
+            .flat_map_iter(|chunk| {
+                states::scan(machine, chunk * chunk_size..(chunk + 1) * chunk_size)
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .unzip();
+        let offsets: Vec<[u8; 3]> = (0..NUM_STATES).map(states::offsets).collect();
+
+        let cycle_types: Vec<Vec<usize>> = (0..NUM_STATES)
+            .into_par_iter()
+            .map(|i| perms[i].compose(&perms[next[next[next[i]]]]).cycle_type())
+            .collect();
+
+        let first = states::current(machine);
+        let entries = (0..NUM_STATES)
+            .into_par_iter()
+            .map(|i| CatalogEntry::new((first + i) % NUM_STATES, &offsets, &next, &cycle_types))
+            .collect();
+        progress.tested(NUM_STATES as u64);
+        progress.finish();
+
+        Self { entries }
+    }
+
+    /// Get all entries within the catalog.
+    pub fn entries(&self) -> &[CatalogEntry] {
+        &self.entries
+    }
+
+    /// Find all entries whose cycle types match the specified characteristic.
+    pub fn find(&self, characteristic: &Characteristic) -> Vec<&CatalogEntry> {
+        let cycle_types = characteristic.cycle_types();
+        self.entries.iter()
+            .filter(|entry| entry.cycle_types == cycle_types)
+            .collect()
+    }
+
+    /// Find all entries like `find`, using the global rayon thread pool. The entries are returned
+    /// in the same order as those returned by `find`.
+    #[cfg(feature = "parallel")]
+    pub fn find_parallel(&self, characteristic: &Characteristic) -> Vec<&CatalogEntry> {
+        use rayon::prelude::*;
+
+        let cycle_types = characteristic.cycle_types();
+        self.entries.par_iter()
+            .filter(|entry| entry.cycle_types == cycle_types)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{PlugBoard, Reflector, Rotator, RotatorGroup};
+    use crate::math::PermutationBuilder;
+
+    fn create_rotator(wiring: &str, offset: u8) -> Rotator {
+        let perm = wiring.bytes().map(|b| b - b'a').collect();
+        Rotator::new(Permutation::from_perm(perm).unwrap(), offset).unwrap()
+    }
+
+    fn create_test_enigma(offsets: [u8; 3], plug_board: PlugBoard) -> Enigma {
+        let rotators = RotatorGroup::new([
+            create_rotator("ekmflgdqvzntowyhxuspaibrcj", offsets[0]),
+            create_rotator("ajdksiruxblhwtmcqgznpyfvoe", offsets[1]),
+            create_rotator("bdfhjlcprtxvznyeiwgakmusqo", offsets[2]),
+        ]);
+        Enigma::new(plug_board, rotators, create_test_reflector())
+    }
+
+    fn create_test_reflector() -> Reflector {
+        Reflector::from_perm(PermutationBuilder::new(RUNE_SET_SIZE)
+            .swap(0, 24).swap(1, 17).swap(2, 20).swap(3, 7).swap(4, 16)
+            .swap(5, 18).swap(6, 11).swap(8, 15).swap(9, 23).swap(10, 13)
+            .swap(12, 14).swap(19, 25).swap(21, 22)
+            .build()).unwrap()
+    }
+
+    fn create_test_plug_board() -> PlugBoard {
+        PlugBoard::from_perm(PermutationBuilder::new(RUNE_SET_SIZE)
+            .swap(0, 12).swap(3, 21).swap(7, 19).swap(9, 25).swap(14, 17)
+            .build()).unwrap()
+    }
+
+    /// Generate indicators of the day by enciphering pseudo-random message keys twice at the
+    /// ground setting of the specified machine.
+    fn create_test_indicators(machine: &Enigma, count: usize) -> Vec<Indicator> {
+        let mut seed = 12345u32;
+        let mut next_rune = || {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            Rune::from_value(((seed >> 16) % RUNE_SET_SIZE as u32) as u8).unwrap()
+        };
+
+        (0..count)
+            .map(|_| {
+                let key = [next_rune(), next_rune(), next_rune()];
+                let mut machine = machine.clone();
+                let mut runes = [key[0]; INDICATOR_LEN];
+                for (i, rune) in runes.iter_mut().enumerate() {
+                    *rune = machine.map_rune(key[i % 3]);
+                }
+                Indicator::new(runes)
+            })
+            .collect()
+    }
+
+    mod indicator_tests {
+        use super::*;
+
+        #[test]
+        fn test_from_str_valid() {
+            let indicator: Indicator = "dmqvbn".parse().unwrap();
+            assert_eq!(indicator.runes()[0], 'd');
+            assert_eq!(indicator.runes()[5], 'n');
+        }
+
+        #[test]
+        fn test_from_str_invalid() {
+            assert!("dmqvb".parse::<Indicator>().is_err());
+            assert!("dmqvbnx".parse::<Indicator>().is_err());
+            assert!("dmq1bn".parse::<Indicator>().is_err());
+        }
+    }
+
+    mod characteristic_tests {
+        use super::*;
+
+        #[test]
+        fn test_from_indicators() {
+            let machine = create_test_enigma([3, 7, 11], create_test_plug_board());
+            let indicators = create_test_indicators(&machine, 200);
+            let characteristic = Characteristic::from_indicators(&indicators).unwrap();
+            assert_eq!(characteristic, Characteristic::from_machine(&machine));
+        }
+
+        #[test]
+        fn test_from_indicators_incomplete() {
+            let machine = create_test_enigma([3, 7, 11], create_test_plug_board());
+            let indicators = create_test_indicators(&machine, 3);
+            assert_eq!(Characteristic::from_indicators(&indicators),
+                Err(CharacteristicError::Incomplete { product: 0 }));
+        }
+
+        #[test]
+        fn test_from_indicators_conflict() {
+            let indicators: Vec<Indicator> = vec![
+                "abcdef".parse().unwrap(),
+                "abcxef".parse().unwrap(),
+            ];
+            assert_eq!(Characteristic::from_indicators(&indicators),
+                Err(CharacteristicError::Conflict {
+                    product: 0,
+                    rune: Rune::from_char('a').unwrap(),
+                }));
+        }
+
+        #[test]
+        fn test_display() {
+            let machine = create_test_enigma([3, 7, 11], create_test_plug_board());
+            let characteristic = Characteristic::from_machine(&machine);
+            let output = characteristic.to_string();
+            let lines: Vec<&str> = output.lines().collect();
+            assert_eq!(lines.len(), 3);
+            assert!(lines[0].starts_with("A1A4: (A"));
+            assert!(lines[2].starts_with("A3A6: ("));
+            assert_eq!(lines[1].chars().filter(|ch| ch.is_ascii_uppercase()).count(), 26 + 2);
+        }
+
+        #[test]
+        fn test_cycle_types_paired() {
+            // The product of two involutions without fixed points has cycles in pairs of equal
+            // length.
+            let machine = create_test_enigma([0, 0, 0], create_test_plug_board());
+            for cycle_type in Characteristic::from_machine(&machine).cycle_types().iter() {
+                assert_eq!(cycle_type.len() % 2, 0);
+                for pair in cycle_type.chunks(2) {
+                    assert_eq!(pair[0], pair[1]);
+                }
+            }
+        }
+    }
+
+    mod catalog_tests {
+        use super::*;
+        use crate::RotorMachine;
+
+        #[test]
+        fn test_find_ground_setting() {
+            let identity = PlugBoard::from_perm(Permutation::identity(RUNE_SET_SIZE)).unwrap();
+            let catalog = Catalog::build(&create_test_enigma([0, 0, 0], identity));
+            assert_eq!(catalog.entries().len(), 17576);
+
+            let machine = create_test_enigma([3, 7, 11], create_test_plug_board());
+            let indicators = create_test_indicators(&machine, 200);
+            let characteristic = Characteristic::from_indicators(&indicators).unwrap();
+
+            let candidates = catalog.find(&characteristic);
+            assert!(!candidates.is_empty());
+            assert!(candidates.len() < 100);
+            assert!(candidates.iter().any(|entry| entry.offsets() == [3, 7, 11]));
+        }
+
+        #[test]
+        fn test_build_lever() {
+            use crate::components::catalog::HISTORICAL_ROTATORS;
+            use crate::components::stepping::SteppingMode;
+
+            let rotators = RotatorGroup::new([2, 1, 0].map(|i| HISTORICAL_ROTATORS[i].1.clone()));
+            let mut machine = Enigma::new(create_test_plug_board(), rotators,
+                create_test_reflector())
+                .with_stepping_mode(SteppingMode::WehrmachtLever);
+            let catalog = Catalog::build(&machine);
+            assert_eq!(catalog.entries().len(), 17576);
+
+            // The middle rotator double steps within the indicators of the first ground setting,
+            // and the second ground setting is skipped by the stepping mechanism.
+            for offsets in &[[20, 3, 0], [5, 4, 9], [3, 7, 11]] {
+                machine.restore(offsets).unwrap();
+                let entry = catalog.entries().iter()
+                    .find(|entry| entry.offsets() == *offsets)
+                    .unwrap();
+                let characteristic = Characteristic::from_machine(&machine);
+                assert_eq!(entry.cycle_types(), &characteristic.cycle_types());
+            }
+        }
+
+        #[cfg(feature = "parallel")]
+        #[test]
+        fn test_build_parallel() {
+            let identity = PlugBoard::from_perm(Permutation::identity(RUNE_SET_SIZE)).unwrap();
+            let machine = create_test_enigma([5, 0, 2], identity);
+            let catalog = Catalog::build(&machine);
+            let parallel = Catalog::build_parallel(&machine);
+
+            assert_eq!(parallel.entries().len(), catalog.entries().len());
+            for (lhs, rhs) in parallel.entries().iter().zip(catalog.entries()) {
+                assert_eq!(lhs.offsets(), rhs.offsets());
+                assert_eq!(lhs.cycle_types(), rhs.cycle_types());
+            }
+
+            let characteristic = Characteristic::from_machine(&machine);
+            let expected: Vec<[u8; 3]> = catalog.find(&characteristic).iter()
+                .map(|entry| entry.offsets())
+                .collect();
+            let actual: Vec<[u8; 3]> = parallel.find_parallel(&characteristic).iter()
+                .map(|entry| entry.offsets())
+                .collect();
+            assert_eq!(actual, expected);
+        }
+    }
+}
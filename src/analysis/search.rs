@@ -0,0 +1,379 @@
+//! This module implements a driver for brute-force searches, which scores every candidate
+//! decryption and stops as soon as one of them looks like natural language.
+//!
+//! The candidate keys are produced by the caller, e.g. by enumerating the keyspace or by any of
+//! the attacks within the [`analysis`] module. Each candidate decryption is scored by the mean
+//! n-gram log-probability given by the [`fitness`] module and, optionally, by the ratio of its
+//! letters covered by the words of a [`Dictionary`]. The scored decryptions are passed to a
+//! callback, and the search stops early once a decryption reaches the configured thresholds:
+//!
+//! ```
+//! # use enigma::{Rune, Settings, SteppingMode};
+//! # use enigma::analysis::search::{Dictionary, Search};
+//! # use enigma::math::Permutation;
+//! #
+//! # fn wiring(s: &str) -> Permutation {
+//! #     Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+//! # }
+//! #
+//! let key = |positions| Settings {
+//!     rotator_wirings: [
+//!         wiring("ekmflgdqvzntowyhxuspaibrcj"),
+//!         wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+//!         wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+//!     ],
+//!     positions,
+//!     rings: [0, 0, 0],
+//!     notches: Default::default(),
+//!     stepping: SteppingMode::Odometer,
+//!     reflector: wiring("yruhqsldpxngokmiebfzcwvjat"),
+//!     plug_pairs: Vec::new(),
+//! };
+//!
+//! let plaintext: Vec<Rune> = "thereportofthecommitteeisready".chars()
+//!     .map(|ch| Rune::from_char(ch).unwrap())
+//!     .collect();
+//! let mut machine = key([3, 0, 0]).create_enigma().unwrap();
+//! let ciphertext: Vec<Rune> = plaintext.iter().map(|r| machine.map_rune(*r)).collect();
+//!
+//! let search = Search::new()
+//!     .stop_at_word_hits(Dictionary::new(&["report", "committee", "ready"]), 0.5);
+//! let mut tried = 0;
+//! let outcome = search.run(&ciphertext, (0..26).map(|p| key([p, 0, 0])), |_| tried += 1);
+//!
+//! assert!(outcome.stopped);
+//! assert_eq!(tried, 4);
+//! assert_eq!(outcome.best.unwrap().plaintext, plaintext);
+//! ```
+//!
+//! [`analysis`]: ../index.html
+//! [`fitness`]: ../fitness/index.html
+//! [`Dictionary`]: struct.Dictionary.html
+//!
+
+use std::collections::HashSet;
+
+use crate::Settings;
+use crate::analysis::fitness::{self, NgramTable};
+use crate::analysis::progress::Progress;
+use crate::utils::Rune;
+
+/// The minimal length of the words within a dictionary. Shorter words occur too often within
+/// random texts to tell anything about a decryption.
+pub const MIN_WORD_LEN: usize = 3;
+
+/// A set of words used to recognize decryptions.
+#[derive(Clone, Debug)]
+pub struct Dictionary {
+    words: HashSet<Vec<u8>>,
+    max_word_len: usize,
+}
+
+impl Dictionary {
+    /// Create a new dictionary with the specified words. Words containing non-letter characters
+    /// or shorter than `MIN_WORD_LEN` letters are ignored.
+    pub fn new<I>(words: I) -> Self
+        where I: IntoIterator,
+              I::Item: AsRef<str> {
+        let words: HashSet<Vec<u8>> = words.into_iter()
+            .filter_map(|word| {
+                word.as_ref().chars()
+                    .map(|ch| Rune::from_char(ch).map(|r| r.value()))
+                    .collect::<Result<Vec<u8>, _>>()
+                    .ok()
+            })
+            .filter(|word| word.len() >= MIN_WORD_LEN)
+            .collect();
+        let max_word_len = words.iter().map(|word| word.len()).max().unwrap_or(0);
+        Self { words, max_word_len }
+    }
+
+    /// Get the number of words within this dictionary.
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Determine whether this dictionary is empty.
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Calculate the ratio of the letters within the specified text that are covered by the
+    /// words of this dictionary. The text is scanned from left to right and the longest word
+    /// starting at each position is taken.
+    ///
+    /// Returns `0` if the text is empty.
+    pub fn hit_ratio(&self, text: &[Rune]) -> f64 {
+        if text.is_empty() {
+            return 0.0;
+        }
+
+        let text: Vec<u8> = text.iter().map(|r| r.value()).collect();
+        let mut covered = 0;
+        let mut position = 0;
+        while position < text.len() {
+            let max_len = self.max_word_len.min(text.len() - position);
+            let word_len = (MIN_WORD_LEN..=max_len).rev()
+                .find(|len| self.words.contains(&text[position..position + len]));
+            match word_len {
+                Some(len) => {
+                    covered += len;
+                    position += len;
+                },
+                None => position += 1,
+            }
+        }
+
+        covered as f64 / text.len() as f64
+    }
+}
+
+/// A scored candidate decryption.
+#[derive(Clone, Debug)]
+pub struct Decryption {
+    /// The candidate key.
+    pub settings: Settings,
+
+    /// The decryption of the ciphertext under the candidate key.
+    pub plaintext: Vec<Rune>,
+
+    /// The mean log-probability of the n-grams within the decryption.
+    pub score: f64,
+
+    /// The ratio of the letters within the decryption covered by the dictionary, or `None` if
+    /// the search has no dictionary.
+    pub word_hits: Option<f64>,
+}
+
+/// The outcome of a search.
+#[derive(Clone, Debug)]
+pub struct Outcome {
+    /// The decryption that stopped the search, or the best scored decryption if the search was
+    /// not stopped early. `None` if there were no candidates.
+    pub best: Option<Decryption>,
+
+    /// The number of candidates tried.
+    pub tried: usize,
+
+    /// Whether the search was stopped early because a decryption reached a threshold.
+    pub stopped: bool,
+}
+
+/// A search driver.
+///
+/// By default, a search scores decryptions with the built-in English trigrams and never stops
+/// early.
+#[derive(Clone, Debug)]
+pub struct Search {
+    table: NgramTable,
+    min_score: Option<f64>,
+    dictionary: Option<(Dictionary, f64)>,
+}
+
+impl Search {
+    /// Create a new search driver with the default options.
+    pub fn new() -> Self {
+        Self {
+            table: fitness::english_trigrams(),
+            min_score: None,
+            dictionary: None,
+        }
+    }
+
+    /// Set the n-gram table used to score decryptions.
+    pub fn ngram_table(mut self, table: NgramTable) -> Self {
+        self.table = table;
+        self
+    }
+
+    /// Stop the search once the mean n-gram log-probability of a decryption reaches the specified
+    /// threshold.
+    ///
+    /// With the built-in English trigrams, English texts score about `-4` and random texts about
+    /// `-5.5`.
+    pub fn stop_at_score(mut self, min_score: f64) -> Self {
+        self.min_score = Some(min_score);
+        self
+    }
+
+    /// Stop the search once the ratio of the letters of a decryption covered by the words of the
+    /// specified dictionary reaches the specified threshold.
+    pub fn stop_at_word_hits(mut self, dictionary: Dictionary, min_ratio: f64) -> Self {
+        self.dictionary = Some((dictionary, min_ratio));
+        self
+    }
+
+    /// Score the decryption of the ciphertext under the specified key.
+    ///
+    /// This function panics if the key is invalid.
+    pub fn score(&self, ciphertext: &[Rune], settings: Settings) -> Decryption {
+        let mut machine = settings.create_enigma().expect("invalid candidate settings");
+        let plaintext: Vec<Rune> = ciphertext.iter().map(|r| machine.map_rune(*r)).collect();
+
+        let num_ngrams = (plaintext.len() + 1).saturating_sub(self.table.n());
+        let score = if num_ngrams == 0 {
+            0.0
+        } else {
+            self.table.score(&plaintext) / num_ngrams as f64
+        };
+        let word_hits = self.dictionary.as_ref()
+            .map(|(dictionary, _)| dictionary.hit_ratio(&plaintext));
+
+        Decryption { settings, plaintext, score, word_hits }
+    }
+
+    /// Determine whether the specified decryption reaches any threshold of this search.
+    pub fn is_hit(&self, decryption: &Decryption) -> bool {
+        let score_hit = self.min_score.is_some_and(|min_score| decryption.score >= min_score);
+        let word_hit = match (&self.dictionary, decryption.word_hits) {
+            (Some((_, min_ratio)), Some(ratio)) => ratio >= *min_ratio,
+            _ => false,
+        };
+        score_hit || word_hit
+    }
+
+    /// Try the specified candidate keys in order. Each decryption is scored and passed to the
+    /// callback. The search stops at the first decryption that reaches any threshold.
+    ///
+    /// This function panics if a candidate key is invalid.
+    pub fn run<I, F>(&self, ciphertext: &[Rune], candidates: I, mut callback: F) -> Outcome
+        where I: IntoIterator<Item = Settings>,
+              F: FnMut(&Decryption) {
+        let mut outcome = Outcome { best: None, tried: 0, stopped: false };
+        let mut progress = Progress::start("search");
+        for settings in candidates {
+            let decryption = self.score(ciphertext, settings);
+            outcome.tried += 1;
+            progress.tested(1);
+            callback(&decryption);
+
+            if self.is_hit(&decryption) {
+                outcome.best = Some(decryption);
+                outcome.stopped = true;
+                break;
+            }
+            if outcome.best.as_ref().is_none_or(|best| decryption.score > best.score) {
+                outcome.best = Some(decryption);
+            }
+        }
+        progress.finish();
+
+        outcome
+    }
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SteppingMode;
+    use crate::math::Permutation;
+
+    fn wiring(s: &str) -> Permutation {
+        Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+    }
+
+    fn to_runes(s: &str) -> Vec<Rune> {
+        s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+    }
+
+    fn key(positions: [u8; 3]) -> Settings {
+        Settings {
+            rotator_wirings: [
+                wiring("ekmflgdqvzntowyhxuspaibrcj"),
+                wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+                wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+            ],
+            positions,
+            rings: [0, 0, 0],
+            notches: Default::default(),
+            stepping: SteppingMode::Odometer,
+            reflector: wiring("yruhqsldpxngokmiebfzcwvjat"),
+            plug_pairs: Vec::new(),
+        }
+    }
+
+    fn encrypt(positions: [u8; 3], plaintext: &[Rune]) -> Vec<Rune> {
+        let mut machine = key(positions).create_enigma().unwrap();
+        plaintext.iter().map(|r| machine.map_rune(*r)).collect()
+    }
+
+    mod dictionary_tests {
+        use super::*;
+
+        #[test]
+        fn test_new() {
+            let dictionary = Dictionary::new(vec!["Attack", "at", "dawn", "d-day", "dawn"]);
+            assert_eq!(dictionary.len(), 2);
+            assert!(Dictionary::new(Vec::<String>::new()).is_empty());
+        }
+
+        #[test]
+        fn test_hit_ratio() {
+            let dictionary = Dictionary::new(&["attack", "tack", "dawn"]);
+            assert_eq!(dictionary.hit_ratio(&to_runes("attackatdawn")), 10.0 / 12.0);
+            assert_eq!(dictionary.hit_ratio(&to_runes("xyz")), 0.0);
+            assert_eq!(dictionary.hit_ratio(&[]), 0.0);
+        }
+    }
+
+    mod search_tests {
+        use super::*;
+
+        const PLAINTEXT: &str = "theconvoywillleavetheharbouratfirstlightandproceednorth";
+
+        #[test]
+        fn test_stop_at_score() {
+            let plaintext = to_runes(PLAINTEXT);
+            let ciphertext = encrypt([5, 2, 0], &plaintext);
+
+            let mut scores = Vec::new();
+            let outcome = Search::new()
+                .stop_at_score(-4.5)
+                .run(&ciphertext, (0..26).map(|p| key([p, 2, 0])), |d| scores.push(d.score));
+
+            assert!(outcome.stopped);
+            assert_eq!(outcome.tried, 6);
+            assert_eq!(scores.len(), 6);
+            assert!(scores[..5].iter().all(|score| *score < -4.5));
+            assert_eq!(outcome.best.unwrap().plaintext, plaintext);
+        }
+
+        #[test]
+        fn test_stop_at_word_hits() {
+            let plaintext = to_runes(PLAINTEXT);
+            let ciphertext = encrypt([0, 0, 7], &plaintext);
+
+            let dictionary = Dictionary::new(&["convoy", "harbour", "light", "north", "first"]);
+            let outcome = Search::new()
+                .stop_at_word_hits(dictionary, 0.3)
+                .run(&ciphertext, (0..26).map(|p| key([0, 0, p])), |d| {
+                    assert!(d.word_hits.is_some());
+                });
+
+            assert!(outcome.stopped);
+            assert_eq!(outcome.tried, 8);
+            assert_eq!(outcome.best.unwrap().settings.positions, [0, 0, 7]);
+        }
+
+        #[test]
+        fn test_no_stop() {
+            let plaintext = to_runes(PLAINTEXT);
+            let ciphertext = encrypt([9, 0, 0], &plaintext);
+
+            let outcome = Search::new().run(&ciphertext, (0..26).map(|p| key([p, 0, 0])), |_| ());
+            assert!(!outcome.stopped);
+            assert_eq!(outcome.tried, 26);
+            assert_eq!(outcome.best.unwrap().settings.positions, [9, 0, 0]);
+
+            let outcome = Search::new().run(&ciphertext, Vec::new(), |_| ());
+            assert!(outcome.best.is_none());
+            assert_eq!(outcome.tried, 0);
+        }
+    }
+}
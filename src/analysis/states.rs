@@ -0,0 +1,76 @@
+//! This module enumerates the states of the 3 rotators of a machine, so that the attacks can
+//! precompute the permutation realized by the machine at every rotator position.
+//!
+//! States are indexed in the odometer order of the offsets, e.g. the offset of the first rotator
+//! changes fastest. The state following a state is determined by the stepping mechanism of the
+//! machine, so it is not necessarily the state of the next index.
+//!
+
+use std::ops::Range;
+
+use crate::{Enigma, RotorMachine};
+use crate::math::Permutation;
+use crate::utils::RUNE_SET_SIZE;
+
+/// The number of states of 3 rotators.
+pub(crate) const NUM_STATES: usize = (RUNE_SET_SIZE as usize) * (RUNE_SET_SIZE as usize) *
+    (RUNE_SET_SIZE as usize);
+
+/// Get the index of the state of the rotators at the specified offsets.
+pub(crate) fn index(offsets: [u8; 3]) -> usize {
+    let n = RUNE_SET_SIZE as usize;
+    offsets[0] as usize + n * (offsets[1] as usize + n * offsets[2] as usize)
+}
+
+/// Get the offsets of the rotators at the state with the specified index.
+pub(crate) fn offsets(index: usize) -> [u8; 3] {
+    let n = RUNE_SET_SIZE as usize;
+    [(index % n) as u8, (index / n % n) as u8, (index / n / n) as u8]
+}
+
+/// Get the index of the current state of the rotators of the specified machine.
+pub(crate) fn current(machine: &Enigma) -> usize {
+    index(machine.rotators().offsets())
+}
+
+/// Get the permutation realized by the specified machine at each of the specified states, together
+/// with the index of the state its stepping mechanism advances to.
+pub(crate) fn scan(machine: &Enigma, states: Range<usize>) -> Vec<(Permutation, usize)> {
+    let mut machine = machine.clone();
+    machine.clear_observer();
+    states
+        .map(|state| {
+            machine.restore(&offsets(state)).unwrap();
+            let perm = machine.permutation();
+            machine.advance_rotators();
+            (perm, current(&machine))
+        })
+        .collect()
+}
+
+/// The states following the states of the rotators of a machine, as determined by its stepping
+/// mechanism. The state following a state is computed the first time it is asked for, so that
+/// walking a few states does not step the machine through all of them.
+pub(crate) struct Successors {
+    machine: Enigma,
+    next: Vec<usize>,
+}
+
+impl Successors {
+    /// Create the successors of the states of the rotators of the specified machine.
+    pub(crate) fn new(machine: &Enigma) -> Self {
+        let mut machine = machine.clone();
+        machine.clear_observer();
+        Self { machine, next: vec![NUM_STATES; NUM_STATES] }
+    }
+
+    /// Get the index of the state following the state of the specified index.
+    pub(crate) fn next(&mut self, state: usize) -> usize {
+        if self.next[state] == NUM_STATES {
+            self.machine.restore(&offsets(state)).unwrap();
+            self.machine.advance_rotators();
+            self.next[state] = current(&self.machine);
+        }
+        self.next[state]
+    }
+}
@@ -0,0 +1,215 @@
+//! This module implements statistical tests telling how far a ciphertext deviates from uniformly
+//! random text.
+//!
+//! The following tests are available:
+//! - The `chi_square` function computes Pearson's chi-square statistic of the letter frequencies
+//!   against the uniform distribution. Values above `CHI_SQUARE_CRITICAL` are unlikely for random
+//!   texts.
+//! - The `window_iocs` function computes the index of coincidence over consecutive windows of a
+//!   text, which reveals parts of a ciphertext that are less random than others.
+//! - The `autocorrelation` function computes the rate of coincidences between a text and itself
+//!   shifted by some letters, which peaks at multiples of the period of a periodic cipher.
+//!
+//! The [`StatsReport`] type summarizes all tests. Since the Enigma never maps a letter to itself,
+//! the ciphertext of a text repeating a single letter never contains that letter, which the
+//! chi-square test detects:
+//!
+//! ```
+//! # use enigma::analysis::stats::StatsReport;
+//! # use enigma::testvectors::TEST_VECTORS;
+//! # use enigma::Rune;
+//! #
+//! let mut machine = TEST_VECTORS[0].create_enigma();
+//! let ciphertext: Vec<Rune> = machine.map_str(&"A".repeat(2600)).chars()
+//!     .map(|ch| Rune::from_char(ch).unwrap())
+//!     .collect();
+//!
+//! let report = StatsReport::new(&ciphertext, 260, 52);
+//! assert!(!report.is_uniform());
+//! assert_eq!(report.window_iocs.len(), 10);
+//! println!("{}", report);
+//! ```
+//!
+//! [`StatsReport`]: struct.StatsReport.html
+//!
+
+use std::fmt::{Display, Formatter};
+
+use crate::analysis::fitness::index_of_coincidence;
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// The critical value of the chi-square statistic with `RUNE_SET_SIZE - 1` degrees of freedom at
+/// the significance level of 1%. Uniformly random texts exceed this value with a probability of
+/// 1% only.
+pub const CHI_SQUARE_CRITICAL: f64 = 44.314;
+
+/// Calculate Pearson's chi-square statistic of the letter frequencies of the specified text
+/// against the uniform distribution.
+///
+/// Returns `0` if the text is empty.
+pub fn chi_square(text: &[Rune]) -> f64 {
+    if text.is_empty() {
+        return 0.0;
+    }
+
+    let mut counts = [0usize; RUNE_SET_SIZE as usize];
+    for rune in text {
+        counts[rune.value() as usize] += 1;
+    }
+
+    let expected = text.len() as f64 / RUNE_SET_SIZE as f64;
+    counts.iter()
+        .map(|count| (*count as f64 - expected).powi(2) / expected)
+        .sum()
+}
+
+/// Calculate the index of coincidence of each consecutive window of the specified number of runes
+/// within the specified text. A trailing window shorter than the others is ignored.
+///
+/// Returns an empty vector if `window` is `0`.
+pub fn window_iocs(text: &[Rune], window: usize) -> Vec<f64> {
+    if window == 0 {
+        return Vec::new();
+    }
+
+    text.chunks_exact(window).map(index_of_coincidence).collect()
+}
+
+/// Calculate the rate of positions at which the specified text coincides with itself shifted by
+/// the specified number of runes. The rate is about `1/26` for uniformly random texts.
+///
+/// Returns `0` if the shift is `0` or not less than the length of the text.
+pub fn autocorrelation(text: &[Rune], shift: usize) -> f64 {
+    if shift == 0 || shift >= text.len() {
+        return 0.0;
+    }
+
+    let coincidences = text.iter().zip(&text[shift..]).filter(|(a, b)| a == b).count();
+    coincidences as f64 / (text.len() - shift) as f64
+}
+
+/// The results of all statistical tests of this module on a text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatsReport {
+    /// The number of runes within the text.
+    pub len: usize,
+
+    /// The chi-square statistic of the text, as calculated by `chi_square`.
+    pub chi_square: f64,
+
+    /// The index of coincidence of the whole text.
+    pub ioc: f64,
+
+    /// The indices of coincidence of the windows of the text, as calculated by `window_iocs`.
+    pub window_iocs: Vec<f64>,
+
+    /// The autocorrelations of the text. The i-th element is the autocorrelation at a shift of
+    /// `i + 1` runes.
+    pub autocorrelations: Vec<f64>,
+}
+
+impl StatsReport {
+    /// Run all tests on the specified text, with windows of the specified number of runes and the
+    /// autocorrelations at shifts up to the specified maximum.
+    pub fn new(text: &[Rune], window: usize, max_shift: usize) -> Self {
+        Self {
+            len: text.len(),
+            chi_square: chi_square(text),
+            ioc: index_of_coincidence(text),
+            window_iocs: window_iocs(text, window),
+            autocorrelations: (1..=max_shift).map(|shift| autocorrelation(text, shift)).collect(),
+        }
+    }
+
+    /// Determine whether the letter frequencies of the text pass the chi-square test, e.g.
+    /// whether the chi-square statistic does not exceed `CHI_SQUARE_CRITICAL`.
+    pub fn is_uniform(&self) -> bool {
+        self.chi_square <= CHI_SQUARE_CRITICAL
+    }
+
+    /// Get the shift with the highest autocorrelation and the autocorrelation at that shift.
+    /// Returns `None` if no autocorrelation has been calculated.
+    pub fn autocorrelation_peak(&self) -> Option<(usize, f64)> {
+        self.autocorrelations.iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, rate)| (i + 1, *rate))
+    }
+}
+
+impl Display for StatsReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Length: {}", self.len)?;
+        writeln!(f, "Chi-square: {:.2} ({})", self.chi_square,
+            if self.is_uniform() { "uniform" } else { "not uniform" })?;
+        writeln!(f, "Index of coincidence: {:.4}", self.ioc)?;
+
+        if !self.window_iocs.is_empty() {
+            let min = self.window_iocs.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = self.window_iocs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            writeln!(f, "Window index of coincidence: {:.4} to {:.4} over {} windows", min, max,
+                self.window_iocs.len())?;
+        }
+
+        if let Some((shift, rate)) = self.autocorrelation_peak() {
+            writeln!(f, "Autocorrelation peak: {:.4} at shift {}", rate, shift)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runes(s: &str) -> Vec<Rune> {
+        s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_chi_square() {
+        assert_eq!(chi_square(&[]), 0.0);
+        assert!(chi_square(&runes(&"abcdefghijklmnopqrstuvwxyz".repeat(4))).abs() < 1e-9);
+
+        // A text of a single letter deviates the most from the uniform distribution.
+        let text = runes(&"a".repeat(26));
+        assert!((chi_square(&text) - 650.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_window_iocs() {
+        let text = runes("aaaaabcdefaaa");
+        assert_eq!(window_iocs(&text, 5), vec![1.0, 0.0]);
+        assert!(window_iocs(&text, 0).is_empty());
+        assert!(window_iocs(&text, 20).is_empty());
+    }
+
+    #[test]
+    fn test_autocorrelation() {
+        let text = runes(&"thequickbrownfox".repeat(8));
+        assert_eq!(autocorrelation(&text, 16), 1.0);
+        assert!(autocorrelation(&text, 5) < 0.1);
+        assert_eq!(autocorrelation(&text, 0), 0.0);
+        assert_eq!(autocorrelation(&text, text.len()), 0.0);
+    }
+
+    #[test]
+    fn test_report() {
+        let text = runes(&"thequickbrownfox".repeat(8));
+        let report = StatsReport::new(&text, 32, 20);
+        assert_eq!(report.len, 128);
+        assert_eq!(report.window_iocs.len(), 4);
+        assert_eq!(report.autocorrelations.len(), 20);
+        assert_eq!(report.autocorrelation_peak(), Some((16, 1.0)));
+        assert!(!report.is_uniform());
+
+        let output = report.to_string();
+        assert!(output.contains("Length: 128\n"));
+        assert!(output.contains("(not uniform)"));
+        assert!(output.contains("Autocorrelation peak: 1.0000 at shift 16\n"));
+
+        let report = StatsReport::new(&text, 0, 0);
+        assert_eq!(report.autocorrelation_peak(), None);
+        assert!(!report.to_string().contains("Window"));
+    }
+}
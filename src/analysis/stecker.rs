@@ -0,0 +1,456 @@
+//! This module implements the recovery of the plug board, e.g. the Steckerverbindungen, when the
+//! rotator settings are already known.
+//!
+//! Two methods are provided:
+//!
+//! - The `deduce` function deduces the plug pairs deterministically from a crib, in the way the
+//!   checking machine was used to verify the stops of the Bombe: a hypothesis is made about the
+//!   partner of the most connected letter of the menu, and the consequences of the hypothesis are
+//!   propagated through the menu until either a contradiction is found or every letter of the
+//!   menu gets a partner.
+//! - The `recover` function recovers the plug board from the ciphertext alone by hill climbing:
+//!   plug pairs are added, replaced or removed one at a time as long as the n-gram score of the
+//!   decryption improves.
+//!
+//! The `recover_with_crib` function combines the two methods: the plug pairs deduced from the crib
+//! are used as the starting point of the hill climbing, which then finds the plug pairs not
+//! covered by the crib.
+//!
+//! The plug pairs within the given settings are always ignored.
+//!
+//! ```
+//! # use enigma::{Rune, Settings, SteppingMode};
+//! # use enigma::analysis::stecker;
+//! # use enigma::math::Permutation;
+//! #
+//! # fn wiring(s: &str) -> Permutation {
+//! #     Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+//! # }
+//! # fn runes(s: &str) -> Vec<Rune> {
+//! #     s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+//! # }
+//! #
+//! let settings = Settings {
+//!     rotator_wirings: [
+//!         wiring("ekmflgdqvzntowyhxuspaibrcj"),
+//!         wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+//!         wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+//!     ],
+//!     positions: [0, 0, 0],
+//!     rings: [0, 0, 0],
+//!     notches: Default::default(),
+//!     stepping: SteppingMode::Odometer,
+//!     reflector: wiring("yruhqsldpxngokmiebfzcwvjat"),
+//!     plug_pairs: vec![(Rune::from_char('a').unwrap(), Rune::from_char('b').unwrap())],
+//! };
+//! let crib = runes("weatherreportforthenorthsea");
+//! let mut machine = settings.create_enigma().unwrap();
+//! let ciphertext: Vec<Rune> = crib.iter().map(|r| machine.map_rune(*r)).collect();
+//!
+//! let deductions = stecker::deduce(&ciphertext, &crib, 0, &settings).unwrap();
+//! assert!(deductions.contains(&settings.plug_pairs));
+//! ```
+//!
+
+use crate::{Enigma, Settings};
+use crate::analysis::fitness::{self, NgramTable};
+use crate::analysis::menu::{self, MenuError};
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// The default maximal number of plug pairs recovered by hill climbing. This is the number of plug
+/// pairs used by the Wehrmacht from 1939 on.
+pub const DEFAULT_MAX_PLUG_PAIRS: usize = 10;
+
+/// A mapping from runes to runes, indexed by rune values.
+pub(crate) type RuneTable = [u8; RUNE_SET_SIZE as usize];
+
+pub(crate) const UNKNOWN: u8 = u8::MAX;
+
+/// Deduce the plug pairs from a crib placed at the specified offset within the ciphertext.
+///
+/// The plug pairs are deduced for the letters connected to the most connected letter of the
+/// menu. Every returned plug pair set is consistent with the whole connected part of the menu;
+/// letters that are not connected to it are left out. An empty result means that the rotator
+/// settings contradict the crib.
+pub fn deduce(ciphertext: &[Rune], crib: &[Rune], offset: usize, settings: &Settings)
+    -> Result<Vec<Vec<(Rune, Rune)>>, MenuError> {
+    let window_end = (offset + crib.len()).min(ciphertext.len());
+    let window = &ciphertext[offset.min(window_end)..window_end];
+    let menu = menu::build(crib, window)?;
+
+    let start = match menu.most_connected_letter() {
+        Some(letter) => letter.value(),
+        None => return Ok(Vec::new()),
+    };
+
+    let scramblers = scrambler_tables(&create_machine(settings), offset + crib.len());
+    let edges: Vec<(usize, usize, &RuneTable)> = menu.edges().iter()
+        .map(|edge| {
+            (edge.plain.value() as usize, edge.cipher.value() as usize,
+                &scramblers[offset + edge.position])
+        })
+        .collect();
+
+    let mut deductions = Vec::new();
+    for partner in 0..RUNE_SET_SIZE {
+        let mut plug = [UNKNOWN; RUNE_SET_SIZE as usize];
+        if connect(&mut plug, start, partner) && propagate(&mut plug, &edges) {
+            deductions.push(plug_to_pairs(&plug));
+        }
+    }
+
+    Ok(deductions)
+}
+
+/// Recover the plug board of the specified rotator settings from the ciphertext by hill climbing,
+/// and return the recovered settings.
+pub fn recover(ciphertext: &[Rune], settings: &Settings) -> Settings {
+    let scramblers = scrambler_tables(&create_machine(settings), ciphertext.len());
+    let (plug, _) = climb_plug_board(ciphertext, &scramblers, identity_plug(),
+        DEFAULT_MAX_PLUG_PAIRS, &fitness::english_trigrams());
+
+    settings.clone().with_plug_pairs(plug_to_pairs(&plug))
+}
+
+/// Recover the plug board of the specified rotator settings by hill climbing, starting from the
+/// plug pairs deduced from a crib placed at the specified offset within the ciphertext.
+///
+/// Returns `Ok(None)` if the rotator settings contradict the crib.
+pub fn recover_with_crib(ciphertext: &[Rune], crib: &[Rune], offset: usize, settings: &Settings)
+    -> Result<Option<Settings>, MenuError> {
+    let deductions = deduce(ciphertext, crib, offset, settings)?;
+    let scramblers = scrambler_tables(&create_machine(settings), ciphertext.len());
+    let table = fitness::english_trigrams();
+
+    let best = deductions.iter()
+        .map(|pairs| {
+            let mut plug = identity_plug();
+            for (a, b) in pairs {
+                plug[a.value() as usize] = b.value();
+                plug[b.value() as usize] = a.value();
+            }
+            let max_plug_pairs = DEFAULT_MAX_PLUG_PAIRS.max(pairs.len());
+            climb_plug_board(ciphertext, &scramblers, plug, max_plug_pairs, &table)
+        })
+        .max_by(|lhs, rhs| lhs.1.partial_cmp(&rhs.1).unwrap());
+
+    Ok(best.map(|(plug, _)| settings.clone().with_plug_pairs(plug_to_pairs(&plug))))
+}
+
+/// Connect `a` and `b` on the specified partially known plug board. Returns `false` if either
+/// letter is already connected to another letter.
+pub(crate) fn connect(plug: &mut RuneTable, a: u8, b: u8) -> bool {
+    let (a_idx, b_idx) = (a as usize, b as usize);
+    if plug[a_idx] == b {
+        return true;
+    }
+    if plug[a_idx] != UNKNOWN || plug[b_idx] != UNKNOWN {
+        return false;
+    }
+
+    plug[a_idx] = b;
+    plug[b_idx] = a;
+    true
+}
+
+/// Propagate the known plug pairs through the menu edges until no more plug pairs can be deduced.
+/// Returns `false` if a contradiction is found.
+pub(crate) fn propagate(plug: &mut RuneTable, edges: &[(usize, usize, &RuneTable)]) -> bool {
+    loop {
+        let mut changed = false;
+        for (plain, cipher, scrambler) in edges {
+            // The plugged plain letter is scrambled into the plugged cipher letter, and vice versa.
+            for (from, to) in &[(*plain, *cipher), (*cipher, *plain)] {
+                if plug[*from] == UNKNOWN {
+                    continue;
+                }
+                let expected = scrambler[plug[*from] as usize];
+                if plug[*to] == expected {
+                    continue;
+                }
+                if !connect(plug, *to as u8, expected) {
+                    return false;
+                }
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return true;
+        }
+    }
+}
+
+fn create_machine(settings: &Settings) -> Enigma {
+    let settings = settings.clone().with_plug_pairs(Vec::new());
+    settings.create_enigma().expect("invalid rotator or reflector wiring in settings")
+}
+
+/// Get the plug board array without any plug pairs.
+pub(crate) fn identity_plug() -> RuneTable {
+    let mut plug = [0u8; RUNE_SET_SIZE as usize];
+    for (i, x) in plug.iter_mut().enumerate() {
+        *x = i as u8;
+    }
+    plug
+}
+
+/// Get the permutations realized by the rotators and the reflector of the specified machine at
+/// the next `count` key presses.
+pub(crate) fn scrambler_tables(machine: &Enigma, count: usize) -> Vec<RuneTable> {
+    let mut machine = machine.clone();
+    let mut tables = Vec::with_capacity(count);
+    for _ in 0..count {
+        let perm = machine.permutation();
+        let mut table = [0u8; RUNE_SET_SIZE as usize];
+        for (i, x) in table.iter_mut().enumerate() {
+            *x = perm.map(i as u8);
+        }
+        tables.push(table);
+        machine.advance_rotators();
+    }
+    tables
+}
+
+fn decrypt(ciphertext: &[Rune], scramblers: &[RuneTable], plug: &RuneTable) -> Vec<Rune> {
+    ciphertext.iter().enumerate()
+        .map(|(i, c)| {
+            let value = plug[scramblers[i][plug[c.value() as usize] as usize] as usize];
+            unsafe { Rune::from_value_unchecked(value) }
+        })
+        .collect()
+}
+
+/// Hill-climb the plug board starting from the specified plug board, and return the best plug
+/// board found together with its n-gram score.
+///
+/// The plug board is first climbed with the index of coincidence, which finds most of the plug
+/// pairs even when the decryption is still far from readable, and then with the n-gram table.
+pub(crate) fn climb_plug_board(ciphertext: &[Rune], scramblers: &[RuneTable], plug: RuneTable,
+    max_plug_pairs: usize, table: &NgramTable) -> (RuneTable, f64) {
+    let (plug, _) = climb(ciphertext, scramblers, plug, max_plug_pairs,
+        fitness::index_of_coincidence);
+    climb(ciphertext, scramblers, plug, max_plug_pairs, |text| table.score(text))
+}
+
+fn climb<F>(ciphertext: &[Rune], scramblers: &[RuneTable], mut plug: RuneTable,
+    max_plug_pairs: usize, score_fn: F) -> (RuneTable, f64)
+    where F: Fn(&[Rune]) -> f64 {
+    let mut best_score = score_fn(&decrypt(ciphertext, scramblers, &plug));
+
+    loop {
+        let mut improved = false;
+        for a in 0..RUNE_SET_SIZE {
+            for b in a + 1..RUNE_SET_SIZE {
+                let trial = match toggle_plug(&plug, a, b, max_plug_pairs) {
+                    Some(trial) => trial,
+                    None => continue,
+                };
+
+                let score = score_fn(&decrypt(ciphertext, scramblers, &trial));
+                if score > best_score {
+                    plug = trial;
+                    best_score = score;
+                    improved = true;
+                }
+            }
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    (plug, best_score)
+}
+
+/// Connect `a` and `b` on the specified plug board, disconnecting their current partners. If `a`
+/// and `b` are already connected, disconnect them instead. Returns `None` if the resulting plug
+/// board would have more pairs than allowed.
+fn toggle_plug(plug: &RuneTable, a: u8, b: u8, max_plug_pairs: usize) -> Option<RuneTable> {
+    let mut trial = *plug;
+    let (a_idx, b_idx) = (a as usize, b as usize);
+
+    if trial[a_idx] == b {
+        trial[a_idx] = a;
+        trial[b_idx] = b;
+        return Some(trial);
+    }
+
+    for x in &[a_idx, b_idx] {
+        let partner = trial[*x] as usize;
+        trial[partner] = partner as u8;
+        trial[*x] = *x as u8;
+    }
+    trial[a_idx] = b;
+    trial[b_idx] = a;
+
+    let num_pairs = trial.iter().enumerate().filter(|(i, x)| **x as usize > *i).count();
+    if num_pairs > max_plug_pairs {
+        return None;
+    }
+
+    Some(trial)
+}
+
+/// Get the plug pairs of the specified plug board array. Letters that are unknown or connected to
+/// themselves are left out.
+pub(crate) fn plug_to_pairs(plug: &RuneTable) -> Vec<(Rune, Rune)> {
+    plug.iter().enumerate()
+        .filter(|(i, x)| **x != UNKNOWN && **x as usize > *i)
+        .map(|(i, x)| (Rune::from_value(i as u8).unwrap(), Rune::from_value(*x).unwrap()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PlugBoard, SteppingMode};
+    use crate::math::Permutation;
+
+    fn wiring(s: &str) -> Permutation {
+        Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+    }
+
+    fn rune(ch: char) -> Rune {
+        Rune::from_char(ch).unwrap()
+    }
+
+    fn runes(s: &str) -> Vec<Rune> {
+        s.chars().map(rune).collect()
+    }
+
+    fn create_test_settings() -> Settings {
+        Settings {
+            rotator_wirings: [
+                wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+                wiring("ekmflgdqvzntowyhxuspaibrcj"),
+                wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+            ],
+            positions: [5, 17, 9],
+            rings: [0, 0, 0],
+            notches: Default::default(),
+            stepping: SteppingMode::Odometer,
+            reflector: wiring("yruhqsldpxngokmiebfzcwvjat"),
+            plug_pairs: vec![
+                (rune('a'), rune('q')), (rune('e'), rune('n')), (rune('t'), rune('x')),
+                (rune('o'), rune('w')), (rune('h'), rune('l')),
+            ],
+        }
+    }
+
+    const PLAINTEXT: &str = "theconvoywillleavetheharbouratfirstlightandproceednorthalongthe\
+        coastuntilitreachestheislandwhereitwillmeettheescortshipsallcaptainsaretokeepstrict\
+        radiosilenceandtoreportanysightingofenemyaircraftorsubmarinesbysignallamponly";
+
+    fn encrypt(settings: &Settings, plaintext: &[Rune]) -> Vec<Rune> {
+        let mut machine = settings.create_enigma().unwrap();
+        plaintext.iter().map(|r| machine.map_rune(*r)).collect()
+    }
+
+    mod plug_tests {
+        use super::*;
+
+        #[test]
+        fn test_toggle_plug() {
+            let plug = toggle_plug(&identity_plug(), 0, 1, 10).unwrap();
+            assert!(PlugBoard::from_perm(Permutation::from_perm(plug.to_vec()).unwrap()).is_ok());
+            assert_eq!(plug_to_pairs(&plug), vec![(rune('a'), rune('b'))]);
+
+            let replaced = toggle_plug(&plug, 1, 2, 10).unwrap();
+            assert!(PlugBoard::from_perm(Permutation::from_perm(replaced.to_vec()).unwrap())
+                .is_ok());
+            assert_eq!(plug_to_pairs(&replaced), vec![(rune('b'), rune('c'))]);
+
+            let removed = toggle_plug(&plug, 0, 1, 10).unwrap();
+            assert!(plug_to_pairs(&removed).is_empty());
+
+            assert!(toggle_plug(&plug, 2, 3, 1).is_none());
+        }
+
+        #[test]
+        fn test_connect() {
+            let mut plug = [UNKNOWN; RUNE_SET_SIZE as usize];
+            assert!(connect(&mut plug, 0, 1));
+            assert!(connect(&mut plug, 1, 0));
+            assert!(!connect(&mut plug, 0, 2));
+            assert!(connect(&mut plug, 3, 3));
+            assert_eq!(plug_to_pairs(&plug), vec![(rune('a'), rune('b'))]);
+        }
+    }
+
+    mod deduce_tests {
+        use super::*;
+
+        #[test]
+        fn test_deduce() {
+            let settings = create_test_settings();
+            let plaintext = runes(PLAINTEXT);
+            let ciphertext = encrypt(&settings, &plaintext);
+
+            let crib = &plaintext[20..50];
+            let deductions = deduce(&ciphertext, crib, 20, &settings).unwrap();
+            assert!(!deductions.is_empty());
+
+            let right: Vec<_> = deductions.iter()
+                .filter(|pairs| pairs.iter().all(|pair| settings.plug_pairs.contains(pair)))
+                .collect();
+            assert_eq!(right.len(), 1);
+        }
+
+        #[test]
+        fn test_deduce_wrong_positions() {
+            let settings = create_test_settings();
+            let plaintext = runes(PLAINTEXT);
+            let ciphertext = encrypt(&settings, &plaintext);
+
+            let mut wrong = settings;
+            wrong.positions = [6, 17, 9];
+            let deductions = deduce(&ciphertext, &plaintext[..40], 0, &wrong).unwrap();
+            assert!(deductions.is_empty());
+        }
+
+        #[test]
+        fn test_deduce_invalid_crib() {
+            let settings = create_test_settings();
+            let ciphertext = encrypt(&settings, &runes(PLAINTEXT));
+
+            assert_eq!(deduce(&ciphertext, &ciphertext[..5], 0, &settings).unwrap_err(),
+                MenuError::SelfEncryption { position: 0 });
+            assert_eq!(deduce(&ciphertext, &ciphertext[..5], ciphertext.len() - 2, &settings)
+                .unwrap_err(), MenuError::LengthMismatch);
+        }
+    }
+
+    mod recover_tests {
+        use super::*;
+
+        fn sorted(pairs: &[(Rune, Rune)]) -> Vec<(Rune, Rune)> {
+            let mut pairs = pairs.to_vec();
+            pairs.sort();
+            pairs
+        }
+
+        #[test]
+        fn test_recover() {
+            let settings = create_test_settings();
+            let ciphertext = encrypt(&settings, &runes(PLAINTEXT));
+
+            let without_plugs = settings.clone().with_plug_pairs(Vec::new());
+            let recovered = recover(&ciphertext, &without_plugs);
+            assert_eq!(sorted(&recovered.plug_pairs), sorted(&settings.plug_pairs));
+        }
+
+        #[test]
+        fn test_recover_with_crib() {
+            let settings = create_test_settings();
+            let plaintext = runes(PLAINTEXT);
+            let ciphertext = encrypt(&settings, &plaintext);
+
+            let recovered = recover_with_crib(&ciphertext, &plaintext[..25], 0, &settings)
+                .unwrap()
+                .unwrap();
+            assert_eq!(sorted(&recovered.plug_pairs), sorted(&settings.plug_pairs));
+        }
+    }
+}
@@ -0,0 +1,433 @@
+//! This module implements Marian Rejewski's recovery of the wiring of an unknown rotator from the
+//! indicators of a single day.
+//!
+//! While the [`rejewski`] module finds the ground setting of a machine whose wirings are known,
+//! this module recovers the wiring of the fast rotator itself. Let `P` be the cyclic shift of the
+//! runes by one, `N` the wiring of the fast rotator, `S` the plug board and `Q` the combined
+//! permutation of the middle rotator, the slow rotator and the reflector. Writing products from
+//! left to right like `Permutation::compose`, the machine realizes the permutation
+//!
+//! ```text
+//! Ai = S P^k N P^-k Q P^k N^-1 P^-k S
+//! ```
+//!
+//! at the `i`-th key press of an indicator, where `k` is the offset of the fast rotator at that
+//! key press. As long as the middle rotator does not step within the indicator, `Q` is the same at
+//! all 6 key presses. The recovery works in two steps:
+//!
+//! 1. The characteristic equations `A1A4 = M1`, `A2A5 = M2` and `A3A6 = M3` are solved for the
+//!    involutions `A1, A2, ..., A6` with the `solve_characteristic` function. The solutions pair
+//!    up the cycles of each product, and message keys known from operator habits narrow them down.
+//! 2. With the plug board known, e.g. on days on which no plugs were used, and the offset of the
+//!    fast rotator known, e.g. from captured key lists, the permutations `Ti = P^-k S Ai S P^k`
+//!    satisfy `Ti Ti+1 = N P^-k W P^k N^-1` for the unknown `W = Q P^-1 Q P`. Thus consecutive
+//!    products are conjugated by `X = N P^-1 N^-1`, which is found by `math::solve_conjugation`,
+//!    and `N` is found from `X` by solving the conjugation equation once more. This is done by
+//!    the `recover_rotor` function.
+//!
+//! The wiring is only determined up to a rotation of its outputs, since the rotation can be
+//! absorbed into `Q`. Each recovered candidate comes with the permutation `Q` that makes it
+//! reproduce the observed permutations. Likewise, a wrong offset gives the wiring as seen under a
+//! different ring setting.
+//!
+//! ```
+//! # use enigma::{Enigma, PlugBoard, Reflector, Rotator, RotatorGroup};
+//! # use enigma::analysis::wiring;
+//! # use enigma::math::Permutation;
+//! # use enigma::utils::RUNE_SET_SIZE;
+//! #
+//! let wiring = Permutation::from_perm(
+//!     b"ekmflgdqvzntowyhxuspaibrcj".iter().map(|b| b - b'a').collect()).unwrap();
+//! # let create_rotator = |wiring: &[u8], offset| {
+//! #     let perm = wiring.iter().map(|b| b - b'a').collect();
+//! #     Rotator::new(Permutation::from_perm(perm).unwrap(), offset).unwrap()
+//! # };
+//! # let rotators = RotatorGroup::new([
+//! #     Rotator::new(wiring.clone(), 4).unwrap(),
+//! #     create_rotator(b"ajdksiruxblhwtmcqgznpyfvoe", 9),
+//! #     create_rotator(b"bdfhjlcprtxvznyeiwgakmusqo", 17),
+//! # ]);
+//! # let reflector = Reflector::from_perm(Permutation::from_perm(
+//! #     b"yruhqsldpxngokmiebfzcwvjat".iter().map(|b| b - b'a').collect()).unwrap()).unwrap();
+//! # let plug_board = PlugBoard::from_perm(Permutation::identity(RUNE_SET_SIZE)).unwrap();
+//! let mut machine: Enigma = // ...
+//! #     Enigma::new(plug_board, rotators, reflector);
+//!
+//! // Pretend that the permutations of the day have been recovered from its indicators.
+//! let perms = [(); 6].map(|_| {
+//!     let perm = machine.permutation();
+//!     machine.advance_rotators();
+//!     perm
+//! });
+//!
+//! let candidates = wiring::recover_rotor(&perms, &Permutation::identity(RUNE_SET_SIZE), 4);
+//! assert!(candidates.iter().any(|candidate| candidate.wiring == wiring));
+//! ```
+//!
+//! [`rejewski`]: ../rejewski/index.html
+//!
+
+use crate::analysis::rejewski::{Characteristic, CharacteristicError, Indicator, INDICATOR_LEN};
+use crate::math::{self, Permutation};
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// A message key known to have been enciphered as an indicator, e.g. guessed from the habits of
+/// an operator.
+pub type KnownKey = (Indicator, [Rune; 3]);
+
+/// A candidate wiring of the fast rotator.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RotorCandidate {
+    /// The wiring of the fast rotator, in the form accepted by `Rotator::new`.
+    pub wiring: Permutation,
+
+    /// The combined permutation of the middle rotator, the slow rotator and the reflector, which
+    /// reproduces the observed permutations together with the wiring.
+    pub inner: Permutation,
+}
+
+/// Solve the characteristic equations of the specified characteristic, e.g. find the involutions
+/// without fixed points `A1, A2, ..., A6` whose products `A1A4`, `A2A5` and `A3A6` are the products
+/// of the characteristic, and which encipher the specified known message keys as their
+/// indicators. At most `limit` solutions are returned.
+pub fn solve_characteristic(characteristic: &Characteristic, keys: &[KnownKey], limit: usize)
+    -> Vec<[Permutation; INDICATOR_LEN]> {
+    let factors: Vec<Vec<(Permutation, Permutation)>> = characteristic.products().iter()
+        .enumerate()
+        .map(|(i, product)| {
+            let hints: Vec<(u8, u8)> = keys.iter()
+                .map(|(indicator, key)| (key[i].value(), indicator.runes()[i].value()))
+                .collect();
+            involution_factors(product, &hints, limit)
+        })
+        .collect();
+
+    let mut solutions = Vec::new();
+    'outer: for (a1, a4) in &factors[0] {
+        for (a2, a5) in &factors[1] {
+            for (a3, a6) in &factors[2] {
+                if solutions.len() >= limit {
+                    break 'outer;
+                }
+                solutions.push([a1.clone(), a2.clone(), a3.clone(), a4.clone(), a5.clone(),
+                    a6.clone()]);
+            }
+        }
+    }
+    solutions
+}
+
+/// Recover the candidate wirings of the fast rotator from the specified permutations realized by
+/// the machine at the 6 key presses of an indicator, given the plug board and the offset of the
+/// fast rotator at the first key press.
+///
+/// The middle rotator should not step within the indicator. Returns an empty vector if the
+/// permutations cannot be produced by a single fast rotator.
+pub fn recover_rotor(perms: &[Permutation; INDICATOR_LEN], plug_board: &Permutation, offset: u8)
+    -> Vec<RotorCandidate> {
+    let n = RUNE_SET_SIZE;
+    let shifts: Vec<Permutation> = (0..INDICATOR_LEN)
+        .map(|i| shift(offset as usize + i))
+        .collect();
+
+    // Strip the plug board and undo the stepping of the fast rotator: `Ti = N P^-k Q P^k N^-1`.
+    let perms: Vec<Permutation> = perms.iter().map(|perm| perm.conjugate_by(plug_board)).collect();
+    let stripped: Vec<Permutation> = perms.iter().zip(&shifts)
+        .map(|(perm, shift)| perm.conjugate_by(shift))
+        .collect();
+    let products: Vec<Permutation> = stripped.windows(2)
+        .map(|pair| pair[0].compose(&pair[1]))
+        .collect();
+
+    // Consecutive products are conjugated by `X = N P^-1 N^-1`, a single cycle of all runes.
+    let relations: Vec<(&Permutation, &Permutation)> = products.windows(2)
+        .map(|pair| (&pair[1], &pair[0]))
+        .collect();
+    let back_shift = shift(n as usize - 1);
+
+    let mut candidates = Vec::new();
+    for x in math::solve_conjugation(n, &relations, &[], n as usize) {
+        if x.max_cycle_len() != n as usize {
+            continue;
+        }
+
+        for wiring in math::solve_conjugation(n, &[(&x, &back_shift)], &[], n as usize) {
+            if let Some(inner) = inner_permutation(&perms, &shifts, &wiring) {
+                candidates.push(RotorCandidate { wiring, inner });
+            }
+        }
+    }
+    candidates
+}
+
+/// Recover the candidate wirings of the fast rotator from the specified indicators of a day like
+/// `recover_rotor`, trying at most `limit` solutions of the characteristic equations.
+pub fn recover_from_indicators(indicators: &[Indicator], keys: &[KnownKey],
+    plug_board: &Permutation, offset: u8, limit: usize)
+    -> Result<Vec<RotorCandidate>, CharacteristicError> {
+    let characteristic = Characteristic::from_indicators(indicators)?;
+
+    let mut candidates: Vec<RotorCandidate> = Vec::new();
+    for perms in solve_characteristic(&characteristic, keys, limit) {
+        for candidate in recover_rotor(&perms, plug_board, offset) {
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    Ok(candidates)
+}
+
+/// Find the pairs of involutions without fixed points `(a, d)` such that `a.compose(&d)` is the
+/// specified product and `a.map(i) == j` for every pair `(i, j)` of the specified hints. At most
+/// `limit` pairs are returned.
+///
+/// The involution `a` maps every cycle of the product onto another cycle of the same length,
+/// reversing its direction, so the solutions are enumerated by pairing up the cycles.
+fn involution_factors(product: &Permutation, hints: &[(u8, u8)], limit: usize)
+    -> Vec<(Permutation, Permutation)> {
+    let mut images = vec![None; product.len()];
+    for (i, j) in hints {
+        for (x, y) in [(*i, *j), (*j, *i)] {
+            match images[x as usize] {
+                Some(image) if image != y => return Vec::new(),
+                _ => images[x as usize] = Some(y),
+            }
+        }
+    }
+
+    let mut solutions = Vec::new();
+    pair_cycles(&product.cycles(), &mut vec![None; product.len()], &images, limit,
+        &mut solutions);
+    solutions.into_iter()
+        .map(|a| {
+            let d = a.compose(product);
+            (a, d)
+        })
+        .collect()
+}
+
+/// Pair up the remaining cycles recursively, extending the specified partial involution.
+fn pair_cycles(cycles: &[Vec<u8>], involution: &mut Vec<Option<u8>>, hints: &[Option<u8>],
+    limit: usize, solutions: &mut Vec<Permutation>) {
+    if solutions.len() >= limit {
+        return;
+    }
+
+    let first = match cycles.iter().position(|c| involution[c[0] as usize].is_none()) {
+        Some(first) => first,
+        None => {
+            let perm = involution.iter().map(|image| image.unwrap()).collect();
+            solutions.push(unsafe { Permutation::from_perm_unchecked(perm) });
+            return;
+        },
+    };
+
+    let c = &cycles[first];
+    let len = c.len();
+    for d in &cycles[first + 1..] {
+        if d.len() != len || involution[d[0] as usize].is_some() {
+            continue;
+        }
+
+        // Map the `j`-th element of `c` to the `(s - j)`-th element of `d`.
+        for s in 0..len {
+            let pairs: Vec<(u8, u8)> = (0..len).map(|j| (c[j], d[(s + len - j) % len])).collect();
+            let consistent = pairs.iter().all(|(x, y)| {
+                hints[*x as usize].is_none_or(|h| h == *y) &&
+                    hints[*y as usize].is_none_or(|h| h == *x)
+            });
+            if !consistent {
+                continue;
+            }
+
+            for (x, y) in &pairs {
+                involution[*x as usize] = Some(*y);
+                involution[*y as usize] = Some(*x);
+            }
+            pair_cycles(cycles, involution, hints, limit, solutions);
+            for (x, y) in &pairs {
+                involution[*x as usize] = None;
+                involution[*y as usize] = None;
+            }
+        }
+    }
+}
+
+/// Get the permutation `Q` of the machine with the specified fast rotator wiring, if it is the
+/// same at all key presses and it is an involution without fixed points.
+fn inner_permutation(perms: &[Permutation], shifts: &[Permutation], wiring: &Permutation)
+    -> Option<Permutation> {
+    let mut inner: Option<Permutation> = None;
+    for (perm, shift) in perms.iter().zip(shifts) {
+        // The fast rotator maps forward with `P^k N P^-k` at offset `k`.
+        let rotor = shift.compose(wiring).compose(&shift.inverse());
+        let q = perm.conjugate_by(&rotor);
+        match &inner {
+            Some(existing) if *existing != q => return None,
+            _ => inner = Some(q),
+        }
+    }
+
+    inner.filter(|q| (0..q.n()).all(|x| q.map(x) != x && q.map(q.map(x)) == x))
+}
+
+/// Get the permutation shifting every rune by the specified amount.
+fn shift(amount: usize) -> Permutation {
+    let perm = (0..RUNE_SET_SIZE)
+        .map(|x| ((x as usize + amount) % RUNE_SET_SIZE as usize) as u8)
+        .collect();
+    unsafe { Permutation::from_perm_unchecked(perm) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{Enigma, PlugBoard, Reflector, Rotator, RotatorGroup};
+    use crate::math::PermutationBuilder;
+
+    const FAST_WIRING: &str = "ekmflgdqvzntowyhxuspaibrcj";
+
+    fn wiring(s: &str) -> Permutation {
+        Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+    }
+
+    fn create_test_plug_board() -> Permutation {
+        PermutationBuilder::new(RUNE_SET_SIZE)
+            .swap(0, 12).swap(3, 21).swap(7, 19).swap(9, 25).swap(14, 17)
+            .build()
+    }
+
+    fn create_test_enigma(plug_board: &Permutation) -> Enigma {
+        let rotators = RotatorGroup::new([
+            Rotator::new(wiring(FAST_WIRING), 5).unwrap(),
+            Rotator::new(wiring("ajdksiruxblhwtmcqgznpyfvoe"), 11).unwrap(),
+            Rotator::new(wiring("bdfhjlcprtxvznyeiwgakmusqo"), 2).unwrap(),
+        ]);
+        let reflector = Reflector::from_perm(wiring("yruhqsldpxngokmiebfzcwvjat")).unwrap();
+        Enigma::new(PlugBoard::from_perm(plug_board.clone()).unwrap(), rotators, reflector)
+    }
+
+    fn create_test_perms(machine: &Enigma) -> [Permutation; INDICATOR_LEN] {
+        let mut machine = machine.clone();
+        [(); INDICATOR_LEN].map(|_| {
+            let perm = machine.permutation();
+            machine.advance_rotators();
+            perm
+        })
+    }
+
+    /// Encipher pseudo-random message keys twice at the ground setting of the specified machine,
+    /// and return the indicators together with their keys.
+    fn create_test_keys(machine: &Enigma, count: usize) -> Vec<KnownKey> {
+        let mut seed = 12345u32;
+        let mut next_rune = || {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            Rune::from_value(((seed >> 16) % RUNE_SET_SIZE as u32) as u8).unwrap()
+        };
+
+        (0..count)
+            .map(|_| {
+                let key = [next_rune(), next_rune(), next_rune()];
+                let mut machine = machine.clone();
+                let mut runes = [key[0]; INDICATOR_LEN];
+                for (i, rune) in runes.iter_mut().enumerate() {
+                    *rune = machine.map_rune(key[i % 3]);
+                }
+                (Indicator::new(runes), key)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_shift() {
+        assert_eq!(shift(0), Permutation::identity(RUNE_SET_SIZE));
+        assert_eq!(shift(3).map(24), 1);
+        assert_eq!(shift(1).compose(&shift(25)), Permutation::identity(RUNE_SET_SIZE));
+    }
+
+    #[test]
+    fn test_involution_factors() {
+        let machine = create_test_enigma(&Permutation::identity(RUNE_SET_SIZE));
+        let perms = create_test_perms(&machine);
+        let product = perms[0].compose(&perms[3]);
+
+        let factors = involution_factors(&product, &[], usize::MAX);
+        assert!(factors.contains(&(perms[0].clone(), perms[3].clone())));
+        for (a, d) in &factors {
+            assert_eq!(a.compose(d), product);
+            assert_eq!(a.compose(a), Permutation::identity(RUNE_SET_SIZE));
+            assert_eq!(d.compose(d), Permutation::identity(RUNE_SET_SIZE));
+        }
+
+        let hints: Vec<(u8, u8)> = (0..6).map(|x| (x, perms[0].map(x))).collect();
+        let factors = involution_factors(&product, &hints, usize::MAX);
+        assert!(factors.len() < 10);
+        assert!(factors.contains(&(perms[0].clone(), perms[3].clone())));
+
+        assert!(involution_factors(&product, &[(0, 0)], usize::MAX).is_empty());
+    }
+
+    #[test]
+    fn test_solve_characteristic() {
+        let machine = create_test_enigma(&create_test_plug_board());
+        let perms = create_test_perms(&machine);
+        let characteristic = Characteristic::from_machine(&machine);
+        let keys = create_test_keys(&machine, 4);
+
+        let solutions = solve_characteristic(&characteristic, &keys, 1000);
+        assert!(!solutions.is_empty());
+        assert!(solutions.len() < 1000);
+        assert!(solutions.contains(&perms));
+        assert_eq!(solve_characteristic(&characteristic, &keys, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_recover_rotor() {
+        let identity = Permutation::identity(RUNE_SET_SIZE);
+        let perms = create_test_perms(&create_test_enigma(&identity));
+        let candidates = recover_rotor(&perms, &identity, 5);
+        assert!(!candidates.is_empty());
+
+        // Every candidate is the true wiring with its outputs rotated.
+        let expected = wiring(FAST_WIRING);
+        assert!(candidates.iter().any(|candidate| candidate.wiring == expected));
+        for candidate in &candidates {
+            let rotation = expected.inverse().compose(&candidate.wiring);
+            assert!((0..RUNE_SET_SIZE as usize).any(|k| rotation == shift(k)));
+        }
+
+        // A wrong offset is indistinguishable from a different ring setting.
+        let candidates = recover_rotor(&perms, &identity, 6);
+        let rotated = expected.conjugate_by(&shift(1));
+        assert!(candidates.iter().any(|candidate| candidate.wiring == rotated));
+
+        let mut shuffled = perms.clone();
+        shuffled.swap(1, 2);
+        assert!(recover_rotor(&shuffled, &identity, 5).is_empty());
+    }
+
+    #[test]
+    fn test_recover_rotor_plug_board() {
+        let plug_board = create_test_plug_board();
+        let perms = create_test_perms(&create_test_enigma(&plug_board));
+        let candidates = recover_rotor(&perms, &plug_board, 5);
+        assert!(candidates.iter().any(|candidate| candidate.wiring == wiring(FAST_WIRING)));
+    }
+
+    #[test]
+    fn test_recover_from_indicators() {
+        let identity = Permutation::identity(RUNE_SET_SIZE);
+        let machine = create_test_enigma(&identity);
+        let keys = create_test_keys(&machine, 200);
+        let indicators: Vec<Indicator> = keys.iter().map(|(indicator, _)| *indicator).collect();
+
+        let candidates = recover_from_indicators(&indicators, &keys[..4], &identity, 5, 1000)
+            .unwrap();
+        assert!(candidates.iter().any(|candidate| candidate.wiring == wiring(FAST_WIRING)));
+
+        assert!(matches!(recover_from_indicators(&indicators[..3], &[], &identity, 5, 1000),
+            Err(CharacteristicError::Incomplete { .. })));
+    }
+}
@@ -0,0 +1,243 @@
+//! This module provides adapters that map the bytes flowing through asynchronous readers and
+//! writers of the `tokio` runtime, so that streams can be enciphered without buffering whole
+//! messages. It is only available with the `tokio` feature.
+//!
+//! The adapters are created by the `Enigma::map_async_read` and `Enigma::map_async_write`
+//! associate functions. They map the bytes like `RotorMachine::map_byte`: ASCII letters are mapped
+//! with their case preserved, and other bytes pass through unchanged.
+//!
+//! ```
+//! # use enigma::{Settings, SteppingMode};
+//! # use enigma::math::Permutation;
+//! # use tokio::io::{AsyncReadExt, AsyncWriteExt};
+//! #
+//! # fn wiring(s: &str) -> Permutation {
+//! #     Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+//! # }
+//! # let settings = Settings {
+//! #     rotator_wirings: [
+//! #         wiring("ekmflgdqvzntowyhxuspaibrcj"),
+//! #         wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+//! #         wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+//! #     ],
+//! #     positions: [0, 5, 19],
+//! #     rings: [0, 0, 0],
+//! #     notches: Default::default(),
+//! #     stepping: SteppingMode::Odometer,
+//! #     reflector: wiring("yruhqsldpxngokmiebfzcwvjat"),
+//! #     plug_pairs: Vec::new(),
+//! # };
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let mut writer = settings.create_enigma().unwrap().map_async_write(Vec::new());
+//! writer.write_all(b"Hello, World").await.unwrap();
+//! writer.flush().await.unwrap();
+//! let (_, ciphertext) = writer.into_parts();
+//!
+//! let mut reader = settings.create_enigma().unwrap().map_async_read(&ciphertext[..]);
+//! let mut plaintext = String::new();
+//! reader.read_to_string(&mut plaintext).await.unwrap();
+//! assert_eq!(plaintext, "Hello, World");
+//! # });
+//! ```
+//!
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{Enigma, RotorMachine};
+
+impl Enigma {
+    /// Wrap the specified reader so that the bytes read from it are mapped by this machine.
+    pub fn map_async_read<R: AsyncRead + Unpin>(self, reader: R) -> MapAsyncRead<R> {
+        MapAsyncRead { machine: self, reader }
+    }
+
+    /// Wrap the specified writer so that the bytes written to it are mapped by this machine.
+    pub fn map_async_write<W: AsyncWrite + Unpin>(self, writer: W) -> MapAsyncWrite<W> {
+        MapAsyncWrite { machine: self, writer, pending: Vec::new(), written: 0 }
+    }
+}
+
+/// An asynchronous reader that maps the bytes read from an inner reader.
+#[derive(Debug)]
+pub struct MapAsyncRead<R> {
+    machine: Enigma,
+    reader: R,
+}
+
+impl<R> MapAsyncRead<R> {
+    /// Get the machine, in its current state, and the inner reader.
+    pub fn into_parts(self) -> (Enigma, R) {
+        (self.machine, self.reader)
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for MapAsyncRead<R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>)
+        -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let start = buf.filled().len();
+        match Pin::new(&mut this.reader).poll_read(cx, buf) {
+            Poll::Ready(Ok(())) => {
+                for byte in &mut buf.filled_mut()[start..] {
+                    *byte = this.machine.map_byte(*byte);
+                }
+                Poll::Ready(Ok(()))
+            },
+            other => other,
+        }
+    }
+}
+
+/// An asynchronous writer that maps the bytes before writing them to an inner writer.
+///
+/// The bytes are mapped as soon as they are accepted, and the mapped bytes that the inner writer
+/// has not accepted yet are kept in a buffer. Flush the writer to write all of them.
+#[derive(Debug)]
+pub struct MapAsyncWrite<W> {
+    machine: Enigma,
+    writer: W,
+    pending: Vec<u8>,
+    written: usize,
+}
+
+impl<W> MapAsyncWrite<W> {
+    /// Get the machine, in its current state, and the inner writer. Mapped bytes that have not
+    /// been written to the inner writer are lost.
+    pub fn into_parts(self) -> (Enigma, W) {
+        (self.machine, self.writer)
+    }
+}
+
+impl<W: AsyncWrite + Unpin> MapAsyncWrite<W> {
+    /// Write the pending mapped bytes to the inner writer.
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.pending.len() {
+            match Pin::new(&mut self.writer).poll_write(cx, &self.pending[self.written..]) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(n)) => self.written += n,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        self.pending.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for MapAsyncWrite<W> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8])
+        -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        match this.poll_pending(cx) {
+            Poll::Ready(Ok(())) => (),
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        }
+
+        for byte in buf {
+            let mapped = this.machine.map_byte(*byte);
+            this.pending.push(mapped);
+        }
+
+        // The bytes are accepted regardless of whether the inner writer can take them right now.
+        if let Poll::Ready(Err(e)) = this.poll_pending(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_pending(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.writer).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_pending(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.writer).poll_shutdown(cx),
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::{Settings, SteppingMode};
+    use crate::math::Permutation;
+
+    fn wiring(s: &str) -> Permutation {
+        Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+    }
+
+    fn create_enigma() -> Enigma {
+        let settings = Settings {
+            rotator_wirings: [
+                wiring("ekmflgdqvzntowyhxuspaibrcj"),
+                wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+                wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+            ],
+            positions: [3, 5, 19],
+            rings: [0, 0, 0],
+            notches: Default::default(),
+            stepping: SteppingMode::Odometer,
+            reflector: wiring("yruhqsldpxngokmiebfzcwvjat"),
+            plug_pairs: Vec::new(),
+        };
+        settings.create_enigma().unwrap()
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn test_map_async_read() {
+        let input = b"Attack at dawn!".repeat(100);
+        let mut reader = create_enigma().map_async_read(&input[..]);
+        let mut output = Vec::new();
+        block_on(reader.read_to_end(&mut output)).unwrap();
+
+        let mut machine = create_enigma();
+        let expected: Vec<u8> = input.iter().map(|b| machine.map_byte(*b)).collect();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_map_async_write() {
+        let input = b"Attack at dawn!".repeat(100);
+        let (client, mut server) = tokio::io::duplex(7);
+        let mut writer = create_enigma().map_async_write(client);
+
+        let output = block_on(async {
+            let read = async {
+                let mut output = Vec::new();
+                server.read_to_end(&mut output).await.unwrap();
+                output
+            };
+            let write = async {
+                for chunk in input.chunks(10) {
+                    writer.write_all(chunk).await.unwrap();
+                }
+                writer.shutdown().await.unwrap();
+            };
+            tokio::join!(read, write).0
+        });
+
+        let mut machine = create_enigma();
+        let expected: Vec<u8> = input.iter().map(|b| machine.map_byte(*b)).collect();
+        assert_eq!(output, expected);
+    }
+}
@@ -0,0 +1,200 @@
+extern crate enigma;
+
+extern crate clap;
+extern crate serde;
+extern crate serde_json;
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use enigma::{Reflector, Rotator, RotatorGroup, Rune};
+use enigma::analysis::bombe::{Bombe, Stop};
+use enigma::analysis::menu;
+use enigma::math::{Permutation, PermutationBuilder};
+use enigma::utils::RUNE_SET_SIZE;
+
+use serde::Deserialize;
+
+#[derive(Clone, Debug)]
+struct InvalidConfigError {
+    message: String,
+}
+
+impl InvalidConfigError {
+    fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl Display for InvalidConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("invalid configuration: {}", self.message))
+    }
+}
+
+impl Error for InvalidConfigError { }
+
+#[derive(Clone, Debug, Deserialize)]
+struct Config {
+    rotators: [String; 3],
+    reflector: Vec<[Rune; 2]>,
+    crib: String,
+    ciphertext: String,
+    #[serde(default)]
+    crib_offset: usize,
+}
+
+impl Config {
+    fn create_rotator(&self, index: usize) -> Rotator {
+        let perm = match create_permutation_from_string(&self.rotators[index]) {
+            Ok(perm) => perm,
+            Err(e) => {
+                eprintln!("invalid rotator setting: {}", e);
+                std::process::exit(1);
+            },
+        };
+
+        match Rotator::new(perm, 0) {
+            Ok(rotator) => rotator,
+            Err(e) => {
+                eprintln!("invalid rotator setting: {}", e);
+                std::process::exit(1);
+            },
+        }
+    }
+
+    fn create_rotator_group(&self) -> RotatorGroup {
+        RotatorGroup::new([
+            self.create_rotator(0),
+            self.create_rotator(1),
+            self.create_rotator(2),
+        ])
+    }
+
+    fn create_reflector(&self) -> Reflector {
+        match Reflector::from_perm(create_permutation_from_swaps(&self.reflector)) {
+            Ok(reflector) => reflector,
+            Err(e) => {
+                eprintln!("invalid reflector setting: {}", e);
+                std::process::exit(1);
+            },
+        }
+    }
+}
+
+fn create_permutation_from_swaps(swaps: &[[Rune; 2]]) -> Permutation {
+    let mut builder = PermutationBuilder::new(RUNE_SET_SIZE);
+
+    for [lhs, rhs] in swaps {
+        builder = builder.swap(lhs.value(), rhs.value());
+    }
+
+    builder.build()
+}
+
+fn create_permutation_from_string(s: &str) -> Result<Permutation, InvalidConfigError> {
+    let mut perm: Vec<u8> = Vec::with_capacity(s.len());
+
+    for ch in s.chars() {
+        if !ch.is_ascii_alphabetic() {
+            return Err(InvalidConfigError::new(
+                format!("{} is not an ASCII alphabetic character", ch)));
+        }
+
+        perm.push(ch.to_ascii_lowercase() as u8 - b'a');
+    }
+
+    Permutation::from_perm(perm)
+        .map_err(|e| InvalidConfigError::new(
+            format!("invalid permutation: {}", e)))
+}
+
+fn load_config(path: &Path) -> Config {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Failed to read configuration file: {}", e);
+            std::process::exit(1);
+        },
+    };
+
+    match serde_json::from_str::<Config>(&content) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Failed to parse configuration: {}", e);
+            std::process::exit(1);
+        },
+    }
+}
+
+fn parse_runes(s: &str, name: &str) -> Vec<Rune> {
+    let mut runes = Vec::with_capacity(s.len());
+    for ch in s.chars() {
+        match Rune::from_char(ch) {
+            Ok(rune) => runes.push(rune),
+            Err(_) if ch.is_whitespace() => (),
+            Err(_) => {
+                eprintln!("Invalid character in {}: {:?}", name, ch);
+                std::process::exit(1);
+            },
+        }
+    }
+    runes
+}
+
+fn format_stop(stop: &Stop) -> String {
+    let positions: Vec<String> = stop.offsets().iter()
+        .map(|offset| Rune::from_value(*offset).unwrap().into_string())
+        .collect();
+    let steckers: Vec<String> = stop.steckers().iter()
+        .map(|(a, b)| format!("{}{}", a, b))
+        .collect();
+    format!("{}  {}={}  {}", positions.join(" "), stop.test_letter(), stop.test_stecker(),
+        steckers.join(" "))
+}
+
+fn main() {
+    let args = clap::App::new("enigma-bombe")
+        .about("Search rotor positions with a simulated Turing-Welchman Bombe")
+        .arg(clap::Arg::with_name("config")
+            .short("c")
+            .long("config")
+            .takes_value(true)
+            .required(true)
+            .help("Path to the configuration file"))
+        .arg(clap::Arg::with_name("all")
+            .short("a")
+            .long("all")
+            .help("Also print stops whose deduced steckers contradict each other"))
+        .get_matches();
+
+    let config = load_config(Path::new(args.value_of("config").unwrap()));
+    let crib = parse_runes(&config.crib, "crib");
+    let ciphertext = parse_runes(&config.ciphertext, "ciphertext");
+    if config.crib_offset + crib.len() > ciphertext.len() {
+        eprintln!("The crib does not fit into the ciphertext at offset {}", config.crib_offset);
+        std::process::exit(1);
+    }
+
+    let ciphertext = &ciphertext[config.crib_offset..config.crib_offset + crib.len()];
+    let menu = match menu::build(&crib, ciphertext) {
+        Ok(menu) => menu,
+        Err(e) => {
+            eprintln!("Invalid crib: {}", e);
+            std::process::exit(1);
+        },
+    };
+    println!("Menu has {} letters and {} closures", menu.letters().len(), menu.closures());
+
+    println!("Running the Bombe over all rotor positions");
+    let bombe = Bombe::new(config.create_rotator_group(), config.create_reflector());
+    let stops: Vec<Stop> = bombe.run(&menu).into_iter()
+        .filter(|stop| args.is_present("all") || stop.is_consistent())
+        .collect();
+
+    for stop in &stops {
+        println!("Stop: {}", format_stop(stop));
+    }
+    println!("{} stops found. Positions are given at the first letter of the crib.", stops.len());
+}
@@ -1,240 +1,1005 @@
 extern crate enigma;
 
 extern crate clap;
+extern crate crossterm;
 extern crate serde;
-extern crate serde_json;
+extern crate toml;
 
-use std::error::Error;
-use std::fmt::{Display, Formatter};
+use std::io::{IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
 
+use crossterm::{cursor, queue, terminal};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::Stylize;
 use serde::Deserialize;
 
-use enigma::{Enigma, PlugBoard, Reflector, Rotator, RotatorGroup, Rune};
-use enigma::math::{Permutation, PermutationBuilder};
-use enigma::utils::{RUNE_SET_SIZE};
+use enigma::{Enigma, Rune};
+use enigma::config::{Config, LetterPair, RotorSpec};
+use enigma::math::Permutation;
+use enigma::state::EnigmaState;
+use enigma::testvectors::{REFLECTORS, ROTATORS};
+use enigma::utils::RUNE_SET_SIZE;
+use enigma::utils::format::{LetterCase, TextFormatter};
+use enigma::utils::morse;
+
+/// A test fixture of the `compat` subcommand, e.g. a message enciphered by another simulator, in
+/// TOML format. The machine is a Wehrmacht Enigma with the rotators I to V and the reflector A or
+/// B, whose rotators step before each letter is enciphered.
+#[derive(Clone, Debug, Deserialize)]
+struct Fixture {
+    /// The name of the fixture, e.g. the simulator and settings it was produced with.
+    #[serde(default)]
+    name: String,
+
+    /// The names of the rotators, from left to right.
+    rotors: [String; 3],
+
+    /// The name of the reflector.
+    reflector: String,
+
+    /// The ring settings as letters, from left to right.
+    #[serde(default = "Fixture::default_rings")]
+    rings: String,
+
+    /// The pairs of letters connected on the plug board, separated by spaces.
+    #[serde(default)]
+    plug_pairs: String,
 
-#[derive(Clone, Debug)]
-struct InvalidConfigError {
-    message: String,
+    /// The window letters before the first letter is enciphered, from left to right.
+    start: String,
+
+    /// The plaintext. Characters that are not letters are skipped.
+    plaintext: String,
+
+    /// The expected ciphertext. Characters that are not letters are skipped.
+    ciphertext: String,
 }
 
-impl InvalidConfigError {
-    fn new(message: String) -> Self {
-        Self { message }
+impl Fixture {
+    fn default_rings() -> String {
+        String::from("AAA")
     }
-}
 
-impl Display for InvalidConfigError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("invalid config: {}", self.message))
+    /// Create the machine described by this fixture.
+    fn create_enigma(&self) -> Result<Enigma, String> {
+        let letters = |field: &str, s: &str| -> Result<[u8; 3], String> {
+            parse_positions(s).map_err(|e| format!("invalid {}: {}", field, e))
+        };
+
+        let mut rotor_order = [0u8; 3];
+        for (index, name) in rotor_order.iter_mut().zip(&self.rotors) {
+            *index = ROTATORS.iter().position(|(n, _, _)| n == name)
+                .ok_or_else(|| format!("unknown rotor {:?}", name))? as u8;
+        }
+        let reflector = REFLECTORS.iter().position(|(n, _)| *n == self.reflector)
+            .ok_or_else(|| format!("unknown reflector {:?}", self.reflector))? as u8;
+
+        let mut plug_pairs = Vec::new();
+        for pair in self.plug_pairs.split_whitespace() {
+            let runes: Vec<Rune> = pair.chars().map(Rune::from_char).collect::<Result<_, _>>()
+                .map_err(|_| format!("invalid plug pair {:?}", pair))?;
+            if runes.len() != 2 {
+                return Err(format!("invalid plug pair {:?}", pair));
+            }
+            plug_pairs.push((runes[0], runes[1]));
+        }
+
+        let state = EnigmaState {
+            rotor_order,
+            reflector,
+            offsets: letters("start", &self.start)?,
+            rings: letters("rings", &self.rings)?,
+            plug_pairs,
+        };
+        let mut machine = state.create_enigma().map_err(|e| e.to_string())?;
+
+        // The rotators of the Wehrmacht Enigma step before the signal passes through them.
+        machine.advance_rotators();
+        Ok(machine)
     }
 }
 
-impl Error for InvalidConfigError { }
+/// Create the machine described by the specified configuration.
+fn create_enigma(config: &Config) -> Enigma {
+    let settings = match config.to_settings() {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Invalid rotator setting: {}", e);
+            std::process::exit(1);
+        },
+    };
 
-#[derive(Clone, Debug, Deserialize)]
-struct Config {
-    plug_board: Vec<[char; 2]>,
-    rotators: [(String, u8); 3],
-    reflector: Vec<[char; 2]>,
+    match settings.create_enigma() {
+        Ok(machine) => machine,
+        Err(e) => {
+            eprintln!("Invalid configuration: {}", e);
+            std::process::exit(1);
+        },
+    }
 }
 
-impl Config {
-    fn create_plug_board(&self) -> PlugBoard {
-        let perm = match create_permutation_from_swaps(&self.plug_board) {
-            Ok(perm) => perm,
-            Err(e) => {
-                eprintln!("Invalid plug board setting: {}", e);
-                std::process::exit(1);
-            },
-        };
+/// Get the name of the specified rotator, or its wiring in uppercase if it is not given by name.
+fn rotor_label(spec: &RotorSpec) -> String {
+    match spec {
+        RotorSpec::Name(name) => name.clone(),
+        RotorSpec::Wiring(wiring) => (0..wiring.n()).map(|i| (b'A' + wiring.map(i)) as char)
+            .collect(),
+    }
+}
 
-        match PlugBoard::from_perm(perm) {
-            Ok(board) => board,
+/// Check the specified configuration thoroughly and describe every problem found, naming the
+/// offending letters. Returns an empty vector if the configuration is valid.
+///
+/// Problems within a single value, e.g. invalid wirings, are already rejected while parsing, so
+/// only the problems that involve several values are checked here.
+fn diagnose(config: &Config) -> Vec<String> {
+    let mut problems = diagnose_pairs("plug board", &config.plug_board, false);
+
+    // The wirings of the rotators, for those whose names are known.
+    let catalog = config.create_catalog();
+    let mut wirings: Vec<Option<Permutation>> = Vec::new();
+    for i in 0..config.rotators.len() {
+        match config.rotator_wiring(&catalog, i) {
+            Ok(wiring) => wirings.push(Some(wiring)),
             Err(e) => {
-                eprintln!("Invalid plug board setting: {}", e);
-                std::process::exit(1);
+                problems.push(format!("rotator {}: {}", i + 1, e));
+                wirings.push(None);
             },
         }
     }
 
-    fn create_rotator(&self, index: usize) -> Rotator {
-        let perm = match create_permutation_from_string(&self.rotators[index].0) {
-            Ok(perm) => perm,
-            Err(e) => {
-                eprintln!("Invalid rotator setting: {}", e);
-                std::process::exit(1);
-            },
-        };
+    // A machine has a single copy of every rotor, so no rotor can take two slots.
+    for i in 0..wirings.len() {
+        for j in i + 1..wirings.len() {
+            if wirings[i].is_some() && wirings[i] == wirings[j] {
+                problems.push(format!("rotators {} and {} are the same rotor {}; every rotor can \
+                    only be used once", i + 1, j + 1, rotor_label(&config.rotators[i].0)));
+            }
+        }
+    }
 
-        let offset = self.rotators[index].1;
+    problems.extend(diagnose_pairs("reflector", &config.reflector, true));
+    problems
+}
 
-        match Rotator::new(perm, offset) {
-            Ok(rotator) => rotator,
-            Err(e) => {
-                eprintln!("Invalid rotator setting: {}", e);
-                std::process::exit(1);
-            },
+/// Describe the problems of the specified letter pairs of a component. If `complete` is set, the
+/// pairs should connect every letter, as those of a reflector.
+fn diagnose_pairs(component: &str, pairs: &[LetterPair], complete: bool) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut used: Vec<Vec<String>> = vec![Vec::new(); RUNE_SET_SIZE as usize];
+
+    for LetterPair(lhs, rhs) in pairs {
+        let name = format!("{}{}", lhs, rhs);
+        used[lhs.value() as usize].push(name.clone());
+        used[rhs.value() as usize].push(name);
+    }
+
+    for (rune, names) in Rune::ALPHABET.iter().zip(&used) {
+        if names.len() > 1 {
+            problems.push(format!("{}: letter {} is used by more than one pair: {}", component,
+                rune, names.join(", ")));
         }
     }
 
-    fn create_rotator_group(&self) -> RotatorGroup {
-        RotatorGroup::new([
-            self.create_rotator(0),
-            self.create_rotator(1),
-            self.create_rotator(2),
-        ])
+    if complete {
+        let missing: Vec<String> = Rune::ALPHABET.iter()
+            .filter(|rune| used[rune.value() as usize].is_empty())
+            .map(|rune| rune.into_string())
+            .collect();
+        if !missing.is_empty() {
+            problems.push(format!("{}: letters {} are not connected, but every letter must be \
+                paired", component, missing.join(" ")));
+        }
     }
 
-    fn create_reflector(&self) -> Reflector {
-        let perm = match create_permutation_from_swaps(&self.reflector) {
-            Ok(perm) => perm,
-            Err(e) => {
-                eprintln!("Invalid reflector setting: {}", e);
-                std::process::exit(1);
-            },
+    problems
+}
+
+fn load_config(path: &Path) -> Config {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Cannot read config file: {}", e);
+            std::process::exit(1);
+        },
+    };
+
+    match Config::from_json(&content) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Failed to parse config: {}", e);
+            std::process::exit(1);
+        },
+    }
+}
+
+/// Parse rotor positions given as window letters, e.g. `AFT` or `A F T`.
+fn parse_positions(s: &str) -> Result<[u8; 3], String> {
+    let letters: Vec<char> = s.chars().filter(|ch| !ch.is_whitespace()).collect();
+    if letters.len() != 3 {
+        return Err(format!("expected 3 window letters, got {}", letters.len()));
+    }
+
+    let mut positions = [0u8; 3];
+    for (i, ch) in letters.into_iter().enumerate() {
+        positions[i] = match Rune::from_char(ch) {
+            Ok(rune) => rune.value(),
+            Err(_) => return Err(format!("{} is not an ASCII alphabetic character", ch)),
         };
+    }
 
-        match Reflector::from_perm(perm) {
-            Ok(reflector) => reflector,
-            Err(e) => {
-                eprintln!("Invalid reflector setting: {}", e);
-                std::process::exit(1);
-            },
+    Ok(positions)
+}
+
+/// Load the configuration file given on the command line and create the configured machine. If
+/// a start position is given on the command line, the rotators are turned until its letters are
+/// shown in the windows, overriding the rotor offsets in the configuration file.
+fn load_machine(args: &clap::ArgMatches) -> (Config, Enigma) {
+    let config = load_config(Path::new(args.value_of("config").unwrap()));
+    let mut machine = create_enigma(&config).with_preserve_case(args.is_present("preserve-case"));
+
+    if let Some(start_position) = args.value_of("start-position") {
+        if let Err(e) = parse_positions(start_position) {
+            eprintln!("Invalid start position: {}", e);
+            std::process::exit(1);
         }
+        let window: String = start_position.split_whitespace().collect();
+        machine.set_window(&window).expect("the start position has been validated");
     }
 
-    fn create_enigma(&self) -> Enigma {
-        let plug_board = self.create_plug_board();
-        let rotator_group = self.create_rotator_group();
-        let reflector = self.create_reflector();
-        Enigma::new(plug_board, rotator_group, reflector)
+    (config, machine)
+}
+
+fn read_input_file(path: &Path) -> String {
+    match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Failed to read input file: {}", e);
+            std::process::exit(1);
+        },
+    }
+}
+
+fn write_output_file(path: &Path, content: &str) {
+    if let Err(e) = std::fs::write(path, content) {
+        eprintln!("Failed to write output file: {}", e);
+        std::process::exit(1);
     }
 }
 
-fn create_permutation_from_swaps(swaps: &Vec<[char; 2]>)
-    -> Result<Permutation, InvalidConfigError> {
-    let mut builder = PermutationBuilder::new(RUNE_SET_SIZE);
+/// Check that the specified ciphertext is arranged in groups of equal length, separated by
+/// whitespace characters. The last group is allowed to be shorter than the others.
+fn validate_grouping(content: &str) -> Result<(), String> {
+    let groups: Vec<&str> = content.split_whitespace().collect();
+    let group_len = match groups.first() {
+        Some(g) => g.chars().count(),
+        None => return Ok(()),
+    };
 
-    for sw in swaps {
-        if !sw[0].is_ascii_alphabetic() {
-            return Err(InvalidConfigError::new(
-                format!("{} is not an ASCII alphabetic character", sw[0])));
-        }
-        if !sw[1].is_ascii_alphabetic() {
-            return Err(InvalidConfigError::new(
-                format!("{} is not an ASCII alphabetic character", sw[1])));
+    for (i, g) in groups.iter().enumerate() {
+        let len = g.chars().count();
+        let is_last = i + 1 == groups.len();
+        if len > group_len || (len < group_len && !is_last) {
+            return Err(format!("group {} has {} letters, expected {}", i + 1, len, group_len));
         }
+    }
 
-        let lhs = sw[0].to_ascii_lowercase() as u8 - 'a' as u8;
-        let rhs = sw[1].to_ascii_lowercase() as u8 - 'a' as u8;
-        builder = builder.swap(lhs, rhs);
+    Ok(())
+}
+
+/// Transform the specified input content with the machine. When decrypting, the grouping of the
+/// input content is validated first. The machine is not advanced if the input content is invalid.
+/// If tracing is requested, the signal path of each rune is printed.
+fn transform(machine: &mut Enigma, input_content: &str, decrypt: bool, trace: bool)
+    -> Result<String, String> {
+    if let Some(ch) = input_content.chars()
+        .find(|ch| !ch.is_ascii_alphabetic() && !ch.is_whitespace()) {
+        return Err(format!("invalid character in input: {:?}", ch));
+    }
+
+    if decrypt {
+        validate_grouping(input_content)
+            .map_err(|e| format!("irregular ciphertext grouping: {}", e))?;
     }
 
-    Ok(builder.build())
+    if trace {
+        print!("{}", machine.render_state());
+    }
+
+    let mut output_content = String::with_capacity(input_content.len());
+    for ch in input_content.chars() {
+        if let Ok(rune) = Rune::from_char(ch) {
+            if trace {
+                println!("[{}] {}", format_positions(machine), machine.trace_rune(rune));
+            }
+            let output = machine.map_rune(rune).into_char();
+            if machine.preserves_case() && ch.is_ascii_lowercase() {
+                output_content.push(output.to_ascii_lowercase());
+            } else {
+                output_content.push(output);
+            }
+        }
+    }
+
+    Ok(output_content)
 }
 
-fn create_permutation_from_string(s: &str) -> Result<Permutation, InvalidConfigError> {
-    let mut perm: Vec<u8> = Vec::with_capacity(s.len());
+/// Format the letters shown in the windows of the machine from left to right, separated by
+/// spaces, so that they can be passed back as a start position.
+fn format_positions(machine: &Enigma) -> String {
+    let positions: Vec<String> = machine.window().chars().map(String::from).collect();
+    positions.join(" ")
+}
 
-    for ch in s.chars() {
-        if !ch.is_ascii_alphabetic() {
-            return Err(InvalidConfigError::new(
-                format!("{} is not an ASCII alphabetic character", ch)));
+fn save_final_positions(args: &clap::ArgMatches, machine: &Enigma) {
+    let final_positions = format_positions(machine);
+    println!("Final rotor positions: {}", final_positions);
+    if let Some(position_file) = args.value_of("save-position") {
+        if let Err(e) = std::fs::write(position_file, format!("{}\n", final_positions)) {
+            eprintln!("Failed to write position file: {}", e);
+            std::process::exit(1);
         }
+    }
+}
 
-        perm.push(ch.to_ascii_lowercase() as u8 - 'a' as u8);
+fn parse_usize_arg(args: &clap::ArgMatches, name: &str) -> usize {
+    match args.value_of(name).map(|v| v.parse::<usize>()) {
+        Some(Ok(value)) => value,
+        Some(Err(e)) => {
+            eprintln!("Invalid value for --{}: {}", name, e);
+            std::process::exit(1);
+        },
+        None => 0,
     }
+}
 
-    Permutation::from_perm(perm)
-        .map_err(|e| InvalidConfigError::new(
-            format!("invalid permutation: {}", e)))
+fn create_formatter(args: &clap::ArgMatches) -> TextFormatter {
+    let case = if args.is_present("lowercase") {
+        LetterCase::Lower
+    } else if args.is_present("preserve-case") {
+        LetterCase::Keep
+    } else {
+        LetterCase::Upper
+    };
+
+    TextFormatter::new()
+        .group_size(parse_usize_arg(args, "group"))
+        .line_width(parse_usize_arg(args, "line-width"))
+        .case(case)
 }
 
-fn load_config(path: &Path) -> Config {
-    let content = match std::fs::read_to_string(path) {
-        Ok(content) => content,
+/// Decode the ciphertext read from an input file from Morse code, line by line. Prosigns, e.g. the
+/// start and the end of the message, are discarded and the words are kept as groups.
+fn decode_morse_input(content: &str) -> Result<String, String> {
+    let mut groups = Vec::new();
+    for line in content.lines() {
+        let decoded = morse::decode(line).map_err(|e| format!("{}", e))?;
+        groups.extend(decoded.split_whitespace()
+            .filter(|group| !group.starts_with('<'))
+            .map(String::from));
+    }
+    Ok(groups.join(" "))
+}
+
+/// Encode the formatted ciphertext into Morse code, line by line.
+fn encode_morse_output(content: &str) -> String {
+    let lines: Vec<String> = content.lines()
+        .map(|line| morse::encode(line).unwrap())
+        .collect();
+    lines.join("\n")
+}
+
+/// Convert the input content from Morse code when decrypting with the `--morse` option.
+fn prepare_input(args: &clap::ArgMatches, decrypt: bool, content: String)
+    -> Result<String, String> {
+    if decrypt && args.is_present("morse") {
+        decode_morse_input(&content)
+    } else {
+        Ok(content)
+    }
+}
+
+/// Format the output content, and convert it to Morse code when encrypting with the `--morse`
+/// option.
+fn finish_output(args: &clap::ArgMatches, decrypt: bool, formatter: &TextFormatter,
+    content: &str) -> String {
+    let output = formatter.format(content);
+    if !decrypt && args.is_present("morse") {
+        encode_morse_output(&output)
+    } else {
+        output
+    }
+}
+
+fn run_transform(args: &clap::ArgMatches, decrypt: bool) {
+    if args.is_present("input-dir") {
+        run_batch_transform(args, decrypt);
+        return;
+    }
+
+    let (_, mut machine) = load_machine(args);
+
+    // Grouping of the input is only validated when decrypting, unless the user asks to discard it.
+    let validate = decrypt && !args.is_present("ungroup");
+    let input_content = read_input_file(Path::new(args.value_of("input").unwrap()));
+    let output_content = match prepare_input(args, decrypt, input_content)
+        .and_then(|content| transform(&mut machine, &content, validate,
+            args.is_present("trace"))) {
+        Ok(content) => finish_output(args, decrypt, &create_formatter(args), &content),
         Err(e) => {
-            eprintln!("Cannot read config file: {}", e);
+            eprintln!("Failed to transform input file: {}", e);
             std::process::exit(1);
         },
     };
+    write_output_file(Path::new(args.value_of("output").unwrap()), &output_content);
 
-    match serde_json::from_str::<Config>(&content) {
-        Ok(config) => config,
+    println!("Transformed contents have been saved to output file.");
+    save_final_positions(args, &machine);
+}
+
+/// Transform every file within the input directory and save the outputs under the same names in
+/// the output directory. Unless chaining is requested, the machine is reset to its initial state
+/// before each file.
+fn run_batch_transform(args: &clap::ArgMatches, decrypt: bool) {
+    let (_, mut machine) = load_machine(args);
+    let initial_machine = machine.clone();
+    let chain = args.is_present("chain");
+    let validate = decrypt && !args.is_present("ungroup");
+
+    let input_dir = Path::new(args.value_of("input-dir").unwrap());
+    let output_dir = Path::new(args.value_of("output-dir").unwrap());
+
+    let mut input_files: Vec<PathBuf> = match std::fs::read_dir(input_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .collect(),
         Err(e) => {
-            eprintln!("Failed to parse config: {}", e);
+            eprintln!("Failed to read input directory: {}", e);
             std::process::exit(1);
         },
+    };
+    input_files.sort();
+
+    if let Err(e) = std::fs::create_dir_all(output_dir) {
+        eprintln!("Failed to create output directory: {}", e);
+        std::process::exit(1);
+    }
+
+    let formatter = create_formatter(args);
+    let mut num_letters = 0usize;
+    let mut failures = Vec::new();
+    for input_file in &input_files {
+        if !chain {
+            machine = initial_machine.clone();
+        }
+
+        let file_name = input_file.file_name().unwrap();
+        let result = std::fs::read_to_string(input_file)
+            .map_err(|e| format!("cannot read file: {}", e))
+            .and_then(|content| prepare_input(args, decrypt, content))
+            .and_then(|content| transform(&mut machine, &content, validate,
+                args.is_present("trace")))
+            .and_then(|output| {
+                num_letters += output.len();
+                let output = finish_output(args, decrypt, &formatter, &output);
+                std::fs::write(output_dir.join(file_name), output)
+                    .map_err(|e| format!("cannot write output file: {}", e))
+            });
+
+        match result {
+            Ok(_) => println!("  {}: ok", file_name.to_string_lossy()),
+            Err(e) => {
+                println!("  {}: {}", file_name.to_string_lossy(), e);
+                failures.push(file_name.to_owned());
+            },
+        }
+    }
+
+    println!("Processed {} files ({} letters), {} succeeded, {} failed.",
+        input_files.len(), num_letters, input_files.len() - failures.len(), failures.len());
+
+    if chain {
+        save_final_positions(args, &machine);
+    }
+
+    if !failures.is_empty() {
+        std::process::exit(1);
     }
 }
 
-fn main() {
-    let args = clap::App::new("enigma-cli")
-        .version("0.1")
-        .author("Sirui Mu <msrlancern@126.com>")
-        .about("Enigma emulator")
-        .arg(clap::Arg::with_name("config")
-            .short("c")
-            .long("config")
-            .value_name("FILE")
-            .help("Path to the Enigma machine configuration file")
-            .takes_value(true)
-            .required(true))
-        .arg(clap::Arg::with_name("input")
-            .short("i")
-            .long("input")
-            .value_name("FILE")
-            .help("Path to the file containing input data")
-            .takes_value(true)
-            .required(true))
-        .arg(clap::Arg::with_name("output")
-            .short("o")
-            .long("output")
-            .value_name("FILE")
-            .help("Path to the file containing output data")
-            .takes_value(true)
-            .required(true))
-        .get_matches();
+fn run_state(args: &clap::ArgMatches) {
+    let (config, machine) = load_machine(args);
 
-    let config_path = PathBuf::from(String::from(args.value_of("config").unwrap()));
-    let config = load_config(&config_path);
-    let mut machine = config.create_enigma();
+    println!("Rotor order:");
+    for (i, (spec, _)) in config.rotators.iter().enumerate() {
+        println!("  {}: {}", i + 1, rotor_label(spec));
+    }
+    print!("{}", machine.render_state());
+    println!("Period: {} key presses", machine.period());
+}
 
-    let input_file_path = PathBuf::from(String::from(args.value_of("input").unwrap()));
-    let input_content = match std::fs::read_to_string(input_file_path) {
-        Ok(content) => content,
+fn run_validate(args: &clap::ArgMatches) {
+    let config = load_config(Path::new(args.value_of("config").unwrap()));
+
+    let problems = diagnose(&config);
+    if problems.is_empty() {
+        println!("The configuration is valid");
+        return;
+    }
+
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    eprintln!("Found {} problem(s) in the configuration", problems.len());
+    std::process::exit(1);
+}
+
+/// Encrypt the input file, decrypt the ciphertext with the machine reset to its initial state,
+/// and compare the result with the input. Characters that are not letters are skipped, and letter
+/// cases are only compared when the case is preserved, as when transforming files.
+fn run_verify(args: &clap::ArgMatches) {
+    let (_, machine) = load_machine(args);
+    let plaintext = read_input_file(Path::new(args.value_of("input").unwrap()));
+
+    let ciphertext = machine.clone().map_str(&plaintext);
+    let decrypted: Vec<char> = machine.clone().map_str(&ciphertext).chars().collect();
+
+    // The letters of the input as the machine outputs them, with their lines and columns.
+    let mut expected = Vec::new();
+    for (line, content) in plaintext.lines().enumerate() {
+        for (column, ch) in content.chars().enumerate() {
+            if Rune::from_char(ch).is_ok() {
+                let ch = if machine.preserves_case() { ch } else { ch.to_ascii_uppercase() };
+                expected.push((ch, line + 1, column + 1));
+            }
+        }
+    }
+
+    let divergence = expected.iter().zip(&decrypted).position(|((ch, _, _), got)| ch != got);
+    match divergence {
+        Some(index) => {
+            let (ch, line, column) = expected[index];
+            let positions = machine.rotators().positions_iter().nth(index).unwrap();
+            let positions: Vec<String> = positions.iter().map(|r| r.into_string()).collect();
+            eprintln!("Round trip diverges at letter {} (line {}, column {}): expected {}, got {} \
+                at rotor positions {}", index + 1, line, column, ch, decrypted[index],
+                positions.join(" "));
+            std::process::exit(1);
+        },
+        None if expected.len() != decrypted.len() => {
+            eprintln!("Round trip diverges after letter {}: expected {} letters, got {}",
+                expected.len().min(decrypted.len()), expected.len(), decrypted.len());
+            std::process::exit(1);
+        },
+        None => println!("Round trip succeeded: {} letters decrypt to the input", expected.len()),
+    }
+}
+
+/// Read and parse the specified fixture file.
+fn load_fixture(path: &Path) -> Result<Fixture, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("cannot read file: {}", e))?;
+    toml::from_str(&content).map_err(|e| {
+        match e.span() {
+            Some(span) => format!("cannot parse fixture at line {}: {}",
+                content[..span.start].matches('\n').count() + 1, e.message()),
+            None => format!("cannot parse fixture: {}", e.message()),
+        }
+    })
+}
+
+/// Check whether the machine of the specified fixture enciphers its plaintext to its ciphertext,
+/// and return a description of the first mismatch if it does not. This function fails if the
+/// fixture does not describe a valid machine.
+fn check_fixture(fixture: &Fixture) -> Result<Option<String>, String> {
+    let mut machine = fixture.create_enigma()?;
+
+    let expected: Vec<char> = fixture.ciphertext.chars()
+        .filter(|ch| ch.is_ascii_alphabetic())
+        .map(|ch| ch.to_ascii_uppercase())
+        .collect();
+    let actual: Vec<char> = machine.map_str(&fixture.plaintext).chars()
+        .map(|ch| ch.to_ascii_uppercase())
+        .collect();
+
+    if let Some(index) = expected.iter().zip(&actual).position(|(a, b)| a != b) {
+        return Ok(Some(format!("ciphertext diverges at letter {}: expected {}, got {}",
+            index + 1, expected[index], actual[index])));
+    }
+    if expected.len() != actual.len() {
+        return Ok(Some(format!("expected {} letters of ciphertext, got {}", expected.len(),
+            actual.len())));
+    }
+    Ok(None)
+}
+
+/// Check every TOML fixture within the fixture directory, and report the fixtures whose
+/// ciphertexts do not match.
+fn run_compat(args: &clap::ArgMatches) {
+    let dir = Path::new(args.value_of("dir").unwrap());
+    let mut fixtures: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "toml"))
+            .collect(),
         Err(e) => {
-            eprintln!("Failed to read input file: {}", e);
+            eprintln!("Failed to read fixture directory: {}", e);
             std::process::exit(1);
         },
     };
+    fixtures.sort();
+
+    let mut matches = 0;
+    for path in &fixtures {
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        let result = load_fixture(path)
+            .and_then(|fixture| Ok((check_fixture(&fixture)?, fixture.name)));
+        match result {
+            Ok((None, name)) => {
+                println!("MATCH     {}: {}", file_name, name);
+                matches += 1;
+            },
+            Ok((Some(mismatch), name)) => println!("MISMATCH  {}: {}: {}", file_name, name,
+                mismatch),
+            Err(e) => println!("ERROR     {}: {}", file_name, e),
+        }
+    }
+
+    println!("{} of {} fixtures match", matches, fixtures.len());
+    if matches < fixtures.len() {
+        std::process::exit(1);
+    }
+}
+
+fn run_preview(args: &clap::ArgMatches) {
+    let (_, machine) = load_machine(args);
+
+    let steps = match args.value_of("steps").unwrap().parse::<usize>() {
+        Ok(steps) => steps,
+        Err(e) => {
+            eprintln!("Invalid number of steps: {}", e);
+            std::process::exit(1);
+        },
+    };
+
+    // The first positions are the current ones, which are not a step ahead.
+    for (i, positions) in machine.rotators().positions_iter().enumerate().skip(1).take(steps) {
+        let positions: Vec<String> = positions.iter().map(|r| r.into_string()).collect();
+        println!("{:>6}: {}", i, positions.join(" "));
+    }
+}
+
+/// The rows of the keyboard and the lamp board, as laid out on the Enigma.
+const LAMP_ROWS: [&str; 3] = ["QWERTZUIO", "ASDFGHJK", "PYXCVBNML"];
+
+/// The number of lines drawn by `draw_lamp_board`.
+const LAMP_BOARD_HEIGHT: u16 = 6;
+
+/// The maximal number of letters keyed in and lit up that are shown below the lamp board.
+const TAPE_LEN: usize = 50;
+
+fn run_interactive(args: &clap::ArgMatches) {
+    let (_, mut machine) = load_machine(args);
+    let trace = args.is_present("trace");
+
+    let result = if std::io::stdin().is_terminal() && std::io::stdout().is_terminal() {
+        terminal::enable_raw_mode()
+            .and_then(|()| {
+                let result = key_in_terminal(&mut machine, trace);
+                terminal::disable_raw_mode()?;
+                result
+            })
+    } else {
+        key_in_stream(&mut machine, trace)
+    };
+
+    if let Err(e) = result {
+        eprintln!("Failed to run the interactive session: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Key in the letters typed on the terminal one keystroke at a time. The terminal should be in raw
+/// mode. After each keystroke, the lamp board is redrawn in place with the lamp of the output
+/// letter lit up, below the signal path of the letter if tracing is requested.
+fn key_in_terminal(machine: &mut Enigma, trace: bool) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "Type letters to key them in. Press Backspace or - to undo the last letter. \
+        Press Esc or Ctrl-D to quit.\r\n")?;
+    if trace {
+        for line in machine.render_state().lines() {
+            write!(stdout, "{}\r\n", line)?;
+        }
+    }
+
+    let mut input = String::new();
+    let mut output = String::new();
+    let mut lamp = None;
+    draw_lamp_board(&mut stdout, machine, lamp, &input, &output)?;
+
+    loop {
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+
+        let mut signal_path = None;
+        match key.code {
+            KeyCode::Esc => break,
+            KeyCode::Char('c') | KeyCode::Char('d')
+                if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+            KeyCode::Backspace | KeyCode::Char('-') => {
+                if input.pop().is_some() {
+                    output.pop();
+                    machine.rewind(1);
+                }
+                lamp = None;
+            },
+            KeyCode::Char(ch) => {
+                let rune = match Rune::from_char(ch) {
+                    Ok(rune) => rune,
+                    Err(_) => continue,
+                };
+                if trace {
+                    signal_path = Some(format!("[{}] {}", format_positions(machine),
+                        machine.trace_rune(rune)));
+                }
+
+                let lit = machine.map_rune(rune);
+                input.push(rune.into_char());
+                output.push(lit.into_char());
+                lamp = Some(lit);
+            },
+            _ => continue,
+        }
+
+        queue!(stdout, cursor::MoveToPreviousLine(LAMP_BOARD_HEIGHT),
+            terminal::Clear(terminal::ClearType::FromCursorDown))?;
+        if let Some(signal_path) = signal_path {
+            write!(stdout, "{}\r\n", signal_path)?;
+        }
+        draw_lamp_board(&mut stdout, machine, lamp, &input, &output)?;
+    }
+
+    Ok(())
+}
+
+/// Draw the rotor windows, the lamp board with the specified lamp lit up, and the last letters
+/// keyed in and lit up. Every line ends with a carriage return, as the terminal is in raw mode.
+fn draw_lamp_board<W: Write>(out: &mut W, machine: &Enigma, lamp: Option<Rune>, input: &str,
+    output: &str) -> std::io::Result<()> {
+    write!(out, "Window: {}\r\n", format_positions(machine))?;
+    for (i, row) in LAMP_ROWS.iter().enumerate() {
+        write!(out, "{}", " ".repeat(i % 2))?;
+        for ch in row.chars() {
+            if lamp.map(Rune::into_char) == Some(ch) {
+                write!(out, " {}", ch.to_string().reverse().bold())?;
+            } else {
+                write!(out, " {}", ch.to_string().dim())?;
+            }
+        }
+        write!(out, "\r\n")?;
+    }
+
+    let tail = |s: &str| s[s.len().saturating_sub(TAPE_LEN)..].to_string();
+    write!(out, "Keys:  {}\r\nLamps: {}\r\n", tail(input), tail(output))?;
+    out.flush()
+}
+
+/// Key in the letters read from the standard input one character at a time, e.g. when it is piped
+/// from another program. The lamp and the rotor windows are printed after each letter.
+fn key_in_stream(machine: &mut Enigma, trace: bool) -> std::io::Result<()> {
+    println!("Type letters to key them in. Type - to undo the last letter. Press Ctrl-D to quit.");
+    if trace {
+        print!("{}", machine.render_state());
+    } else {
+        println!("Window: {}", format_positions(machine));
+    }
+
+    let mut keyed = 0usize;
+    for byte in std::io::stdin().lock().bytes() {
+        let ch = byte? as char;
+        if ch == '-' {
+            if keyed == 0 {
+                println!("  nothing to undo");
+            } else {
+                keyed -= 1;
+                machine.rewind(1);
+                println!("  undo    window: {}", format_positions(machine));
+            }
+            continue;
+        }
 
-    let mut output_content = String::with_capacity(input_content.len());
-    for ch in input_content.chars() {
         let rune = match Rune::from_char(ch) {
             Ok(r) => r,
             Err(_) => {
-                if ch.is_whitespace() {
-                    continue;
+                if !ch.is_whitespace() {
+                    println!("  {:?} is not a key on the keyboard, ignored", ch);
                 }
-                eprintln!("Invalid character in input");
-                std::process::exit(1);
+                continue;
             },
         };
-        let mapped_rune = machine.map_rune(rune);
-        output_content.push(mapped_rune.into_char());
+
+        if trace {
+            println!("  [{}] {}", format_positions(machine), machine.trace_rune(rune));
+        }
+
+        let lamp = machine.map_rune(rune);
+        keyed += 1;
+        println!("  {} -> lamp {}    window: {}", rune, lamp, format_positions(machine));
+        std::io::stdout().flush()?;
     }
 
-    let output_file_path = PathBuf::from(String::from(args.value_of("output").unwrap()));
-    match std::fs::write(output_file_path, output_content) {
-        Ok(_) => (),
-        Err(e) => {
-            eprintln!("Failed to write output file: {}", e);
-            std::process::exit(1);
-        },
-    };
+    Ok(())
+}
 
-    println!("Transformed contents have been saved to output file.");
+fn config_arg<'a, 'b>() -> clap::Arg<'a, 'b> {
+    clap::Arg::with_name("config")
+        .short("c")
+        .long("config")
+        .value_name("FILE")
+        .help("Path to the Enigma machine configuration file")
+        .takes_value(true)
+        .required(true)
+}
+
+fn start_position_arg<'a, 'b>() -> clap::Arg<'a, 'b> {
+    clap::Arg::with_name("start-position")
+        .short("p")
+        .long("start-position")
+        .value_name("LETTERS")
+        .help("Rotor window letters to start from, overriding the configured offsets")
+        .takes_value(true)
+}
+
+fn trace_arg<'a, 'b>() -> clap::Arg<'a, 'b> {
+    clap::Arg::with_name("trace")
+        .short("t")
+        .long("trace")
+        .help("Print the signal path through the machine for each letter")
+}
+
+fn transform_subcommand<'a, 'b>(name: &'a str, about: &'a str) -> clap::App<'a, 'b> {
+    clap::SubCommand::with_name(name)
+        .about(about)
+        .arg(config_arg())
+        .arg(start_position_arg())
+        .arg(clap::Arg::with_name("input")
+            .short("i")
+            .long("input")
+            .value_name("FILE")
+            .help("Path to the file containing input data")
+            .takes_value(true)
+            .required_unless("input-dir")
+            .conflicts_with("input-dir")
+            .requires("output"))
+        .arg(clap::Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .value_name("FILE")
+            .help("Path to the file containing output data")
+            .takes_value(true)
+            .requires("input"))
+        .arg(clap::Arg::with_name("input-dir")
+            .long("input-dir")
+            .value_name("DIR")
+            .help("Path to the directory whose files are transformed one by one")
+            .takes_value(true)
+            .requires("output-dir"))
+        .arg(clap::Arg::with_name("output-dir")
+            .long("output-dir")
+            .value_name("DIR")
+            .help("Path to the directory where the transformed files are saved")
+            .takes_value(true)
+            .requires("input-dir"))
+        .arg(clap::Arg::with_name("chain")
+            .long("chain")
+            .help("Do not reset the machine between files in directory mode")
+            .requires("input-dir"))
+        .arg(clap::Arg::with_name("group")
+            .short("g")
+            .long("group")
+            .value_name("N")
+            .help("Arrange the output in groups of N letters")
+            .takes_value(true))
+        .arg(clap::Arg::with_name("line-width")
+            .short("w")
+            .long("line-width")
+            .value_name("N")
+            .help("Break the output into lines of at most N characters")
+            .takes_value(true))
+        .arg(clap::Arg::with_name("uppercase")
+            .long("uppercase")
+            .help("Print the output in upper case letters (default)")
+            .conflicts_with("lowercase"))
+        .arg(clap::Arg::with_name("lowercase")
+            .long("lowercase")
+            .help("Print the output in lower case letters")
+            .conflicts_with("preserve-case"))
+        .arg(clap::Arg::with_name("preserve-case")
+            .long("preserve-case")
+            .help("Print each output letter in the letter case of its input letter")
+            .conflicts_with("uppercase"))
+        .arg(trace_arg())
+        .arg(clap::Arg::with_name("morse")
+            .long("morse")
+            .help("Write the ciphertext in Morse code when encrypting, or read it in Morse code \
+                when decrypting"))
+        .arg(clap::Arg::with_name("ungroup")
+            .long("ungroup")
+            .help("Discard the grouping of the input without validating it"))
+        .arg(clap::Arg::with_name("save-position")
+            .long("save-position")
+            .value_name("FILE")
+            .help("Path to the file where the final rotor positions are saved")
+            .takes_value(true))
+}
+
+fn main() {
+    let args = clap::App::new("enigma-cli")
+        .version("0.1")
+        .author("Sirui Mu <msrlancern@126.com>")
+        .about("Enigma emulator")
+        .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(transform_subcommand("encrypt", "Encrypt a plaintext file"))
+        .subcommand(transform_subcommand("decrypt", "Decrypt a ciphertext file"))
+        .subcommand(clap::SubCommand::with_name("state")
+            .about("Print the rotor order and positions of the configured machine")
+            .arg(config_arg())
+            .arg(start_position_arg()))
+        .subcommand(clap::SubCommand::with_name("validate")
+            .about("Check the configuration file and report every problem found")
+            .arg(config_arg()))
+        .subcommand(clap::SubCommand::with_name("verify")
+            .about("Encrypt and decrypt a file and check that the original text is restored")
+            .arg(config_arg())
+            .arg(start_position_arg())
+            .arg(clap::Arg::with_name("input")
+                .short("i")
+                .long("input")
+                .value_name("FILE")
+                .help("Path to the plaintext file")
+                .takes_value(true)
+                .required(true))
+            .arg(clap::Arg::with_name("preserve-case")
+                .long("preserve-case")
+                .help("Also compare the letter case of the decrypted text")))
+        .subcommand(clap::SubCommand::with_name("compat")
+            .about("Check the machine against test fixtures produced by other simulators")
+            .arg(clap::Arg::with_name("dir")
+                .short("d")
+                .long("dir")
+                .value_name("DIR")
+                .help("Path to the directory of TOML fixture files")
+                .takes_value(true)
+                .required(true)))
+        .subcommand(clap::SubCommand::with_name("preview")
+            .about("Print the next rotor positions without processing any text")
+            .arg(config_arg())
+            .arg(start_position_arg())
+            .arg(clap::Arg::with_name("steps")
+                .short("n")
+                .long("steps")
+                .value_name("N")
+                .help("Number of steps to preview")
+                .takes_value(true)
+                .default_value("10")))
+        .subcommand(clap::SubCommand::with_name("interactive")
+            .about("Key in letters one by one and watch the lamps and rotor windows")
+            .arg(config_arg())
+            .arg(start_position_arg())
+            .arg(trace_arg()))
+        .get_matches();
+
+    match args.subcommand() {
+        ("encrypt", Some(sub_args)) => run_transform(sub_args, false),
+        ("decrypt", Some(sub_args)) => run_transform(sub_args, true),
+        ("state", Some(sub_args)) => run_state(sub_args),
+        ("validate", Some(sub_args)) => run_validate(sub_args),
+        ("verify", Some(sub_args)) => run_verify(sub_args),
+        ("compat", Some(sub_args)) => run_compat(sub_args),
+        ("preview", Some(sub_args)) => run_preview(sub_args),
+        ("interactive", Some(sub_args)) => run_interactive(sub_args),
+        _ => unreachable!(),
+    }
 }
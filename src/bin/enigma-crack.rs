@@ -1,29 +1,43 @@
 extern crate enigma;
 
+extern crate bincode;
 extern crate clap;
+extern crate rayon;
 extern crate serde;
 extern crate serde_json;
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::collections::btree_map::Entry;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
-use std::fs::File;
-use std::iter::FromIterator;
-use std::io::{BufReader, BufRead};
-use std::path::{Path, PathBuf};
+use std::io::{IsTerminal, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::time::Instant;
 
 use enigma::{Enigma, Reflector, Rotator, RotatorGroup, PlugBoard};
+use enigma::analysis::rejewski::{Catalog, Characteristic, Indicator};
 use enigma::math::{Permutation, PermutationBuilder};
+use enigma::settings::Keyspace;
 use enigma::utils::{Rune, RUNE_SET_SIZE};
 
-use serde::Deserialize;
+use bincode::Options;
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug)]
-struct InvalidConfigError;
+struct InvalidConfigError {
+    message: String,
+}
+
+impl InvalidConfigError {
+    fn new(message: String) -> Self {
+        Self { message }
+    }
+}
 
 impl Display for InvalidConfigError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str("invalid configuration")
+        f.write_fmt(format_args!("invalid configuration: {}", self.message))
     }
 }
 
@@ -31,8 +45,8 @@ impl Error for InvalidConfigError { }
 
 #[derive(Clone, Debug, Deserialize)]
 struct Config {
-    rotators: [String; 3],
-    reflector: Vec<[char; 2]>,
+    rotators: Vec<String>,
+    reflector: Vec<[Rune; 2]>,
     secret_headers: String,
 }
 
@@ -55,24 +69,16 @@ impl Config {
         }
     }
 
-    fn create_rotator_group(&self) -> RotatorGroup {
+    fn create_rotator_group(&self, order: [usize; 3]) -> RotatorGroup {
         RotatorGroup::new([
-            self.create_rotator(0),
-            self.create_rotator(1),
-            self.create_rotator(2),
+            self.create_rotator(order[0]),
+            self.create_rotator(order[1]),
+            self.create_rotator(order[2]),
         ])
     }
 
     fn create_reflector(&self) -> Reflector {
-        let perm = match create_permutation_from_swaps(&self.reflector) {
-            Ok(perm) => perm,
-            Err(e) => {
-                eprintln!("invalid reflector setting: {}", e);
-                std::process::exit(1);
-            },
-        };
-
-        match Reflector::from_perm(perm) {
+        match Reflector::from_perm(create_permutation_from_swaps(&self.reflector)) {
             Ok(reflector) => reflector,
             Err(e) => {
                 eprintln!("invalid reflector setting: {}", e);
@@ -81,36 +87,72 @@ impl Config {
         }
     }
 
-    fn create_enigma(&self) -> Enigma {
+    /// Get all rotor orders to try, as indices into the configured rotators. If `fixed` is `true`,
+    /// only the first 3 configured rotators in their configured order are tried. Otherwise every
+    /// ordered choice of 3 distinct configured rotators is tried.
+    fn rotor_orders(&self, fixed: bool) -> Vec<[usize; 3]> {
+        if fixed {
+            return vec![[0, 1, 2]];
+        }
+
+        Keyspace::new(self.rotators.len()).rotor_orders()
+    }
+
+    /// Get the key identifying the catalog of the specified rotor order within a catalog file. The
+    /// key is the FNV-1a hash of the rotator wirings, in order, and the reflector wiring.
+    fn catalog_key(&self, order: [usize; 3]) -> u64 {
+        let rotators: Vec<Permutation> = order.iter()
+            .map(|i| create_permutation_from_string(&self.rotators[*i]).unwrap())
+            .collect();
+        let reflector = create_permutation_from_swaps(&self.reflector);
+
+        let mut hasher = Fnv1a::new();
+        for perm in rotators.iter().chain(std::iter::once(&reflector)) {
+            for i in 0..RUNE_SET_SIZE {
+                hasher.write(perm.map(i) as u64);
+            }
+        }
+        hasher.finish()
+    }
+
+    fn create_enigma(&self, order: [usize; 3]) -> Enigma {
         let plug_board = PlugBoard::from_perm(
             PermutationBuilder::new(RUNE_SET_SIZE).build()
         ).unwrap();
-        let rotator_group = self.create_rotator_group();
+        let rotator_group = self.create_rotator_group(order);
         let reflector = self.create_reflector();
         Enigma::new(plug_board, rotator_group, reflector)
     }
 }
 
-fn create_permutation_from_swaps(swaps: &Vec<[char; 2]>)
-                                 -> Result<Permutation, InvalidConfigError> {
-    let mut builder = PermutationBuilder::new(RUNE_SET_SIZE);
+/// The 64-bit FNV-1a hash, which is stable across runs and platforms.
+struct Fnv1a {
+    hash: u64,
+}
 
-    for sw in swaps {
-        if !sw[0].is_ascii_alphabetic() {
-            return Err(InvalidConfigError::new(
-                format!("{} is not an ASCII alphabetic character", sw[0])));
-        }
-        if !sw[1].is_ascii_alphabetic() {
-            return Err(InvalidConfigError::new(
-                format!("{} is not an ASCII alphabetic character", sw[1])));
-        }
+impl Fnv1a {
+    fn new() -> Self {
+        Self { hash: 0xcbf2_9ce4_8422_2325 }
+    }
 
-        let lhs = sw[0].to_ascii_lowercase() as u8 - 'a' as u8;
-        let rhs = sw[1].to_ascii_lowercase() as u8 - 'a' as u8;
-        builder = builder.swap(lhs, rhs);
+    fn write(&mut self, value: u64) {
+        self.hash ^= value;
+        self.hash = self.hash.wrapping_mul(0x0100_0000_01b3);
     }
 
-    Ok(builder.build())
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+fn create_permutation_from_swaps(swaps: &[[Rune; 2]]) -> Permutation {
+    let mut builder = PermutationBuilder::new(RUNE_SET_SIZE);
+
+    for [lhs, rhs] in swaps {
+        builder = builder.swap(lhs.value(), rhs.value());
+    }
+
+    builder.build()
 }
 
 fn create_permutation_from_string(s: &str) -> Result<Permutation, InvalidConfigError> {
@@ -122,7 +164,7 @@ fn create_permutation_from_string(s: &str) -> Result<Permutation, InvalidConfigE
                 format!("{} is not an ASCII alphabetic character", ch)));
         }
 
-        perm.push(ch.to_ascii_lowercase() as u8 - 'a' as u8);
+        perm.push(ch.to_ascii_lowercase() as u8 - b'a');
     }
 
     Permutation::from_perm(perm)
@@ -148,175 +190,624 @@ fn load_config(path: &Path) -> Config {
     }
 }
 
-type CycleDecomposition = Vec<usize>;
+fn load_secret_headers(path: &Path) -> Vec<Indicator> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Failed to read secret header file: {}", e);
+            std::process::exit(1);
+        },
+    };
 
-fn get_cycle_decomposition(perm: &Permutation) -> CycleDecomposition {
     let mut ret = Vec::new();
-    let mut visited = vec![false; perm.len()];
-
-    for i in 0..perm.len() {
-        if visited[i] {
+    for (i, ln) in content.lines().enumerate() {
+        if ln.trim().is_empty() {
             continue;
         }
 
-        let mut cycle_len = 1;
-        let mut j = perm[i] as usize;
-        while j != i {
-            visited[j] = true;
-            cycle_len += 1;
-            j = perm[j] as usize;
+        match ln.parse::<Indicator>() {
+            Ok(indicator) => ret.push(indicator),
+            Err(e) => {
+                eprintln!("Failed to parse secret header file: line {}: {}", i + 1, e);
+                std::process::exit(1);
+            },
         }
-
-        ret.push(cycle_len);
     }
 
     ret
 }
 
-fn get_all_rotator_reflector_perm(config: &Config) -> Vec<Permutation> {
-    let num_perms = (RUNE_SET_SIZE as usize) * (RUNE_SET_SIZE as usize) * (RUNE_SET_SIZE as usize);
-    let mut machine = config.create_enigma();
-    let mut ret = Vec::with_capacity(num_perms);
+/// Load the catalogs within the specified catalog file, keyed by `Config::catalog_key`. Returns an
+/// empty map if the file does not exist.
+fn load_catalogs(path: &Path) -> BTreeMap<u64, Catalog> {
+    if !path.exists() {
+        return BTreeMap::new();
+    }
 
-    for _ in 0..num_perms {
-        let mut perm_vec = Vec::with_capacity(RUNE_SET_SIZE as usize);
-        for i in 0..RUNE_SET_SIZE {
-            let mapped = machine.map_rune_static(Rune::from_value(mapped).unwrap()).value();
-            perm_vec.push(mapped);
-        }
+    let content = match std::fs::read(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Failed to read catalog file: {}", e);
+            std::process::exit(1);
+        },
+    };
 
-        let perm = Permutation::from_perm(perm_vec).unwrap();
-        ret.push(perm);
+    match bincode::DefaultOptions::new().deserialize(&content) {
+        Ok(catalogs) => catalogs,
+        Err(e) => {
+            eprintln!("Failed to parse catalog file: {}", e);
+            std::process::exit(1);
+        },
+    }
+}
 
-        machine.advance_rotators();
+fn save_catalogs(path: &Path, catalogs: &BTreeMap<u64, Catalog>) {
+    let content = match bincode::DefaultOptions::new().serialize(catalogs) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Failed to serialize catalogs: {}", e);
+            std::process::exit(1);
+        },
+    };
+
+    if let Err(e) = std::fs::write(path, content) {
+        eprintln!("Failed to write catalog file: {}", e);
+        std::process::exit(1);
     }
+}
 
-    ret
+/// The state of an interrupted search.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Checkpoint {
+    /// The key identifying the search, see `search_key`.
+    search: u64,
+
+    /// The number of rotor orders completely searched.
+    completed: usize,
+
+    /// The ranks of the keys found so far.
+    matches: Vec<u64>,
 }
 
-fn combine_permutations(lhs: &Permutation, rhs: &Permutation) -> Permutation {
-    let mut ret = Vec::with_capacity(RUNE_SET_SIZE as usize);
-    for i in 0..RUNE_SET_SIZE {
-        let output = rhs.map(lhs.map(i));
-        ret[i as usize] = output;
+impl Checkpoint {
+    fn new(search: u64) -> Self {
+        Self { search, completed: 0, matches: Vec::new() }
     }
+}
 
-    Permutation::from_perm(ret).unwrap()
+/// Get the key identifying a search over the specified rotor orders and keyspace ranks for the
+/// specified characteristic, so that a checkpoint is only resumed by the same search.
+fn search_key(config: &Config, orders: &[[usize; 3]], ranks: &Range<u64>,
+    characteristic: &Characteristic) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(ranks.start);
+    hasher.write(ranks.end);
+    for order in orders {
+        hasher.write(config.catalog_key(*order));
+    }
+    for cycle_type in characteristic.cycle_types().iter() {
+        for length in cycle_type {
+            hasher.write(*length as u64);
+        }
+        hasher.write(0);
+    }
+    hasher.finish()
 }
 
-fn load_secret_headers(path: &Path) -> Vec<String> {
-    let file = match File::open(path) {
-        Ok(f) => f,
+/// Load the checkpoint within the specified checkpoint file. Returns `None` if the file does not
+/// exist or belongs to a different search.
+fn load_checkpoint(path: &Path, search: u64, reporter: &Reporter) -> Option<Checkpoint> {
+    if !path.exists() {
+        return None;
+    }
+
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
         Err(e) => {
-            eprintln!("Failed to open secret header file: {}", e);
+            eprintln!("Failed to read checkpoint file: {}", e);
             std::process::exit(1);
         },
     };
-    let mut reader = BufReader::new(file);
-    let mut ret = Vec::new();
-    for ln in reader.lines().map(|l| l.unwrap().trim()) {
-        if ln.len() != 6 {
-            eprintln!("Failed to parse secret header file: some line has a length other than 6");
+
+    let checkpoint = match serde_json::from_str::<Checkpoint>(&content) {
+        Ok(checkpoint) => checkpoint,
+        Err(e) => {
+            eprintln!("Failed to parse checkpoint file: {}", e);
             std::process::exit(1);
+        },
+    };
+
+    if checkpoint.search != search {
+        reporter.status("The checkpoint file belongs to a different search, starting from scratch");
+        return None;
+    }
+
+    Some(checkpoint)
+}
+
+/// Save the checkpoint to the specified checkpoint file. The checkpoint is written to a temporary
+/// file first, so that an interruption while saving does not corrupt the previous checkpoint.
+fn save_checkpoint(path: &Path, checkpoint: &Checkpoint) {
+    let temp_path = path.with_extension("tmp");
+    let content = serde_json::to_string(checkpoint).unwrap();
+    let result = std::fs::write(&temp_path, content)
+        .and_then(|_| std::fs::rename(&temp_path, path));
+    if let Err(e) = result {
+        eprintln!("Failed to write checkpoint file: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl OutputFormat {
+    fn from_arg(arg: &str) -> Self {
+        match arg {
+            "json" => OutputFormat::Json,
+            "csv" => OutputFormat::Csv,
+            _ => OutputFormat::Text,
         }
+    }
 
-        let mut s = ln.to_ascii_lowercase();
-        for ch in s.chars() {
-            if !ch.is_ascii_alphabetic() {
-                eprintln!("Failed to parse secret header file: non-alphabetic characters found");
-                std::process::exit(1);
-            }
+}
+
+/// Reports the status and the progress of the search.
+#[derive(Clone, Copy, Debug)]
+struct Reporter {
+    format: OutputFormat,
+    quiet: bool,
+}
+
+impl Reporter {
+    /// Print a status message. Status messages go to the standard error when the candidates are
+    /// printed in a machine-readable format, so that they do not mix with the records.
+    fn status(&self, message: &str) {
+        if self.quiet {
+            return;
         }
 
-        ret.push(s);
+        match self.format {
+            OutputFormat::Text => println!("{}", message),
+            _ => eprintln!("{}", message),
+        }
     }
 
-    ret
+    /// Start reporting the progress of a phase with the specified number of items. The progress
+    /// bar is only drawn when the standard error is a terminal.
+    fn progress(&self, phase: &'static str, total: usize) -> Progress {
+        let visible = !self.quiet && std::io::stderr().is_terminal();
+        let progress = Progress { phase, total, done: 0, start: Instant::now(), visible };
+        progress.draw();
+        progress
+    }
+}
+
+/// A progress bar drawn on the standard error.
+struct Progress {
+    phase: &'static str,
+    total: usize,
+    done: usize,
+    start: Instant,
+    visible: bool,
+}
+
+impl Progress {
+    const BAR_WIDTH: usize = 30;
+
+    /// Mark the specified number of items as done.
+    fn add(&mut self, items: usize) {
+        self.done += items;
+        self.draw();
+    }
+
+    fn finish(self) {
+        if self.visible {
+            eprintln!();
+        }
+    }
+
+    fn draw(&self) {
+        if !self.visible {
+            return;
+        }
+
+        let filled = (self.done * Self::BAR_WIDTH).checked_div(self.total)
+            .unwrap_or(Self::BAR_WIDTH);
+        let bar = format!("{}{}", "#".repeat(filled), ".".repeat(Self::BAR_WIDTH - filled));
+
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 { self.done as f64 / elapsed } else { 0.0 };
+        let eta = if rate > 0.0 {
+            let secs = ((self.total - self.done) as f64 / rate).round() as u64;
+            format!("{:02}:{:02}", secs / 60, secs % 60)
+        } else {
+            String::from("--:--")
+        };
+
+        eprint!("\r{}: [{}] {}/{} {:.1}/s ETA {}", self.phase, bar, self.done, self.total, rate,
+            eta);
+        std::io::stderr().flush().ok();
+    }
+}
+
+/// A candidate setting printed in a machine-readable format.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct CandidateRecord {
+    /// The 1-based indices of the configured rotators, in the order they are placed into the
+    /// machine.
+    rotor_order: [usize; 3],
+
+    /// The wirings of the rotators in the order they are placed into the machine.
+    rotators: [String; 3],
+
+    /// The window letters of the ground setting, in rotator order.
+    positions: String,
+
+    /// The rank of the rotor order and the ground setting within the keyspace.
+    rank: u64,
+
+    /// The fraction of the doubled message key letters that agree when the secret headers are
+    /// deciphered at the ground setting, from 0 to 1. The plug board is not known yet, so the
+    /// right setting scores highest but not necessarily 1.
+    score: f64,
+}
+
+/// The slice of the keyspace searched by a run, as given by `--shard`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+struct Shard {
+    /// The 1-based index of the slice.
+    index: u64,
+
+    /// The number of slices.
+    count: u64,
+}
+
+impl Shard {
+    fn parse(s: &str) -> Result<Self, String> {
+        let mut parts = s.splitn(2, '/');
+        let index = parts.next().and_then(|index| index.trim().parse::<u64>().ok());
+        let count = parts.next().and_then(|count| count.trim().parse::<u64>().ok());
+        match (index, count) {
+            (Some(index), Some(count)) if index >= 1 && index <= count =>
+                Ok(Self { index, count }),
+            _ => Err(format!("expected K/N with 1 <= K <= N, got {}", s)),
+        }
+    }
 }
 
-fn get_secret_permutations(secret_headers: &Vec<String>) -> [Permutation; 3] {
-    let mut secret_perm_buf = [
-        vec![-1i8; RUNE_SET_SIZE as usize],
-        vec![-1i8; RUNE_SET_SIZE as usize],
-        vec![-1i8; RUNE_SET_SIZE as usize],
-    ];
-    for secret in &secret_headers {
-        let secret_bytes = secret.as_bytes();
-        for i in 0..3usize {
-            let input = secret_bytes[i];
-            let output = secret_bytes[i + 3];
-
-            if secret_perm_buf[i][input as usize] != -1 {
-                if secret_perm_buf[i][input as usize] != output as i8 {
-                    eprintln!("Error: conflicting secret permutation");
-                    std::process::exit(1);
-                }
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct CrackResult {
+    /// The slice of the keyspace searched, if the search was sharded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shard: Option<Shard>,
+
+    candidates: Vec<CandidateRecord>,
+}
+
+fn print_result(format: OutputFormat, result: &CrackResult) {
+    let candidates = &result.candidates;
+    match format {
+        OutputFormat::Text => {
+            for candidate in candidates {
+                let order: Vec<String> = candidate.rotor_order.iter()
+                    .map(|i| i.to_string())
+                    .collect();
+                let positions: Vec<String> = candidate.positions.chars()
+                    .map(|ch| ch.to_string())
+                    .collect();
+                println!("Found possible settings: rotor order {}, ground setting {}",
+                    order.join(" "), positions.join(" "));
+            }
+
+            let orders: BTreeSet<[usize; 3]> = candidates.iter().map(|c| c.rotor_order).collect();
+            println!("{} possible settings found in {} rotor orders",
+                candidates.len(), orders.len());
+        },
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(result).unwrap());
+        },
+        OutputFormat::Csv => {
+            println!("rotor_order,positions,rank,score");
+            for candidate in candidates {
+                let order: Vec<String> = candidate.rotor_order.iter()
+                    .map(|i| i.to_string())
+                    .collect();
+                println!("{},{},{},{}", order.join(" "), candidate.positions, candidate.rank,
+                    candidate.score);
             }
+        },
+    }
+}
+
+/// Decipher the specified secret headers at the specified ground setting without plug board, and
+/// get the fraction of the doubled message key letters that agree.
+fn score_candidate(config: &Config, order: [usize; 3], offsets: [u8; 3],
+    secret_headers: &[Indicator]) -> f64 {
+    let mut machine = config.create_enigma(order);
+    let window: String = offsets.iter().rev()
+        .map(|offset| Rune::from_value(*offset).unwrap().into_char())
+        .collect();
+    machine.set_window(&window).unwrap();
+
+    let mut agreements = 0usize;
+    for indicator in secret_headers {
+        let mut machine = machine.clone();
+        let key: Vec<Rune> = indicator.runes().iter().map(|rune| machine.map_rune(*rune)).collect();
+        agreements += (0..3).filter(|i| key[*i] == key[*i + 3]).count();
+    }
+    agreements as f64 / (3 * secret_headers.len().max(1)) as f64
+}
 
-            secret_perm_buf[i][input as usize] = output;
+fn create_candidate_record(config: &Config, keyspace: &Keyspace, rank: u64,
+    secret_headers: &[Indicator]) -> CandidateRecord {
+    let (order, offsets) = keyspace.unrank(rank).unwrap();
+    CandidateRecord {
+        rotor_order: [order[0] + 1, order[1] + 1, order[2] + 1],
+        rotators: [
+            config.rotators[order[0]].clone(),
+            config.rotators[order[1]].clone(),
+            config.rotators[order[2]].clone(),
+        ],
+        positions: offsets.iter()
+            .map(|offset| Rune::from_value(*offset).unwrap().into_char().to_ascii_uppercase())
+            .collect(),
+        rank,
+        score: score_candidate(config, order, offsets, secret_headers),
+    }
+}
+
+fn format_arg<'a, 'b>() -> clap::Arg<'a, 'b> {
+    clap::Arg::with_name("format")
+        .long("format")
+        .takes_value(true)
+        .possible_values(&["text", "json", "csv"])
+        .default_value("text")
+        .help("Output format of the candidate settings")
+}
+
+fn quiet_arg<'a, 'b>() -> clap::Arg<'a, 'b> {
+    clap::Arg::with_name("quiet")
+        .short("q")
+        .long("quiet")
+        .help("Do not print status messages and progress bars")
+}
+
+fn create_reporter(args: &clap::ArgMatches) -> Reporter {
+    Reporter {
+        format: OutputFormat::from_arg(args.value_of("format").unwrap()),
+        quiet: args.is_present("quiet"),
+    }
+}
+
+fn run_search(args: &clap::ArgMatches) {
+    let reporter = create_reporter(args);
+
+    if let Some(threads) = args.value_of("threads") {
+        let threads = match threads.parse::<usize>() {
+            Ok(threads) if threads > 0 => threads,
+            _ => {
+                eprintln!("Invalid number of threads: {}", threads);
+                std::process::exit(1);
+            },
+        };
+        if let Err(e) = rayon::ThreadPoolBuilder::new().num_threads(threads).build_global() {
+            eprintln!("Failed to create thread pool: {}", e);
+            std::process::exit(1);
         }
     }
 
-    for p in &mut secret_perm_buf {
-        if p.contains(&-1i8) {
-            eprintln!("Error: too little secrets to crack");
+    let shard = args.value_of("shard").map(|shard| match Shard::parse(shard) {
+        Ok(shard) => shard,
+        Err(e) => {
+            eprintln!("Invalid shard: {}", e);
             std::process::exit(1);
+        },
+    });
+
+    let config = load_config(Path::new(args.value_of("config").unwrap()));
+    if config.rotators.len() < 3 {
+        eprintln!("At least 3 rotators are required, but {} are configured", config.rotators.len());
+        std::process::exit(1);
+    }
+    let secret_headers = load_secret_headers(Path::new(&config.secret_headers));
+
+    reporter.status("Analyzing cycles in secret headers");
+    let characteristic = match Characteristic::from_indicators(&secret_headers) {
+        Ok(characteristic) => characteristic,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        },
+    };
+
+    // Only the rotor orders overlapping the shard are searched, and only the keys within the shard
+    // are reported.
+    let keyspace = Keyspace::new(config.rotators.len());
+    let ranks = match shard {
+        Some(shard) => keyspace.shard(shard.index - 1, shard.count),
+        None => 0..keyspace.len(),
+    };
+    let orders: Vec<[usize; 3]> = config.rotor_orders(args.is_present("fixed-order")).into_iter()
+        .filter(|order| {
+            let first = keyspace.rank(*order, [0, 0, 0]).unwrap();
+            first < ranks.end && first + Keyspace::NUM_POSITIONS > ranks.start
+        })
+        .collect();
+    if let Some(shard) = shard {
+        reporter.status(&format!("Searching shard {} of {}: keys {} to {} in {} rotor orders",
+            shard.index, shard.count, ranks.start, ranks.end, orders.len()));
+    }
+
+    let catalog_path = args.value_of("catalog").map(Path::new);
+    let mut catalogs = catalog_path.map(load_catalogs).unwrap_or_default();
+
+    let checkpoint_path = args.value_of("checkpoint").map(Path::new);
+    let search = search_key(&config, &orders, &ranks, &characteristic);
+    let mut checkpoint = checkpoint_path
+        .and_then(|path| load_checkpoint(path, search, &reporter))
+        .unwrap_or_else(|| Checkpoint::new(search));
+    if checkpoint.completed > 0 {
+        reporter.status(&format!("Resuming from checkpoint: {} of {} rotor orders already searched",
+            checkpoint.completed, orders.len()));
+    }
+
+    reporter.status("Generating all permutations and matching their cycle decomposition");
+    let pending = &orders[checkpoint.completed..];
+
+    // The progress of the catalog phase counts the states cataloged, and the progress of the
+    // search phase counts the catalog entries matched against the characteristic.
+    let missing: BTreeSet<u64> = pending.iter()
+        .map(|order| config.catalog_key(*order))
+        .filter(|key| !catalogs.contains_key(key))
+        .collect();
+    let catalogs_changed = !missing.is_empty();
+    let mut progress = reporter.progress("catalog",
+        missing.len() * Keyspace::NUM_POSITIONS as usize);
+    for order in pending {
+        if let Entry::Vacant(entry) = catalogs.entry(config.catalog_key(*order)) {
+            entry.insert(Catalog::build_parallel(&config.create_enigma(*order)));
+            progress.add(Keyspace::NUM_POSITIONS as usize);
         }
     }
+    progress.finish();
+
+    let mut progress = reporter.progress("search",
+        pending.len() * Keyspace::NUM_POSITIONS as usize);
+    for order in pending {
+        let catalog = &catalogs[&config.catalog_key(*order)];
+        checkpoint.matches.extend(catalog.find_parallel(&characteristic).iter()
+            .map(|entry| keyspace.rank(*order, entry.offsets()).unwrap())
+            .filter(|rank| ranks.contains(rank)));
+        checkpoint.completed += 1;
+        if let Some(path) = checkpoint_path {
+            save_checkpoint(path, &checkpoint);
+        }
+
+        progress.add(catalog.entries().len());
+    }
+    progress.finish();
 
-    secret_perm_buf.iter().map(
-        |buf| Permutation::from(buf.iter().map(|x| *x as u8).collect()).unwrap()
-    ).collect()
+    if let Some(path) = catalog_path {
+        if catalogs_changed {
+            save_catalogs(path, &catalogs);
+        }
+    }
+    if let Some(path) = checkpoint_path {
+        // The search is complete, so there is nothing left to resume.
+        std::fs::remove_file(path).ok();
+    }
+
+    let candidates: Vec<CandidateRecord> = checkpoint.matches.iter()
+        .map(|rank| create_candidate_record(&config, &keyspace, *rank, &secret_headers))
+        .collect();
+
+    print_result(reporter.format, &CrackResult { shard, candidates });
 }
 
-fn is_cycle_decomposition_eq(lhs: &CycleDecomposition, rhs: &CycleDecomposition) -> bool {
-    HashSet::from_iter(lhs.iter().copied()) == HashSet::from_iter(rhs.iter().copied())
+fn run_merge(args: &clap::ArgMatches) {
+    let reporter = create_reporter(args);
+
+    let mut shards = Vec::new();
+    let mut candidates = Vec::new();
+    for path in args.values_of("inputs").unwrap() {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Failed to read result file {}: {}", path, e);
+                std::process::exit(1);
+            },
+        };
+
+        match serde_json::from_str::<CrackResult>(&content) {
+            Ok(result) => {
+                shards.extend(result.shard);
+                candidates.extend(result.candidates);
+            },
+            Err(e) => {
+                eprintln!("Failed to parse result file {}: {}", path, e);
+                std::process::exit(1);
+            },
+        }
+    }
+
+    if let Some(count) = shards.first().map(|shard| shard.count) {
+        if shards.iter().any(|shard| shard.count != count) {
+            eprintln!("The result files are from searches split into different numbers of shards");
+            std::process::exit(1);
+        }
+
+        let missing: Vec<String> = (1..=count)
+            .filter(|index| shards.iter().all(|shard| shard.index != *index))
+            .map(|index| index.to_string())
+            .collect();
+        if !missing.is_empty() {
+            reporter.status(&format!("Warning: missing shards {} of {}", missing.join(", "),
+                count));
+        }
+    }
+
+    candidates.sort_by_key(|candidate| candidate.rank);
+    candidates.dedup_by_key(|candidate| candidate.rank);
+
+    print_result(reporter.format, &CrackResult { shard: None, candidates });
 }
 
 fn main() {
     let args = clap::App::new("enigma-crack")
         .about("Crack Enigma machine with Marian Rejewski's method")
+        .setting(clap::AppSettings::SubcommandsNegateReqs)
         .arg(clap::Arg::with_name("config")
             .short("c")
             .long("config")
             .takes_value(true)
             .required(true)
             .help("Path to the configuration file"))
+        .arg(clap::Arg::with_name("threads")
+            .short("j")
+            .long("threads")
+            .takes_value(true)
+            .value_name("N")
+            .help("Number of worker threads, defaults to the number of logical CPUs"))
+        .arg(clap::Arg::with_name("catalog")
+            .long("catalog")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Path to a catalog file to reuse catalogs from and save new catalogs to"))
+        .arg(clap::Arg::with_name("fixed-order")
+            .long("fixed-order")
+            .help("Only try the first 3 configured rotators in their configured order"))
+        .arg(format_arg())
+        .arg(quiet_arg())
+        .arg(clap::Arg::with_name("checkpoint")
+            .long("checkpoint")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Path to a checkpoint file to save the progress to and resume the search from"))
+        .arg(clap::Arg::with_name("shard")
+            .long("shard")
+            .takes_value(true)
+            .value_name("K/N")
+            .conflicts_with("fixed-order")
+            .help("Only search the K-th of N disjoint slices of the keyspace"))
+        .subcommand(clap::SubCommand::with_name("merge")
+            .about("Merge the JSON result files of sharded searches")
+            .arg(format_arg())
+            .arg(quiet_arg())
+            .arg(clap::Arg::with_name("inputs")
+                .takes_value(true)
+                .multiple(true)
+                .required(true)
+                .value_name("FILE")
+                .help("Paths to the JSON result files")))
         .get_matches();
 
-    let config = load_config(&PathBuf::from(String::from(args.value_of("config").unwrap())));
-    let secret_headers = load_secret_headers(&PathBuf::from(&config.secret_headers));
-
-    println!("Generating all permutations and their corresponding cycle decomposition");
-    let all_perms = get_all_rotator_reflector_perm(&config);
-
-    let mut merged_perms = Vec::with_capacity(all_perms.len());
-    for i in 0..all_perms.len() {
-        let j = (i + 3) % all_perms.len();
-        merged_perms.push(combine_permutations(&all_perms[i], &all_perms[j]));
-    }
-
-    let merged_perms_decomp: Vec<CycleDecomposition> =
-        merged_perms.iter().map(|p| get_cycle_decomposition(p)).collect();
-
-    println!("Analyzing cycles in secret headers");
-    let secret_perms = get_secret_permutations(&secret_headers);
-    let secret_perms_decomp: [CycleDecomposition; 3] = secret_perms.iter().map(
-        |p| get_cycle_decomposition(p)
-    ).collect();
-
-    println!("Matching existing cycles dictionary");
-    let mut possible_settings = Vec::new();
-    for i in 0..merged_perms.len() {
-        let j = (i + 1) % merged_perms.len();
-        let k = (i + 2) % merged_perms.len();
-        if is_cycle_decomposition_eq(&merged_perms_decomp[i], &secret_perms_decomp[0]) &&
-            is_cycle_decomposition_eq(&merged_perms_decomp[j], &secret_perms_decomp[1]) &&
-            is_cycle_decomposition_eq(&merged_perms_decomp[k], &secret_perms_decomp[2]) {
-            possible_settings.push(i);
-            println!("Found possible settings: {}", i);
-        }
+    match args.subcommand() {
+        ("merge", Some(merge_args)) => run_merge(merge_args),
+        _ => run_search(&args),
     }
-
-    println!("All possible settings: {:?}", possible_settings);
 }
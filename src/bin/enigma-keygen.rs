@@ -0,0 +1,192 @@
+extern crate enigma;
+
+extern crate clap;
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use enigma::PlugBoard;
+use enigma::math::Permutation;
+use enigma::state::EnigmaState;
+use enigma::testvectors::ROTATORS;
+use enigma::utils::{Rune, RUNE_SET_SIZE};
+
+/// The index of the reflector B within `testvectors::REFLECTORS`, which all generated keys use.
+const REFLECTOR_B: u8 = 1;
+
+/// A SplitMix64 generator. Keys are not meant to protect secrets, so a fast generator with a
+/// reproducible seed is preferred over a cryptographic one.
+struct Rng(u64);
+
+impl Rng {
+    /// Create a generator seeded from the randomly keyed hasher of the standard library and the
+    /// current time.
+    fn from_entropy() -> Self {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default());
+        Self(hasher.finish())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Draw an integer uniformly from `0..n`, ignoring the negligible modulo bias.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// The key of a single day. All values are listed from left to right.
+struct DailyKey {
+    state: EnigmaState,
+}
+
+impl DailyKey {
+    fn random(rng: &mut Rng, plug_pairs: usize) -> Self {
+        // Draw 3 distinct rotators with a partial Fisher-Yates shuffle.
+        let mut rotators: Vec<u8> = (0..ROTATORS.len() as u8).collect();
+        for i in 0..3 {
+            let j = i + rng.below(rotators.len() - i);
+            rotators.swap(i, j);
+        }
+
+        let mut cycle_type = vec![2; plug_pairs];
+        cycle_type.resize(RUNE_SET_SIZE as usize - plug_pairs, 1);
+        let perm = Permutation::random_with_cycle_type(&cycle_type, |n| rng.below(n)).unwrap();
+        let plug_pairs = PlugBoard::from_perm(perm).unwrap().pairs();
+
+        let mut letter = || rng.below(RUNE_SET_SIZE as usize) as u8;
+        let rings = [letter(), letter(), letter()];
+        let offsets = [letter(), letter(), letter()];
+
+        Self {
+            state: EnigmaState {
+                rotor_order: [rotators[0], rotators[1], rotators[2]],
+                reflector: REFLECTOR_B,
+                offsets,
+                rings,
+                plug_pairs,
+            },
+        }
+    }
+
+    /// Format this key as a row of a key sheet.
+    fn to_sheet_row(&self, day: usize) -> String {
+        let rotors: Vec<&str> = self.state.rotor_order.iter()
+            .map(|index| ROTATORS[*index as usize].0)
+            .collect();
+        let rings: Vec<String> = self.state.rings.iter()
+            .map(|ring| format!("{:02}", ring + 1))
+            .collect();
+        let plug_pairs: Vec<String> = self.state.plug_pairs.iter()
+            .map(|(a, b)| format!("{}{}", a, b))
+            .collect();
+        let ground: String = self.state.offsets.iter()
+            .map(|offset| Rune::from_value(*offset).unwrap().into_char())
+            .collect();
+
+        format!("{:>3}  {:<12}  {}  {:<38}  {}", day, rotors.join(" "), rings.join(" "),
+            plug_pairs.join(" "), ground)
+    }
+
+    /// Format this key as a configuration file read by `enigma-cli`.
+    fn to_json(&self) -> String {
+        let machine = self.state.create_enigma().unwrap();
+        machine.to_config().unwrap().to_json()
+    }
+}
+
+fn parse_count(args: &clap::ArgMatches, name: &str, default: usize) -> usize {
+    match args.value_of(name) {
+        Some(value) => match value.parse::<usize>() {
+            Ok(count) => count,
+            Err(_) => {
+                eprintln!("Invalid {}: {}", name, value);
+                std::process::exit(1);
+            },
+        },
+        None => default,
+    }
+}
+
+fn main() {
+    let args = clap::App::new("enigma-keygen")
+        .about("Generate random valid keys of the Wehrmacht Enigma")
+        .arg(clap::Arg::with_name("days")
+            .short("d")
+            .long("days")
+            .takes_value(true)
+            .value_name("N")
+            .help("Generate a key sheet of N days instead of a single key"))
+        .arg(clap::Arg::with_name("plug-pairs")
+            .short("p")
+            .long("plug-pairs")
+            .takes_value(true)
+            .value_name("N")
+            .default_value("10")
+            .help("Number of pairs of letters connected on the plug board, at most 13"))
+        .arg(clap::Arg::with_name("format")
+            .short("f")
+            .long("format")
+            .takes_value(true)
+            .possible_values(&["sheet", "json"])
+            .default_value("sheet")
+            .help("Output format of the keys"))
+        .arg(clap::Arg::with_name("seed")
+            .long("seed")
+            .takes_value(true)
+            .value_name("SEED")
+            .help("Seed of the random generator, to generate the same keys again"))
+        .get_matches();
+
+    let single = !args.is_present("days");
+    let days = parse_count(&args, "days", 1);
+    if days == 0 {
+        eprintln!("Invalid days: the key sheet must cover at least 1 day");
+        std::process::exit(1);
+    }
+    let plug_pairs = parse_count(&args, "plug-pairs", 10);
+    if plug_pairs > RUNE_SET_SIZE as usize / 2 {
+        eprintln!("Invalid plug-pairs: at most {} pairs fit on the plug board",
+            RUNE_SET_SIZE / 2);
+        std::process::exit(1);
+    }
+
+    let mut rng = match args.value_of("seed") {
+        Some(seed) => match seed.parse::<u64>() {
+            Ok(seed) => Rng(seed),
+            Err(_) => {
+                eprintln!("Invalid seed: {}", seed);
+                std::process::exit(1);
+            },
+        },
+        None => Rng::from_entropy(),
+    };
+    let keys: Vec<DailyKey> = (0..days).map(|_| DailyKey::random(&mut rng, plug_pairs)).collect();
+
+    match args.value_of("format").unwrap() {
+        "json" if single => print!("{}", keys[0].to_json()),
+        "json" => {
+            let keys: Vec<String> = keys.iter()
+                .map(|key| key.to_json().trim_end().to_string())
+                .collect();
+            println!("[\n{}\n]", keys.join(",\n"));
+        },
+        _ => {
+            // Key sheets list the days in descending order, so that the key of a day is cut off
+            // and destroyed after its use.
+            println!("Day  {:<12}  {:<8}  {:<38}  Ground", "Rotors", "Rings", "Plug board");
+            for (day, key) in keys.iter().enumerate().rev() {
+                println!("{}", key.to_sheet_row(day + 1));
+            }
+        },
+    }
+}
@@ -0,0 +1,344 @@
+extern crate enigma;
+
+extern crate clap;
+extern crate serde;
+extern crate serde_json;
+extern crate tiny_http;
+
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use enigma::{Enigma, Rune, Settings, SteppingMode};
+use enigma::analysis::rejewski::{Catalog, Characteristic, Indicator};
+use enigma::config::Config;
+use enigma::math::{Permutation, PermutationBuilder};
+use enigma::settings::Keyspace;
+use enigma::utils::RUNE_SET_SIZE;
+
+/// The maximal size of a request body, in bytes.
+const MAX_BODY_LEN: u64 = 1 << 20;
+
+/// The maximal number of live sessions.
+const MAX_SESSIONS: usize = 10000;
+
+/// The maximal number of rotators given to the Rejewski attack. A catalog is built for each of the
+/// n * (n - 1) * (n - 2) rotor orders, so this bounds the work of a single request.
+const MAX_REJEWSKI_ROTATORS: usize = 8;
+
+#[derive(Clone, Debug, Deserialize)]
+struct EncryptRequest {
+    /// The session whose machine maps the text. The machine keeps its state across requests.
+    session: Option<String>,
+
+    /// The configuration of a machine that maps the text and is discarded afterwards.
+    config: Option<Config>,
+
+    text: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct EncryptResponse {
+    text: String,
+
+    /// The window letters of the rotors after the text is mapped.
+    positions: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct SessionResponse {
+    session: String,
+    positions: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct RejewskiRequest {
+    rotators: Vec<String>,
+    reflector: Vec<[Rune; 2]>,
+    indicators: Vec<String>,
+
+    /// Only try the first 3 rotators in their given order.
+    #[serde(default)]
+    fixed_order: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct RejewskiCandidate {
+    /// The 1-based indices of the given rotators, in the order they are placed into the machine.
+    rotor_order: [usize; 3],
+
+    /// The window letters of the ground setting.
+    positions: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct RejewskiResponse {
+    candidates: Vec<RejewskiCandidate>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// An error response with its HTTP status code.
+struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn bad_request<T: ToString>(message: T) -> Self {
+        Self { status: 400, message: message.to_string() }
+    }
+
+    fn not_found() -> Self {
+        Self { status: 404, message: String::from("not found") }
+    }
+}
+
+/// The machines of the live sessions, keyed by session ID.
+struct Sessions {
+    machines: Mutex<HashMap<String, Enigma>>,
+    ids: RandomState,
+}
+
+impl Sessions {
+    fn new() -> Self {
+        Self { machines: Mutex::new(HashMap::new()), ids: RandomState::new() }
+    }
+
+    fn create(&self, machine: Enigma) -> Result<SessionResponse, HttpError> {
+        let mut machines = self.machines.lock().unwrap();
+        if machines.len() >= MAX_SESSIONS {
+            return Err(HttpError { status: 503, message: String::from("too many sessions") });
+        }
+
+        let mut hasher = self.ids.build_hasher();
+        hasher.write_usize(machines.len());
+        hasher.write_u128(std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default());
+        let session = format!("{:016x}", hasher.finish());
+
+        let positions = format_positions(&machine);
+        machines.insert(session.clone(), machine);
+        Ok(SessionResponse { session, positions })
+    }
+
+    fn delete(&self, session: &str) -> Result<(), HttpError> {
+        match self.machines.lock().unwrap().remove(session) {
+            Some(_) => Ok(()),
+            None => Err(HttpError::not_found()),
+        }
+    }
+
+    fn map_str(&self, session: &str, text: &str) -> Result<EncryptResponse, HttpError> {
+        let mut machines = self.machines.lock().unwrap();
+        let machine = machines.get_mut(session).ok_or_else(HttpError::not_found)?;
+        let text = machine.map_str(text);
+        Ok(EncryptResponse { text, positions: format_positions(machine) })
+    }
+}
+
+fn parse_rune(ch: char) -> Result<Rune, String> {
+    Rune::from_char(ch).map_err(|_| format!("{} is not an ASCII alphabetic character", ch))
+}
+
+fn parse_wiring(s: &str) -> Result<Permutation, String> {
+    let perm = s.chars().map(|ch| parse_rune(ch).map(|r| r.value())).collect::<Result<_, _>>()?;
+    Permutation::from_perm(perm).map_err(|e| format!("invalid permutation: {}", e))
+}
+
+/// Create the machine described by the specified configuration, in the format of `enigma-cli`.
+fn create_enigma(config: &Config) -> Result<Enigma, HttpError> {
+    let settings = config.to_settings().map_err(HttpError::bad_request)?;
+    settings.create_enigma().map_err(HttpError::bad_request)
+}
+
+fn create_permutation_from_swaps(swaps: &[[Rune; 2]]) -> Permutation {
+    let mut builder = PermutationBuilder::new(RUNE_SET_SIZE);
+    for [lhs, rhs] in swaps {
+        builder = builder.swap(lhs.value(), rhs.value());
+    }
+    builder.build()
+}
+
+fn format_positions(machine: &Enigma) -> String {
+    machine.window()
+}
+
+fn parse_body<T: serde::de::DeserializeOwned>(request: &mut Request) -> Result<T, HttpError> {
+    let mut body = String::new();
+    request.as_reader().take(MAX_BODY_LEN).read_to_string(&mut body)
+        .map_err(|e| HttpError::bad_request(format!("cannot read request body: {}", e)))?;
+    serde_json::from_str(&body)
+        .map_err(|e| HttpError::bad_request(format!("invalid request body: {}", e)))
+}
+
+fn encrypt(sessions: &Sessions, request: EncryptRequest) -> Result<EncryptResponse, HttpError> {
+    match (&request.session, &request.config) {
+        (Some(session), None) => sessions.map_str(session, &request.text),
+        (None, Some(config)) => {
+            let mut machine = create_enigma(config)?;
+            let text = machine.map_str(&request.text);
+            Ok(EncryptResponse { text, positions: format_positions(&machine) })
+        },
+        _ => Err(HttpError::bad_request("exactly one of session and config should be given")),
+    }
+}
+
+fn crack_rejewski(request: RejewskiRequest) -> Result<RejewskiResponse, HttpError> {
+    if request.rotators.len() < 3 {
+        return Err(HttpError::bad_request("at least 3 rotators should be given"));
+    }
+    if request.rotators.len() > MAX_REJEWSKI_ROTATORS {
+        return Err(HttpError::bad_request(
+            format!("at most {} rotators should be given", MAX_REJEWSKI_ROTATORS)));
+    }
+
+    let rotators = request.rotators.iter()
+        .map(|w| parse_wiring(w))
+        .collect::<Result<Vec<Permutation>, String>>()
+        .map_err(HttpError::bad_request)?;
+    let reflector = create_permutation_from_swaps(&request.reflector);
+    let indicators = request.indicators.iter()
+        .map(|s| s.parse::<Indicator>())
+        .collect::<Result<Vec<Indicator>, _>>()
+        .map_err(HttpError::bad_request)?;
+    let characteristic = Characteristic::from_indicators(&indicators)
+        .map_err(HttpError::bad_request)?;
+
+    let orders = if request.fixed_order {
+        vec![[0, 1, 2]]
+    } else {
+        Keyspace::new(rotators.len()).rotor_orders()
+    };
+
+    let mut candidates = Vec::new();
+    for order in orders {
+        let settings = Settings {
+            rotator_wirings: [
+                rotators[order[0]].clone(),
+                rotators[order[1]].clone(),
+                rotators[order[2]].clone(),
+            ],
+            positions: [0, 0, 0],
+            rings: [0, 0, 0],
+            notches: Default::default(),
+            stepping: SteppingMode::Odometer,
+            reflector: reflector.clone(),
+            plug_pairs: Vec::new(),
+        };
+        let machine = settings.create_enigma().map_err(HttpError::bad_request)?;
+        let catalog = Catalog::build_parallel(&machine);
+        for entry in catalog.find_parallel(&characteristic) {
+            let mut ground = settings.clone();
+            ground.positions = entry.offsets();
+            let ground = ground.create_enigma().map_err(HttpError::bad_request)?;
+            candidates.push(RejewskiCandidate {
+                rotor_order: [order[0] + 1, order[1] + 1, order[2] + 1],
+                positions: format_positions(&ground),
+            });
+        }
+    }
+
+    Ok(RejewskiResponse { candidates })
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Response<std::io::Cursor<Vec<u8>>> {
+    let content_type = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .unwrap();
+    Response::from_data(serde_json::to_vec(body).unwrap())
+        .with_status_code(status)
+        .with_header(content_type)
+}
+
+fn handle(sessions: &Sessions, mut request: Request) {
+    let url = request.url().to_owned();
+    let path = url.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    let result = match (request.method(), segments.as_slice()) {
+        (Method::Post, ["sessions"]) => parse_body::<Config>(&mut request)
+            .and_then(|config| create_enigma(&config))
+            .and_then(|machine| sessions.create(machine))
+            .map(|response| json_response(201, &response)),
+        (Method::Delete, ["sessions", session]) => sessions.delete(session)
+            .map(|()| Response::from_data(Vec::new()).with_status_code(204)),
+        (Method::Post, ["encrypt"]) | (Method::Post, ["decrypt"]) =>
+            parse_body::<EncryptRequest>(&mut request)
+                .and_then(|body| encrypt(sessions, body))
+                .map(|response| json_response(200, &response)),
+        (Method::Post, ["crack", "rejewski"]) => parse_body::<RejewskiRequest>(&mut request)
+            .and_then(crack_rejewski)
+            .map(|response| json_response(200, &response)),
+        _ => Err(HttpError::not_found()),
+    };
+
+    let response = result.unwrap_or_else(|e| {
+        json_response(e.status, &ErrorResponse { error: e.message })
+    });
+    if let Err(e) = request.respond(response) {
+        eprintln!("Failed to send response: {}", e);
+    }
+}
+
+fn main() {
+    let args = clap::App::new("enigma-server")
+        .about("Serve the Enigma emulator and crack procedures over HTTP")
+        .arg(clap::Arg::with_name("bind")
+            .short("b")
+            .long("bind")
+            .takes_value(true)
+            .default_value("127.0.0.1:8080")
+            .help("Address to listen on"))
+        .arg(clap::Arg::with_name("threads")
+            .short("j")
+            .long("threads")
+            .takes_value(true)
+            .default_value("4")
+            .help("Number of requests handled concurrently"))
+        .get_matches();
+
+    let threads: usize = match args.value_of("threads").unwrap().parse() {
+        Ok(threads) if threads > 0 => threads,
+        _ => {
+            eprintln!("Invalid number of threads");
+            std::process::exit(1);
+        },
+    };
+
+    let bind = args.value_of("bind").unwrap();
+    let server = match Server::http(bind) {
+        Ok(server) => Arc::new(server),
+        Err(e) => {
+            eprintln!("Cannot listen on {}: {}", bind, e);
+            std::process::exit(1);
+        },
+    };
+    println!("Listening on {}", bind);
+
+    let sessions = Arc::new(Sessions::new());
+    let workers: Vec<_> = (0..threads)
+        .map(|_| {
+            let server = server.clone();
+            let sessions = sessions.clone();
+            std::thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    handle(&sessions, request);
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
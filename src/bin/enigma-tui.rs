@@ -0,0 +1,314 @@
+extern crate enigma;
+
+extern crate clap;
+extern crate ratatui;
+
+use std::io;
+use std::path::Path;
+
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Alignment, Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::Frame;
+
+use enigma::{Enigma, LampBoard, RotorMachine, Rune};
+use enigma::config::{Config, LetterPair, Offset};
+use enigma::utils::RUNE_SET_SIZE;
+
+/// The number of letters shown in the input and output tapes.
+const TAPE_LEN: usize = 60;
+
+/// Create the machine described by the specified configuration.
+fn create_enigma(config: &Config) -> Result<Enigma, String> {
+    let settings = config.to_settings().map_err(|e| e.to_string())?;
+    settings.create_enigma().map_err(|e| e.to_string())
+}
+
+fn load_config(path: &Path) -> Config {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Cannot read config file: {}", e);
+            std::process::exit(1);
+        },
+    };
+
+    match Config::from_json(&content) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Failed to parse config: {}", e);
+            std::process::exit(1);
+        },
+    }
+}
+
+/// The state of the panel.
+struct App {
+    config: Config,
+    machine: Enigma,
+
+    /// The index of the rotor turned by the Up and Down keys.
+    selected: usize,
+
+    /// The lamp board, whose lamp of the output of the last key press is lit.
+    lamps: LampBoard,
+
+    input: String,
+    output: String,
+
+    /// The number of key presses that can be undone. Changing the settings clears the history.
+    undoable: usize,
+
+    /// The first letter of a plug pair being entered, if the panel is in plug mode.
+    plug_mode: Option<Option<Rune>>,
+
+    status: String,
+}
+
+impl App {
+    fn new(config: Config, machine: Enigma) -> Self {
+        Self {
+            config,
+            machine,
+            selected: 0,
+            lamps: LampBoard::qwertz(),
+            input: String::new(),
+            output: String::new(),
+            undoable: 0,
+            plug_mode: None,
+            status: String::new(),
+        }
+    }
+
+    fn key_in(&mut self, rune: Rune) {
+        let output = self.machine.map_rune(rune);
+        self.input.push(rune.into_char());
+        self.output.push(output.into_char());
+        self.lamps.light(output);
+        self.undoable += 1;
+    }
+
+    fn undo(&mut self) {
+        if self.undoable == 0 {
+            return;
+        }
+
+        self.machine.rewind(1);
+        self.input.pop();
+        self.output.pop();
+        self.lamps.clear();
+        self.undoable -= 1;
+    }
+
+    fn turn_selected(&mut self, forward: bool) {
+        let mut state = self.machine.state();
+        let offset = state[self.selected];
+        state[self.selected] = if forward {
+            (offset + 1) % RUNE_SET_SIZE
+        } else {
+            (offset + RUNE_SET_SIZE - 1) % RUNE_SET_SIZE
+        };
+        self.machine.restore(&state).unwrap();
+        self.settings_changed();
+    }
+
+    /// Connect the specified letters on the plug board, or disconnect them if they are connected.
+    fn toggle_plug(&mut self, lhs: Rune, rhs: Rune) {
+        let connected = |LetterPair(a, b): &LetterPair| {
+            (*a, *b) == (lhs, rhs) || (*a, *b) == (rhs, lhs)
+        };
+
+        let mut config = self.config.clone();
+        if config.plug_board.iter().any(connected) {
+            config.plug_board.retain(|p| !connected(p));
+        } else {
+            config.plug_board.push(LetterPair(lhs, rhs));
+        }
+
+        let state = self.machine.state();
+        for (rotator, offset) in config.rotators.iter_mut().zip(state) {
+            rotator.1 = Offset(offset);
+        }
+
+        match create_enigma(&config) {
+            Ok(machine) => {
+                self.config = config;
+                self.machine = machine;
+                self.settings_changed();
+            },
+            Err(e) => self.status = format!("Cannot plug {}{}: {}", lhs, rhs, e),
+        }
+    }
+
+    fn settings_changed(&mut self) {
+        self.lamps.clear();
+        self.undoable = 0;
+        self.status.clear();
+    }
+
+    /// Handle the specified key press. Returns `false` if the panel should be closed.
+    fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        if modifiers.contains(KeyModifiers::CONTROL) {
+            return code != KeyCode::Char('c');
+        }
+
+        if let Some(first) = self.plug_mode {
+            match code {
+                KeyCode::Char(ch) => if let Ok(rune) = Rune::from_char(ch) {
+                    match first {
+                        Some(first) => {
+                            self.plug_mode = None;
+                            if first != rune {
+                                self.toggle_plug(first, rune);
+                            }
+                        },
+                        None => self.plug_mode = Some(Some(rune)),
+                    }
+                },
+                KeyCode::Tab | KeyCode::Esc => self.plug_mode = None,
+                _ => (),
+            }
+            return true;
+        }
+
+        match code {
+            KeyCode::Esc => return false,
+            KeyCode::Char(ch) => if let Ok(rune) = Rune::from_char(ch) {
+                self.key_in(rune);
+            },
+            KeyCode::Backspace => self.undo(),
+            KeyCode::Left => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Right => self.selected = (self.selected + 1).min(2),
+            KeyCode::Up => self.turn_selected(true),
+            KeyCode::Down => self.turn_selected(false),
+            KeyCode::Tab => {
+                self.plug_mode = Some(None);
+                self.lamps.clear();
+            },
+            _ => (),
+        }
+        true
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [rotors, lamps, plugs, tapes, help] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(5),
+            Constraint::Length(3),
+            Constraint::Length(4),
+            Constraint::Min(1),
+        ]).areas(frame.area());
+
+        let windows: Vec<Span> = self.machine.state().iter().enumerate()
+            .flat_map(|(i, offset)| {
+                let letter = Rune::from_value(*offset).unwrap().into_char();
+                let style = if i == self.selected {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                };
+                vec![Span::styled(format!(" {} ", letter), style), Span::raw("  ")]
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(Line::from(windows))
+            .alignment(Alignment::Center)
+            .block(Block::bordered().title("Rotors")), rotors);
+
+        let rows: Vec<Line> = self.lamps.layout().rows().iter()
+            .map(|row| {
+                let lamps: Vec<Span> = row.iter()
+                    .map(|lamp| {
+                        let style = if self.lamps.is_lit(*lamp) {
+                            Style::default().fg(Color::Black).bg(Color::Yellow)
+                        } else {
+                            Style::default().fg(Color::DarkGray)
+                        };
+                        Span::styled(format!(" {} ", lamp.into_char()), style)
+                    })
+                    .collect();
+                Line::from(lamps)
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(rows)
+            .alignment(Alignment::Center)
+            .block(Block::bordered().title("Lamps")), lamps);
+
+        let mut pairs: Vec<String> = self.config.plug_board.iter()
+            .map(|LetterPair(lhs, rhs)| format!("{}{}", lhs, rhs))
+            .collect();
+        if let Some(first) = self.plug_mode {
+            let first = first.map(|r| r.into_char()).unwrap_or('_');
+            pairs.push(format!("[{}_]", first));
+        }
+        frame.render_widget(Paragraph::new(pairs.join(" "))
+            .block(Block::bordered().title("Plug Board")), plugs);
+
+        let tape = |s: &str| {
+            let start = s.len().saturating_sub(TAPE_LEN);
+            s.as_bytes()[start..].chunks(5)
+                .map(|group| String::from_utf8_lossy(group).into_owned())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        frame.render_widget(Paragraph::new(vec![
+            Line::from(format!("In:  {}", tape(&self.input))),
+            Line::from(format!("Out: {}", tape(&self.output))),
+        ]).block(Block::bordered().title("Tape")), tapes);
+
+        let help_text = if self.plug_mode.is_some() {
+            "Type 2 letters to connect or disconnect them | Tab/Esc: cancel"
+        } else {
+            "Letters: key in | Backspace: undo | Left/Right: select rotor | Up/Down: turn rotor \
+                | Tab: plug mode | Esc: quit"
+        };
+        frame.render_widget(Paragraph::new(vec![
+            Line::styled(self.status.as_str(), Style::default().fg(Color::Red)),
+            Line::styled(help_text, Style::default().fg(Color::DarkGray)),
+        ]), help);
+    }
+}
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !app.handle_key(key.code, key.modifiers) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn main() {
+    let args = clap::App::new("enigma-tui")
+        .about("Operate an Enigma machine on an interactive panel")
+        .arg(clap::Arg::with_name("config")
+            .short("c")
+            .long("config")
+            .takes_value(true)
+            .required(true)
+            .help("Path to the machine configuration file"))
+        .get_matches();
+
+    let config = load_config(Path::new(args.value_of("config").unwrap()));
+    let machine = match create_enigma(&config) {
+        Ok(machine) => machine,
+        Err(e) => {
+            eprintln!("Invalid config: {}", e);
+            std::process::exit(1);
+        },
+    };
+
+    let mut app = App::new(config, machine);
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+
+    if let Err(e) = result {
+        eprintln!("Terminal error: {}", e);
+        std::process::exit(1);
+    }
+}
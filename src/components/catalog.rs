@@ -0,0 +1,284 @@
+//! This module implements a catalog of rotators that can be referred to by name, e.g. from
+//! configuration files.
+//!
+//! The [`RotorCatalog`] type maps names to the wirings and notches of rotators. The catalog
+//! returned by `RotorCatalog::historical` is preloaded with the rotators I to VIII of the Wehrmacht
+//! and Kriegsmarine Enigma, and further rotators can be registered at runtime:
+//!
+//! ```
+//! # use enigma::components::RotorCatalog;
+//! # use enigma::math::Permutation;
+//! # use enigma::Rune;
+//! #
+//! let mut catalog = RotorCatalog::historical();
+//! let wiring = Permutation::from_perm((0..26).map(|i| (i + 1) % 26).collect()).unwrap();
+//! catalog.register("MY-ROTOR", wiring, &[Rune::from_char('x').unwrap()]).unwrap();
+//!
+//! let rotator = catalog.create_rotator("MY-ROTOR", 0).unwrap();
+//! assert_eq!(rotator.map_forward(Rune::from_char('a').unwrap()), Rune::from_char('b').unwrap());
+//! assert_eq!(catalog.notches("VI").unwrap().len(), 2);
+//! ```
+//!
+//! The historical rotators and reflectors are also available as the `static` items
+//! [`HISTORICAL_ROTATORS`] and [`HISTORICAL_REFLECTORS`], which borrow their wiring tables from
+//! constant arrays and thus need no initialization at runtime:
+//!
+//! ```
+//! # use enigma::components::catalog::{HISTORICAL_REFLECTORS, HISTORICAL_ROTATORS};
+//! # use enigma::Rune;
+//! #
+//! let (name, rotator) = &HISTORICAL_ROTATORS[0];
+//! assert_eq!(*name, "I");
+//! assert_eq!(rotator.map_forward(Rune::from_char('a').unwrap()), Rune::from_char('e').unwrap());
+//!
+//! let (name, reflector) = &HISTORICAL_REFLECTORS[1];
+//! assert_eq!(*name, "B");
+//! assert_eq!(reflector.map(Rune::from_char('a').unwrap()), Rune::from_char('y').unwrap());
+//! ```
+//!
+//! [`RotorCatalog`]: struct.RotorCatalog.html
+//! [`HISTORICAL_ROTATORS`]: static.HISTORICAL_ROTATORS.html
+//! [`HISTORICAL_REFLECTORS`]: static.HISTORICAL_REFLECTORS.html
+//!
+
+use crate::components::reflector::Reflector;
+use crate::components::rotator::{InvalidRotatorPermutationError, Rotator};
+use crate::math::{self, Permutation};
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// A wiring table holding the values of the runes wired to each rune.
+type WiringTable = [u8; RUNE_SET_SIZE as usize];
+
+/// Convert the specified lower case letters to their values in const context.
+const fn wiring_table(letters: &[u8; RUNE_SET_SIZE as usize]) -> WiringTable {
+    let mut table = [0u8; RUNE_SET_SIZE as usize];
+    let mut i = 0;
+    while i < table.len() {
+        table[i] = rune(letters[i]).value();
+        i += 1;
+    }
+    table
+}
+
+/// Convert the specified lower case letter to its rune in const context.
+const fn rune(letter: u8) -> Rune {
+    match Rune::from_value(letter.wrapping_sub(b'a')) {
+        Ok(rune) => rune,
+        Err(_) => panic!("invalid letter"),
+    }
+}
+
+/// The wirings of the rotators I to VIII.
+static ROTATOR_WIRINGS: [WiringTable; 8] = [
+    wiring_table(b"ekmflgdqvzntowyhxuspaibrcj"),
+    wiring_table(b"ajdksiruxblhwtmcqgznpyfvoe"),
+    wiring_table(b"bdfhjlcprtxvznyeiwgakmusqo"),
+    wiring_table(b"esovpzjayquirhxlnftgkdcmwb"),
+    wiring_table(b"vzbrgityupsdnhlxawmjqofeck"),
+    wiring_table(b"jpgvoumfyqbenhzrdkasxlictw"),
+    wiring_table(b"nzjhgrcxmyswboufaivlpekqdt"),
+    wiring_table(b"fkqhtlxocbjspdzramewniuygv"),
+];
+
+/// The inverse wirings of the rotators I to VIII.
+static ROTATOR_INVERSE_WIRINGS: [WiringTable; 8] = [
+    math::invert(&ROTATOR_WIRINGS[0]),
+    math::invert(&ROTATOR_WIRINGS[1]),
+    math::invert(&ROTATOR_WIRINGS[2]),
+    math::invert(&ROTATOR_WIRINGS[3]),
+    math::invert(&ROTATOR_WIRINGS[4]),
+    math::invert(&ROTATOR_WIRINGS[5]),
+    math::invert(&ROTATOR_WIRINGS[6]),
+    math::invert(&ROTATOR_WIRINGS[7]),
+];
+
+/// The notches of the rotators I to VIII.
+static ROTATOR_NOTCHES: [&[Rune]; 8] = [
+    &[rune(b'q')],
+    &[rune(b'e')],
+    &[rune(b'v')],
+    &[rune(b'j')],
+    &[rune(b'z')],
+    &[rune(b'z'), rune(b'm')],
+    &[rune(b'z'), rune(b'm')],
+    &[rune(b'z'), rune(b'm')],
+];
+
+/// Create the static rotator at the specified index within the tables above.
+const fn historical_rotator(index: usize) -> Rotator {
+    Rotator::from_static(&ROTATOR_WIRINGS[index], &ROTATOR_INVERSE_WIRINGS[index],
+        ROTATOR_NOTCHES[index])
+}
+
+/// The rotators I to VIII of the Wehrmacht and Kriegsmarine Enigma at offset `0`, named by their
+/// Roman numerals.
+pub static HISTORICAL_ROTATORS: [(&str, Rotator); 8] = [
+    ("I", historical_rotator(0)),
+    ("II", historical_rotator(1)),
+    ("III", historical_rotator(2)),
+    ("IV", historical_rotator(3)),
+    ("V", historical_rotator(4)),
+    ("VI", historical_rotator(5)),
+    ("VII", historical_rotator(6)),
+    ("VIII", historical_rotator(7)),
+];
+
+/// The wirings of the reflectors A, B and C.
+static REFLECTOR_WIRINGS: [WiringTable; 3] = [
+    wiring_table(b"ejmzalyxvbwfcrquontspikhgd"),
+    wiring_table(b"yruhqsldpxngokmiebfzcwvjat"),
+    wiring_table(b"fvpjiaoyedrzxwgctkuqsbnmhl"),
+];
+
+/// The reflectors A, B and C of the Wehrmacht Enigma, named by their letters.
+pub static HISTORICAL_REFLECTORS: [(&str, Reflector); 3] = [
+    ("A", Reflector::from_static(&REFLECTOR_WIRINGS[0])),
+    ("B", Reflector::from_static(&REFLECTOR_WIRINGS[1])),
+    ("C", Reflector::from_static(&REFLECTOR_WIRINGS[2])),
+];
+
+/// A rotator within a catalog.
+#[derive(Clone, Debug)]
+struct Entry {
+    name: String,
+    rotator: Rotator,
+}
+
+/// A catalog of rotators identified by their names.
+#[derive(Clone, Debug, Default)]
+pub struct RotorCatalog {
+    entries: Vec<Entry>,
+}
+
+impl RotorCatalog {
+    /// Create an empty catalog.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a catalog of the rotators I to VIII of the Wehrmacht and Kriegsmarine Enigma, named
+    /// by their Roman numerals.
+    pub fn historical() -> Self {
+        // The rotators borrow the wiring tables of the static items, so nothing is copied here.
+        let entries = HISTORICAL_ROTATORS.iter()
+            .map(|(name, rotator)| Entry { name: String::from(*name), rotator: rotator.clone() })
+            .collect();
+        Self { entries }
+    }
+
+    /// Add a rotator to this catalog under the specified name, replacing the rotator previously
+    /// registered under that name. This function fails if the size of the wiring is not
+    /// `RUNE_SET_SIZE`.
+    pub fn register(&mut self, name: &str, wiring: Permutation, notches: &[Rune])
+        -> Result<(), InvalidRotatorPermutationError> {
+        let rotator = Rotator::new(wiring, 0)?.with_notches(notches);
+        let entry = Entry { name: String::from(name), rotator };
+        match self.entries.iter_mut().find(|e| e.name == name) {
+            Some(e) => *e = entry,
+            None => self.entries.push(entry),
+        }
+        Ok(())
+    }
+
+    /// Get the entry of the specified name.
+    fn get(&self, name: &str) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    /// Get the wiring of the rotator with the specified name.
+    pub fn wiring(&self, name: &str) -> Option<&Permutation> {
+        self.get(name).map(|e| e.rotator.wiring())
+    }
+
+    /// Get the notches of the rotator with the specified name.
+    pub fn notches(&self, name: &str) -> Option<&[Rune]> {
+        self.get(name).map(|e| e.rotator.notches())
+    }
+
+    /// Create the rotator with the specified name at the specified offset.
+    pub fn create_rotator(&self, name: &str, offset: u8) -> Option<Rotator> {
+        self.get(name).map(|e| {
+            let mut rotator = e.rotator.clone();
+            rotator.set_offset(offset);
+            rotator
+        })
+    }
+
+    /// Determine whether a rotator with the specified name is within this catalog.
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Get the names of the rotators within this catalog, in the order of registration.
+    pub fn names(&self) -> Vec<&str> {
+        self.entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    /// Get the number of rotators within this catalog.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Determine whether this catalog is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::components::tests::*;
+    use crate::testvectors::{REFLECTORS, ROTATORS};
+
+    #[test]
+    fn test_historical() {
+        let catalog = RotorCatalog::historical();
+        assert_eq!(catalog.names(), vec!["I", "II", "III", "IV", "V", "VI", "VII", "VIII"]);
+        assert_eq!(catalog.wiring("I").unwrap().map(0), 4);
+        assert_eq!(catalog.notches("V").unwrap(), &[Rune::from_char('z').unwrap()]);
+        assert!(!catalog.contains("IX"));
+    }
+
+    #[test]
+    fn test_historical_statics() {
+        let wiring = |letters: &str| Permutation::from_perm(
+            letters.chars().map(|ch| Rune::from_char(ch).unwrap().value()).collect()).unwrap();
+
+        for (name, letters, notch) in &ROTATORS {
+            let (_, rotator) = HISTORICAL_ROTATORS.iter().find(|(n, _)| n == name).unwrap();
+            let expected = Rotator::new(wiring(letters), 0).unwrap()
+                .with_notches(&[Rune::from_char(*notch).unwrap()]);
+            let a = Rune::from_char('a').unwrap();
+            assert_eq!(rotator.wiring(), expected.wiring());
+            assert_eq!(rotator.map_backward(rotator.map_forward(a)), a);
+            assert_eq!(rotator.notches(), expected.notches());
+        }
+
+        for ((name, letters), (static_name, reflector)) in
+            REFLECTORS.iter().zip(&HISTORICAL_REFLECTORS) {
+            assert_eq!(name, static_name);
+            assert_eq!(*reflector, Reflector::from_perm(wiring(letters)).unwrap());
+        }
+        assert!(HISTORICAL_REFLECTORS[2].1.is_valid());
+    }
+
+    #[test]
+    fn test_register() {
+        let mut catalog = RotorCatalog::new();
+        assert!(catalog.is_empty());
+
+        let wiring = create_test_perm_builder().build();
+        catalog.register("X", wiring.clone(), &[]).unwrap();
+        catalog.register("X", wiring.inverse(), &[Rune::from_char('a').unwrap()]).unwrap();
+        assert_eq!(catalog.len(), 1);
+        assert_eq!(catalog.notches("X").unwrap().len(), 1);
+
+        let rotator = catalog.create_rotator("X", 3).unwrap();
+        assert_eq!(rotator.offset(), 3);
+        assert!(catalog.create_rotator("Y", 0).is_none());
+
+        assert!(catalog.register("Y", Permutation::identity(5), &[]).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! This module provides the implementation of the entry wheel (Eintrittswalze) component within an
+//! Enigma machine.
+//!
+//! The entry wheel is a static wheel between the plug board and the first rotator. It connects the
+//! wires coming from the keyboard to the contacts of the first rotator. In the Wehrmacht Enigma,
+//! the i-th letter of the alphabet is wired to the i-th contact, so the entry wheel has no effect.
+//! In the commercial Enigma machines, the letters are wired in the order of the keyboard keys,
+//! `QWERTZUIOASDFGHJKPYXCVBNML`.
+//!
+//! Entry wheels are represented by the [`EntryWheel`] type. An entry wheel maps runes like a
+//! rotator that never advances: the `map_forward` associate function maps a letter to the contact
+//! it is wired to, and the `map_backward` associate function maps the other way:
+//!
+//! ```
+//! # use enigma::components::entry_wheel::EntryWheel;
+//! # use enigma::Rune;
+//! #
+//! let wheel = EntryWheel::qwertz();
+//! assert_eq!(wheel.map_forward(Rune::from_char('w').unwrap()).value(), 1);
+//! assert_eq!(wheel.map_backward(Rune::from_value(1).unwrap()), 'w');
+//! ```
+//!
+//! [`EntryWheel`]: struct.EntryWheel.html
+//!
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+use crate::math::Permutation;
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// The keyboard keys connected to the contacts of the entry wheel of the commercial Enigma
+/// machines, in the order of the contacts.
+pub const QWERTZ: &str = "qwertzuioasdfghjkpyxcvbnml";
+
+/// Error indicating that the permutation specified to create an entry wheel is invalid.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidEntryWheelPermutationError;
+
+impl Display for InvalidEntryWheelPermutationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid entry wheel permutation")
+    }
+}
+
+impl Error for InvalidEntryWheelPermutationError { }
+
+/// An entry wheel.
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(not(feature = "redact-debug"), derive(Debug))]
+pub struct EntryWheel {
+    perm_forward: Permutation,
+    perm_backward: Permutation,
+}
+
+impl EntryWheel {
+    /// Create an entry wheel from the specified permutation, which maps each letter to the contact
+    /// it is wired to.
+    ///
+    /// The size of the specified permutation should be `RUNE_SET_SIZE`.
+    pub fn from_perm(perm: Permutation) -> Result<Self, InvalidEntryWheelPermutationError> {
+        if perm.n() != RUNE_SET_SIZE {
+            return Err(InvalidEntryWheelPermutationError);
+        }
+
+        let perm_backward = perm.inverse();
+        Ok(Self { perm_forward: perm, perm_backward })
+    }
+
+    /// Create an entry wheel that wires the i-th letter to the i-th contact, as in the Wehrmacht
+    /// Enigma.
+    pub fn identity() -> Self {
+        Self::from_perm(Permutation::identity(RUNE_SET_SIZE)).unwrap()
+    }
+
+    /// Create an entry wheel that wires the letters to the contacts in the order of the keyboard
+    /// keys, as in the commercial Enigma machines.
+    pub fn qwertz() -> Self {
+        let contacts: Vec<u8> = QWERTZ.bytes().map(|b| b - b'a').collect();
+        let perm = Permutation::from_perm(contacts).unwrap().inverse();
+        Self::from_perm(perm).unwrap()
+    }
+
+    /// Determine whether this entry wheel wires the i-th letter to the i-th contact.
+    pub fn is_identity(&self) -> bool {
+        (0..RUNE_SET_SIZE).all(|i| self.perm_forward.map(i) == i)
+    }
+
+    /// Get the permutation mapping each letter to the contact it is wired to.
+    pub fn perm(&self) -> &Permutation {
+        &self.perm_forward
+    }
+
+    /// Map the specified letter to the contact it is wired to.
+    pub fn map_forward(&self, input: Rune) -> Rune {
+        unsafe { Rune::from_value_unchecked(self.perm_forward.map(input.value())) }
+    }
+
+    /// Map the specified contact to the letter wired to it.
+    pub fn map_backward(&self, input: Rune) -> Rune {
+        unsafe { Rune::from_value_unchecked(self.perm_backward.map(input.value())) }
+    }
+}
+
+#[cfg(feature = "redact-debug")]
+impl std::fmt::Debug for EntryWheel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EntryWheel { .. }")
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for EntryWheel {
+    fn zeroize(&mut self) {
+        self.perm_forward.zeroize();
+        self.perm_backward.zeroize();
+    }
+}
+
+impl Default for EntryWheel {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::components::tests::*;
+
+    mod entry_wheel_tests {
+        use super::*;
+
+        #[test]
+        fn test_from_perm_invalid_size() {
+            let perm = Permutation::from_perm(vec![0u8, 1u8, 2u8, 3u8]).unwrap();
+            assert!(EntryWheel::from_perm(perm).is_err());
+        }
+
+        #[test]
+        fn test_identity() {
+            let wheel = EntryWheel::identity();
+            assert!(wheel.is_identity());
+            assert_eq!(wheel.map_forward(Rune::from_char('k').unwrap()), 'k');
+            assert!(!EntryWheel::qwertz().is_identity());
+        }
+
+        #[test]
+        fn test_qwertz() {
+            let wheel = EntryWheel::qwertz();
+            for (i, ch) in QWERTZ.chars().enumerate() {
+                let rune = Rune::from_char(ch).unwrap();
+                assert_eq!(wheel.map_forward(rune).value() as usize, i);
+                assert_eq!(wheel.map_backward(wheel.map_forward(rune)), rune);
+            }
+        }
+
+        #[test]
+        fn test_from_perm() {
+            let wheel = EntryWheel::from_perm(create_test_perm_builder().build()).unwrap();
+            assert_eq!(wheel.map_forward(Rune::from_char('a').unwrap()), 'b');
+            assert_eq!(wheel.map_backward(Rune::from_char('b').unwrap()), 'a');
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! This module provides the implementation of the keyboard (Tastatur) of an Enigma machine.
+//!
+//! The keyboard of the Enigma machines has 26 keys arranged in 3 rows. Unlike typewriters of the
+//! time, the bottom row starts with `P`, so the German machines use the layout
+//!
+//! ```text
+//! Q W E R T Z U I O
+//!  A S D F G H J K
+//! P Y X C V B N M L
+//! ```
+//!
+//! Keyboards are represented by the [`Keyboard`] type, which maps the physical position of a key
+//! to the rune it types, and the other way around:
+//!
+//! ```
+//! # use enigma::components::keyboard::Keyboard;
+//! # use enigma::Rune;
+//! #
+//! let keyboard = Keyboard::qwertz();
+//! assert_eq!(keyboard.key_at(2, 0).unwrap(), 'p');
+//! assert_eq!(keyboard.position(Rune::from_char('a').unwrap()), (1, 0));
+//! ```
+//!
+//! [`Keyboard`]: struct.Keyboard.html
+//!
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// The rows of the keyboard of the German Enigma machines, from top to bottom.
+pub const QWERTZ_ROWS: [&str; 3] = ["qwertzuio", "asdfghjk", "pyxcvbnml"];
+
+/// Error indicating that the rows specified to create a keyboard do not contain each letter
+/// exactly once.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidKeyboardLayoutError;
+
+impl Display for InvalidKeyboardLayoutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid keyboard layout")
+    }
+}
+
+impl Error for InvalidKeyboardLayoutError { }
+
+/// A keyboard.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Keyboard {
+    rows: Vec<Vec<Rune>>,
+    positions: [(usize, usize); RUNE_SET_SIZE as usize],
+}
+
+impl Keyboard {
+    /// Create a keyboard from the specified rows of keys, from top to bottom. Each letter should
+    /// appear exactly once.
+    pub fn from_rows(rows: &[&str]) -> Result<Self, InvalidKeyboardLayoutError> {
+        let mut positions = [None; RUNE_SET_SIZE as usize];
+        let mut runes = Vec::with_capacity(rows.len());
+        for (i, row) in rows.iter().enumerate() {
+            let mut row_runes = Vec::with_capacity(row.len());
+            for (j, ch) in row.chars().enumerate() {
+                let rune = Rune::from_char(ch).map_err(|_| InvalidKeyboardLayoutError)?;
+                let position = &mut positions[rune.value() as usize];
+                if position.is_some() {
+                    return Err(InvalidKeyboardLayoutError);
+                }
+                *position = Some((i, j));
+                row_runes.push(rune);
+            }
+            runes.push(row_runes);
+        }
+
+        let mut checked = [(0, 0); RUNE_SET_SIZE as usize];
+        for (position, checked) in positions.iter().zip(checked.iter_mut()) {
+            *checked = position.ok_or(InvalidKeyboardLayoutError)?;
+        }
+
+        Ok(Self { rows: runes, positions: checked })
+    }
+
+    /// Create the keyboard of the German Enigma machines.
+    pub fn qwertz() -> Self {
+        Self::from_rows(&QWERTZ_ROWS).unwrap()
+    }
+
+    /// Get the keys of this keyboard, row by row from top to bottom.
+    pub fn rows(&self) -> &[Vec<Rune>] {
+        &self.rows
+    }
+
+    /// Get the rune typed by the key at the specified row and column, counted from the top left
+    /// corner.
+    pub fn key_at(&self, row: usize, column: usize) -> Option<Rune> {
+        self.rows.get(row).and_then(|keys| keys.get(column)).copied()
+    }
+
+    /// Get the row and the column of the key that types the specified rune.
+    pub fn position(&self, rune: Rune) -> (usize, usize) {
+        self.positions[rune.value() as usize]
+    }
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Self::qwertz()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod keyboard_tests {
+        use super::*;
+
+        #[test]
+        fn test_qwertz() {
+            let keyboard = Keyboard::qwertz();
+            assert_eq!(keyboard.rows().len(), 3);
+            assert_eq!(keyboard.key_at(0, 5).unwrap(), 'z');
+            assert_eq!(keyboard.key_at(1, 8), None);
+            assert_eq!(keyboard.key_at(3, 0), None);
+
+            for (i, row) in keyboard.rows().iter().enumerate() {
+                for (j, key) in row.iter().enumerate() {
+                    assert_eq!(keyboard.position(*key), (i, j));
+                }
+            }
+        }
+
+        #[test]
+        fn test_from_rows_invalid() {
+            assert!(Keyboard::from_rows(&["abcdefghijklm", "nopqrstuvwxy"]).is_err());
+            assert!(Keyboard::from_rows(&["abcdefghijklm", "nopqrstuvwxya"]).is_err());
+            assert!(Keyboard::from_rows(&["abcdefghijklm", "nopqrstuvwxy1"]).is_err());
+        }
+
+        #[test]
+        fn test_from_rows() {
+            let keyboard = Keyboard::from_rows(&["abcdefghijklm", "nopqrstuvwxyz"]).unwrap();
+            assert_eq!(keyboard.position(Rune::from_char('n').unwrap()), (1, 0));
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! This module provides the implementation of the lamp board (Lampenfeld) of an Enigma machine.
+//!
+//! The lamp board has a lamp for each letter, arranged in the same layout as the keys of the
+//! keyboard. While a key is held down, the lamp of the output letter lights up. The lamp board is
+//! represented by the [`LampBoard`] type, which tracks the lit lamp so that frontends can render
+//! it:
+//!
+//! ```
+//! # use enigma::components::lampboard::LampBoard;
+//! # use enigma::Rune;
+//! #
+//! let mut lamps = LampBoard::qwertz();
+//! lamps.light(Rune::from_char('q').unwrap());
+//! assert!(lamps.is_lit(Rune::from_char('q').unwrap()));
+//! assert_eq!(lamps.lit_position(), Some((0, 0)));
+//!
+//! lamps.clear();
+//! assert_eq!(lamps.lit(), None);
+//! ```
+//!
+//! [`LampBoard`]: struct.LampBoard.html
+//!
+
+use crate::components::keyboard::Keyboard;
+use crate::utils::Rune;
+
+/// A lamp board.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LampBoard {
+    layout: Keyboard,
+    lit: Option<Rune>,
+}
+
+impl LampBoard {
+    /// Create a lamp board whose lamps are arranged like the keys of the specified keyboard. No
+    /// lamp is lit.
+    pub fn new(layout: Keyboard) -> Self {
+        Self { layout, lit: None }
+    }
+
+    /// Create the lamp board of the German Enigma machines.
+    pub fn qwertz() -> Self {
+        Self::new(Keyboard::qwertz())
+    }
+
+    /// Get the arrangement of the lamps.
+    pub fn layout(&self) -> &Keyboard {
+        &self.layout
+    }
+
+    /// Light the lamp of the specified rune. The previously lit lamp goes out.
+    pub fn light(&mut self, rune: Rune) {
+        self.lit = Some(rune);
+    }
+
+    /// Turn off the lit lamp.
+    pub fn clear(&mut self) {
+        self.lit = None;
+    }
+
+    /// Get the rune whose lamp is lit.
+    pub fn lit(&self) -> Option<Rune> {
+        self.lit
+    }
+
+    /// Determine whether the lamp of the specified rune is lit.
+    pub fn is_lit(&self, rune: Rune) -> bool {
+        self.lit == Some(rune)
+    }
+
+    /// Get the row and the column of the lit lamp.
+    pub fn lit_position(&self) -> Option<(usize, usize)> {
+        self.lit.map(|rune| self.layout.position(rune))
+    }
+}
+
+impl Default for LampBoard {
+    fn default() -> Self {
+        Self::qwertz()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod lampboard_tests {
+        use super::*;
+
+        #[test]
+        fn test_light() {
+            let mut lamps = LampBoard::default();
+            assert_eq!(lamps.lit(), None);
+            assert_eq!(lamps.lit_position(), None);
+
+            lamps.light(Rune::from_char('m').unwrap());
+            lamps.light(Rune::from_char('l').unwrap());
+            assert!(!lamps.is_lit(Rune::from_char('m').unwrap()));
+            assert!(lamps.is_lit(Rune::from_char('l').unwrap()));
+            assert_eq!(lamps.lit_position(), Some((2, 8)));
+        }
+    }
+}
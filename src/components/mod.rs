@@ -1,14 +1,52 @@
 //! This module implements the core components within an Enigma machine, include plug boards,
-//! rotators and reflectors.
+//! entry wheels, rotators, reflectors and the stepping mechanisms of rotators, as well as the
+//! keyboard and the lamp board operated by the user. Rotators can be referred to by name through a
+//! catalog of rotators.
 //!
 
+pub mod catalog;
+pub mod entry_wheel;
+pub mod keyboard;
+pub mod lampboard;
 pub mod plug_board;
 pub mod reflector;
 pub mod rotator;
+pub mod stepping;
 
+pub use catalog::RotorCatalog;
+pub use entry_wheel::{EntryWheel, InvalidEntryWheelPermutationError};
+pub use keyboard::{InvalidKeyboardLayoutError, Keyboard};
+pub use lampboard::LampBoard;
 pub use plug_board::{InvalidPlugBoardPermutationError, PlugBoard};
 pub use reflector::{InvalidReflectorPermutationError, Reflector};
 pub use rotator::{InvalidRotatorPermutationError, Rotator, RotatorGroup};
+pub use stepping::{SteppingKind, SteppingMechanism, SteppingMode};
+
+use std::fmt::Formatter;
+
+use crate::math::Permutation;
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// Get the pairs of runes swapped by the specified involution. Each pair lists its smaller rune
+/// first, and the pairs are sorted by their first runes.
+fn swapped_pairs(perm: &Permutation) -> Vec<(Rune, Rune)> {
+    let rune = |value: u8| unsafe { Rune::from_value_unchecked(value) };
+    (0..RUNE_SET_SIZE)
+        .filter(|i| perm.map(*i) > *i)
+        .map(|i| (rune(i), rune(perm.map(i))))
+        .collect()
+}
+
+/// Write the specified pairs of runes in the form `AB CD EF`.
+fn write_pairs(f: &mut Formatter<'_>, pairs: &[(Rune, Rune)]) -> std::fmt::Result {
+    for (i, (a, b)) in pairs.iter().enumerate() {
+        if i > 0 {
+            f.write_str(" ")?;
+        }
+        write!(f, "{}{}", a, b)?;
+    }
+    Ok(())
+}
 
 #[cfg(test)]
 mod tests {
@@ -21,4 +59,10 @@ mod tests {
             .swap(10, 11).swap(12, 13).swap(14, 15).swap(16, 17).swap(18, 19)
             .swap(20, 21).swap(22, 23).swap(24, 25)
     }
+
+    /// Create unstructured data to generate arbitrary components from.
+    #[cfg(feature = "arbitrary")]
+    pub fn create_test_data() -> Vec<u8> {
+        (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect()
+    }
 }
@@ -14,7 +14,17 @@
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 
+#[cfg(feature = "arbitrary")]
+use arbitrary::{Arbitrary, Unstructured};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::components::{swapped_pairs, write_pairs};
 use crate::math::Permutation;
+#[cfg(feature = "arbitrary")]
+use crate::settings::plug_pairs_to_perm;
+#[cfg(feature = "arbitrary")]
+use crate::utils::arbitrary_pairs;
 use crate::utils::{Rune, RUNE_SET_SIZE};
 
 /// Error indicating that the permutation specified to create a PlugBoard is invalid.
@@ -32,7 +42,8 @@ impl Error for InvalidPlugBoardPermutationError { }
 /// A plug board.
 ///
 /// A plug board can be regarded as a rune permutation whose longest cycle is no longer than 2.
-#[derive(Clone, Debug)]
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(not(feature = "redact-debug"), derive(Debug))]
 pub struct PlugBoard {
     perm: Permutation,
 }
@@ -44,11 +55,7 @@ impl PlugBoard {
     /// - Its size should be `RUNE_SET_SIZE`;
     /// - The length of the longest cycle within the permutation should be no larger than 2.
     pub fn from_perm(perm: Permutation) -> Result<Self, InvalidPlugBoardPermutationError> {
-        if perm.n() != RUNE_SET_SIZE {
-            return Err(InvalidPlugBoardPermutationError);
-        }
-
-        if perm.max_cycle_len() > 2 {
+        if !is_valid_perm(&perm) {
             return Err(InvalidPlugBoardPermutationError);
         }
 
@@ -58,6 +65,10 @@ impl PlugBoard {
     /// Create a plug board from the specified permutation, without any sanity checks.
     ///
     /// Users should avoid using this function. Instead, call the `from_perm` function.
+    ///
+    /// # Safety
+    ///
+    /// The specified permutation must satisfy the requirements listed in `from_perm`.
     pub unsafe fn from_perm_unchecked(perm: Permutation) -> Self {
         Self { perm }
     }
@@ -68,6 +79,64 @@ impl PlugBoard {
             Rune::from_value_unchecked(self.perm.map(input.value()))
         }
     }
+
+    /// Get the pairs of runes connected on this plug board, at most 13. Each pair lists its smaller
+    /// rune first, and the pairs are sorted by their first runes.
+    pub fn pairs(&self) -> Vec<(Rune, Rune)> {
+        swapped_pairs(&self.perm)
+    }
+
+    /// Determine whether the permutation of this plug board satisfies the requirements listed in
+    /// `from_perm`. This only fails for plug boards created by `from_perm_unchecked`.
+    pub fn is_valid(&self) -> bool {
+        is_valid_perm(&self.perm)
+    }
+}
+
+impl Display for PlugBoard {
+    /// Write the pairs of runes connected on this plug board in the form `AB CD EF`. An empty plug
+    /// board writes nothing.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write_pairs(f, &self.pairs())
+    }
+}
+
+/// Determine whether the specified permutation satisfies the requirements of a plug board.
+fn is_valid_perm(perm: &Permutation) -> bool {
+    perm.n() == RUNE_SET_SIZE && perm.max_cycle_len() <= 2
+}
+
+#[cfg(feature = "redact-debug")]
+impl std::fmt::Debug for PlugBoard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PlugBoard { .. }")
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for PlugBoard {
+    fn zeroize(&mut self) {
+        self.perm.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for PlugBoard {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for PlugBoard { }
+
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for PlugBoard {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let count = u.int_in_range(0..=RUNE_SET_SIZE as usize / 2)?;
+        let perm = plug_pairs_to_perm(&arbitrary_pairs(u, count)?).unwrap();
+        Ok(Self { perm })
+    }
 }
 
 #[cfg(test)]
@@ -85,6 +154,17 @@ mod tests {
             assert!(PlugBoard::from_perm(perm).is_ok());
         }
 
+        #[cfg(feature = "arbitrary")]
+        #[test]
+        fn test_arbitrary() {
+            let data = create_test_data();
+            let mut u = Unstructured::new(&data);
+            for _ in 0..16 {
+                let plug = PlugBoard::arbitrary(&mut u).unwrap();
+                assert!(PlugBoard::from_perm(plug.perm.clone()).is_ok());
+            }
+        }
+
         #[test]
         fn test_from_perm_invalid_size() {
             let perm = Permutation::from_perm(vec![0u8, 1u8, 2u8, 3u8]).unwrap();
@@ -105,5 +185,30 @@ mod tests {
             assert_eq!(board.map(Rune::from_char('a').unwrap()), 'b');
             assert_eq!(board.map(Rune::from_char('c').unwrap()), 'd');
         }
+
+        #[test]
+        fn test_pairs() {
+            let perm = PermutationBuilder::new(RUNE_SET_SIZE).swap(25, 0).swap(3, 2).build();
+            let board = PlugBoard::from_perm(perm).unwrap();
+            assert_eq!(board.pairs(), vec![
+                (Rune::from_char('a').unwrap(), Rune::from_char('z').unwrap()),
+                (Rune::from_char('c').unwrap(), Rune::from_char('d').unwrap()),
+            ]);
+            assert_eq!(board.to_string(), "AZ CD");
+
+            let board = PlugBoard::from_perm(Permutation::identity(RUNE_SET_SIZE)).unwrap();
+            assert!(board.pairs().is_empty());
+            assert_eq!(board.to_string(), "");
+        }
+
+        #[test]
+        fn test_is_valid() {
+            let board = PlugBoard::from_perm(create_test_perm_builder().build()).unwrap();
+            assert!(board.is_valid());
+
+            let perm = create_test_perm_builder().swap(0, 2).build();
+            let board = unsafe { PlugBoard::from_perm_unchecked(perm) };
+            assert!(!board.is_valid());
+        }
     }
 }
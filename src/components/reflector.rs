@@ -74,7 +74,17 @@ use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 
+#[cfg(feature = "arbitrary")]
+use arbitrary::{Arbitrary, Unstructured};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::components::{swapped_pairs, write_pairs};
 use crate::math::Permutation;
+#[cfg(feature = "arbitrary")]
+use crate::settings::plug_pairs_to_perm;
+#[cfg(feature = "arbitrary")]
+use crate::utils::arbitrary_pairs;
 use crate::utils::{Rune, RUNE_SET_SIZE};
 
 /// Error indicating that the permutation of a reflector is invalid.
@@ -92,7 +102,8 @@ impl Error for InvalidReflectorPermutationError { }
 /// A reflector.
 ///
 /// Reflector can be viewed as a Rune permutation that does not have any fixed points.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(not(feature = "redact-debug"), derive(Debug))]
 pub struct Reflector {
     perm: Permutation,
 }
@@ -107,19 +118,7 @@ impl Reflector {
     /// This function performs sanity checks against the conditions above. If any of the conditions
     /// are not satisfied, this function will fail.
     pub fn from_perm(perm: Permutation) -> Result<Self, InvalidReflectorPermutationError> {
-        if perm.n() != RUNE_SET_SIZE {
-            return Err(InvalidReflectorPermutationError);
-        }
-
-        // Checks that perm does not have any fixed points.
-        for i in 0..perm.n() {
-            if perm.map(i) == i {
-                return Err(InvalidReflectorPermutationError);
-            }
-        }
-
-        // Checks that the length of the longest cycle within perm is 2.
-        if perm.max_cycle_len() != 2 {
+        if !is_valid_perm(&perm) {
             return Err(InvalidReflectorPermutationError);
         }
 
@@ -128,16 +127,101 @@ impl Reflector {
 
     /// Create a new reflector from the specified permutation without sanity checks. Usage of this
     /// function should be avoided. Use the `from_perm` associate function instead.
+    ///
+    /// # Safety
+    ///
+    /// The specified permutation must satisfy the requirements listed in `from_perm`.
     pub unsafe fn from_perm_unchecked(perm: Permutation) -> Self {
         Self { perm }
     }
 
+    /// Create a new reflector that borrows the specified static permutation array. This function
+    /// can be called in const context, so that reflectors can be `static` items, e.g. the
+    /// historical reflectors within the `catalog` module.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the specified array does not satisfy the requirements listed in `from_perm`. In
+    /// const context, this fails the compilation.
+    pub const fn from_static(perm: &'static [u8; RUNE_SET_SIZE as usize]) -> Self {
+        let mut i = 0;
+        while i < RUNE_SET_SIZE as usize {
+            let j = perm[i] as usize;
+            assert!(j < RUNE_SET_SIZE as usize && j != i && perm[j] as usize == i,
+                "invalid reflector permutation");
+            i += 1;
+        }
+
+        Self { perm: Permutation::from_static(perm) }
+    }
+
     /// Get the output rune produced by this reflector that corresponds to the specified input rune.
     pub fn map(&self, input: Rune) -> Rune {
         unsafe {
             Rune::from_value_unchecked(self.perm.map(input.value()))
         }
     }
+
+    /// Get the 13 pairs of runes swapped by this reflector. Each pair lists its smaller rune first,
+    /// and the pairs are sorted by their first runes.
+    pub fn pairs(&self) -> Vec<(Rune, Rune)> {
+        swapped_pairs(&self.perm)
+    }
+
+    /// Determine whether the permutation of this reflector satisfies the requirements listed in
+    /// `from_perm`. This only fails for reflectors created by `from_perm_unchecked`.
+    pub fn is_valid(&self) -> bool {
+        is_valid_perm(&self.perm)
+    }
+}
+
+impl Display for Reflector {
+    /// Write the pairs of runes swapped by this reflector in the form `AB CD EF`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write_pairs(f, &self.pairs())
+    }
+}
+
+/// Determine whether the specified permutation satisfies the requirements of a reflector.
+fn is_valid_perm(perm: &Permutation) -> bool {
+    // The permutation should not have any fixed points, and the length of the longest cycle
+    // within it should be 2.
+    perm.n() == RUNE_SET_SIZE &&
+        (0..perm.n()).all(|i| perm.map(i) != i) &&
+        perm.max_cycle_len() == 2
+}
+
+#[cfg(feature = "redact-debug")]
+impl std::fmt::Debug for Reflector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Reflector { .. }")
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for Reflector {
+    fn zeroize(&mut self) {
+        self.perm.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Reflector {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for Reflector { }
+
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for Reflector {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        // Pairing all runes leaves no fixed points.
+        let perm = plug_pairs_to_perm(&arbitrary_pairs(u, RUNE_SET_SIZE as usize / 2)?).unwrap();
+        Ok(Self { perm })
+    }
 }
 
 impl TryFrom<Permutation> for Reflector {
@@ -186,6 +270,17 @@ mod tests {
             assert!(Reflector::from_perm(perm).is_err());
         }
 
+        #[test]
+        #[should_panic]
+        fn test_from_static_invalid() {
+            // The identity permutation has fixed points.
+            static IDENTITY: [u8; RUNE_SET_SIZE as usize] = [
+                0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+                13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+            ];
+            Reflector::from_static(&IDENTITY);
+        }
+
         #[test]
         fn test_map() {
             let reflector = Reflector::from_perm(
@@ -196,5 +291,36 @@ mod tests {
             assert_eq!(reflector.map(Rune::from_char('c').unwrap()), 'd');
             assert_eq!(reflector.map(Rune::from_char('d').unwrap()), 'c');
         }
+
+        #[test]
+        fn test_pairs() {
+            let reflector = Reflector::from_perm(create_test_perm_builder().build()).unwrap();
+            let pairs = reflector.pairs();
+            assert_eq!(pairs.len(), 13);
+            assert_eq!(pairs[1], (Rune::from_char('c').unwrap(), Rune::from_char('d').unwrap()));
+
+            let reflector = &crate::components::catalog::HISTORICAL_REFLECTORS[1].1;
+            assert_eq!(reflector.to_string(), "AY BR CU DH EQ FS GL IP JX KN MO TZ VW");
+        }
+
+        #[cfg(feature = "arbitrary")]
+        #[test]
+        fn test_arbitrary() {
+            let data = create_test_data();
+            let mut u = Unstructured::new(&data);
+            for _ in 0..16 {
+                assert!(Reflector::arbitrary(&mut u).unwrap().is_valid());
+            }
+        }
+
+        #[test]
+        fn test_is_valid() {
+            let reflector = Reflector::from_perm(create_test_perm_builder().build()).unwrap();
+            assert!(reflector.is_valid());
+
+            let perm = create_test_perm_builder().swap(0, 1).build();
+            let reflector = unsafe { Reflector::from_perm_unchecked(perm) };
+            assert!(!reflector.is_valid());
+        }
     }
 }
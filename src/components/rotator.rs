@@ -24,13 +24,28 @@
 //! their offsets are "chained". When advancing offsets, the offset of the first rotator is
 //! advanced. If the offset goes from `RUNE_MAX_VALUE` to `0`, then the offset of the second rotator
 //! is advanced. The same rule applies for the second and the third rotators in a rotator group.
+//! The historical stepping mechanisms, which turn over the next rotator at the notches of a
+//! rotator, can be selected with the `with_stepping` associate function.
+//!
+//! Rotator groups can hold any number of rotators, e.g. the 4 rotators of the naval M4 Enigma or
+//! the 5 rotors of the Typex. A slot can be flagged as static with the `with_static` associate
+//! function, like the Greek wheel of the M4 or the stators of the Typex: its rotator maps runes at
+//! the offset set by hand, but the stepping mechanism skips it.
 //!
 //! [`Rotator`]: struct.Rotator.html
 //!
 
+use std::borrow::Cow;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+#[cfg(feature = "arbitrary")]
+use arbitrary::{Arbitrary, Unstructured};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
+use crate::components::stepping::{Odometer, SteppingMechanism, SteppingMode};
 use crate::math::Permutation;
 use crate::utils::{Rune, RUNE_SET_SIZE};
 
@@ -50,11 +65,15 @@ impl Error for InvalidRotatorPermutationError { }
 ///
 /// A plug board can be regarded as a rune permutation whose longest cycle is no longer than 2,
 /// together with an offset value to be applied to the input rune before permutation substitution.
-#[derive(Clone, Debug)]
+///
+/// Rotators are equal if they have the same wiring, offset and notches.
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(not(feature = "redact-debug"), derive(Debug))]
 pub struct Rotator {
     perm_forward: Permutation,
     perm_backward: Permutation,
     offset: u8,
+    notches: Cow<'static, [Rune]>,
 }
 
 impl Rotator {
@@ -74,21 +93,147 @@ impl Rotator {
             perm_forward: perm,
             perm_backward,
             offset: offset % RUNE_SET_SIZE,
+            notches: Cow::Borrowed(&[]),
         })
     }
 
     /// Create a new rotator from the specified permutation and offset value, without sanity checks.
     ///
     /// Users should avoid using this function. Instead, call the `from_perm` function.
+    ///
+    /// # Safety
+    ///
+    /// The size of the specified permutation must be `RUNE_SET_SIZE`.
     pub unsafe fn new_unchecked(perm: Permutation, offset: u8) -> Self {
         let perm_backward = perm.inverse();
         Self {
             perm_forward: perm,
             perm_backward,
             offset: offset % RUNE_SET_SIZE,
+            notches: Cow::Borrowed(&[]),
+        }
+    }
+
+    /// Create a new rotator at offset `0` that borrows the specified static wiring tables and
+    /// notches. This function can be called in const context, so that rotators can be `static`
+    /// items, e.g. the historical rotators within the `catalog` module.
+    ///
+    /// The inverse wiring can be computed in const context by `math::invert`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `inverse` is not the inverse permutation of `wiring`. In const context, this fails
+    /// the compilation.
+    pub const fn from_static(wiring: &'static [u8; RUNE_SET_SIZE as usize],
+        inverse: &'static [u8; RUNE_SET_SIZE as usize], notches: &'static [Rune]) -> Self {
+        let mut i = 0;
+        while i < RUNE_SET_SIZE as usize {
+            assert!((wiring[i] as usize) < RUNE_SET_SIZE as usize &&
+                inverse[wiring[i] as usize] as usize == i, "mismatched inverse wiring");
+            i += 1;
+        }
+
+        Self {
+            perm_forward: Permutation::from_static(wiring),
+            perm_backward: Permutation::from_static(inverse),
+            offset: 0,
+            notches: Cow::Borrowed(notches),
+        }
+    }
+
+    /// Set the notches of this rotator. A rotator is at a notch when its offset equals the value of
+    /// one of the notches; the stepping mechanism then steps the next rotator as well.
+    ///
+    /// Rotators have no notches by default. The odometer stepping mechanism ignores notches.
+    pub fn with_notches(mut self, notches: &[Rune]) -> Self {
+        self.notches = Cow::Owned(notches.to_vec());
+        self
+    }
+
+    /// Get this rotator inserted reversed, e.g. flipped over, as the Typex and the SIGABA allow.
+    /// The contacts are mirrored and swap sides, so `i` is mapped to `-perm⁻¹(-i)` rather than to
+    /// `perm⁻¹(i)`, and the notch at letter `n` sits at offset `-n`. The offset is kept.
+    ///
+    /// Reversing a rotator twice gives the original rotator.
+    pub fn reversed(&self) -> Self {
+        let mirror = |i: u8| (RUNE_SET_SIZE - i) % RUNE_SET_SIZE;
+        let flip = |perm: &Permutation| {
+            let perm = (0..RUNE_SET_SIZE).map(|i| mirror(perm.map(mirror(i)))).collect();
+            unsafe { Permutation::from_perm_unchecked(perm) }
+        };
+        let notches = self.notches.iter()
+            .map(|notch| unsafe { Rune::from_value_unchecked(mirror(notch.value())) })
+            .collect();
+
+        Self {
+            perm_forward: flip(&self.perm_backward),
+            perm_backward: flip(&self.perm_forward),
+            offset: self.offset,
+            notches: Cow::Owned(notches),
+        }
+    }
+
+    /// Insert this rotator reversed if `reversed` is true. See `reversed`.
+    pub fn with_reversed(self, reversed: bool) -> Self {
+        if reversed {
+            self.reversed()
+        } else {
+            self
         }
     }
 
+    /// Get the current offset value of this rotator.
+    pub fn offset(&self) -> u8 {
+        self.offset
+    }
+
+    /// Get the position of this rotator, e.g. its window letter. See `window_letter`.
+    pub fn position(&self) -> Rune {
+        self.window_letter()
+    }
+
+    /// Turn this rotator to the specified position, e.g. set its offset to the value of the
+    /// specified rune.
+    pub fn set_position(&mut self, position: Rune) {
+        self.set_offset(position.value());
+    }
+
+    /// Get the forward permutation of this rotator, which maps runes entering from the plug board
+    /// side at offset `0`.
+    pub fn wiring(&self) -> &Permutation {
+        &self.perm_forward
+    }
+
+    /// Get the backward permutation of this rotator, e.g. the inverse of `wiring`.
+    pub fn inverse_wiring(&self) -> &Permutation {
+        &self.perm_backward
+    }
+
+    /// Get the notches of this rotator, in the order they were specified to `with_notches`.
+    pub fn notches(&self) -> &[Rune] {
+        &self.notches
+    }
+
+    /// Get the letter shown in the window of this rotator, e.g. the rune whose value is the offset
+    /// of this rotator.
+    ///
+    /// Rotators have no rings, so machines emulating ring settings by shifting the offsets and
+    /// the notches show the window letter shifted back by the ring setting.
+    pub fn window_letter(&self) -> Rune {
+        unsafe { Rune::from_value_unchecked(self.offset) }
+    }
+
+    /// Set the offset value of this rotator.
+    pub(crate) fn set_offset(&mut self, offset: u8) {
+        self.offset = offset % RUNE_SET_SIZE;
+    }
+
+    /// Determine whether this rotator is at one of its notches, e.g. whether its window letter is
+    /// one of its notches. The stepping mechanism then steps the next rotator as well.
+    pub fn at_notch(&self) -> bool {
+        self.notches.iter().any(|notch| notch.value() == self.offset)
+    }
+
     /// Map the specified input rune to output rune.
     pub fn map_forward(&self, input: Rune) -> Rune {
         self.map(&self.perm_forward, input)
@@ -104,6 +249,21 @@ impl Rotator {
         self.offset != 0
     }
 
+    /// Step the underlying offset value backwards, undoing `advance`. Returns `false` if the
+    /// offset rolls back from `0` to `RUNE_SET_SIZE - 1`.
+    pub fn retreat(&mut self) -> bool {
+        self.offset = (self.offset + RUNE_SET_SIZE - 1) % RUNE_SET_SIZE;
+        self.offset != RUNE_SET_SIZE - 1
+    }
+
+    /// Advance the underlying offset value by the specified number of steps, and return the
+    /// number of times the offset rolls back to `0`.
+    pub fn advance_by(&mut self, steps: u64) -> u64 {
+        let total = self.offset as u64 + steps;
+        self.offset = (total % RUNE_SET_SIZE as u64) as u8;
+        total / RUNE_SET_SIZE as u64
+    }
+
     fn map(&self, perm: &Permutation, input: Rune) -> Rune {
         let input_value = (input.value() + self.offset) % RUNE_SET_SIZE;
         let mut mapped_value = perm.map(input_value);
@@ -120,23 +280,119 @@ impl Rotator {
     }
 }
 
-/// A rotator group that chains the 3 rotators within an Enigma machine.
+#[cfg(feature = "redact-debug")]
+impl std::fmt::Debug for Rotator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Rotator").field("offset", &self.offset).finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for Rotator {
+    fn zeroize(&mut self) {
+        self.perm_forward.zeroize();
+        self.perm_backward.zeroize();
+        self.offset.zeroize();
+        if let Cow::Owned(notches) = &mut self.notches {
+            notches.zeroize();
+        }
+        self.notches = Cow::Borrowed(&[]);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Rotator {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for Rotator { }
+
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for Rotator {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let perm = Permutation::arbitrary_of_size(u, RUNE_SET_SIZE)?;
+        let offset = u.int_in_range(0..=RUNE_SET_SIZE - 1)?;
+        let mut notches = Vec::new();
+        for value in 0..RUNE_SET_SIZE {
+            if u.ratio(1u8, 4u8)? {
+                notches.push(unsafe { Rune::from_value_unchecked(value) });
+            }
+        }
+        Ok(Rotator::new(perm, offset).unwrap().with_notches(&notches))
+    }
+}
+
+/// A rotator group that chains the rotators within a rotor machine, 3 rotators by default.
 ///
 /// When mapping input runes, the input rune is passed into a transformation pipeline formed by the
-/// 3 rotators within the group.
+/// rotators within the group.
+///
+/// The offsets of the rotators are also chained by a stepping mechanism. By default, the offset
+/// of the first rotator is advanced. If it rolls back from `RUNE_SET_SIZE - 1` to `0`, then the
+/// offset of the second rotator is advanced. This rule applies to the second and third rotator
+/// within the group. Other mechanisms are provided by the [`stepping`] module.
+///
+/// Slots flagged as static are skipped by the stepping mechanism, which sees the rotators of the
+/// other slots only, in slot order. The rotators of static slots still map runes.
 ///
-/// The offsets of the 3 rotators are also chained. When advancing, the offset of the first rotator
-/// is advanced. If it rolls back from `RUNE_SET_SIZE - 1` to `0`, then the offset of the second
-/// rotator is advanced. This rule applies to the second and third rotator within the group.
+/// [`stepping`]: ../stepping/index.html
 #[derive(Clone, Debug)]
-pub struct RotatorGroup {
-    rotators: [Rotator; 3],
+pub struct RotatorGroup<const N: usize = 3> {
+    rotators: [Rotator; N],
+    statics: [bool; N],
+    stepping: Arc<dyn SteppingMechanism>,
 }
 
-impl RotatorGroup {
-    /// Create a new rotator group that chains the specified 3 rotators.
-    pub fn new(rotators: [Rotator; 3]) -> Self {
-        Self { rotators }
+impl<const N: usize> RotatorGroup<N> {
+    /// Create a new rotator group that chains the specified rotators with the odometer stepping
+    /// mechanism. All slots are stepping.
+    pub fn new(rotators: [Rotator; N]) -> Self {
+        Self { rotators, statics: [false; N], stepping: Arc::new(Odometer) }
+    }
+
+    /// Set the stepping mechanism of this rotator group.
+    pub fn with_stepping<S>(mut self, stepping: S) -> Self
+        where S: SteppingMechanism + 'static {
+        self.stepping = Arc::new(stepping);
+        self
+    }
+
+    /// Set the stepping mechanism of this rotator group to the one selected by the specified mode.
+    /// See [`SteppingMode`] for how the modes differ.
+    ///
+    /// [`SteppingMode`]: ../stepping/enum.SteppingMode.html
+    pub fn with_stepping_mode(mut self, mode: SteppingMode) -> Self {
+        self.set_stepping_mode(mode);
+        self
+    }
+
+    /// Flag the specified slot of this rotator group as static or stepping. The rotator of a
+    /// static slot never steps.
+    ///
+    /// This function panics if the slot is out of range.
+    pub fn with_static(mut self, slot: usize, is_static: bool) -> Self {
+        self.statics[slot] = is_static;
+        self
+    }
+
+    /// Get the stepping mechanism of this rotator group.
+    pub fn stepping(&self) -> &dyn SteppingMechanism {
+        self.stepping.as_ref()
+    }
+
+    /// Determine whether the specified slot of this rotator group is static.
+    ///
+    /// This function panics if the slot is out of range.
+    pub fn is_static(&self, slot: usize) -> bool {
+        self.statics[slot]
+    }
+
+    /// Get the rotators within the group, including the rotators of static slots.
+    pub fn rotators(&self) -> &[Rotator; N] {
+        &self.rotators
     }
 
     /// Map the input rune to output rune in the forward direction.
@@ -155,14 +411,133 @@ impl RotatorGroup {
         input
     }
 
-    /// Advance the offsets of the 3 rotators within the group, with the rules described in the
-    /// `RotatorGroup` documentation.
+    /// Advance the offsets of the rotators of the stepping slots with the stepping mechanism of the
+    /// group.
     pub fn advance(&mut self) {
-        for r in &mut self.rotators {
-            if r.advance() {
-                break;
+        self.step_with(|stepping, rotators| stepping.advance(rotators));
+    }
+
+    /// Step the offsets of the rotators of the stepping slots backwards, undoing `advance`.
+    pub fn retreat(&mut self) {
+        self.step_with(|stepping, rotators| stepping.retreat(rotators));
+    }
+
+    /// Get the number of steps after which the offsets of the rotators repeat, e.g. the length of
+    /// the cycle of rotator states reached from the current state. States that are passed only
+    /// once before the cycle is entered are not counted.
+    pub fn period(&self) -> u64 {
+        // Brent's cycle detection algorithm.
+        let mut power = 1u64;
+        let mut period = 1u64;
+        let mut tortoise = self.offsets();
+        let mut hare = self.clone();
+        hare.advance();
+        while hare.offsets() != tortoise {
+            if power == period {
+                tortoise = hare.offsets();
+                power *= 2;
+                period = 0;
             }
+            hare.advance();
+            period += 1;
         }
+        period
+    }
+
+    /// Get an iterator over the window letters of the rotators at each state this rotator group
+    /// passes through when advanced, starting with the current state. The window letters of each
+    /// state are in the order of the rotator group.
+    ///
+    /// The iterator advances a copy of this rotator group lazily, so it never ends and this group
+    /// is not changed.
+    pub fn positions_iter(&self) -> PositionsIter<N> {
+        PositionsIter { group: self.clone() }
+    }
+
+    pub(crate) fn offsets(&self) -> [u8; N] {
+        std::array::from_fn(|i| self.rotators[i].offset)
+    }
+
+    /// Advance the offsets of the rotators of the stepping slots by the specified number of steps.
+    /// This is equivalent to calling `advance` `steps` times, but takes constant time with the
+    /// odometer stepping mechanism.
+    pub fn advance_by(&mut self, steps: u64) {
+        self.step_with(|stepping, rotators| stepping.advance_by(rotators, steps));
+    }
+
+    /// Set the stepping mechanism of this rotator group to the one selected by the specified mode.
+    pub(crate) fn set_stepping_mode(&mut self, mode: SteppingMode) {
+        self.stepping = mode.mechanism();
+    }
+
+    /// Set the offsets of the rotators within the group, including the rotators of static slots.
+    pub(crate) fn set_offsets(&mut self, offsets: [u8; N]) {
+        for (r, offset) in self.rotators.iter_mut().zip(&offsets) {
+            r.set_offset(*offset);
+        }
+    }
+
+    /// Call the specified function with the stepping mechanism and the rotators of the stepping
+    /// slots, in slot order.
+    fn step_with<F>(&mut self, f: F)
+        where F: FnOnce(&dyn SteppingMechanism, &mut [Rotator]) {
+        // The rotators of the static slots are moved behind the others while the function runs,
+        // so that the rotators of the stepping slots form a slice. Moving a rotator does not copy
+        // its wiring.
+        let mut len = 0;
+        for i in 0..N {
+            if !self.statics[i] {
+                self.rotators[len..=i].rotate_right(1);
+                len += 1;
+            }
+        }
+
+        f(self.stepping.as_ref(), &mut self.rotators[..len]);
+
+        for i in (0..N).rev() {
+            if !self.statics[i] {
+                len -= 1;
+                self.rotators[len..=i].rotate_left(1);
+            }
+        }
+    }
+}
+
+impl<const N: usize> PartialEq for RotatorGroup<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.rotators == other.rotators &&
+            self.statics == other.statics &&
+            self.stepping.kind() == other.stepping.kind()
+    }
+}
+
+impl<const N: usize> Eq for RotatorGroup<N> { }
+
+/// An iterator over the window letters of the rotators of a rotator group at each state the group
+/// passes through, as created by `RotatorGroup::positions_iter`.
+#[derive(Clone, Debug)]
+pub struct PositionsIter<const N: usize = 3> {
+    group: RotatorGroup<N>,
+}
+
+impl<const N: usize> Iterator for PositionsIter<N> {
+    type Item = [Rune; N];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let positions = std::array::from_fn(|i| self.group.rotators[i].window_letter());
+        self.group.advance();
+        Some(positions)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<const N: usize> Zeroize for RotatorGroup<N> {
+    fn zeroize(&mut self) {
+        self.rotators.zeroize();
     }
 }
 
@@ -198,6 +573,66 @@ mod tests {
             assert!(Rotator::new(perm, 0).is_err());
         }
 
+        #[cfg(feature = "arbitrary")]
+        #[test]
+        fn test_arbitrary() {
+            let data = create_test_data();
+            let mut u = Unstructured::new(&data);
+            let rotators: Vec<Rotator> = (0..16).map(|_| Rotator::arbitrary(&mut u).unwrap())
+                .collect();
+            assert!(rotators.iter().all(|r| r.wiring().n() == RUNE_SET_SIZE));
+            assert!(rotators.iter().any(|r| r.offset() != 0 && !r.notches().is_empty()));
+        }
+
+        #[test]
+        fn test_notches() {
+            let notches = [Rune::from_char('z').unwrap(), Rune::from_char('m').unwrap()];
+            let mut rotator = Rotator::new(create_test_perm_builder().build(), 11).unwrap()
+                .with_notches(&notches);
+            assert_eq!(rotator.notches(), &notches);
+            assert_eq!(rotator.window_letter(), 'l');
+            assert!(!rotator.at_notch());
+
+            rotator.advance();
+            assert_eq!(rotator.window_letter(), 'm');
+            assert!(rotator.at_notch());
+
+            rotator.advance_by(13);
+            assert_eq!(rotator.window_letter(), 'z');
+            assert!(rotator.at_notch());
+        }
+
+        #[test]
+        fn test_reversed() {
+            let a = Rune::from_char('a').unwrap();
+            let rotator = Rotator::new(create_test_perm_builder().build(), 3).unwrap()
+                .with_notches(&[a, Rune::from_char('c').unwrap()]);
+            let reversed = rotator.reversed();
+            assert_eq!(reversed.offset(), 3);
+            assert_ne!(reversed.wiring(), rotator.wiring());
+            assert_ne!(reversed.wiring(), rotator.inverse_wiring());
+            assert_eq!(reversed.notches(), &[a, Rune::from_char('y').unwrap()]);
+
+            let twice = reversed.with_reversed(true);
+            assert_eq!(twice.wiring(), rotator.wiring());
+            assert_eq!(twice.notches(), rotator.notches());
+            assert_eq!(twice.map_backward(twice.map_forward(a)), a);
+        }
+
+        #[test]
+        fn test_accessors() {
+            let perm = create_test_perm_builder_shift().build();
+            let mut rotator = Rotator::new(perm.clone(), 30).unwrap();
+            assert_eq!(rotator.offset(), 4);
+            assert_eq!(rotator.position(), 'e');
+            assert_eq!(rotator.wiring(), &perm);
+            assert_eq!(rotator.inverse_wiring(), &perm.inverse());
+
+            rotator.set_position(Rune::from_char('q').unwrap());
+            assert_eq!(rotator.offset(), 16);
+            assert_eq!(rotator.position(), rotator.window_letter());
+        }
+
         #[test]
         fn test_map_forward() {
             let perm = create_test_perm_builder_shift().build();
@@ -222,7 +657,7 @@ mod tests {
             let mut rotator = Rotator::new(perm, 0).unwrap();
 
             rotator.advance();
-            assert_eq!(rotator.offset, 1);
+            assert_eq!(rotator.offset(), 1);
 
             assert_eq!(rotator.map_forward(Rune::from_char('a').unwrap()), 'z');
             assert_eq!(rotator.map_forward(Rune::from_char('b').unwrap()), 'c');
@@ -243,7 +678,43 @@ mod tests {
             }
 
             assert!(!rotator.advance());
-            assert_eq!(rotator.offset, 0);
+            assert_eq!(rotator.offset(), 0);
+        }
+
+        #[test]
+        fn test_retreat() {
+            let perm = create_test_perm_builder_shift().build();
+            let mut rotator = Rotator::new(perm, 1).unwrap();
+
+            assert!(rotator.retreat());
+            assert_eq!(rotator.offset(), 0);
+            assert!(!rotator.retreat());
+            assert_eq!(rotator.offset(), RUNE_SET_SIZE - 1);
+            assert!(!rotator.advance());
+            assert_eq!(rotator.offset(), 0);
+        }
+
+        #[test]
+        fn test_advance_by() {
+            let perm = create_test_perm_builder_shift().build();
+            let mut rotator = Rotator::new(perm, 20).unwrap();
+
+            assert_eq!(rotator.advance_by(0), 0);
+            assert_eq!(rotator.offset(), 20);
+            assert_eq!(rotator.advance_by(5), 0);
+            assert_eq!(rotator.offset(), 25);
+            assert_eq!(rotator.advance_by(1), 1);
+            assert_eq!(rotator.offset(), 0);
+            assert_eq!(rotator.advance_by(26 * 3 + 4), 3);
+            assert_eq!(rotator.offset(), 4);
+        }
+
+        #[cfg(feature = "redact-debug")]
+        #[test]
+        fn test_debug_redacted() {
+            let perm = create_test_perm_builder_shift().build();
+            let rotator = Rotator::new(perm, 3).unwrap();
+            assert_eq!(format!("{:?}", rotator), "Rotator { offset: 3, .. }");
         }
     }
 
@@ -267,6 +738,139 @@ mod tests {
             assert_eq!(group.map_forward(Rune::from_char('b').unwrap()), 'e');
         }
 
+        #[test]
+        fn test_eq() {
+            use crate::components::stepping::Lever;
+
+            #[derive(Debug)]
+            struct Custom;
+
+            impl SteppingMechanism for Custom {
+                fn advance(&self, rotators: &mut [Rotator]) {
+                    Odometer.advance(rotators);
+                }
+            }
+
+            let mut group = create_test_group();
+            assert_eq!(group, create_test_group().with_stepping(Odometer));
+            assert_ne!(group, create_test_group().with_stepping(Lever));
+            assert_ne!(group, create_test_group().with_stepping(Custom));
+            assert_eq!(create_test_group().with_stepping(Custom),
+                create_test_group().with_stepping(Custom));
+
+            group.advance();
+            assert_ne!(group, create_test_group());
+            group.retreat();
+            assert_eq!(group, create_test_group());
+        }
+
+        #[test]
+        fn test_stepping_modes() {
+            use crate::components::catalog::HISTORICAL_ROTATORS;
+            use crate::components::stepping::Lever;
+
+            // Rotors III, II and I at the window letters U, D and A, from right to left.
+            let create_group = |mode: SteppingMode| {
+                let mut rotators = [2, 1, 0].map(|i| HISTORICAL_ROTATORS[i].1.clone());
+                for (r, offset) in rotators.iter_mut().zip(&[20, 3, 0]) {
+                    r.set_offset(*offset);
+                }
+                RotatorGroup::new(rotators).with_stepping_mode(mode)
+            };
+            assert_eq!(create_group(SteppingMode::default()), create_group(SteppingMode::Odometer));
+            assert_eq!(create_group(SteppingMode::WehrmachtLever),
+                create_group(SteppingMode::Odometer).with_stepping(Lever));
+
+            // The modes agree until rotor III reaches its notch at V. The lever then steps rotor II
+            // to its notch at E, and steps it again together with rotor I.
+            let mut odometer = create_group(SteppingMode::Odometer);
+            let mut lever = create_group(SteppingMode::WehrmachtLever);
+            let expected = [
+                ([21, 3, 0], [21, 3, 0]),
+                ([22, 3, 0], [22, 4, 0]),
+                ([23, 3, 0], [23, 5, 1]),
+                ([24, 3, 0], [24, 5, 1]),
+            ];
+            for (odometer_offsets, lever_offsets) in &expected {
+                odometer.advance();
+                lever.advance();
+                assert_eq!(&odometer.offsets(), odometer_offsets);
+                assert_eq!(&lever.offsets(), lever_offsets);
+            }
+
+            // The odometer steps rotor II when rotor III rolls over from Z to A instead.
+            odometer.advance_by(2);
+            assert_eq!(odometer.offsets(), [0, 4, 0]);
+            assert_eq!(odometer.period(), 26 * 26 * 26);
+            assert_eq!(lever.period(), 26 * 25 * 26);
+        }
+
+        #[test]
+        fn test_positions_iter() {
+            let rune = |ch: char| Rune::from_char(ch).unwrap();
+            let mut group = create_test_group();
+            group.set_offsets([24, 25, 0]);
+
+            let positions: Vec<[Rune; 3]> = group.positions_iter().take(3).collect();
+            assert_eq!(positions, vec![
+                [rune('y'), rune('z'), rune('a')],
+                [rune('z'), rune('z'), rune('a')],
+                [rune('a'), rune('a'), rune('b')],
+            ]);
+            assert_eq!(group.offsets(), [24, 25, 0]);
+
+            let state = group.positions_iter().nth(1000).unwrap();
+            group.advance_by(1000);
+            assert_eq!(state.map(|r| r.value()), group.offsets());
+        }
+
+        #[test]
+        fn test_static_slots() {
+            let perm = create_test_perm_builder_shift().build();
+            let rotator = |offset: u8| Rotator::new(perm.clone(), offset).unwrap();
+
+            // The static slot between the stepping slots is skipped by the carry.
+            let mut group = RotatorGroup::new([rotator(25), rotator(3), rotator(0)])
+                .with_static(1, true);
+            assert!(group.is_static(1));
+            group.advance();
+            assert_eq!(group.offsets(), [0, 3, 1]);
+            group.advance_by(26 * 2);
+            assert_eq!(group.offsets(), [0, 3, 3]);
+            group.retreat();
+            assert_eq!(group.offsets(), [25, 3, 2]);
+            assert_eq!(group.period(), 26 * 26);
+
+            // The static slots still map runes.
+            let stepping = RotatorGroup::new([rotator(25), rotator(3), rotator(2)]);
+            let a = Rune::from_char('a').unwrap();
+            assert_eq!(group.map_forward(a), stepping.map_forward(a));
+            assert_eq!(group.map_backward(a), stepping.map_backward(a));
+        }
+
+        #[test]
+        fn test_four_rotators() {
+            use crate::components::stepping::Lever;
+
+            // A naval M4 Enigma, whose Greek wheel next to the reflector never steps.
+            let perm = create_test_perm_builder_shift().build();
+            let notch = [Rune::from_char('z').unwrap()];
+            let rotator = |offset: u8| Rotator::new(perm.clone(), offset).unwrap()
+                .with_notches(&notch);
+            let mut group = RotatorGroup::new([rotator(25), rotator(24), rotator(0), rotator(7)])
+                .with_stepping(Lever)
+                .with_static(3, true);
+            assert!(!group.is_static(0));
+
+            group.advance();
+            assert_eq!(group.offsets(), [0, 25, 0, 7]);
+            group.advance();
+            assert_eq!(group.offsets(), [1, 0, 1, 7]);
+            group.advance_by(1000);
+            assert_eq!(group.offsets()[3], 7);
+            assert_eq!(group.period(), 26 * 25 * 26);
+        }
+
         #[test]
         fn test_map_backward() {
             let group = create_test_group();
@@ -279,26 +883,79 @@ mod tests {
             let mut group = create_test_group();
 
             group.advance();
-            assert_eq!(group.rotators[0].offset, 1);
-            assert_eq!(group.rotators[1].offset, 0);
-            assert_eq!(group.rotators[2].offset, 0);
+            assert_eq!(group.rotators[0].offset(), 1);
+            assert_eq!(group.rotators[1].offset(), 0);
+            assert_eq!(group.rotators[2].offset(), 0);
 
-            while group.rotators[0].offset != RUNE_VALUE_MAX {
+            while group.rotators[0].offset() != RUNE_VALUE_MAX {
                 group.advance();
             }
             group.advance();
-            assert_eq!(group.rotators[0].offset, 0);
-            assert_eq!(group.rotators[1].offset, 1);
-            assert_eq!(group.rotators[2].offset, 0);
+            assert_eq!(group.rotators[0].offset(), 0);
+            assert_eq!(group.rotators[1].offset(), 1);
+            assert_eq!(group.rotators[2].offset(), 0);
 
-            while group.rotators[1].offset != RUNE_VALUE_MAX ||
-                group.rotators[0].offset != RUNE_VALUE_MAX {
+            while group.rotators[1].offset() != RUNE_VALUE_MAX ||
+                group.rotators[0].offset() != RUNE_VALUE_MAX {
                 group.advance();
             }
             group.advance();
-            assert_eq!(group.rotators[0].offset, 0);
-            assert_eq!(group.rotators[1].offset, 0);
-            assert_eq!(group.rotators[2].offset, 1);
+            assert_eq!(group.rotators[0].offset(), 0);
+            assert_eq!(group.rotators[1].offset(), 0);
+            assert_eq!(group.rotators[2].offset(), 1);
+        }
+
+        #[test]
+        fn test_period() {
+            let mut group = create_test_group();
+            assert_eq!(group.period(), 17576);
+            group.advance_by(12345);
+            assert_eq!(group.period(), 17576);
+        }
+
+        #[test]
+        fn test_retreat() {
+            let offsets = |group: &RotatorGroup| -> Vec<u8> {
+                group.rotators.iter().map(|r| r.offset()).collect()
+            };
+
+            let mut group = create_test_group();
+            group.retreat();
+            assert_eq!(offsets(&group), vec![RUNE_VALUE_MAX; 3]);
+
+            group.advance_by(26 * 26 + 26 - 1);
+            assert_eq!(offsets(&group), vec![24, 0, 1]);
+            for _ in 0..1000 {
+                let before = offsets(&group);
+                group.advance();
+                group.retreat();
+                assert_eq!(offsets(&group), before);
+                group.retreat();
+            }
+        }
+
+        #[test]
+        fn test_advance_by() {
+            let offsets = |group: &RotatorGroup| -> Vec<u8> {
+                group.rotators.iter().map(|r| r.offset()).collect()
+            };
+
+            for steps in &[0u64, 1, 25, 26, 677, 17575, 17576, 123_456_789] {
+                let mut expected = create_test_group();
+                for _ in 0..steps % 17576 {
+                    expected.advance();
+                }
+
+                let mut group = create_test_group();
+                group.advance_by(*steps);
+                assert_eq!(offsets(&group), offsets(&expected));
+            }
+
+            let mut group = create_test_group();
+            group.advance_by(u64::MAX);
+            let state = u64::MAX % 17576;
+            assert_eq!(offsets(&group),
+                vec![(state % 26) as u8, (state / 26 % 26) as u8, (state / 676) as u8]);
         }
     }
 }
@@ -0,0 +1,499 @@
+//! This module implements the stepping mechanisms that advance the rotators of a rotator group at
+//! each key press.
+//!
+//! A stepping mechanism is a type implementing the [`SteppingMechanism`] trait. It is selected when
+//! a rotator group is created with the `RotatorGroup::with_stepping` associate function. The
+//! following mechanisms are provided:
+//!
+//! - [`Odometer`] steps the rotators like the digits of an odometer: the first rotator steps at
+//!   every key press, and each rotator steps the next one when it rolls back from
+//!   `RUNE_SET_SIZE - 1` to `0`. The notches of the rotators are ignored. This is the default
+//!   mechanism of rotator groups.
+//! - [`Lever`] models the pawls and ratchets of the Wehrmacht Enigma. A rotator at one of its
+//!   notches steps the next rotator, and the pawl engaging the notch also steps the notched rotator
+//!   itself, which produces the well-known double stepping of the middle rotator.
+//! - [`Gear`] models the cog wheels of the Abwehr Enigma G. A rotator at one of its notches steps
+//!   the next rotator when it steps itself, and there is no double stepping.
+//!
+//! Custom stepping rules can be implemented by implementing the [`SteppingMechanism`] trait.
+//!
+//! Front-ends that let users choose between the simplified odometer and the historically accurate
+//! stepping of the Wehrmacht Enigma can use the [`SteppingMode`] selector, which is accepted by
+//! `RotatorGroup::with_stepping_mode` and `Enigma::with_stepping_mode`. The two modes agree until
+//! a rotator reaches one of its notches or rolls back from `RUNE_SET_SIZE - 1` to `0`.
+//!
+//! ```
+//! # use enigma::components::{Rotator, RotatorGroup};
+//! # use enigma::components::stepping::Lever;
+//! # use enigma::math::Permutation;
+//! # use enigma::Rune;
+//! #
+//! # fn wiring(s: &str) -> Permutation {
+//! #     Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+//! # }
+//! # fn rune(ch: char) -> Rune {
+//! #     Rune::from_char(ch).unwrap()
+//! # }
+//! #
+//! // Rotors III, II and I at the window letters U, D and A, from right to left.
+//! let mut group = RotatorGroup::new([
+//!     Rotator::new(wiring("bdfhjlcprtxvznyeiwgakmusqo"), 20).unwrap().with_notches(&[rune('v')]),
+//!     Rotator::new(wiring("ajdksiruxblhwtmcqgznpyfvoe"), 3).unwrap().with_notches(&[rune('e')]),
+//!     Rotator::new(wiring("ekmflgdqvzntowyhxuspaibrcj"), 0).unwrap().with_notches(&[rune('q')]),
+//! ]).with_stepping(Lever);
+//!
+//! let offsets = |group: &RotatorGroup| -> Vec<u8> {
+//!     group.rotators().iter().map(|r| r.offset()).collect()
+//! };
+//! group.advance();
+//! assert_eq!(offsets(&group), vec![21, 3, 0]);
+//! group.advance();
+//! assert_eq!(offsets(&group), vec![22, 4, 0]);
+//! // The middle rotator steps again together with the left rotator.
+//! group.advance();
+//! assert_eq!(offsets(&group), vec![23, 5, 1]);
+//! ```
+//!
+//! [`SteppingMechanism`]: trait.SteppingMechanism.html
+//! [`SteppingMode`]: enum.SteppingMode.html
+//! [`Odometer`]: struct.Odometer.html
+//! [`Lever`]: struct.Lever.html
+//! [`Gear`]: struct.Gear.html
+//!
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::components::rotator::Rotator;
+use crate::utils::RUNE_SET_SIZE;
+
+/// A mechanism that advances the rotators of a rotator group at each key press.
+///
+/// The rotators are passed in the order of the rotator group, e.g. the first rotator is the one
+/// nearest to the plug board.
+pub trait SteppingMechanism: Debug + Send + Sync {
+    /// Advance the specified rotators by one key press.
+    fn advance(&self, rotators: &mut [Rotator]);
+
+    /// Get the kind of this mechanism. Rotator groups are equal only if their mechanisms are of the
+    /// same kind.
+    ///
+    /// The default implementation identifies the mechanism by the name of its type, so mechanisms
+    /// of the same type are considered alike.
+    fn kind(&self) -> SteppingKind {
+        SteppingKind::Custom(std::any::type_name::<Self>())
+    }
+
+    /// Advance the specified rotators by the specified number of key presses.
+    ///
+    /// The default implementation calls `advance` once per key press while the rotators have not
+    /// passed as many states as there are. Beyond that, it measures the period of the states and
+    /// skips whole periods, so it calls `advance` at most about 3 times the number of states, e.g.
+    /// about 53,000 times for 3 rotators. With so many rotators that the number of states does not
+    /// fit into a `u64`, it calls `advance` once per key press. Mechanisms whose resulting offsets
+    /// can be computed arithmetically should override it, as `Odometer` does.
+    fn advance_by(&self, rotators: &mut [Rotator], steps: u64) {
+        let num_states = (RUNE_SET_SIZE as u64).checked_pow(rotators.len() as u32)
+            .unwrap_or(u64::MAX);
+        if steps <= num_states {
+            for _ in 0..steps {
+                self.advance(rotators);
+            }
+            return;
+        }
+
+        // After passing as many states as there are, the rotators are on a cycle of states.
+        for _ in 0..num_states {
+            self.advance(rotators);
+        }
+        let start = offsets(rotators);
+        let mut period = 0u64;
+        loop {
+            self.advance(rotators);
+            period += 1;
+            if offsets(rotators) == start {
+                break;
+            }
+        }
+
+        for _ in 0..(steps - num_states) % period {
+            self.advance(rotators);
+        }
+    }
+
+    /// Step the specified rotators backwards by one key press, undoing `advance`.
+    ///
+    /// The default implementation tries every combination of stepping each rotator back by one
+    /// position, and takes one that `advance` maps to the current state. Mechanisms that step a
+    /// rotator by more than one position at a time should override it. If several states lead to
+    /// the current state, e.g. some of them can only be set by hand and are never reached by
+    /// stepping, the state with the longest history of predecessors is preferred, looking back as
+    /// many key presses as there are rotators. If no state leads to the current state, the
+    /// rotators are left unchanged.
+    fn retreat(&self, rotators: &mut [Rotator]) {
+        let mut best: Option<(usize, Vec<Rotator>)> = None;
+        for candidate in predecessors(self, rotators) {
+            let history = history_len(self, &candidate, rotators.len());
+            if best.as_ref().is_none_or(|(best_history, _)| history > *best_history) {
+                best = Some((history, candidate));
+            }
+        }
+
+        if let Some((_, previous)) = best {
+            rotators.clone_from_slice(&previous);
+        }
+    }
+}
+
+/// Get the length of the longest chain of predecessors of the specified state of the rotators, up
+/// to the specified maximum.
+fn history_len<S>(stepping: &S, rotators: &[Rotator], max: usize) -> usize
+    where S: SteppingMechanism + ?Sized {
+    if max == 0 {
+        return 0;
+    }
+    predecessors(stepping, rotators).iter()
+        .map(|previous| 1 + history_len(stepping, previous, max - 1))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Get all states of the specified rotators that the stepping mechanism maps to their current
+/// state by stepping each rotator by at most one position.
+fn predecessors<S>(stepping: &S, rotators: &[Rotator]) -> Vec<Vec<Rotator>>
+    where S: SteppingMechanism + ?Sized {
+    let current = offsets(rotators);
+    (0..1usize << rotators.len())
+        .filter_map(|mask| {
+            let mut candidate = rotators.to_vec();
+            for (i, r) in candidate.iter_mut().enumerate() {
+                if mask & (1 << i) != 0 {
+                    r.retreat();
+                }
+            }
+
+            let mut next = candidate.clone();
+            stepping.advance(&mut next);
+            if offsets(&next) == current {
+                Some(candidate)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn offsets(rotators: &[Rotator]) -> Vec<u8> {
+    rotators.iter().map(|r| r.offset()).collect()
+}
+
+/// The kind of a stepping mechanism, as returned by `SteppingMechanism::kind`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SteppingKind {
+    /// The [`Odometer`] mechanism.
+    ///
+    /// [`Odometer`]: struct.Odometer.html
+    Odometer,
+
+    /// The [`Lever`] mechanism.
+    ///
+    /// [`Lever`]: struct.Lever.html
+    Lever,
+
+    /// The [`Gear`] mechanism.
+    ///
+    /// [`Gear`]: struct.Gear.html
+    Gear,
+
+    /// A mechanism implemented outside of this crate, identified by a name.
+    Custom(&'static str),
+}
+
+/// A selector of the stepping mechanisms of the Enigma.
+///
+/// Machines keep the simplified odometer stepping unless a mode is selected, so that existing
+/// ciphertexts keep decrypting. The modes differ as follows:
+/// - With `Odometer`, a rotator steps the next one when its offset rolls back from
+///   `RUNE_SET_SIZE - 1` to `0`, and notches are ignored. The rotators behave like the digits of
+///   an odometer and pass through all `26 * 26 * 26` states.
+/// - With `WehrmachtLever`, a rotator steps the next one when it is at one of its notches, and the
+///   middle rotator steps again at the next key press if it is at its own notch. This double step
+///   skips states, e.g. there are `26 * 25 * 26` states with one notch per rotator. Rotators
+///   without notches never step the next rotator in this mode.
+///
+/// With the `serde` feature, the modes are serialized as `"odometer"` and `"wehrmacht_lever"`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SteppingMode {
+    /// The simplified stepping of the [`Odometer`] mechanism.
+    ///
+    /// [`Odometer`]: struct.Odometer.html
+    #[default]
+    Odometer,
+
+    /// The historically accurate stepping of the [`Lever`] mechanism.
+    ///
+    /// [`Lever`]: struct.Lever.html
+    WehrmachtLever,
+}
+
+impl SteppingMode {
+    /// Get the stepping mechanism selected by this mode.
+    pub(crate) fn mechanism(self) -> Arc<dyn SteppingMechanism> {
+        match self {
+            SteppingMode::Odometer => Arc::new(Odometer),
+            SteppingMode::WehrmachtLever => Arc::new(Lever),
+        }
+    }
+
+    /// Get the mode selecting the stepping mechanism of the specified kind. Returns `None` if no
+    /// mode selects mechanisms of that kind.
+    pub(crate) fn from_kind(kind: SteppingKind) -> Option<Self> {
+        match kind {
+            SteppingKind::Odometer => Some(SteppingMode::Odometer),
+            SteppingKind::Lever => Some(SteppingMode::WehrmachtLever),
+            SteppingKind::Gear | SteppingKind::Custom(_) => None,
+        }
+    }
+}
+
+/// The odometer stepping mechanism, which ignores the notches of the rotators.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Odometer;
+
+impl SteppingMechanism for Odometer {
+    fn advance(&self, rotators: &mut [Rotator]) {
+        for r in rotators {
+            if r.advance() {
+                break;
+            }
+        }
+    }
+
+    fn kind(&self) -> SteppingKind {
+        SteppingKind::Odometer
+    }
+
+    fn advance_by(&self, rotators: &mut [Rotator], steps: u64) {
+        let mut carry = steps;
+        for r in rotators {
+            if carry == 0 {
+                break;
+            }
+            carry = r.advance_by(carry);
+        }
+    }
+
+    fn retreat(&self, rotators: &mut [Rotator]) {
+        for r in rotators {
+            if r.retreat() {
+                break;
+            }
+        }
+    }
+}
+
+/// The lever stepping mechanism of the Wehrmacht Enigma, which double steps the middle rotator.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Lever;
+
+impl SteppingMechanism for Lever {
+    fn advance(&self, rotators: &mut [Rotator]) {
+        // There is a pawl in front of each rotator. The pawl in front of the i-th rotator engages
+        // the notch of the (i - 1)-th rotator, pushing both rotators. The rotators are stepped from
+        // the last one, so that the notches of the rotators not stepped yet are still in place.
+        let len = rotators.len();
+        for i in (0..len).rev() {
+            let pushed = i == 0 || rotators[i - 1].at_notch();
+            let pushing = i + 1 < len && rotators[i].at_notch();
+            if pushed || pushing {
+                rotators[i].advance();
+            }
+        }
+    }
+
+    fn kind(&self) -> SteppingKind {
+        SteppingKind::Lever
+    }
+}
+
+/// The gear stepping mechanism of the Abwehr Enigma G, in which each rotator steps the next one
+/// when it leaves one of its notches.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Gear;
+
+impl SteppingMechanism for Gear {
+    fn advance(&self, rotators: &mut [Rotator]) {
+        for r in rotators {
+            let carry = r.at_notch();
+            r.advance();
+            if !carry {
+                break;
+            }
+        }
+    }
+
+    fn kind(&self) -> SteppingKind {
+        SteppingKind::Gear
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::tests::*;
+    use crate::utils::Rune;
+
+    fn create_test_rotators(offsets: [u8; 3], notches: [&[u8]; 3]) -> Vec<Rotator> {
+        offsets.iter().zip(&notches)
+            .map(|(offset, notches)| {
+                let notches: Vec<Rune> = notches.iter()
+                    .map(|n| Rune::from_value(*n).unwrap())
+                    .collect();
+                Rotator::new(create_test_perm_builder().build(), *offset).unwrap()
+                    .with_notches(&notches)
+            })
+            .collect()
+    }
+
+    fn count_states(stepping: &dyn SteppingMechanism, rotators: &mut [Rotator]) -> usize {
+        let start = offsets(rotators);
+        let mut count = 0;
+        loop {
+            stepping.advance(rotators);
+            count += 1;
+            if offsets(rotators) == start {
+                return count;
+            }
+        }
+    }
+
+    mod odometer_tests {
+        use super::*;
+
+        #[test]
+        fn test_advance() {
+            let mut rotators = create_test_rotators([25, 25, 3], [&[4], &[4], &[4]]);
+            Odometer.advance(&mut rotators);
+            assert_eq!(offsets(&rotators), vec![0, 0, 4]);
+            assert_eq!(count_states(&Odometer, &mut rotators), 17576);
+        }
+
+        #[test]
+        fn test_advance_by_retreat() {
+            let mut rotators = create_test_rotators([0, 0, 0], [&[], &[], &[]]);
+            Odometer.advance_by(&mut rotators, 17576 * 2 + 27);
+            assert_eq!(offsets(&rotators), vec![1, 1, 0]);
+            Odometer.retreat(&mut rotators);
+            Odometer.retreat(&mut rotators);
+            assert_eq!(offsets(&rotators), vec![25, 0, 0]);
+        }
+    }
+
+    mod lever_tests {
+        use super::*;
+
+        #[test]
+        fn test_double_step() {
+            let mut rotators = create_test_rotators([20, 3, 0], [&[21], &[4], &[16]]);
+            let mut expected = vec![vec![21, 3, 0], vec![22, 4, 0], vec![23, 5, 1], vec![24, 5, 1]];
+            expected.reverse();
+            while let Some(e) = expected.pop() {
+                Lever.advance(&mut rotators);
+                assert_eq!(offsets(&rotators), e);
+            }
+        }
+
+        #[test]
+        fn test_period() {
+            let mut rotators = create_test_rotators([0, 0, 0], [&[21], &[4], &[16]]);
+            assert_eq!(count_states(&Lever, &mut rotators), 26 * 25 * 26);
+        }
+
+        #[test]
+        fn test_advance_by() {
+            let mut expected = create_test_rotators([7, 2, 9], [&[21], &[4], &[16]]);
+            let mut rotators = expected.clone();
+            for steps in &[0u64, 1, 30, 1000, 16900] {
+                for _ in 0..*steps {
+                    Lever.advance(&mut expected);
+                }
+                Lever.advance_by(&mut rotators, *steps);
+                assert_eq!(offsets(&rotators), offsets(&expected));
+            }
+
+            Lever.advance_by(&mut rotators, 16900 * 1000);
+            assert_eq!(offsets(&rotators), offsets(&expected));
+        }
+
+        #[test]
+        fn test_advance_by_many_rotators() {
+            // The number of states of 14 rotators does not fit into a u64.
+            let notch = [Rune::from_value(21).unwrap()];
+            let mut expected: Vec<Rotator> = (0..14)
+                .map(|_| Rotator::new(create_test_perm_builder().build(), 0).unwrap()
+                    .with_notches(&notch))
+                .collect();
+            let mut rotators = expected.clone();
+            for _ in 0..1000 {
+                Lever.advance(&mut expected);
+            }
+            Lever.advance_by(&mut rotators, 1000);
+            assert_eq!(offsets(&rotators), offsets(&expected));
+        }
+
+        #[test]
+        fn test_retreat() {
+            let mut rotators = create_test_rotators([20, 3, 0], [&[21], &[4], &[16]]);
+            for _ in 0..3 {
+                Lever.advance(&mut rotators);
+            }
+            for expected in &[vec![22, 4, 0], vec![21, 3, 0], vec![20, 3, 0]] {
+                Lever.retreat(&mut rotators);
+                assert_eq!(&offsets(&rotators), expected);
+            }
+        }
+
+        #[test]
+        fn test_retreat_cycle() {
+            let mut rotators = create_test_rotators([0, 0, 0], [&[21], &[4], &[16]]);
+            Lever.advance_by(&mut rotators, 17576);
+            for _ in 0..26 * 25 * 26 {
+                let previous = offsets(&rotators);
+                Lever.advance(&mut rotators);
+                let next = offsets(&rotators);
+                Lever.retreat(&mut rotators);
+                assert_eq!(offsets(&rotators), previous);
+                Lever.advance(&mut rotators);
+                assert_eq!(offsets(&rotators), next);
+            }
+        }
+    }
+
+    mod gear_tests {
+        use super::*;
+
+        #[test]
+        fn test_advance() {
+            let mut rotators = create_test_rotators([21, 4, 0], [&[21], &[4], &[16]]);
+            Gear.advance(&mut rotators);
+            assert_eq!(offsets(&rotators), vec![22, 5, 1]);
+            Gear.advance(&mut rotators);
+            assert_eq!(offsets(&rotators), vec![23, 5, 1]);
+        }
+
+        #[test]
+        fn test_multiple_notches() {
+            let mut rotators = create_test_rotators([0, 0, 0], [&[0, 13], &[0], &[0]]);
+            for _ in 0..13 {
+                Gear.advance(&mut rotators);
+            }
+            assert_eq!(offsets(&rotators), vec![13, 1, 1]);
+            Gear.advance(&mut rotators);
+            assert_eq!(offsets(&rotators), vec![14, 2, 1]);
+            // The middle rotator steps twice per revolution of the first rotator.
+            assert_eq!(count_states(&Gear, &mut rotators), 26 * 13 * 26);
+        }
+    }
+}
@@ -0,0 +1,428 @@
+//! This module implements the configuration files of Enigma machines read by `enigma-cli`,
+//! `enigma-tui` and the C interface, whose format is described in `docs/Configuration.md`.
+//!
+//! Values that cannot describe any machine, e.g. offsets out of range, pairs connecting a letter
+//! to itself or wirings that repeat a letter, are rejected while the file is parsed. The
+//! `Config::from_json` associate function reports the path of the offending field together with
+//! its line and column:
+//!
+//! ```
+//! # use enigma::config::Config;
+//! #
+//! let json = r#"{
+//!   "plug_board": [["a", "p"]],
+//!   "rotators": [["I", 0], ["II", 30], ["III", 19]],
+//!   "reflector": [["a", "b"]]
+//! }"#;
+//!
+//! let e = Config::from_json(json).unwrap_err();
+//! assert_eq!(e.path, "rotators[1][1]");
+//! assert_eq!((e.line, e.column), (3, 35));
+//! ```
+//!
+//! Problems that involve several fields, e.g. a letter used by two plug pairs, are only detected
+//! when the machine is created from the settings returned by `Config::to_settings`.
+//!
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Deserializer};
+use serde::de::{Error as DeError, Unexpected};
+
+use crate::{RotorCatalog, Settings, SteppingMode};
+use crate::math::{Permutation, PermutationBuilder};
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// Error indicating that a configuration file cannot be parsed.
+#[derive(Clone, Debug)]
+pub struct ParseConfigError {
+    /// The path of the field at which parsing failed, e.g. `rotators[1][0]`, or `.` if parsing
+    /// failed at the top level.
+    pub path: String,
+
+    /// The line at which parsing failed, starting from `1`.
+    pub line: usize,
+
+    /// The column at which parsing failed, starting from `1`.
+    pub column: usize,
+
+    /// The description of the problem.
+    pub message: String,
+}
+
+impl Display for ParseConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} at line {}, column {}", self.path, self.message, self.line, self.column)
+    }
+}
+
+impl Error for ParseConfigError { }
+
+/// Error indicating that a rotator is given by a name that is neither a historical nor a custom
+/// rotor.
+#[derive(Clone, Debug)]
+pub struct UnknownRotorError {
+    /// The unknown name.
+    pub name: String,
+}
+
+impl Display for UnknownRotorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown rotor {:?}", self.name)
+    }
+}
+
+impl Error for UnknownRotorError { }
+
+/// The configuration of an Enigma machine.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Config {
+    /// The pairs of letters connected on the plug board.
+    pub plug_board: Vec<LetterPair>,
+
+    /// The rotators and their offsets, in the order of the rotator group.
+    pub rotators: [(RotorSpec, Offset); 3],
+
+    /// The ring settings of the rotators, in the order of the rotator group. The ring settings are
+    /// `[0, 0, 0]` if omitted.
+    #[serde(default)]
+    pub rings: [Offset; 3],
+
+    /// The stepping mode of the rotator group. The odometer stepping mode, which ignores the
+    /// notches, is used if omitted.
+    #[serde(default)]
+    pub stepping: SteppingMode,
+
+    /// The pairs of letters swapped by the reflector.
+    pub reflector: Vec<LetterPair>,
+
+    /// The wirings and notches of the rotators that can be referred to by name, in addition to the
+    /// historical rotators.
+    #[serde(default)]
+    pub custom_rotors: HashMap<String, (Wiring, Letters)>,
+}
+
+impl Config {
+    /// Parse a configuration file in JSON format.
+    pub fn from_json(json: &str) -> Result<Self, ParseConfigError> {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        let config: Self = serde_path_to_error::deserialize(&mut deserializer)
+            .map_err(|e| {
+                let path = e.path().to_string();
+                let inner = e.into_inner();
+
+                // The message of the JSON error ends with the position, which is reported apart.
+                let position = format!(" at line {} column {}", inner.line(), inner.column());
+                let message = inner.to_string();
+                let message = message.strip_suffix(&position).unwrap_or(&message).to_string();
+                ParseConfigError { path, line: inner.line(), column: inner.column(), message }
+            })?;
+        Ok(config)
+    }
+
+    /// Create the catalog of the historical rotators and the custom rotators of this
+    /// configuration. A custom rotor replaces the historical rotator of the same name.
+    pub fn create_catalog(&self) -> RotorCatalog {
+        let mut catalog = RotorCatalog::historical();
+        for (name, (wiring, notches)) in &self.custom_rotors {
+            catalog.register(name, wiring.0.clone(), &notches.0)
+                .expect("wirings are checked while parsing");
+        }
+        catalog
+    }
+
+    /// Get the wiring of the rotator at the specified index of the rotator group, looking up
+    /// named rotators within the specified catalog.
+    pub fn rotator_wiring(&self, catalog: &RotorCatalog, index: usize)
+        -> Result<Permutation, UnknownRotorError> {
+        match &self.rotators[index].0 {
+            RotorSpec::Wiring(wiring) => Ok(wiring.clone()),
+            RotorSpec::Name(name) => catalog.wiring(name)
+                .cloned()
+                .ok_or_else(|| UnknownRotorError { name: name.clone() }),
+        }
+    }
+
+    /// Get the notches of the rotator at the specified index of the rotator group, looking up named
+    /// rotators within the specified catalog. Rotators given by their wirings have no notches.
+    pub fn rotator_notches(&self, catalog: &RotorCatalog, index: usize) -> Vec<Rune> {
+        match &self.rotators[index].0 {
+            RotorSpec::Wiring(_) => Vec::new(),
+            RotorSpec::Name(name) => catalog.notches(name)
+                .map(<[Rune]>::to_vec)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Get the settings described by this configuration. This function fails if a rotator is
+    /// given by an unknown name.
+    pub fn to_settings(&self) -> Result<Settings, UnknownRotorError> {
+        let catalog = self.create_catalog();
+        let mut reflector = PermutationBuilder::new(RUNE_SET_SIZE);
+        for pair in &self.reflector {
+            reflector = reflector.swap(pair.0.value(), pair.1.value());
+        }
+
+        Ok(Settings {
+            rotator_wirings: [
+                self.rotator_wiring(&catalog, 0)?,
+                self.rotator_wiring(&catalog, 1)?,
+                self.rotator_wiring(&catalog, 2)?,
+            ],
+            positions: [0, 1, 2].map(|i| self.rotators[i].1.0),
+            rings: self.rings.map(|ring| ring.0),
+            notches: [0, 1, 2].map(|i| self.rotator_notches(&catalog, i)),
+            stepping: self.stepping,
+            reflector: reflector.build(),
+            plug_pairs: self.plug_board.iter().map(|pair| (pair.0, pair.1)).collect(),
+        })
+    }
+}
+
+/// A pair of distinct letters, written as an array of 2 ASCII letters of either case, e.g.
+/// `["a", "p"]`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LetterPair(pub Rune, pub Rune);
+
+impl<'de> Deserialize<'de> for LetterPair {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de> {
+        let [lhs, rhs] = <[Rune; 2]>::deserialize(deserializer)?;
+        if lhs == rhs {
+            return Err(D::Error::custom(
+                format!("pair {}{} connects the letter {} to itself", lhs, rhs, lhs)));
+        }
+        Ok(Self(lhs, rhs))
+    }
+}
+
+/// The offset or the ring setting of a rotator, from `0` to `25`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Offset(pub u8);
+
+impl<'de> Deserialize<'de> for Offset {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de> {
+        let offset = u64::deserialize(deserializer)?;
+        if offset >= RUNE_SET_SIZE as u64 {
+            return Err(D::Error::invalid_value(Unexpected::Unsigned(offset),
+                &"an offset from 0 to 25"));
+        }
+        Ok(Self(offset as u8))
+    }
+}
+
+/// The wiring of a rotator, written as a string listing every letter exactly once.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Wiring(pub Permutation);
+
+impl<'de> Deserialize<'de> for Wiring {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de> {
+        let s = String::deserialize(deserializer)?;
+        parse_wiring(&s).map(Self)
+    }
+}
+
+/// A rotator given either by its wiring or by its name.
+///
+/// A string of 26 characters is a wiring and should list every letter exactly once. Any other
+/// string names a historical rotator, e.g. `I` to `VIII`, or a custom rotor.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RotorSpec {
+    /// The name of a rotator within the catalog.
+    Name(String),
+
+    /// The wiring of the rotator.
+    Wiring(Permutation),
+}
+
+impl<'de> Deserialize<'de> for RotorSpec {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de> {
+        let s = String::deserialize(deserializer)?;
+        if s.chars().count() == RUNE_SET_SIZE as usize {
+            parse_wiring(&s).map(RotorSpec::Wiring)
+        } else {
+            Ok(RotorSpec::Name(s))
+        }
+    }
+}
+
+/// A set of letters written as a string of ASCII letters of either case, e.g. the notches of a
+/// rotator.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Letters(pub Vec<Rune>);
+
+impl<'de> Deserialize<'de> for Letters {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de> {
+        let s = String::deserialize(deserializer)?;
+        s.chars().map(parse_letter).collect::<Result<_, _>>().map(Self)
+    }
+}
+
+fn parse_letter<E>(ch: char) -> Result<Rune, E>
+    where E: DeError {
+    Rune::from_char(ch).map_err(|_| E::invalid_value(Unexpected::Char(ch), &"an ASCII letter"))
+}
+
+fn parse_wiring<E>(s: &str) -> Result<Permutation, E>
+    where E: DeError {
+    let mut counts = [0usize; RUNE_SET_SIZE as usize];
+    let mut perm = Vec::with_capacity(s.len());
+    for ch in s.chars() {
+        let rune = parse_letter::<E>(ch)?;
+        counts[rune.value() as usize] += 1;
+        perm.push(rune.value());
+    }
+
+    let letters = |pred: fn(usize) -> bool| -> Vec<String> {
+        Rune::ALPHABET.iter()
+            .filter(|rune| pred(counts[rune.value() as usize]))
+            .map(|rune| rune.into_string())
+            .collect()
+    };
+    let repeated = letters(|count| count > 1);
+    if !repeated.is_empty() {
+        return Err(E::custom(format!("wiring repeats the letters {}", repeated.join(" "))));
+    }
+    let missing = letters(|count| count == 0);
+    if !missing.is_empty() {
+        return Err(E::custom(format!("wiring misses the letters {}", missing.join(" "))));
+    }
+
+    Ok(Permutation::from_perm(perm).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod config_tests {
+        use super::*;
+
+        use crate::testvectors::ROTATORS;
+
+        fn rune(ch: char) -> Rune {
+            Rune::from_char(ch).unwrap()
+        }
+
+        #[test]
+        fn test_example() {
+            let config = Config::from_json(include_str!("../config.example.json")).unwrap();
+            assert_eq!(config.plug_board[0], LetterPair(rune('a'), rune('p')));
+            assert_eq!(config.rotators[2].1, Offset(19));
+            assert!(config.custom_rotors.is_empty());
+
+            let settings = config.to_settings().unwrap();
+            assert_eq!(settings.positions, [0, 5, 19]);
+            assert_eq!(settings.rings, [0, 0, 0]);
+            assert!(settings.create_enigma().is_ok());
+        }
+
+        #[test]
+        fn test_rings() {
+            let json = r#"{
+                "plug_board": [],
+                "rotators": [["I", 0], ["II", 1], ["III", 2]],
+                "rings": [3, 0, 25],
+                "reflector": [["a", "y"], ["b", "r"], ["c", "u"], ["d", "h"], ["e", "q"],
+                    ["f", "s"], ["g", "l"], ["i", "p"], ["j", "x"], ["k", "n"], ["m", "o"],
+                    ["t", "z"], ["v", "w"]]
+            }"#;
+            let settings = Config::from_json(json).unwrap().to_settings().unwrap();
+            assert_eq!(settings.rings, [3, 0, 25]);
+            assert_eq!(settings.create_enigma().unwrap().window(), "BBD");
+
+            let json = json.replace("25]", "26]");
+            assert_eq!(Config::from_json(&json).unwrap_err().path, "rings[2]");
+        }
+
+        #[test]
+        fn test_stepping() {
+            let json = r#"{
+                "plug_board": [],
+                "rotators": [["III", 21], ["II", 1], ["ekmflgdqvzntowyhxuspaibrcj", 2]],
+                "stepping": "wehrmacht_lever",
+                "reflector": [["a", "y"], ["b", "r"], ["c", "u"], ["d", "h"], ["e", "q"],
+                    ["f", "s"], ["g", "l"], ["i", "p"], ["j", "x"], ["k", "n"], ["m", "o"],
+                    ["t", "z"], ["v", "w"]]
+            }"#;
+            let settings = Config::from_json(json).unwrap().to_settings().unwrap();
+            assert_eq!(settings.notches, [vec![rune('v')], vec![rune('e')], Vec::new()]);
+            assert_eq!(settings.stepping, SteppingMode::WehrmachtLever);
+
+            // The first rotator is at its notch and steps the second rotator.
+            let mut machine = settings.create_enigma().unwrap();
+            machine.map_str("a");
+            assert_eq!(machine.rotators().offsets(), [22, 2, 2]);
+
+            let json = json.replace("wehrmacht_lever", "lever");
+            assert_eq!(Config::from_json(&json).unwrap_err().path, "stepping");
+        }
+
+        #[test]
+        fn test_to_json_round_trip() {
+            let machine = crate::testvectors::TEST_VECTORS[1].create_enigma();
+            let settings = machine.to_config().unwrap();
+            let config = Config::from_json(&settings.to_json()).unwrap();
+            assert_eq!(config.to_settings().unwrap(), settings);
+        }
+
+        #[test]
+        fn test_rotor_names() {
+            let json = r#"{
+                "plug_board": [],
+                "rotators": [["I", 0], ["MINE", 1], ["ekmflgdqvzntowyhxuspaibrcj", 2]],
+                "reflector": [["a", "b"]],
+                "custom_rotors": {"MINE": ["ajdksiruxblhwtmcqgznpyfvoe", "e"]}
+            }"#;
+            let config = Config::from_json(json).unwrap();
+            assert_eq!(config.rotators[1].0, RotorSpec::Name(String::from("MINE")));
+            assert_eq!(config.custom_rotors["MINE"].1, Letters(vec![rune('e')]));
+
+            let settings = config.to_settings().unwrap();
+            assert_eq!(settings.rotator_wirings[0], settings.rotator_wirings[2]);
+            assert_eq!(settings.rotator_wirings[1], parse_wiring::<serde_json::Error>(ROTATORS[1].1)
+                .unwrap());
+
+            let json = json.replace("\"MINE\", 1", "\"IX\", 1");
+            let e = Config::from_json(&json).unwrap().to_settings().unwrap_err();
+            assert_eq!(e.name, "IX");
+        }
+
+        #[test]
+        fn test_errors() {
+            let error = |plug_board: &str, rotator: &str| {
+                let json = format!("{{\"plug_board\": {}, \"rotators\": [{}, [\"II\", 0], \
+                    [\"III\", 0]], \"reflector\": []}}", plug_board, rotator);
+                Config::from_json(&json).unwrap_err()
+            };
+
+            let e = error("[[\"a\", \"A\"]]", "[\"I\", 0]");
+            assert_eq!(e.path, "plug_board[0]");
+            assert_eq!(e.message, "pair AA connects the letter A to itself");
+            assert_eq!(e.line, 1);
+
+            let e = error("[[\"a\", \"1\"]]", "[\"I\", 0]");
+            assert_eq!(e.path, "plug_board[0][1]");
+            assert!(e.message.contains("character `1`"));
+
+            let e = error("[]", "[\"I\", 26]");
+            assert_eq!(e.path, "rotators[0][1]");
+            assert!(e.message.contains("an offset from 0 to 25"));
+
+            let e = error("[]", "[\"ekmflgdqvzntowyhxuspaibrce\", 0]");
+            assert_eq!(e.path, "rotators[0][0]");
+            assert_eq!(e.message, "wiring repeats the letters E");
+
+            let e = Config::from_json("{\"plug_board\": []}").unwrap_err();
+            assert_eq!(e.path, ".");
+            assert!(e.message.contains("missing field `rotators`"));
+            assert!(e.to_string().ends_with("at line 1, column 18"));
+        }
+    }
+}
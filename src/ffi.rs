@@ -0,0 +1,312 @@
+//! This module exposes the Enigma machine to C and C++ programs. It is only available with the
+//! `ffi` feature, and the declarations of the functions are given by the C header
+//! `include/enigma.h`.
+//!
+//! A machine is created from a configuration in the JSON format described by
+//! `docs/Configuration.md` with `enigma_new_from_json`, and it must be released with
+//! `enigma_free`. Functions that fail return a null pointer or a negative value, and the reason
+//! of the last failure on the calling thread is returned by `enigma_last_error`:
+//!
+//! ```c
+//! Enigma *machine = enigma_new_from_json(config);
+//! if (!machine) {
+//!     fprintf(stderr, "%s\n", enigma_last_error());
+//!     return 1;
+//! }
+//!
+//! char text[] = "Hello, World";
+//! enigma_map_buffer(machine, (uint8_t *)text, strlen(text));
+//! enigma_free(machine);
+//! ```
+//!
+//! Like the `map_str` associate function, the buffer functions map ASCII letters and advance the
+//! rotors after each of them. Unlike `map_str`, other bytes are kept unchanged, and the letter case
+//! is preserved.
+//!
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+use crate::{Enigma, RotorMachine};
+use crate::config::Config;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Create a new Enigma machine from the specified configuration in JSON format. Returns a null
+/// pointer if the configuration is invalid.
+///
+/// # Safety
+///
+/// `json` must point to a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn enigma_new_from_json(json: *const c_char) -> *mut Enigma {
+    if json.is_null() {
+        set_last_error(String::from("null configuration"));
+        return ptr::null_mut();
+    }
+
+    let json = match CStr::from_ptr(json).to_str() {
+        Ok(json) => json,
+        Err(e) => {
+            set_last_error(format!("invalid UTF-8 in configuration: {}", e));
+            return ptr::null_mut();
+        },
+    };
+    let machine = Config::from_json(json)
+        .map_err(|e| format!("failed to parse config: {}", e))
+        .and_then(|config| config.to_settings().map_err(|e| e.to_string()))
+        .and_then(|settings| settings.create_enigma().map_err(|e| e.to_string()));
+
+    match machine {
+        Ok(machine) => Box::into_raw(Box::new(machine)),
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        },
+    }
+}
+
+/// Create a copy of the specified machine in its current state.
+///
+/// # Safety
+///
+/// `machine` must be a pointer returned by this library that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn enigma_clone(machine: *const Enigma) -> *mut Enigma {
+    match machine.as_ref() {
+        Some(machine) => Box::into_raw(Box::new(machine.clone())),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Release the specified machine. Null pointers are ignored.
+///
+/// # Safety
+///
+/// `machine` must be null or a pointer returned by this library that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn enigma_free(machine: *mut Enigma) {
+    if !machine.is_null() {
+        drop(Box::from_raw(machine));
+    }
+}
+
+/// Map the ASCII letters within the specified buffer in place. Returns the number of letters
+/// mapped, or `-1` if a pointer is null.
+///
+/// # Safety
+///
+/// `machine` must be a pointer returned by this library that has not been freed, and `buf` must
+/// point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn enigma_map_buffer(machine: *mut Enigma, buf: *mut u8, len: usize)
+    -> isize {
+    let machine = match machine.as_mut() {
+        Some(machine) => machine,
+        None => return -1,
+    };
+    if buf.is_null() {
+        return -1;
+    }
+
+    let buf = std::slice::from_raw_parts_mut(buf, len);
+    let mut mapped = 0;
+    for byte in buf {
+        if byte.is_ascii_alphabetic() {
+            mapped += 1;
+        }
+        *byte = machine.map_byte(*byte);
+    }
+    mapped
+}
+
+/// Map the specified ASCII character. Characters that are not letters are returned unchanged and
+/// do not advance the rotors.
+///
+/// # Safety
+///
+/// `machine` must be a pointer returned by this library that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn enigma_map_char(machine: *mut Enigma, ch: c_char) -> c_char {
+    match machine.as_mut() {
+        Some(machine) => machine.map_byte(ch as u8) as c_char,
+        None => ch,
+    }
+}
+
+/// Write the offsets of the 3 rotators of the specified machine to `positions`. Returns `0` on
+/// success, or `-1` if a pointer is null.
+///
+/// # Safety
+///
+/// `machine` must be a pointer returned by this library that has not been freed, and `positions`
+/// must point to 3 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn enigma_get_positions(machine: *const Enigma, positions: *mut u8)
+    -> c_int {
+    match machine.as_ref() {
+        Some(machine) if !positions.is_null() => {
+            let state = machine.state();
+            ptr::copy_nonoverlapping(state.as_ptr(), positions, state.len());
+            0
+        },
+        _ => -1,
+    }
+}
+
+/// Set the offsets of the 3 rotators of the specified machine. Returns `0` on success, or `-1` if
+/// a pointer is null.
+///
+/// # Safety
+///
+/// `machine` must be a pointer returned by this library that has not been freed, and `positions`
+/// must point to 3 readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn enigma_set_positions(machine: *mut Enigma, positions: *const u8)
+    -> c_int {
+    match machine.as_mut() {
+        Some(machine) if !positions.is_null() => {
+            let positions = std::slice::from_raw_parts(positions, 3);
+            match machine.restore(positions) {
+                Ok(()) => 0,
+                Err(_) => -1,
+            }
+        },
+        _ => -1,
+    }
+}
+
+/// Get the reason of the last failure on the calling thread, or a null pointer if nothing has
+/// failed. The string is owned by the library and remains valid until the next failure on the
+/// same thread.
+#[no_mangle]
+pub extern "C" fn enigma_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| match &*e.borrow() {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+        "plug_board": [["a", "p"], ["q", "k"], ["t", "w"]],
+        "rotators": [
+            ["rcpdnugiozlmhetwsjxykvfqab", 0],
+            ["uzqhaybgotivrknxjcwmdpsefl", 5],
+            ["itaulnzmbeovckrhyxqdwgfsjp", 19]
+        ],
+        "reflector": [
+            ["a", "b"], ["c", "d"], ["e", "f"], ["g", "h"], ["i", "j"], ["k", "l"], ["m", "n"],
+            ["o", "p"], ["q", "r"], ["s", "t"], ["u", "v"], ["w", "x"], ["y", "z"]
+        ]
+    }"#;
+
+    fn new_machine(json: &str) -> *mut Enigma {
+        let json = CString::new(json).unwrap();
+        unsafe { enigma_new_from_json(json.as_ptr()) }
+    }
+
+    #[test]
+    fn test_map_buffer() {
+        let machine = new_machine(CONFIG);
+        assert!(!machine.is_null());
+
+        let mut text = *b"Hello, World";
+        unsafe {
+            let copy = enigma_clone(machine);
+            assert_eq!(enigma_map_buffer(machine, text.as_mut_ptr(), text.len()), 10);
+            assert_ne!(&text, b"Hello, World");
+            assert_eq!(text[5..7], *b", ");
+
+            assert_eq!(enigma_map_buffer(copy, text.as_mut_ptr(), text.len()), 10);
+            assert_eq!(&text, b"Hello, World");
+
+            enigma_free(copy);
+            enigma_free(machine);
+        }
+    }
+
+    #[test]
+    fn test_load_exported_settings() {
+        let machine = new_machine(CONFIG);
+        unsafe {
+            let settings = (*machine).to_config().unwrap();
+            let copy = new_machine(&settings.to_json());
+            assert!(!copy.is_null());
+            assert!((*machine).is_equivalent(&*copy, 1000));
+
+            enigma_free(copy);
+            enigma_free(machine);
+        }
+    }
+
+    #[test]
+    fn test_rotor_names() {
+        let config = r#"{
+            "plug_board": [],
+            "rotators": [["MY-ROTOR", 0], ["II", 5], ["III", 19]],
+            "reflector": [
+                ["a", "b"], ["c", "d"], ["e", "f"], ["g", "h"], ["i", "j"], ["k", "l"], ["m", "n"],
+                ["o", "p"], ["q", "r"], ["s", "t"], ["u", "v"], ["w", "x"], ["y", "z"]
+            ],
+            "custom_rotors": { "MY-ROTOR": ["rcpdnugiozlmhetwsjxykvfqab", "q"] }
+        }"#;
+
+        let machine = new_machine(config);
+        assert!(!machine.is_null());
+        unsafe {
+            let settings = (*machine).to_config().unwrap();
+            assert_eq!(settings.rotator_wirings[0].map(0), b'r' - b'a');
+            assert_eq!(settings.rotator_wirings[1].map(1), b'j' - b'a');
+            enigma_free(machine);
+        }
+
+        assert!(new_machine(&config.replace("\"II\"", "\"IX\"")).is_null());
+    }
+
+    #[test]
+    fn test_positions() {
+        let machine = new_machine(CONFIG);
+        let mut positions = [0u8; 3];
+        unsafe {
+            assert_eq!(enigma_get_positions(machine, positions.as_mut_ptr()), 0);
+            assert_eq!(positions, [0, 5, 19]);
+
+            let ch = enigma_map_char(machine, b'a' as c_char);
+            assert_eq!(enigma_map_char(machine, b'!' as c_char), b'!' as c_char);
+            assert_eq!(enigma_set_positions(machine, [0, 5, 19].as_ptr()), 0);
+            assert_eq!(enigma_map_char(machine, ch), b'a' as c_char);
+
+            enigma_free(machine);
+        }
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let machine = new_machine(r#"{"plug_board": []}"#);
+        assert!(machine.is_null());
+
+        let message = unsafe { CStr::from_ptr(enigma_last_error()) };
+        assert!(message.to_str().unwrap().starts_with("failed to parse config"));
+
+        let config = CONFIG.replace("rcpdnugiozlmhetwsjxykvfqab", "rcpdnugiozlmhetwsjxykvfqa1");
+        assert!(new_machine(&config).is_null());
+        let message = unsafe { CStr::from_ptr(enigma_last_error()) };
+        assert!(message.to_str().unwrap()
+            .starts_with("failed to parse config: rotators[0][0]: invalid value: character `1`"));
+
+        assert!(unsafe { enigma_new_from_json(ptr::null()) }.is_null());
+    }
+}
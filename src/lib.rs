@@ -1,59 +1,1440 @@
 //! This crate provides an implementation of the Enigma machine.
 //!
 
+pub mod analysis;
+#[cfg(feature = "tokio")]
+pub mod async_io;
 pub mod components;
+#[cfg(feature = "config")]
+pub mod config;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod machines;
 pub mod math;
+pub mod procedure;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod render;
+pub mod session;
+pub mod settings;
+pub mod state;
+#[cfg(feature = "cipher")]
+pub mod stream_cipher;
+pub mod testvectors;
 pub mod utils;
+pub mod variants;
+
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 pub use crate::components::*;
+pub use crate::machines::RotorMachine;
+pub use crate::settings::Settings;
 pub use crate::utils::Rune;
 
-// An Enigma machine.
+use crate::machines::InvalidStateError;
+use crate::session::SessionLog;
+use crate::math::Permutation;
+use crate::utils::RUNE_SET_SIZE;
+
+/// The number of distinct states of the rotators within an Enigma machine.
+const NUM_ROTATOR_STATES: usize = (RUNE_SET_SIZE as usize) * (RUNE_SET_SIZE as usize) *
+    (RUNE_SET_SIZE as usize);
+
+/// An Enigma machine.
+#[derive(Clone, Debug)]
 pub struct Enigma {
     plug: PlugBoard,
+    entry_wheel: EntryWheel,
     rotators: RotatorGroup,
     reflector: Reflector,
+    lookup: Option<Arc<LookupTable>>,
+    observer: Option<Observer>,
+    stats: EnigmaStats,
+    preserve_case: bool,
+    rings: [u8; 3],
+    log: Option<SessionLog>,
 }
 
 impl Enigma {
-    /// Create a new Enigma machine with its components.
+    /// Create a new Enigma machine with its components. The entry wheel wires the i-th letter to
+    /// the i-th contact of the first rotator.
     pub fn new(plug: PlugBoard, rotators: RotatorGroup, reflector: Reflector) -> Self {
-        Self { plug, rotators, reflector }
+        Self {
+            plug,
+            entry_wheel: EntryWheel::identity(),
+            rotators,
+            reflector,
+            lookup: None,
+            observer: None,
+            stats: EnigmaStats::default(),
+            preserve_case: false,
+            rings: [0; 3],
+            log: None,
+        }
+    }
+
+    /// Set the entry wheel between the plug board and the rotators of this machine.
+    pub fn with_entry_wheel(mut self, entry_wheel: EntryWheel) -> Self {
+        self.entry_wheel = entry_wheel;
+        self.lookup = None;
+        self
+    }
+
+    /// Set the stepping mechanism of the rotators of this machine to the one selected by the
+    /// specified mode, replacing the mechanism of the rotator group the machine was created with.
+    /// See [`SteppingMode`] for how the modes differ.
+    ///
+    /// [`SteppingMode`]: components/stepping/enum.SteppingMode.html
+    pub fn with_stepping_mode(mut self, mode: SteppingMode) -> Self {
+        self.rotators.set_stepping_mode(mode);
+        self
+    }
+
+    /// Set whether the functions mapping characters, e.g. `map_str`, map lower case letters to
+    /// lower case letters. By default, all output letters are upper case, as `Rune::into_char`
+    /// produces them.
+    pub fn with_preserve_case(mut self, preserve_case: bool) -> Self {
+        self.preserve_case = preserve_case;
+        self
+    }
+
+    /// Determine whether the functions mapping characters preserve the letter case of the input.
+    pub fn preserves_case(&self) -> bool {
+        self.preserve_case
+    }
+
+    /// Set the ring settings of the rotators of this machine, from left to right as on a key sheet,
+    /// i.e. in the reverse order of the rotator group. The values are taken modulo
+    /// `RUNE_SET_SIZE`. The ring settings are `[0, 0, 0]` by default.
+    ///
+    /// Rotators have no rings, so machines emulate ring settings by shifting the offsets and the
+    /// notches of their rotators back, as the machines created by `EnigmaState::create_enigma` do.
+    /// The ring settings set here do not change the mapping of this machine. They translate
+    /// between the offsets of the rotators and the window letters in `window` and `set_window`.
+    pub fn with_ring_settings(mut self, rings: [u8; 3]) -> Self {
+        self.rings = rings.map(|ring| ring % RUNE_SET_SIZE);
+        self
+    }
+
+    /// Get the ring settings of the rotators of this machine, from left to right.
+    pub fn ring_settings(&self) -> [u8; 3] {
+        self.rings
+    }
+
+    /// Get the letters shown in the windows of this machine, from left to right as the operator
+    /// sees them, i.e. in the reverse order of the rotator group. Each window letter is the offset
+    /// of its rotator shifted forward by its ring setting.
+    pub fn window(&self) -> String {
+        let offsets = self.rotators.offsets();
+        (0..3)
+            .map(|i| {
+                let value = (offsets[2 - i] + self.rings[i]) % RUNE_SET_SIZE;
+                unsafe { Rune::from_value_unchecked(value) }.into_char()
+            })
+            .collect()
+    }
+
+    /// Turn the rotators of this machine until the specified letters are shown in the windows,
+    /// from left to right as on a key sheet, e.g. `"ABC"` shows `C` at the first rotator of the
+    /// rotator group. This is how key sheets and manuals give the ground setting and the message
+    /// keys. The letters are case insensitive.
+    ///
+    /// This function fails if the string does not consist of exactly 3 English letters, and
+    /// leaves the machine unchanged then.
+    pub fn set_window(&mut self, window: &str) -> Result<(), InvalidWindowError> {
+        let letters = window.chars()
+            .map(Rune::from_char)
+            .collect::<Result<Vec<Rune>, _>>()
+            .map_err(|_| InvalidWindowError)?;
+        if letters.len() != 3 {
+            return Err(InvalidWindowError);
+        }
+
+        let mut offsets = [0u8; 3];
+        for (i, letter) in letters.iter().enumerate() {
+            offsets[2 - i] = (letter.value() + RUNE_SET_SIZE - self.rings[i]) % RUNE_SET_SIZE;
+        }
+        self.rotators.set_offsets(offsets);
+        Ok(())
+    }
+
+    /// Get the entry wheel within this Enigma machine.
+    pub fn entry_wheel(&self) -> &EntryWheel {
+        &self.entry_wheel
+    }
+
+    /// Precompute the outputs of this machine at every state of its rotators, so that mapping a
+    /// rune becomes a single table lookup instead of a walk through all components.
+    ///
+    /// Building the table costs about as much as mapping 450,000 runes, so it pays off for long
+    /// texts and for brute-force loops that map many texts. The table does not depend on the
+    /// current state of the rotators, and it is shared by all clones of this machine.
+    pub fn precompute(&mut self) {
+        if self.lookup.is_some() {
+            return;
+        }
+
+        let mut machine = self.clone();
+        let mut outputs = vec![[0u8; RUNE_SET_SIZE as usize]; NUM_ROTATOR_STATES];
+        for (state, output) in outputs.iter_mut().enumerate() {
+            let n = RUNE_SET_SIZE as usize;
+            machine.rotators.set_offsets([(state % n) as u8, (state / n % n) as u8,
+                (state / n / n) as u8]);
+            for (i, x) in output.iter_mut().enumerate() {
+                *x = machine.walk(Rune::from_value(i as u8).unwrap()).value();
+            }
+        }
+
+        self.lookup = Some(Arc::new(LookupTable { outputs }));
+    }
+
+    /// Determine whether the outputs of this machine have been precomputed by `precompute`.
+    pub fn is_precomputed(&self) -> bool {
+        self.lookup.is_some()
+    }
+
+    /// Set the function called whenever the positions of the rotators of this machine change,
+    /// replacing the previous one. Clones of this machine share the observer.
+    ///
+    /// The observer is called once for each step taken by `map_rune`, `advance_rotators` and
+    /// `rewind`, and once for each call to `advance_by`.
+    pub fn set_observer<F>(&mut self, observer: F)
+        where F: Fn(&StepEvent) + Send + Sync + 'static {
+        self.observer = Some(Observer(Arc::new(observer)));
+    }
+
+    /// Remove the observer set by `set_observer`.
+    pub fn clear_observer(&mut self) {
+        self.observer = None;
+    }
+
+    /// Get the numbers of runes mapped and rotor steps taken by this machine since it was created
+    /// or since the last call to `reset_stats`.
+    pub fn stats(&self) -> EnigmaStats {
+        self.stats
+    }
+
+    /// Reset the counters returned by `stats` to zero.
+    pub fn reset_stats(&mut self) {
+        self.stats = EnigmaStats::default();
+    }
+
+    /// Change the positions of the rotators with the specified function and notify the observer.
+    fn step_rotators<F>(&mut self, step: F)
+        where F: FnOnce(&mut RotatorGroup) {
+        match &self.observer {
+            Some(observer) => {
+                let old_positions = self.rotators.offsets();
+                step(&mut self.rotators);
+                let new_positions = self.rotators.offsets();
+                (observer.0)(&StepEvent { old_positions, new_positions });
+            },
+            None => step(&mut self.rotators),
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(positions = ?self.rotators.offsets(), "rotators stepped");
+    }
+
+    /// Get the rotator group within this Enigma machine.
+    pub fn rotators(&self) -> &RotatorGroup {
+        &self.rotators
     }
 
     /// Map the specified input rune to output rune, but do not advance the rotators.
-    pub fn map_rune_static(&self, mut input: Rune) -> Rune {
+    pub fn map_rune_static(&self, input: Rune) -> Rune {
+        match &self.lookup {
+            Some(lookup) => {
+                let output = lookup.outputs[self.rotator_state()][input.value() as usize];
+                unsafe { Rune::from_value_unchecked(output) }
+            },
+            None => self.walk(input),
+        }
+    }
+
+    /// Map the specified input rune through all components of this machine.
+    fn walk(&self, mut input: Rune) -> Rune {
         input = self.plug.map(input);
+        input = self.entry_wheel.map_forward(input);
         input = self.rotators.map_forward(input);
         input = self.reflector.map(input);
         input = self.rotators.map_backward(input);
+        input = self.entry_wheel.map_backward(input);
         input = self.plug.map(input);
 
         input
     }
 
+    /// Get the rune permutation realized by the machine in its current state, i.e. the mapping
+    /// performed by `map_rune_static`.
+    pub fn permutation(&self) -> Permutation {
+        let perm = (0..RUNE_SET_SIZE)
+            .map(|i| self.map_rune_static(Rune::from_value(i).unwrap()).value())
+            .collect();
+        unsafe { Permutation::from_perm_unchecked(perm) }
+    }
+
+    /// Get the settings of this machine in its current state, i.e. the wirings of its components
+    /// and the current offsets, ring settings and notches of its rotators, so that the machine can
+    /// be saved as a configuration file with `Settings::to_json`.
+    ///
+    /// Returns `None` if settings cannot describe this machine, e.g. if its entry wheel is not the
+    /// identity, one of its rotators is static or its stepping mechanism is not selected by a
+    /// `SteppingMode`.
+    pub fn to_config(&self) -> Option<Settings> {
+        if !self.entry_wheel.is_identity() || (0..3).any(|slot| self.rotators.is_static(slot)) {
+            return None;
+        }
+        let stepping = SteppingMode::from_kind(self.rotators.stepping().kind())?;
+
+        // Rotators have no rings, so their notches are shifted back by the ring settings. Settings
+        // give the notches as window letters instead.
+        let rotators = self.rotators.rotators();
+        let notches = [0, 1, 2].map(|i| {
+            let ring = self.rings[2 - i];
+            rotators[i].notches().iter()
+                .map(|notch| Rune::from_value((notch.value() + ring) % RUNE_SET_SIZE).unwrap())
+                .collect()
+        });
+        let reflector = Rune::ALPHABET.iter()
+            .map(|rune| self.reflector.map(*rune).value())
+            .collect();
+        Some(Settings {
+            rotator_wirings: [0, 1, 2].map(|i| rotators[i].wiring().clone()),
+            positions: self.rotators.offsets(),
+            rings: [self.rings[2], self.rings[1], self.rings[0]],
+            notches,
+            stepping,
+            reflector: unsafe { Permutation::from_perm_unchecked(reflector) },
+            plug_pairs: self.plug.pairs(),
+        })
+    }
+
+    /// Determine whether the mapping of this machine in its current state is an involution without
+    /// fixed points, i.e. whether the same state decrypts what it encrypts and never maps a rune to
+    /// itself.
+    ///
+    /// This holds for every machine whose components are valid, so it is useful as a debug
+    /// assertion after creating a machine from untrusted input or with unchecked constructors.
+    pub fn verify_reciprocal(&self) -> bool {
+        let perm = self.permutation();
+        (0..RUNE_SET_SIZE).all(|i| perm.map(i) != i && perm.map(perm.map(i)) == i)
+    }
+
+    /// Determine whether this machine and the other machine realize the same rune permutation at
+    /// each of the next specified number of key presses, starting from their current states.
+    ///
+    /// Machines specified differently can be equivalent, e.g. when the plug board and the rotator
+    /// offsets compensate each other, so cryptanalysts use this to remove duplicate keys from the
+    /// results of a search. Neither machine is advanced.
+    pub fn is_equivalent(&self, other: &Enigma, steps: u64) -> bool {
+        let mut lhs = self.clone();
+        let mut rhs = other.clone();
+        lhs.clear_observer();
+        rhs.clear_observer();
+
+        for _ in 0..steps {
+            if lhs.permutation() != rhs.permutation() {
+                return false;
+            }
+            lhs.advance_rotators();
+            rhs.advance_rotators();
+        }
+        true
+    }
+
+    /// Map the specified input rune to output rune and record every intermediate rune along the
+    /// signal path, but do not advance the rotators.
+    pub fn trace_rune(&self, input: Rune) -> RuneTrace {
+        let rotators = self.rotators.rotators();
+
+        let wired = !self.entry_wheel.is_identity();
+
+        let plug_forward = self.plug.map(input);
+        let mut current = self.entry_wheel.map_forward(plug_forward);
+        let entry_forward = if wired { Some(current) } else { None };
+        let mut rotators_forward = [current; 3];
+        for (i, r) in rotators.iter().enumerate() {
+            current = r.map_forward(current);
+            rotators_forward[i] = current;
+        }
+
+        let reflector = self.reflector.map(current);
+        let mut rotators_backward = [reflector; 3];
+        current = reflector;
+        for (i, r) in rotators.iter().enumerate().rev() {
+            current = r.map_backward(current);
+            rotators_backward[i] = current;
+        }
+
+        current = self.entry_wheel.map_backward(current);
+        let entry_backward = if wired { Some(current) } else { None };
+        let plug_backward = self.plug.map(current);
+
+        RuneTrace {
+            input,
+            plug_forward,
+            entry_forward,
+            rotators_forward,
+            reflector,
+            rotators_backward,
+            entry_backward,
+            plug_backward,
+        }
+    }
+
     /// Map the specified input rune to output rune.
     pub fn map_rune(&mut self, input: Rune) -> Rune {
         let ret = self.map_rune_static(input);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(input = %input, output = %ret, "map_rune");
+        self.stats.characters += 1;
+        self.log_rune(input, ret);
         self.advance_rotators();
         ret
     }
 
+    /// Map the specified input runes to output runes and append them to the specified buffer.
+    pub fn map_runes(&mut self, input: &[Rune], output: &mut Vec<Rune>) {
+        output.reserve(input.len());
+        output.extend(input.iter().map(|rune| self.map_rune(*rune)));
+    }
+
     /// Map all runes within the specified string to output rune and returns all output runes as a
     /// string.
     pub fn map_str(&mut self, s: &str) -> String {
         let mut output = String::new();
+        self.map_str_into(s, &mut output);
+        output
+    }
+
+    /// Map all runes within the specified string to output runes and append them to the specified
+    /// string. Characters that are not English letters are skipped.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all,
+        fields(len = s.len())))]
+    pub fn map_str_into(&mut self, s: &str, output: &mut String) {
+        output.reserve(s.len());
         for ch in s.chars() {
-            match Rune::from_char(ch) {
-                Ok(rune) => output.push(self.map_rune(rune).into_char()),
-                _ => (),
-            };
+            if let Ok(rune) = Rune::from_char(ch) {
+                let mapped = self.map_rune(rune);
+                output.push(self.output_char(ch, mapped));
+            }
+        }
+    }
+
+    /// Map the specified characters lazily like `map_str`, yielding the output characters as they
+    /// are mapped. This avoids building intermediate strings, e.g. for generated text or for lines
+    /// read from a file. Characters that are not English letters are skipped.
+    ///
+    /// The rotators advance as the returned iterator is consumed.
+    pub fn map_chars<'a, I>(&'a mut self, chars: I) -> impl Iterator<Item = char> + 'a
+        where I: IntoIterator<Item = char>,
+              I::IntoIter: 'a {
+        chars.into_iter()
+            .filter_map(|ch| Rune::from_char(ch).ok().map(|rune| (ch, rune)))
+            .map(move |(ch, rune)| {
+                let mapped = self.map_rune(rune);
+                self.output_char(ch, mapped)
+            })
+    }
+
+    /// Convert the specified output rune to a character, in lower case if the specified input
+    /// character is lower case and this machine preserves case.
+    fn output_char(&self, input: char, output: Rune) -> char {
+        let output = output.into_char();
+        if self.preserve_case && input.is_ascii_lowercase() {
+            output.to_ascii_lowercase()
+        } else {
+            output
+        }
+    }
+
+    /// Map all runes within the specified string like `map_str`, but fail on the first character
+    /// that is not an English letter instead of skipping it. Nothing is mapped and the rotators do
+    /// not advance if this function fails.
+    pub fn try_map_str(&mut self, s: &str) -> Result<String, MapError> {
+        if let Some((index, ch)) = s.char_indices().find(|(_, ch)| Rune::from_char(*ch).is_err()) {
+            return Err(MapError { index, ch });
+        }
+        Ok(self.map_str(s))
+    }
+
+    /// Map the specified bytes and append them to the specified buffer. ASCII letters are mapped
+    /// like `RotorMachine::map_byte` does, with their case preserved, and the other bytes are
+    /// treated according to the specified policy. Unlike the functions mapping strings, this
+    /// function does not validate UTF-8, which saves time on large ASCII files.
+    ///
+    /// Under `BytePolicy::Reject`, nothing is mapped and the rotators do not advance if this
+    /// function fails.
+    pub fn map_bytes(&mut self, input: &[u8], output: &mut Vec<u8>, policy: BytePolicy)
+        -> Result<(), ByteMapError> {
+        if policy == BytePolicy::Reject {
+            if let Some(index) = input.iter().position(|byte| !byte.is_ascii_alphabetic()) {
+                return Err(ByteMapError { index, byte: input[index] });
+            }
+        }
+
+        output.reserve(input.len());
+        for byte in input {
+            if byte.is_ascii_alphabetic() || policy == BytePolicy::PassThrough {
+                output.push(self.map_byte(*byte));
+            }
+        }
+        Ok(())
+    }
+
+    /// Map all runes within the specified string like `map_str`, and record the window letters of
+    /// the rotators at each keystroke, i.e. before the rotators advance, like an operator's work
+    /// sheet. Each element holds an input character, its output character and the window letters
+    /// as returned by `window`, i.e. from left to right and shifted by the ring settings.
+    /// Characters that are not English letters are skipped.
+    pub fn map_str_annotated(&mut self, s: &str) -> Vec<(char, char, String)> {
+        s.chars()
+            .filter_map(|ch| Rune::from_char(ch).ok().map(|rune| (ch, rune)))
+            .map(|(ch, rune)| {
+                let positions = self.window();
+                let mapped = self.map_rune(rune);
+                (ch, self.output_char(ch, mapped), positions)
+            })
+            .collect()
+    }
+
+    /// Map all runes within the specified string like `map_str`, encrypting chunks of
+    /// `chunk_size` runes in parallel on the global rayon thread pool.
+    ///
+    /// Since the stepping of the rotators does not depend on the input, each chunk is mapped by a
+    /// clone of this machine fast-forwarded to the start of the chunk. This pays off for inputs of
+    /// several megabytes, especially after `precompute`.
+    ///
+    /// Machines that are logging their session or have an observer set by `set_observer` map the
+    /// string sequentially, so that the log records the runes in order and the observer is called
+    /// once for each step.
+    ///
+    /// This function panics if `chunk_size` is 0.
+    #[cfg(feature = "parallel")]
+    pub fn map_str_parallel(&mut self, s: &str, chunk_size: usize) -> String {
+        use rayon::prelude::*;
+
+        assert!(chunk_size > 0, "chunk size should be positive");
+        if self.log.is_some() || self.observer.is_some() {
+            return self.map_str(s);
+        }
+
+        let input: Vec<(char, Rune)> = s.chars()
+            .filter_map(|ch| Rune::from_char(ch).ok().map(|rune| (ch, rune)))
+            .collect();
+        let chunks: Vec<(String, EnigmaStats)> = input.par_chunks(chunk_size)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let mut machine = self.clone();
+                machine.clear_observer();
+                machine.advance_by((i * chunk_size) as u64);
+                machine.reset_stats();
+                let output = chunk.iter()
+                    .map(|(ch, rune)| {
+                        let mapped = machine.map_rune(*rune);
+                        machine.output_char(*ch, mapped)
+                    })
+                    .collect();
+                (output, machine.stats)
+            })
+            .collect();
+
+        self.advance_by(input.len() as u64);
+        let mut output = String::with_capacity(input.len());
+        for (chunk, stats) in chunks {
+            output.push_str(&chunk);
+            self.stats.merge(&stats);
         }
         output
     }
 
     /// Manually advance the rotators by one step.
     pub fn advance_rotators(&mut self) {
-        self.rotators.advance();
+        let old_positions = self.rotators.offsets();
+        self.step_rotators(RotatorGroup::advance);
+        let new_positions = self.rotators.offsets();
+
+        self.stats.steps += 1;
+        for (i, count) in self.stats.rotor_steps.iter_mut().enumerate() {
+            if old_positions[i] != new_positions[i] {
+                *count += 1;
+            }
+        }
+    }
+
+    /// Advance the rotators by the specified number of steps, as if that many runes were mapped.
+    /// The steps are not counted by `stats`.
+    ///
+    /// This takes constant time with the odometer stepping mechanism. Other mechanisms step the
+    /// rotators one key press at a time, up to about `3 * 26 * 26 * 26` key presses regardless of
+    /// the number of steps, as described by `SteppingMechanism::advance_by`.
+    pub fn advance_by(&mut self, steps: u64) {
+        self.step_rotators(|rotators| rotators.advance_by(steps));
+    }
+
+    /// Get the number of key presses after which the state of this machine repeats. Since the plug
+    /// board and the reflector never change, this is the period of the rotator group.
+    pub fn period(&self) -> u64 {
+        self.rotators.period()
+    }
+
+    /// Step the rotators backwards by the specified number of steps, undoing the mapping of that
+    /// many runes. The counters returned by `stats` are not changed.
+    pub fn rewind(&mut self, steps: u64) {
+        // The rotators never pass more states than there are, so only longer rewinds are reduced
+        // by the period, which depends on the stepping mechanism.
+        let steps = if steps > NUM_ROTATOR_STATES as u64 {
+            steps % self.period()
+        } else {
+            steps
+        };
+        for _ in 0..steps {
+            self.step_rotators(RotatorGroup::retreat);
+        }
+    }
+
+    /// Freeze this machine so that its rotators never advance, i.e. every rune is mapped by the
+    /// same substitution. This is useful for analyzing the machine one state at a time.
+    pub fn frozen(self) -> FrozenEnigma {
+        FrozenEnigma { machine: self }
+    }
+
+    /// Get the index of the current state of the rotators within the lookup table.
+    fn rotator_state(&self) -> usize {
+        let n = RUNE_SET_SIZE as usize;
+        self.rotators.rotators().iter().rev()
+            .fold(0, |state, r| state * n + r.offset() as usize)
+    }
+}
+
+impl RotorMachine for Enigma {
+    fn map_rune_static(&self, input: Rune) -> Rune {
+        Enigma::map_rune_static(self, input)
+    }
+
+    fn advance_rotators(&mut self) {
+        Enigma::advance_rotators(self)
+    }
+
+    fn state(&self) -> Vec<u8> {
+        self.rotators.rotators().iter().map(|r| r.offset()).collect()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), InvalidStateError> {
+        match state {
+            &[r0, r1, r2] => {
+                self.rotators.set_offsets([r0, r1, r2]);
+                Ok(())
+            },
+            _ => Err(InvalidStateError),
+        }
+    }
+}
+
+impl PartialEq for Enigma {
+    /// Determine whether two machines have the same components in the same states, show the same
+    /// window letters and map characters alike. The lookup tables, observers, statistics and
+    /// session logs of the machines are ignored.
+    fn eq(&self, other: &Self) -> bool {
+        self.plug == other.plug &&
+            self.entry_wheel == other.entry_wheel &&
+            self.rotators == other.rotators &&
+            self.reflector == other.reflector &&
+            self.preserve_case == other.preserve_case &&
+            self.rings == other.rings
+    }
+}
+
+impl Eq for Enigma { }
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for Enigma {
+    fn zeroize(&mut self) {
+        self.plug.zeroize();
+        self.entry_wheel.zeroize();
+        self.rotators.zeroize();
+        self.reflector.zeroize();
+        self.lookup = None;
+        self.stats = EnigmaStats::default();
+        self.rings.zeroize();
+        self.log = None;
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Enigma {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for Enigma { }
+
+/// Error indicating that a string contains a character that cannot be mapped, as returned by
+/// `Enigma::try_map_str`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MapError {
+    /// The byte index of the character within the string.
+    pub index: usize,
+
+    /// The character.
+    pub ch: char,
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot map character {:?} at byte {}", self.ch, self.index)
+    }
+}
+
+impl Error for MapError { }
+
+/// Error indicating that a string does not consist of 3 window letters, as returned by
+/// `Enigma::set_window`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InvalidWindowError;
+
+impl Display for InvalidWindowError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("window letters must be 3 English letters")
+    }
+}
+
+impl Error for InvalidWindowError { }
+
+/// The treatment of the bytes that are not ASCII letters by `Enigma::map_bytes`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BytePolicy {
+    /// Copy the bytes unchanged to the output.
+    PassThrough,
+
+    /// Drop the bytes from the output.
+    Skip,
+
+    /// Fail on the first such byte.
+    Reject,
+}
+
+/// Error indicating that a byte cannot be mapped under `BytePolicy::Reject`, as returned by
+/// `Enigma::map_bytes`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ByteMapError {
+    /// The index of the byte within the input.
+    pub index: usize,
+
+    /// The byte.
+    pub byte: u8,
+}
+
+impl Display for ByteMapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot map byte 0x{:02x} at index {}", self.byte, self.index)
+    }
+}
+
+impl Error for ByteMapError { }
+
+/// An Enigma machine whose rotators never advance, as created by `Enigma::frozen`.
+///
+/// The machine maps all runes by the substitution of the state in which it was frozen. Unlike a
+/// loop over `Enigma::map_rune_static`, a frozen machine cannot be advanced by mistake, since the
+/// mapping functions take `&self` and `RotorMachine::advance_rotators` does nothing.
+#[derive(Clone, Debug)]
+pub struct FrozenEnigma {
+    machine: Enigma,
+}
+
+impl FrozenEnigma {
+    /// Get the frozen machine.
+    pub fn machine(&self) -> &Enigma {
+        &self.machine
+    }
+
+    /// Unfreeze the machine, which then advances its rotators from the state it was frozen in.
+    pub fn into_inner(self) -> Enigma {
+        self.machine
+    }
+
+    /// Map the specified input rune to output rune.
+    pub fn map_rune(&self, input: Rune) -> Rune {
+        self.machine.map_rune_static(input)
+    }
+
+    /// Map all runes within the specified string to output rune and returns all output runes as a
+    /// string. Characters that are not English letters are skipped.
+    pub fn map_str(&self, s: &str) -> String {
+        s.chars()
+            .filter_map(|ch| Rune::from_char(ch).ok().map(|rune| (ch, rune)))
+            .map(|(ch, rune)| self.machine.output_char(ch, self.map_rune(rune)))
+            .collect()
+    }
+
+    /// Get the substitution performed by this machine.
+    pub fn permutation(&self) -> Permutation {
+        self.machine.permutation()
+    }
+}
+
+impl RotorMachine for FrozenEnigma {
+    fn map_rune_static(&self, input: Rune) -> Rune {
+        self.machine.map_rune_static(input)
+    }
+
+    fn advance_rotators(&mut self) { }
+
+    fn state(&self) -> Vec<u8> {
+        RotorMachine::state(&self.machine)
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), InvalidStateError> {
+        self.machine.restore(state)
+    }
+}
+
+/// The outputs of an Enigma machine at every state of its rotators, indexed by the offsets of the
+/// rotators read as a base-26 number whose least significant digit is the offset of the first
+/// rotator.
+struct LookupTable {
+    outputs: Vec<[u8; RUNE_SET_SIZE as usize]>,
+}
+
+impl Debug for LookupTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LookupTable { .. }")
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for LookupTable {
+    fn drop(&mut self) {
+        self.outputs.zeroize();
+    }
+}
+
+/// The function set by `Enigma::set_observer`.
+#[derive(Clone)]
+struct Observer(Arc<dyn Fn(&StepEvent) + Send + Sync>);
+
+impl Debug for Observer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Observer { .. }")
+    }
+}
+
+/// The numbers of runes mapped and rotor steps taken by an Enigma machine, as returned by
+/// `Enigma::stats`.
+///
+/// Only the runes mapped by `Enigma::map_rune`, or the functions built upon it, and the steps taken
+/// by `Enigma::advance_rotators` are counted. Jumps of the rotators by `advance_by`, `rewind` and
+/// `RotorMachine::restore` are not.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct EnigmaStats {
+    /// The number of runes mapped.
+    pub characters: u64,
+
+    /// The number of steps taken by the rotator group.
+    pub steps: u64,
+
+    /// The number of steps in which each rotator has moved. The i-th element corresponds to the
+    /// i-th rotator in the rotator group, so with the odometer stepping mechanism, the 2nd and the
+    /// 3rd elements count the turnovers of the 1st and the 2nd rotators.
+    pub rotor_steps: [u64; 3],
+}
+
+impl EnigmaStats {
+    /// Add the specified counters to these counters.
+    #[cfg(feature = "parallel")]
+    fn merge(&mut self, other: &EnigmaStats) {
+        self.characters += other.characters;
+        self.steps += other.steps;
+        for (count, other) in self.rotor_steps.iter_mut().zip(&other.rotor_steps) {
+            *count += other;
+        }
+    }
+}
+
+/// A change of the positions of the rotators within an Enigma machine, as reported to the function
+/// set by `Enigma::set_observer`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StepEvent {
+    /// The offsets of the rotators before the change.
+    pub old_positions: [u8; 3],
+
+    /// The offsets of the rotators after the change.
+    pub new_positions: [u8; 3],
+}
+
+impl StepEvent {
+    /// Determine which rotators have moved. The i-th element corresponds to the i-th rotator in
+    /// the rotator group.
+    pub fn stepped(&self) -> [bool; 3] {
+        let mut stepped = [false; 3];
+        for (i, s) in stepped.iter_mut().enumerate() {
+            *s = self.old_positions[i] != self.new_positions[i];
+        }
+        stepped
+    }
+}
+
+/// The signal path of a rune through an Enigma machine, as produced by `Enigma::trace_rune`.
+///
+/// Each field holds the rune produced by the corresponding component.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RuneTrace {
+    /// The input rune.
+    pub input: Rune,
+
+    /// The output of the plug board on the way to the reflector.
+    pub plug_forward: Rune,
+
+    /// The output of the entry wheel on the way to the reflector, or `None` if the entry wheel
+    /// wires the i-th letter to the i-th contact.
+    pub entry_forward: Option<Rune>,
+
+    /// The outputs of the rotators on the way to the reflector. The i-th element is the output of
+    /// the i-th rotator in the rotator group.
+    pub rotators_forward: [Rune; 3],
+
+    /// The output of the reflector.
+    pub reflector: Rune,
+
+    /// The outputs of the rotators on the way back from the reflector. The i-th element is the
+    /// output of the i-th rotator in the rotator group.
+    pub rotators_backward: [Rune; 3],
+
+    /// The output of the entry wheel on the way back from the reflector, or `None` if the entry
+    /// wheel wires the i-th letter to the i-th contact.
+    pub entry_backward: Option<Rune>,
+
+    /// The output of the plug board on the way back from the reflector, which is the output rune.
+    pub plug_backward: Rune,
+}
+
+impl RuneTrace {
+    /// Get the output rune.
+    pub fn output(&self) -> Rune {
+        self.plug_backward
+    }
+}
+
+impl Display for RuneTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> P {}", self.input, self.plug_forward)?;
+        if let Some(r) = self.entry_forward {
+            write!(f, " -> E {}", r)?;
+        }
+        for (i, r) in self.rotators_forward.iter().enumerate() {
+            write!(f, " -> R{} {}", i + 1, r)?;
+        }
+        write!(f, " -> U {}", self.reflector)?;
+        for (i, r) in self.rotators_backward.iter().enumerate().rev() {
+            write!(f, " -> R{} {}", i + 1, r)?;
+        }
+        if let Some(r) = self.entry_backward {
+            write!(f, " -> E {}", r)?;
+        }
+        write!(f, " -> P {}", self.plug_backward)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::math::PermutationBuilder;
+
+    fn create_test_enigma() -> Enigma {
+        let shift = Permutation::first_with_cycle_type(&[RUNE_SET_SIZE as usize]).unwrap();
+
+        let plug = PlugBoard::from_perm(PermutationBuilder::new(RUNE_SET_SIZE)
+            .swap(0, 1)
+            .build()).unwrap();
+        let rotators = RotatorGroup::new([
+            Rotator::new(shift.clone(), 0).unwrap(),
+            Rotator::new(shift.clone(), 0).unwrap(),
+            Rotator::new(shift, 0).unwrap(),
+        ]);
+        let reflector = Reflector::from_perm(Permutation::first_with_cycle_type(&[2; 13]).unwrap())
+            .unwrap();
+
+        Enigma::new(plug, rotators, reflector)
+    }
+
+    mod enigma_tests {
+        use super::*;
+
+        #[test]
+        fn test_rotor_machine() {
+            let mut machine = create_test_enigma();
+            machine.advance_by(1000);
+            assert_eq!(RotorMachine::state(&machine), vec![12, 12, 1]);
+            crate::machines::tests::check_restore(&mut machine);
+        }
+
+        #[test]
+        fn test_map_str_reciprocal() {
+            let mut machine = create_test_enigma();
+            let ciphertext = machine.map_str("Hello, World");
+
+            let mut machine = create_test_enigma();
+            assert_eq!(machine.map_str(&ciphertext), "HELLOWORLD");
+        }
+
+        #[test]
+        fn test_map_runes() {
+            let input: Vec<Rune> = "helloworld".chars().map(|ch| Rune::from_char(ch).unwrap())
+                .collect();
+            let mut machine = create_test_enigma();
+            let mut output = vec![Rune::from_char('x').unwrap()];
+            machine.map_runes(&input, &mut output);
+            machine.map_runes(&[], &mut output);
+
+            let mut expected = create_test_enigma();
+            assert_eq!(output.len(), 11);
+            assert_eq!(output[0], 'x');
+            let output: String = output[1..].iter().map(|rune| rune.into_char()).collect();
+            assert_eq!(output, expected.map_str("helloworld"));
+        }
+
+        #[test]
+        fn test_map_str_into() {
+            let mut machine = create_test_enigma();
+            let mut output = String::from(">");
+            machine.map_str_into("Hello, ", &mut output);
+            machine.map_str_into("World", &mut output);
+
+            let mut expected = create_test_enigma();
+            assert_eq!(output, format!(">{}", expected.map_str("HelloWorld")));
+        }
+
+        #[cfg(feature = "parallel")]
+        #[test]
+        fn test_map_str_parallel() {
+            let input = "The quick brown fox jumps over the lazy dog. ".repeat(100);
+            let mut expected = create_test_enigma();
+            let expected_output = expected.map_str(&input);
+
+            for chunk_size in &[1, 7, 1000, 10000] {
+                let mut machine = create_test_enigma();
+                assert_eq!(machine.map_str_parallel(&input, *chunk_size), expected_output);
+                assert_eq!(machine.rotator_state(), expected.rotator_state());
+                assert_eq!(machine.stats(), expected.stats());
+            }
+        }
+
+        #[cfg(feature = "parallel")]
+        #[test]
+        fn test_map_str_parallel_observer() {
+            use std::sync::atomic::{AtomicUsize, Ordering};
+
+            let count = Arc::new(AtomicUsize::new(0));
+            let mut machine = create_test_enigma();
+            let observed = count.clone();
+            machine.set_observer(move |_| {
+                observed.fetch_add(1, Ordering::Relaxed);
+            });
+
+            let output = machine.map_str_parallel("helloworld", 3);
+            assert_eq!(output, create_test_enigma().map_str("helloworld"));
+            assert_eq!(count.load(Ordering::Relaxed), 10);
+        }
+
+        #[test]
+        fn test_advance_by() {
+            let mut expected = create_test_enigma();
+            for _ in 0..1000 {
+                expected.advance_rotators();
+            }
+
+            let mut machine = create_test_enigma();
+            machine.advance_by(1000);
+            assert_eq!(machine.rotator_state(), expected.rotator_state());
+            assert_eq!(machine.map_str("helloworld"), expected.map_str("helloworld"));
+
+            machine.advance_by(NUM_ROTATOR_STATES as u64 * 7);
+            assert_eq!(machine.rotator_state(), expected.rotator_state());
+        }
+
+        #[test]
+        fn test_period() {
+            let mut machine = create_test_enigma();
+            let period = machine.period();
+            assert_eq!(period, NUM_ROTATOR_STATES as u64);
+
+            let first = machine.map_str("helloworld");
+            machine.advance_by(period - 10);
+            assert_eq!(machine.map_str("helloworld"), first);
+        }
+
+        #[test]
+        fn test_rewind() {
+            let mut machine = create_test_enigma();
+            let ciphertext = machine.map_str("helloworld");
+            machine.rewind(5);
+            assert_eq!(machine.map_str("world"), &ciphertext[5..]);
+
+            machine.rewind(10 + NUM_ROTATOR_STATES as u64 * 3);
+            assert_eq!(machine.rotator_state(), 0);
+            machine.rewind(1);
+            assert_eq!(machine.rotator_state(), NUM_ROTATOR_STATES - 1);
+        }
+
+        #[test]
+        fn test_rewind_lever() {
+            use crate::components::catalog::HISTORICAL_ROTATORS;
+            use crate::components::stepping::SteppingMode;
+
+            let mut machine = create_test_enigma();
+            let rotators = [2, 1, 0].map(|i| HISTORICAL_ROTATORS[i].1.clone());
+            machine.rotators = RotatorGroup::new(rotators);
+            machine.rotators.set_offsets([3, 7, 11]);
+            let mut machine = machine.with_stepping_mode(SteppingMode::WehrmachtLever);
+            assert_eq!(machine.period(), 16900);
+
+            for steps in &[100u64, 16900, 17576, 20000, 16900 * 3 + 5] {
+                machine.advance_by(*steps);
+                machine.rewind(*steps);
+                assert_eq!(machine.rotators().offsets(), [3, 7, 11], "{} steps", steps);
+            }
+        }
+
+        #[test]
+        fn test_stats() {
+            let mut machine = create_test_enigma();
+            machine.map_str("a".repeat(700).as_str());
+            machine.advance_rotators();
+            machine.advance_by(100);
+            machine.rewind(50);
+
+            assert_eq!(machine.stats(), EnigmaStats {
+                characters: 700,
+                steps: 701,
+                rotor_steps: [701, 26, 1],
+            });
+
+            machine.reset_stats();
+            assert_eq!(machine.stats(), EnigmaStats::default());
+        }
+
+        #[test]
+        fn test_eq() {
+            let mut machine = create_test_enigma();
+            machine.precompute();
+            machine.map_str("enigma");
+            machine.rewind(6);
+            assert_eq!(machine, create_test_enigma());
+
+            machine.advance_rotators();
+            assert_ne!(machine, create_test_enigma());
+            assert_ne!(create_test_enigma().with_preserve_case(true), create_test_enigma());
+        }
+
+        #[test]
+        fn test_stepping_mode() {
+            use crate::components::catalog::HISTORICAL_ROTATORS;
+            use crate::components::stepping::SteppingMode;
+
+            let create_machine = |mode: SteppingMode| {
+                let mut machine = create_test_enigma();
+                let rotators = [2, 1, 0].map(|i| HISTORICAL_ROTATORS[i].1.clone());
+                machine.rotators = RotatorGroup::new(rotators);
+                machine.rotators.set_offsets([20, 3, 0]);
+                machine.with_stepping_mode(mode)
+            };
+
+            // The ciphertexts agree until the lever double steps the middle rotator.
+            let plaintext = "A".repeat(40);
+            let odometer = create_machine(SteppingMode::Odometer).map_str(&plaintext);
+            let lever = create_machine(SteppingMode::WehrmachtLever).map_str(&plaintext);
+            assert_eq!(odometer[..2], lever[..2]);
+            assert_ne!(odometer[2..], lever[2..]);
+
+            let mut machine = create_machine(SteppingMode::WehrmachtLever);
+            machine.advance_by(3);
+            assert_eq!(machine.rotators().offsets(), [23, 5, 1]);
+            assert_eq!(create_machine(SteppingMode::Odometer).with_stepping_mode(
+                SteppingMode::WehrmachtLever), create_machine(SteppingMode::WehrmachtLever));
+        }
+
+        #[test]
+        fn test_window() {
+            use crate::testvectors::TEST_VECTORS;
+
+            let mut machine = create_test_enigma();
+            assert_eq!(machine.window(), "AAA");
+            machine.set_window("xyZ").unwrap();
+            assert_eq!(machine.rotators().offsets(), [25, 24, 23]);
+            assert_eq!(machine.window(), "XYZ");
+            assert!(machine.set_window("AB").is_err());
+            assert!(machine.set_window("A1C").is_err());
+            assert_eq!(machine.window(), "XYZ");
+
+            // The window letters account for the ring settings, BUL in the Barbarossa messages.
+            let vector = &TEST_VECTORS[1];
+            let mut machine = vector.create_enigma();
+            assert_eq!(machine.ring_settings(), [1, 20, 11]);
+            // The machine has stepped once from the message key BLA.
+            assert_eq!(machine.window(), "BLB");
+            machine.map_str(&vector.ciphertext[..10]);
+            machine.set_window(vector.message_key).unwrap();
+            machine.advance_rotators();
+            assert_eq!(machine, vector.create_enigma());
+        }
+
+        #[test]
+        fn test_observer() {
+            use std::sync::Mutex;
+
+            let events = Arc::new(Mutex::new(Vec::new()));
+            let mut machine = create_test_enigma();
+            machine.advance_by(25);
+            let recorded = events.clone();
+            machine.set_observer(move |e| recorded.lock().unwrap().push(*e));
+
+            machine.map_str("ab");
+            machine.rewind(1);
+            machine.advance_by(26);
+            machine.clear_observer();
+            machine.advance_rotators();
+
+            let events = events.lock().unwrap();
+            assert_eq!(*events, vec![
+                StepEvent { old_positions: [25, 0, 0], new_positions: [0, 1, 0] },
+                StepEvent { old_positions: [0, 1, 0], new_positions: [1, 1, 0] },
+                StepEvent { old_positions: [1, 1, 0], new_positions: [0, 1, 0] },
+                StepEvent { old_positions: [0, 1, 0], new_positions: [0, 2, 0] },
+            ]);
+            assert_eq!(events[0].stepped(), [true, true, false]);
+            assert_eq!(events[3].stepped(), [false, true, false]);
+        }
+
+        #[test]
+        fn test_precompute() {
+            let mut expected = create_test_enigma();
+            let mut machine = create_test_enigma();
+            assert!(!machine.is_precomputed());
+            machine.precompute();
+            assert!(machine.is_precomputed());
+
+            for _ in 0..NUM_ROTATOR_STATES + 100 {
+                let input = Rune::from_value(machine.rotator_state() as u8 % RUNE_SET_SIZE)
+                    .unwrap();
+                assert_eq!(machine.map_rune(input), expected.map_rune(input));
+            }
+
+            let clone = machine.clone();
+            assert!(clone.is_precomputed());
+            assert_eq!(clone.rotator_state(), expected.rotator_state());
+        }
+
+        #[test]
+        fn test_permutation() {
+            let machine = create_test_enigma();
+            let perm = machine.permutation();
+            for i in 0..RUNE_SET_SIZE {
+                let rune = Rune::from_value(i).unwrap();
+                assert_eq!(perm.map(i), machine.map_rune_static(rune).value());
+            }
+        }
+
+        #[test]
+        fn test_verify_reciprocal() {
+            let mut machine = create_test_enigma();
+            for _ in 0..100 {
+                assert!(machine.verify_reciprocal());
+                machine.advance_rotators();
+            }
+
+            let reflector = unsafe {
+                Reflector::from_perm_unchecked(PermutationBuilder::new(RUNE_SET_SIZE).build())
+            };
+            let machine = Enigma::new(machine.plug.clone(), machine.rotators.clone(), reflector);
+            assert!(!machine.verify_reciprocal());
+        }
+
+        #[test]
+        fn test_is_equivalent() {
+            let machine = create_test_enigma();
+            assert!(machine.is_equivalent(&machine, 1000));
+
+            // The mapping of a rotator wired as a shift does not depend on its offset.
+            let mut other = machine.clone();
+            other.rotators.set_offsets([5, 7, 9]);
+            assert!(machine.is_equivalent(&other, 1000));
+
+            let plug = PlugBoard::from_perm(PermutationBuilder::new(RUNE_SET_SIZE).build())
+                .unwrap();
+            let other = Enigma::new(plug, machine.rotators.clone(), machine.reflector.clone());
+            assert!(!machine.is_equivalent(&other, 1));
+            assert!(machine.is_equivalent(&other, 0));
+        }
+
+        #[test]
+        fn test_trace_rune() {
+            let machine = create_test_enigma();
+            let trace = machine.trace_rune(Rune::from_char('a').unwrap());
+
+            assert_eq!(trace.input, 'a');
+            assert_eq!(trace.plug_forward, 'b');
+            assert_eq!(trace.rotators_forward, ['c', 'd', 'e']);
+            assert_eq!(trace.reflector, 'f');
+            assert_eq!(trace.rotators_backward, ['c', 'd', 'e']);
+            assert_eq!(trace.plug_backward, 'c');
+            assert_eq!(trace.output(), machine.map_rune_static(trace.input));
+        }
+
+        #[test]
+        fn test_entry_wheel() {
+            let machine = create_test_enigma();
+            assert!(machine.entry_wheel().is_identity());
+            let trace = machine.trace_rune(Rune::from_char('a').unwrap());
+            assert_eq!(trace.entry_forward, None);
+            assert_eq!(trace.entry_backward, None);
+
+            let machine = create_test_enigma().with_entry_wheel(EntryWheel::qwertz());
+            let trace = machine.trace_rune(Rune::from_char('a').unwrap());
+            assert_eq!(trace.plug_forward, 'b');
+            assert_eq!(trace.entry_forward, Some(Rune::from_value(22).unwrap()));
+            assert_eq!(trace.output(), machine.map_rune_static(trace.input));
+            assert_ne!(trace.output(), create_test_enigma().map_rune_static(trace.input));
+            assert!(trace.to_string().contains(" -> E W -> R1 "));
+
+            let mut machine = machine;
+            let ciphertext = machine.map_str("helloworld");
+            let mut machine = create_test_enigma().with_entry_wheel(EntryWheel::qwertz());
+            assert_eq!(machine.map_str(&ciphertext), "HELLOWORLD");
+        }
+
+        #[test]
+        fn test_preserve_case() {
+            let mut machine = create_test_enigma().with_preserve_case(true);
+            assert!(machine.preserves_case());
+            let ciphertext = machine.map_str("Hello, World");
+            assert_eq!(ciphertext, create_test_enigma().map_str("Hello, World")
+                .char_indices()
+                .map(|(i, ch)| if i == 0 || i == 5 { ch } else { ch.to_ascii_lowercase() })
+                .collect::<String>());
+
+            let mut machine = create_test_enigma().with_preserve_case(true);
+            assert_eq!(machine.map_str(&ciphertext), "HelloWorld");
+            let expected = create_test_enigma().map_str("aB");
+            let output: String = create_test_enigma().with_preserve_case(true)
+                .map_chars("aB".chars())
+                .collect();
+            assert_eq!(output, expected[..1].to_ascii_lowercase() + &expected[1..]);
+        }
+
+        #[test]
+        fn test_map_chars() {
+            let text = "Hello,\nWorld";
+            let mut machine = create_test_enigma();
+            let output: String = machine.map_chars(text.lines().flat_map(str::chars)).collect();
+            assert_eq!(output, create_test_enigma().map_str(text));
+
+            assert_eq!(machine.map_chars(std::iter::repeat('a')).take(2).count(), 2);
+            assert_eq!(machine.rotators().offsets(), [12, 0, 0]);
+        }
+
+        #[test]
+        fn test_try_map_str() {
+            let mut machine = create_test_enigma();
+            let ciphertext = machine.try_map_str("HelloWorld").unwrap();
+            assert_eq!(ciphertext, create_test_enigma().map_str("HelloWorld"));
+
+            let mut machine = create_test_enigma();
+            let err = machine.try_map_str("Grüße").unwrap_err();
+            assert_eq!(err, MapError { index: 2, ch: 'ü' });
+            assert_eq!(err.to_string(), "cannot map character 'ü' at byte 2");
+            assert_eq!(machine.rotators().offsets(), [0, 0, 0]);
+            assert!(machine.try_map_str("").unwrap().is_empty());
+        }
+
+        #[test]
+        fn test_map_bytes() {
+            let input = b"Hi, \xffyou";
+            let mut expected = create_test_enigma();
+            let expected: Vec<u8> = input.iter().map(|byte| expected.map_byte(*byte)).collect();
+
+            let mut output = vec![b'>'];
+            create_test_enigma().map_bytes(input, &mut output, BytePolicy::PassThrough).unwrap();
+            assert_eq!(output[1..], expected[..]);
+
+            let mut output = Vec::new();
+            create_test_enigma().map_bytes(input, &mut output, BytePolicy::Skip).unwrap();
+            let letters: Vec<u8> = expected.into_iter().filter(u8::is_ascii_alphabetic).collect();
+            assert_eq!(output, letters);
+
+            let mut machine = create_test_enigma();
+            let mut output = Vec::new();
+            let err = machine.map_bytes(input, &mut output, BytePolicy::Reject).unwrap_err();
+            assert_eq!(err, ByteMapError { index: 2, byte: b',' });
+            assert_eq!(err.to_string(), "cannot map byte 0x2c at index 2");
+            assert!(output.is_empty());
+            assert_eq!(machine.rotators().offsets(), [0, 0, 0]);
+            machine.map_bytes(b"Hi", &mut output, BytePolicy::Reject).unwrap();
+            assert_eq!(output, letters[..2]);
+        }
+
+        #[test]
+        fn test_map_str_annotated() {
+            let mut machine = create_test_enigma();
+            machine.advance_by(24);
+            let annotated = machine.clone().map_str_annotated("Hi, you");
+            assert_eq!(annotated.len(), 5);
+
+            let output: String = annotated.iter().map(|(_, out, _)| out).collect();
+            assert_eq!(output, machine.map_str("Hi, you"));
+            assert_eq!(annotated[1].0, 'i');
+            assert_eq!(annotated[0].2, "AAY");
+            assert_eq!(annotated[2].2, "ABA");
+
+            let annotated = machine.with_ring_settings([0, 2, 1]).map_str_annotated("Hi");
+            assert_eq!(annotated[0].2, "ADE");
+        }
+
+        #[test]
+        fn test_trace_rune_display() {
+            let machine = create_test_enigma();
+            let trace = machine.trace_rune(Rune::from_char('a').unwrap());
+            assert_eq!(trace.to_string(),
+                "A -> P B -> R1 C -> R2 D -> R3 E -> U F -> R3 E -> R2 D -> R1 C -> P C");
+        }
+    }
+
+    mod frozen_enigma_tests {
+        use super::*;
+
+        #[test]
+        fn test_map_str() {
+            let mut machine = create_test_enigma();
+            machine.advance_by(30);
+            let frozen = machine.clone().frozen();
+            let a = Rune::from_char('a').unwrap();
+            let output = frozen.map_rune(a);
+            assert_eq!(output, machine.map_rune_static(a));
+            assert_eq!(frozen.map_str("a, a a"), output.into_string().repeat(3));
+            assert_eq!(frozen.permutation(), machine.permutation());
+            assert_eq!(frozen.permutation().max_cycle_len(), 2);
+        }
+
+        #[test]
+        fn test_rotor_machine() {
+            let mut frozen = create_test_enigma().frozen();
+            let ciphertext = RotorMachine::map_str(&mut frozen, "aaaa");
+            assert_eq!(ciphertext, ciphertext[..1].repeat(4));
+            assert_eq!(RotorMachine::state(&frozen), vec![0, 0, 0]);
+
+            frozen.restore(&[1, 2, 3]).unwrap();
+            let mut machine = frozen.into_inner();
+            machine.map_str("a");
+            assert_eq!(machine.rotators().offsets(), [2, 2, 3]);
+        }
     }
 }
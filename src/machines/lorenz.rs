@@ -0,0 +1,393 @@
+//! This module implements the German Lorenz SZ40/42 teleprinter cipher attachment, known as Tunny
+//! at Bletchley Park.
+//!
+//! Unlike the rotor machines, the Lorenz machine works on the 5-bit characters of the ITA2
+//! teleprinter code, given by the [`Ita2`] type. It has 12 pin wheels, given by the [`Wheel`]
+//! type, each pin of which is either active or inactive:
+//! - The 5 chi wheels have 41, 31, 29, 26 and 23 pins. They step at every character.
+//! - The 5 psi wheels have 43, 47, 51, 53 and 59 pins. They step together when the active pin of
+//!   the second motor wheel is active.
+//! - The 2 motor wheels have 61 and 37 pins. The first one steps at every character, and the
+//!   second one steps when the active pin of the first one is active.
+//!
+//! The key of a character has one bit from the active pin of each chi wheel, combined by XOR with
+//! one bit from the active pin of each psi wheel. The key is combined with the character by XOR,
+//! so encryption and decryption are the same operation. The limitations of the later SZ42 models,
+//! which make the second motor wheel depend on the plaintext, are not modeled.
+//!
+//! Characters are written in the notation of Bletchley Park: the letters stand for themselves, and
+//! the codes without a letter are written as `/` (null), `9` (space), `3` (carriage return), `4`
+//! (line feed), `8` (figure shift) and `+` (letter shift).
+//!
+//! [`Ita2`]: struct.Ita2.html
+//! [`Wheel`]: struct.Wheel.html
+//!
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::ops::BitXor;
+
+/// The number of distinct ITA2 characters.
+pub const ITA2_SET_SIZE: u8 = 32;
+
+/// The characters of the ITA2 codes in the notation of Bletchley Park, indexed by code. The first
+/// impulse of a code is its most significant bit.
+const ITA2_CHARS: &[u8; ITA2_SET_SIZE as usize] = b"/T3O9HNM4LRGIPCVEZDBSYFXAWJ8UQK+";
+
+/// The numbers of pins of the chi wheels.
+pub const CHI_SIZES: [usize; 5] = [41, 31, 29, 26, 23];
+
+/// The numbers of pins of the psi wheels.
+pub const PSI_SIZES: [usize; 5] = [43, 47, 51, 53, 59];
+
+/// The numbers of pins of the motor wheels.
+pub const MOTOR_SIZES: [usize; 2] = [61, 37];
+
+/// Error indicating that the value or the character of an ITA2 character is out of range.
+#[derive(Clone, Copy, Debug)]
+pub struct Ita2OutOfRangeError;
+
+impl Display for Ita2OutOfRangeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ITA2 character is out of range")
+    }
+}
+
+impl Error for Ita2OutOfRangeError { }
+
+/// A 5-bit character of the ITA2 teleprinter code.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Ita2 {
+    value: u8,
+}
+
+impl Ita2 {
+    /// Create an ITA2 character from the specified 5-bit code.
+    pub fn from_value(value: u8) -> Result<Self, Ita2OutOfRangeError> {
+        if value >= ITA2_SET_SIZE {
+            return Err(Ita2OutOfRangeError);
+        }
+
+        Ok(Self { value })
+    }
+
+    /// Get the 5-bit code of this character.
+    pub fn value(&self) -> u8 {
+        self.value
+    }
+
+    /// Get the specified impulse of this character, counting from `0`.
+    pub fn bit(&self, impulse: usize) -> bool {
+        self.value >> (4 - impulse) & 1 == 1
+    }
+
+    /// Create an ITA2 character from the specified character in the notation of Bletchley Park.
+    /// Letters are case-insensitive.
+    pub fn from_char(value: char) -> Result<Self, Ita2OutOfRangeError> {
+        let value = value.to_ascii_uppercase();
+        ITA2_CHARS.iter()
+            .position(|ch| *ch as char == value)
+            .map(|value| Self { value: value as u8 })
+            .ok_or(Ita2OutOfRangeError)
+    }
+
+    /// Get the character of this ITA2 character in the notation of Bletchley Park.
+    pub fn into_char(self) -> char {
+        ITA2_CHARS[self.value as usize] as char
+    }
+}
+
+impl BitXor for Ita2 {
+    type Output = Ita2;
+
+    fn bitxor(self, rhs: Ita2) -> Ita2 {
+        Ita2 { value: self.value ^ rhs.value }
+    }
+}
+
+impl Display for Ita2 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.into_char())
+    }
+}
+
+/// Error indicating that the pins of a wheel are invalid.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidWheelError;
+
+impl Display for InvalidWheelError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid wheel pins")
+    }
+}
+
+impl Error for InvalidWheelError { }
+
+/// A pin wheel of the Lorenz machine.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Wheel {
+    pins: Vec<bool>,
+    position: usize,
+}
+
+impl Wheel {
+    /// Create a new wheel with the specified pins, starting at the specified position. A pin is
+    /// active if it is `true`.
+    ///
+    /// The wheel should have at least one pin.
+    pub fn new(pins: Vec<bool>, position: usize) -> Result<Self, InvalidWheelError> {
+        if pins.is_empty() {
+            return Err(InvalidWheelError);
+        }
+
+        let position = position % pins.len();
+        Ok(Self { pins, position })
+    }
+
+    /// Create a new wheel from a pattern of its pins, starting at the specified position. Active
+    /// pins are written as `x` and inactive pins as `.`, e.g. `x..xx.x`.
+    pub fn from_pattern(pattern: &str, position: usize) -> Result<Self, InvalidWheelError> {
+        let pins = pattern.chars()
+            .map(|ch| match ch {
+                'x' | 'X' => Ok(true),
+                '.' => Ok(false),
+                _ => Err(InvalidWheelError),
+            })
+            .collect::<Result<Vec<bool>, InvalidWheelError>>()?;
+        Self::new(pins, position)
+    }
+
+    /// Get the number of pins of this wheel.
+    pub fn len(&self) -> usize {
+        self.pins.len()
+    }
+
+    /// Determine whether this wheel has no pins, which is never the case.
+    pub fn is_empty(&self) -> bool {
+        self.pins.is_empty()
+    }
+
+    /// Get the current position of this wheel.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Determine whether the pin at the current position is active.
+    pub fn pin(&self) -> bool {
+        self.pins[self.position]
+    }
+
+    /// Advance this wheel by one pin.
+    pub fn advance(&mut self) {
+        self.position = (self.position + 1) % self.pins.len();
+    }
+}
+
+/// Error indicating that the number of pins of a wheel of a Lorenz machine is wrong.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidWheelSizeError;
+
+impl Display for InvalidWheelSizeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid number of wheel pins")
+    }
+}
+
+impl Error for InvalidWheelSizeError { }
+
+/// A Lorenz SZ40/42 machine.
+#[derive(Clone, Debug)]
+pub struct Lorenz {
+    chi: [Wheel; 5],
+    psi: [Wheel; 5],
+    motors: [Wheel; 2],
+}
+
+impl Lorenz {
+    /// Create a new Lorenz machine with the specified chi, psi and motor wheels.
+    ///
+    /// The numbers of pins of the wheels should be given by `CHI_SIZES`, `PSI_SIZES` and
+    /// `MOTOR_SIZES`.
+    pub fn new(chi: [Wheel; 5], psi: [Wheel; 5], motors: [Wheel; 2])
+        -> Result<Self, InvalidWheelSizeError> {
+        let sizes_match = |wheels: &[Wheel], sizes: &[usize]| {
+            wheels.iter().zip(sizes).all(|(w, size)| w.len() == *size)
+        };
+        if !sizes_match(&chi, &CHI_SIZES) || !sizes_match(&psi, &PSI_SIZES) ||
+            !sizes_match(&motors, &MOTOR_SIZES) {
+            return Err(InvalidWheelSizeError);
+        }
+
+        Ok(Self { chi, psi, motors })
+    }
+
+    /// Get the chi wheels of this machine.
+    pub fn chi_wheels(&self) -> &[Wheel; 5] {
+        &self.chi
+    }
+
+    /// Get the psi wheels of this machine.
+    pub fn psi_wheels(&self) -> &[Wheel; 5] {
+        &self.psi
+    }
+
+    /// Get the motor wheels of this machine.
+    pub fn motor_wheels(&self) -> &[Wheel; 2] {
+        &self.motors
+    }
+
+    /// Get the key of the next character, but do not advance the wheels.
+    pub fn key(&self) -> Ita2 {
+        let value = self.chi.iter().zip(&self.psi)
+            .fold(0, |value, (chi, psi)| value << 1 | (chi.pin() ^ psi.pin()) as u8);
+        Ita2 { value }
+    }
+
+    /// Map the specified input character to output character.
+    pub fn map(&mut self, input: Ita2) -> Ita2 {
+        let ret = input ^ self.key();
+        self.advance();
+        ret
+    }
+
+    /// Map all ITA2 characters within the specified string to output characters and returns all
+    /// output characters as a string. Characters that are not in the notation of Bletchley Park
+    /// are ignored.
+    pub fn map_str(&mut self, s: &str) -> String {
+        s.chars()
+            .filter_map(|ch| Ita2::from_char(ch).ok())
+            .map(|ch| self.map(ch).into_char())
+            .collect()
+    }
+
+    /// Advance the wheels by one character.
+    pub fn advance(&mut self) {
+        if self.motors[1].pin() {
+            for w in &mut self.psi {
+                w.advance();
+            }
+        }
+        if self.motors[0].pin() {
+            self.motors[1].advance();
+        }
+        self.motors[0].advance();
+
+        for w in &mut self.chi {
+            w.advance();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wheel(size: usize, seed: usize) -> Wheel {
+        let pins = (0..size).map(|i| (i * 7 + seed * 13 + i * i) % 5 < 2).collect();
+        Wheel::new(pins, seed).unwrap()
+    }
+
+    fn create_machine() -> Lorenz {
+        let chi = [wheel(41, 1), wheel(31, 2), wheel(29, 3), wheel(26, 4), wheel(23, 5)];
+        let psi = [wheel(43, 6), wheel(47, 7), wheel(51, 8), wheel(53, 9), wheel(59, 10)];
+        Lorenz::new(chi, psi, [wheel(61, 11), wheel(37, 12)]).unwrap()
+    }
+
+    mod ita2_tests {
+        use super::*;
+
+        #[test]
+        fn test_chars() {
+            for value in 0..ITA2_SET_SIZE {
+                let ch = Ita2::from_value(value).unwrap();
+                assert_eq!(Ita2::from_char(ch.into_char()).unwrap(), ch);
+            }
+            assert_eq!(Ita2::from_char('a').unwrap().value(), 0b11000);
+            assert_eq!(Ita2::from_char('9').unwrap().value(), 0b00100);
+            assert!(Ita2::from_char('1').is_err());
+            assert!(Ita2::from_value(ITA2_SET_SIZE).is_err());
+        }
+
+        #[test]
+        fn test_bit() {
+            let ch = Ita2::from_char('b').unwrap();
+            let bits: Vec<bool> = (0..5).map(|i| ch.bit(i)).collect();
+            assert_eq!(bits, vec![true, false, false, true, true]);
+        }
+
+        #[test]
+        fn test_xor() {
+            let a = Ita2::from_char('a').unwrap();
+            let b = Ita2::from_char('b').unwrap();
+            assert_eq!((a ^ b).value(), 0b01011);
+            assert_eq!((a ^ b).into_char(), 'G');
+            assert_eq!(a ^ b ^ b, a);
+        }
+    }
+
+    mod wheel_tests {
+        use super::*;
+
+        #[test]
+        fn test_from_pattern() {
+            let mut w = Wheel::from_pattern("x..x", 3).unwrap();
+            assert_eq!(w.len(), 4);
+            assert!(w.pin());
+            w.advance();
+            assert_eq!(w.position(), 0);
+            assert!(w.pin());
+            w.advance();
+            assert!(!w.pin());
+
+            assert!(Wheel::from_pattern("x.o", 0).is_err());
+            assert!(Wheel::from_pattern("", 0).is_err());
+        }
+    }
+
+    mod lorenz_tests {
+        use super::*;
+
+        #[test]
+        fn test_invalid_sizes() {
+            let chi = [wheel(41, 1), wheel(31, 2), wheel(29, 3), wheel(26, 4), wheel(24, 5)];
+            let psi = [wheel(43, 6), wheel(47, 7), wheel(51, 8), wheel(53, 9), wheel(59, 10)];
+            assert!(Lorenz::new(chi, psi, [wheel(61, 11), wheel(37, 12)]).is_err());
+        }
+
+        #[test]
+        fn test_reciprocal() {
+            let plaintext = "FROM9OKH9TO9AOK+3+4+".repeat(20);
+            let ciphertext = create_machine().map_str(&plaintext);
+            assert_ne!(ciphertext, plaintext);
+            assert_eq!(create_machine().map_str(&ciphertext), plaintext);
+        }
+
+        #[test]
+        fn test_stepping() {
+            let mut machine = create_machine();
+            for _ in 0..500 {
+                let before = machine.clone();
+                machine.advance();
+
+                for (b, a) in before.chi.iter().zip(&machine.chi) {
+                    assert_eq!(a.position(), (b.position() + 1) % b.len());
+                }
+                let psi_steps = before.motors[1].pin();
+                for (b, a) in before.psi.iter().zip(&machine.psi) {
+                    assert_eq!(a.position() != b.position(), psi_steps);
+                }
+                let mu37_steps = before.motors[0].pin();
+                assert_eq!(machine.motors[1].position() != before.motors[1].position(), mu37_steps);
+            }
+        }
+
+        #[test]
+        fn test_key() {
+            let machine = create_machine();
+            let key = machine.key();
+            for i in 0..5 {
+                assert_eq!(key.bit(i), machine.chi[i].pin() ^ machine.psi[i].pin());
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! This module implements cipher machines other than the Enigma.
+//!
+//! The following machines are available:
+//! - The German Lorenz SZ40/42 teleprinter cipher, in the [`lorenz`] submodule;
+//! - The American SIGABA, in the [`sigaba`] submodule;
+//! - The British Typex, in the [`typex`] submodule.
+//!
+//! # Rotor Machines
+//!
+//! The [`RotorMachine`] trait abstracts over the machines that map runes with rotors: the
+//! [`Enigma`], the Enigma variants in the [`variants`] module, the SIGABA and the Typex. Front-ends
+//! and analysis drivers written against the trait work with all of them:
+//!
+//! ```
+//! # use enigma::machines::RotorMachine;
+//! # use enigma::variants::EnigmaG;
+//! #
+//! fn encrypt_twice<M: RotorMachine>(machine: &mut M, message: &str) -> (String, String) {
+//!     let state = machine.state();
+//!     let first = machine.map_str(message);
+//!     machine.restore(&state).unwrap();
+//!     (first, machine.map_str(message))
+//! }
+//!
+//! let (first, second) = encrypt_twice(&mut EnigmaG::historical([0, 1, 2], [0, 0, 0], 0), "ABC");
+//! assert_eq!(first, second);
+//! ```
+//!
+//! The Lorenz machine works on teleprinter characters instead of runes, so it does not implement
+//! the trait.
+//!
+//! [`RotorMachine`]: trait.RotorMachine.html
+//! [`Enigma`]: ../struct.Enigma.html
+//! [`variants`]: ../variants/index.html
+//! [`lorenz`]: lorenz/index.html
+//! [`sigaba`]: sigaba/index.html
+//! [`typex`]: typex/index.html
+//!
+
+pub mod lorenz;
+pub mod sigaba;
+pub mod typex;
+
+pub use lorenz::Lorenz;
+pub use sigaba::Sigaba;
+pub use typex::Typex;
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::math::Permutation;
+use crate::utils::Rune;
+
+/// Error indicating that a state snapshot does not fit a rotor machine.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidStateError;
+
+impl Display for InvalidStateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid rotor machine state")
+    }
+}
+
+impl Error for InvalidStateError { }
+
+/// A machine that maps runes with rotors, whose mapping changes as its rotors step.
+pub trait RotorMachine {
+    /// Map the specified input rune to output rune, but do not advance the rotors. For machines
+    /// whose encryption and decryption differ, this is the encryption.
+    fn map_rune_static(&self, input: Rune) -> Rune;
+
+    /// Advance the rotors by one step, as if a rune were mapped.
+    fn advance_rotators(&mut self);
+
+    /// Get a snapshot of the state of this machine, e.g. the offsets of all of its rotors that can
+    /// be set by hand, in the order of the signal path. The wirings are not part of the state.
+    fn state(&self) -> Vec<u8>;
+
+    /// Restore the state of this machine from a snapshot returned by `state`.
+    ///
+    /// This function fails if the snapshot has the wrong number of offsets.
+    fn restore(&mut self, state: &[u8]) -> Result<(), InvalidStateError>;
+
+    /// Map the specified input rune to output rune.
+    fn map_rune(&mut self, input: Rune) -> Rune {
+        let ret = self.map_rune_static(input);
+        self.advance_rotators();
+        ret
+    }
+
+    /// Map the specified byte. An ASCII letter is mapped as a rune with its case preserved, and
+    /// other bytes are returned unchanged without advancing the rotors.
+    fn map_byte(&mut self, byte: u8) -> u8 {
+        match Rune::from_ascii(byte) {
+            Ok(rune) if byte.is_ascii_lowercase() =>
+                self.map_rune(rune).into_ascii().to_ascii_lowercase(),
+            Ok(rune) => self.map_rune(rune).into_ascii(),
+            Err(_) => byte,
+        }
+    }
+
+    /// Map all runes within the specified string to output rune and returns all output runes as a
+    /// string.
+    fn map_str(&mut self, s: &str) -> String {
+        s.chars()
+            .filter_map(|ch| Rune::from_char(ch).ok())
+            .map(|rune| self.map_rune(rune).into_char())
+            .collect()
+    }
+}
+
+/// Get the contact opposite to the specified contact of a wheel with `n` contacts when the wheel is
+/// flipped over.
+fn mirror(i: u8, n: u8) -> u8 {
+    (n - i) % n
+}
+
+/// Get the wiring of a wheel with the specified wiring when it is flipped over. The contacts are
+/// mirrored and swap sides, so `i` is mapped to `-perm⁻¹(-i)`.
+fn reverse(perm: &Permutation) -> Permutation {
+    let n = perm.n();
+    let inverse = perm.inverse();
+    Permutation::from_perm((0..n).map(|i| mirror(inverse.map(mirror(i, n)), n)).collect())
+        .unwrap()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Check that the specified machine returns to the same mapping when its state is restored.
+    pub fn check_restore<M: RotorMachine>(machine: &mut M) {
+        let state = machine.state();
+        let ciphertext = RotorMachine::map_str(machine, "ROTORMACHINESTATE");
+        assert_ne!(machine.state(), state);
+
+        machine.restore(&state).unwrap();
+        assert_eq!(machine.state(), state);
+        assert_eq!(RotorMachine::map_str(machine, "ROTORMACHINESTATE"), ciphertext);
+
+        assert!(machine.restore(&state[1..]).is_err());
+    }
+}
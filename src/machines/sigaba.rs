@@ -0,0 +1,397 @@
+//! This module implements the American SIGABA, also known as the ECM Mark II.
+//!
+//! The SIGABA has 3 banks of 5 rotors each:
+//! - The cipher bank encrypts the letters. Unlike the Enigma, there is no reflector: a letter
+//!   passes the cipher rotors once, so encryption and decryption are different operations.
+//! - The control bank decides which cipher rotors step. At each letter, 4 signals enter the control
+//!   bank at the contacts `F`, `G`, `H` and `I`, and its 26 outputs are wired in groups to the 10
+//!   inputs of the index bank as given by `CONTROL_TO_INDEX`. The middle 3 control rotors step like
+//!   an odometer: the third rotator steps at every letter, the fourth one steps when the third one
+//!   rolls over, and the second one steps when the fourth one rolls over. The first and the last
+//!   control rotors never step.
+//! - The index bank consists of 5 smaller rotors with 10 contacts each, which are set by hand and
+//!   never step. Its outputs are wired in pairs to the cipher rotors as given by
+//!   `INDEX_TO_CIPHER`, and each cipher rotor that receives a signal steps. Between 1 and 4 cipher
+//!   rotors step at each letter.
+//!
+//! Any rotor can be inserted reversed. Such rotors are created by the `rotor` function and the
+//! `IndexRotor::new` associate function.
+//!
+//! The cipher has no letter for the space. When encrypting text with the `encrypt_str` associate
+//! function, spaces are replaced by `Z` and `Z` by `X` before encryption, and `decrypt_str` turns
+//! `Z` back into spaces:
+//!
+//! ```
+//! # use enigma::machines::sigaba::{self, IndexRotor, Sigaba};
+//! # use enigma::math::Permutation;
+//! #
+//! # fn wiring(s: &str) -> Permutation {
+//! #     Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+//! # }
+//! # fn digits(s: &str) -> Permutation {
+//! #     Permutation::from_perm(s.bytes().map(|b| b - b'0').collect()).unwrap()
+//! # }
+//! #
+//! let wirings = [
+//!     "ekmflgdqvzntowyhxuspaibrcj", "ajdksiruxblhwtmcqgznpyfvoe", "bdfhjlcprtxvznyeiwgakmusqo",
+//!     "esovpzjayquirhxlnftgkdcmwb", "vzbrgityupsdnhlxawmjqofeck",
+//! ];
+//! let index_wirings = ["7591482630", "3810592764", "4086153297", "3980526174", "6497135280"];
+//!
+//! let create_machine = || {
+//!     let bank = |offsets: [u8; 5]| {
+//!         let rotor = |i: usize| sigaba::rotor(wiring(wirings[i]), offsets[i], i % 2 == 1)
+//!             .unwrap();
+//!         [rotor(0), rotor(1), rotor(2), rotor(3), rotor(4)]
+//!     };
+//!     let index = |i: usize| IndexRotor::new(digits(index_wirings[i]), i as u8, false).unwrap();
+//!     Sigaba::new(bank([0, 4, 8, 15, 16]), bank([23, 42, 7, 10, 3]),
+//!         [index(0), index(1), index(2), index(3), index(4)])
+//! };
+//!
+//! let ciphertext = create_machine().encrypt_str("Attack at dawn");
+//! assert_eq!(create_machine().decrypt_str(&ciphertext), "ATTACK AT DAWN");
+//! ```
+//!
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::components::{InvalidRotatorPermutationError, Rotator};
+use crate::machines::{reverse, InvalidStateError, RotorMachine};
+use crate::math::Permutation;
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// The number of contacts of an index rotor.
+pub const INDEX_SIZE: u8 = 10;
+
+/// The contacts of the control bank that receive a signal at each letter, e.g. `F`, `G`, `H` and
+/// `I`.
+pub const CONTROL_INPUTS: [u8; 4] = [5, 6, 7, 8];
+
+/// The input of the index bank connected to each output of the control bank. Input `0` is not
+/// connected.
+pub const CONTROL_TO_INDEX: [u8; RUNE_SET_SIZE as usize] = [
+    9, 1, 2, 3, 3, 4, 4, 4, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7, 7, 7, 8, 8, 8, 8, 8, 8,
+];
+
+/// The cipher rotor stepped by each output of the index bank.
+pub const INDEX_TO_CIPHER: [usize; INDEX_SIZE as usize] = [0, 4, 4, 3, 3, 2, 2, 1, 1, 0];
+
+/// Create a SIGABA cipher or control rotor from the specified wiring and offset. If `reversed` is
+/// true, the rotor is inserted reversed.
+///
+/// The size of the specified permutation should be `RUNE_SET_SIZE`.
+pub fn rotor(perm: Permutation, offset: u8, reversed: bool)
+    -> Result<Rotator, InvalidRotatorPermutationError> {
+    Ok(Rotator::new(perm, offset)?.with_reversed(reversed))
+}
+
+/// Error indicating that the permutation specified to create an index rotor is invalid.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidIndexRotorPermutationError;
+
+impl Display for InvalidIndexRotorPermutationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid index rotor permutation")
+    }
+}
+
+impl Error for InvalidIndexRotorPermutationError { }
+
+/// An index rotor of the SIGABA, which has 10 contacts and never steps.
+#[derive(Clone, Debug)]
+pub struct IndexRotor {
+    perm: Permutation,
+    offset: u8,
+}
+
+impl IndexRotor {
+    /// Create a new index rotor from the specified wiring and offset. If `reversed` is true, the
+    /// rotor is inserted reversed.
+    ///
+    /// The size of the specified permutation should be `INDEX_SIZE`.
+    pub fn new(perm: Permutation, offset: u8, reversed: bool)
+        -> Result<Self, InvalidIndexRotorPermutationError> {
+        if perm.n() != INDEX_SIZE {
+            return Err(InvalidIndexRotorPermutationError);
+        }
+
+        let perm = if reversed { reverse(&perm) } else { perm };
+        Ok(Self { perm, offset: offset % INDEX_SIZE })
+    }
+
+    /// Get the offset of this index rotor.
+    pub fn offset(&self) -> u8 {
+        self.offset
+    }
+
+    /// Map the specified contact, which should be less than `INDEX_SIZE`.
+    pub fn map(&self, input: u8) -> u8 {
+        let mapped = self.perm.map((input + self.offset) % INDEX_SIZE);
+        (mapped + INDEX_SIZE - self.offset) % INDEX_SIZE
+    }
+}
+
+/// A SIGABA machine.
+#[derive(Clone, Debug)]
+pub struct Sigaba {
+    cipher: [Rotator; 5],
+    control: [Rotator; 5],
+    index: [IndexRotor; 5],
+}
+
+impl Sigaba {
+    /// Create a new SIGABA machine with the specified cipher, control and index rotors. The first
+    /// cipher rotor is the one nearest to the keyboard, and the signals enter the control and the
+    /// index banks at their first rotors.
+    pub fn new(cipher: [Rotator; 5], control: [Rotator; 5], index: [IndexRotor; 5]) -> Self {
+        Self { cipher, control, index }
+    }
+
+    /// Get the cipher rotors of this machine.
+    pub fn cipher_rotors(&self) -> &[Rotator; 5] {
+        &self.cipher
+    }
+
+    /// Get the control rotors of this machine.
+    pub fn control_rotors(&self) -> &[Rotator; 5] {
+        &self.control
+    }
+
+    /// Get the index rotors of this machine.
+    pub fn index_rotors(&self) -> &[IndexRotor; 5] {
+        &self.index
+    }
+
+    /// Get the cipher rotors that step at the next letter, given by their indices.
+    pub fn stepping_rotors(&self) -> Vec<usize> {
+        let mut stepping = [false; 5];
+        for input in &CONTROL_INPUTS {
+            let mut current = unsafe { Rune::from_value_unchecked(*input) };
+            for r in &self.control {
+                current = r.map_forward(current);
+            }
+
+            let mut contact = CONTROL_TO_INDEX[current.value() as usize];
+            for r in &self.index {
+                contact = r.map(contact);
+            }
+            stepping[INDEX_TO_CIPHER[contact as usize]] = true;
+        }
+
+        (0..5).filter(|i| stepping[*i]).collect()
+    }
+
+    /// Encrypt the specified rune, and advance the rotors.
+    pub fn encrypt(&mut self, input: Rune) -> Rune {
+        let mut current = input;
+        for r in &self.cipher {
+            current = r.map_forward(current);
+        }
+        self.advance_rotors();
+        current
+    }
+
+    /// Decrypt the specified rune, and advance the rotors.
+    pub fn decrypt(&mut self, input: Rune) -> Rune {
+        let mut current = input;
+        for r in self.cipher.iter().rev() {
+            current = r.map_backward(current);
+        }
+        self.advance_rotors();
+        current
+    }
+
+    /// Encrypt all runes within the specified string, replacing spaces with `Z` and `Z` with `X`
+    /// first. Other characters that are not runes are ignored.
+    pub fn encrypt_str(&mut self, s: &str) -> String {
+        s.chars()
+            .filter_map(|ch| match ch {
+                ' ' => Some(Rune::from_char('z').unwrap()),
+                'z' | 'Z' => Some(Rune::from_char('x').unwrap()),
+                _ => Rune::from_char(ch).ok(),
+            })
+            .map(|rune| self.encrypt(rune).into_char())
+            .collect()
+    }
+
+    /// Decrypt all runes within the specified string, and replace `Z` in the output with spaces.
+    /// Characters that are not runes are ignored.
+    pub fn decrypt_str(&mut self, s: &str) -> String {
+        s.chars()
+            .filter_map(|ch| Rune::from_char(ch).ok())
+            .map(|rune| match self.decrypt(rune).into_char() {
+                'Z' => ' ',
+                ch => ch,
+            })
+            .collect()
+    }
+
+    /// Advance the rotors by one letter: step the cipher rotors selected by the control and the
+    /// index banks, then step the control rotors.
+    pub fn advance_rotors(&mut self) {
+        for i in self.stepping_rotors() {
+            self.cipher[i].advance();
+        }
+
+        if !self.control[2].advance() && !self.control[3].advance() {
+            self.control[1].advance();
+        }
+    }
+}
+
+impl RotorMachine for Sigaba {
+    /// Encrypt the specified rune, but do not advance the rotors.
+    fn map_rune_static(&self, input: Rune) -> Rune {
+        self.cipher.iter().fold(input, |current, r| r.map_forward(current))
+    }
+
+    fn advance_rotators(&mut self) {
+        self.advance_rotors()
+    }
+
+    /// Get the offsets of the cipher, the control and the index rotors.
+    fn state(&self) -> Vec<u8> {
+        self.cipher.iter().chain(&self.control).map(|r| r.offset())
+            .chain(self.index.iter().map(|r| r.offset))
+            .collect()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), InvalidStateError> {
+        if state.len() != 15 {
+            return Err(InvalidStateError);
+        }
+
+        for (r, offset) in self.cipher.iter_mut().chain(&mut self.control).zip(state) {
+            r.set_offset(*offset);
+        }
+        for (r, offset) in self.index.iter_mut().zip(&state[10..]) {
+            r.offset = offset % INDEX_SIZE;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIRINGS: [&str; 5] = [
+        "ekmflgdqvzntowyhxuspaibrcj", "ajdksiruxblhwtmcqgznpyfvoe", "bdfhjlcprtxvznyeiwgakmusqo",
+        "esovpzjayquirhxlnftgkdcmwb", "vzbrgityupsdnhlxawmjqofeck",
+    ];
+
+    const INDEX_WIRINGS: [&str; 5] = [
+        "7591482630", "3810592764", "4086153297", "3980526174", "6497135280",
+    ];
+
+    fn wiring(s: &str) -> Permutation {
+        Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+    }
+
+    fn digits(s: &str) -> Permutation {
+        Permutation::from_perm(s.bytes().map(|b| b - b'0').collect()).unwrap()
+    }
+
+    fn bank(offsets: [u8; 5], reversed: bool) -> [Rotator; 5] {
+        let rotor = |i: usize| super::rotor(wiring(WIRINGS[i]), offsets[i], reversed).unwrap();
+        [rotor(0), rotor(1), rotor(2), rotor(3), rotor(4)]
+    }
+
+    fn create_machine() -> Sigaba {
+        let index = |i: usize| {
+            IndexRotor::new(digits(INDEX_WIRINGS[i]), i as u8 * 3, i == 2).unwrap()
+        };
+        Sigaba::new(bank([3, 17, 8, 21, 0], false), bank([11, 5, 24, 25, 9], true),
+            [index(0), index(1), index(2), index(3), index(4)])
+    }
+
+    fn offsets(rotors: &[Rotator]) -> Vec<u8> {
+        rotors.iter().map(|r| r.offset()).collect()
+    }
+
+    mod index_rotor_tests {
+        use super::*;
+
+        #[test]
+        fn test_invalid_size() {
+            assert!(IndexRotor::new(Permutation::identity(RUNE_SET_SIZE), 0, false).is_err());
+        }
+
+        #[test]
+        fn test_map() {
+            let r = IndexRotor::new(digits("7591482630"), 2, false).unwrap();
+            assert_eq!(r.offset(), 2);
+            assert_eq!(r.map(0), 7);
+            assert_eq!(r.map(9), 3);
+
+            let r = IndexRotor::new(digits("7591482630"), 0, true).unwrap();
+            let mapped: Vec<u8> = (0..INDEX_SIZE).map(|i| r.map(i)).collect();
+            assert_eq!(mapped, vec![1, 8, 5, 0, 3, 9, 6, 2, 4, 7]);
+        }
+    }
+
+    mod sigaba_tests {
+        use super::*;
+
+        #[test]
+        fn test_decrypt() {
+            let plaintext = "THE SIGABA WAS NEVER BROKEN DURING THE WAR ".repeat(10);
+            let ciphertext = create_machine().encrypt_str(&plaintext);
+            assert_eq!(ciphertext.len(), plaintext.len());
+            assert_eq!(create_machine().decrypt_str(&ciphertext), plaintext);
+        }
+
+        #[test]
+        fn test_rotor_machine() {
+            let mut machine = create_machine();
+            assert_eq!(machine.state(),
+                vec![3, 17, 8, 21, 0, 11, 5, 24, 25, 9, 0, 3, 6, 9, 2]);
+
+            let ciphertext = create_machine().encrypt_str("ROTORMACHINE");
+            assert_eq!(RotorMachine::map_str(&mut machine, "ROTORMACHINE"), ciphertext);
+            crate::machines::tests::check_restore(&mut machine);
+        }
+
+        #[test]
+        fn test_space_and_z() {
+            let ciphertext = create_machine().encrypt_str("ZERO ZULU");
+            assert_eq!(create_machine().decrypt_str(&ciphertext), "XERO XULU");
+        }
+
+        #[test]
+        fn test_cipher_stepping() {
+            let mut machine = create_machine();
+            for _ in 0..1000 {
+                let stepping = machine.stepping_rotors();
+                assert!(!stepping.is_empty() && stepping.len() <= 4);
+
+                let before = offsets(machine.cipher_rotors());
+                machine.advance_rotors();
+                let after = offsets(machine.cipher_rotors());
+                for i in 0..5 {
+                    let expected = if stepping.contains(&i) {
+                        (before[i] + 1) % RUNE_SET_SIZE
+                    } else {
+                        before[i]
+                    };
+                    assert_eq!(after[i], expected);
+                }
+            }
+        }
+
+        #[test]
+        fn test_control_stepping() {
+            let mut machine = create_machine();
+            machine.advance_rotors();
+            assert_eq!(offsets(machine.control_rotors()), vec![11, 5, 25, 25, 9]);
+            machine.advance_rotors();
+            assert_eq!(offsets(machine.control_rotors()), vec![11, 6, 0, 0, 9]);
+            for _ in 0..RUNE_SET_SIZE {
+                machine.advance_rotors();
+            }
+            assert_eq!(offsets(machine.control_rotors()), vec![11, 6, 0, 1, 9]);
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! This module implements the British Typex.
+//!
+//! The Typex is built like the Enigma, with the following differences:
+//! - There are 5 rotors. The 2 rotors nearest to the keyboard are stators: they can be set to any
+//!   position by hand, but they never step. The other 3 rotors are stepped by levers like the
+//!   rotators of the Wehrmacht Enigma.
+//! - The rotors have many notches each, so the middle and the last rotors step more often.
+//! - A rotor can be inserted reversed, e.g. flipped over, which gives it another wiring. Such
+//!   rotors are created by the `rotor` function.
+//! - There is no plug board.
+//!
+//! The wirings of the Typex rotors were never published, so the machine is created from the
+//! wirings given by the user:
+//!
+//! ```
+//! # use enigma::components::Reflector;
+//! # use enigma::machines::typex::{self, Typex};
+//! # use enigma::math::Permutation;
+//! # use enigma::Rune;
+//! #
+//! # fn wiring(s: &str) -> Permutation {
+//! #     Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+//! # }
+//! # fn runes(s: &str) -> Vec<Rune> {
+//! #     s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+//! # }
+//! #
+//! let create_machine = || {
+//!     let rotor = |perm: &str, notches: &str, offset: u8, reversed: bool| {
+//!         typex::rotor(wiring(perm), &runes(notches), offset, reversed).unwrap()
+//!     };
+//!     let stators = [
+//!         rotor("ekmflgdqvzntowyhxuspaibrcj", "", 4, false),
+//!         rotor("ajdksiruxblhwtmcqgznpyfvoe", "", 19, true),
+//!     ];
+//!     let rotators = [
+//!         rotor("bdfhjlcprtxvznyeiwgakmusqo", "aeimquy", 0, false),
+//!         rotor("esovpzjayquirhxlnftgkdcmwb", "bfjnrvz", 7, true),
+//!         rotor("vzbrgityupsdnhlxawmjqofeck", "cgkosw", 11, false),
+//!     ];
+//!     let reflector = Reflector::from_perm(wiring("yruhqsldpxngokmiebfzcwvjat")).unwrap();
+//!     Typex::new(stators, rotators, reflector)
+//! };
+//!
+//! let ciphertext = create_machine().map_str("Typex");
+//! assert_eq!(create_machine().map_str(&ciphertext), "TYPEX");
+//! ```
+//!
+
+use std::convert::TryFrom;
+
+use crate::components::{InvalidRotatorPermutationError, Reflector, Rotator, RotatorGroup};
+use crate::components::stepping::Lever;
+use crate::machines::{InvalidStateError, RotorMachine};
+use crate::math::Permutation;
+use crate::utils::Rune;
+
+/// Create a Typex rotor from the specified wiring, notches and offset.
+///
+/// If `reversed` is true, the rotor is inserted reversed: its contacts are mirrored and swap sides,
+/// so `i` is mapped to `-perm⁻¹(-i)`, and the notch at letter `n` sits at offset `-n`.
+///
+/// The size of the specified permutation should be `RUNE_SET_SIZE`.
+pub fn rotor(perm: Permutation, notches: &[Rune], offset: u8, reversed: bool)
+    -> Result<Rotator, InvalidRotatorPermutationError> {
+    Ok(Rotator::new(perm, offset)?.with_notches(notches).with_reversed(reversed))
+}
+
+/// A Typex machine.
+#[derive(Clone, Debug)]
+pub struct Typex {
+    rotors: RotatorGroup<5>,
+    reflector: Reflector,
+}
+
+impl Typex {
+    /// Create a new Typex machine with the specified stators, rotators and reflector. The first
+    /// stator is the one nearest to the keyboard, and the first rotator is the one nearest to the
+    /// stators. The rotators are stepped by levers, and the notches of the stators are ignored.
+    pub fn new(stators: [Rotator; 2], rotators: [Rotator; 3], reflector: Reflector) -> Self {
+        let [s0, s1] = stators;
+        let [r0, r1, r2] = rotators;
+        let rotors = RotatorGroup::new([s0, s1, r0, r1, r2])
+            .with_stepping(Lever)
+            .with_static(0, true)
+            .with_static(1, true);
+        Self { rotors, reflector }
+    }
+
+    /// Get the 2 stators of this machine.
+    pub fn stators(&self) -> &[Rotator; 2] {
+        <&[Rotator; 2]>::try_from(&self.rotors.rotators()[..2]).unwrap()
+    }
+
+    /// Get the rotor group of this machine. Its first 2 slots hold the stators, which are static.
+    pub fn rotators(&self) -> &RotatorGroup<5> {
+        &self.rotors
+    }
+
+    /// Get the reflector of this machine.
+    pub fn reflector(&self) -> &Reflector {
+        &self.reflector
+    }
+
+    /// Map the specified input rune to output rune, but do not advance the rotators.
+    pub fn map_rune_static(&self, input: Rune) -> Rune {
+        let current = self.rotors.map_forward(input);
+        let current = self.reflector.map(current);
+        self.rotors.map_backward(current)
+    }
+
+    /// Map the specified input rune to output rune.
+    pub fn map_rune(&mut self, input: Rune) -> Rune {
+        let ret = self.map_rune_static(input);
+        self.advance_rotators();
+        ret
+    }
+
+    /// Map all runes within the specified string to output rune and returns all output runes as a
+    /// string.
+    pub fn map_str(&mut self, s: &str) -> String {
+        s.chars()
+            .filter_map(|ch| Rune::from_char(ch).ok())
+            .map(|rune| self.map_rune(rune).into_char())
+            .collect()
+    }
+
+    /// Advance the rotators by one step. The stators never step.
+    pub fn advance_rotators(&mut self) {
+        self.rotors.advance();
+    }
+}
+
+impl RotorMachine for Typex {
+    fn map_rune_static(&self, input: Rune) -> Rune {
+        Typex::map_rune_static(self, input)
+    }
+
+    fn advance_rotators(&mut self) {
+        Typex::advance_rotators(self)
+    }
+
+    fn state(&self) -> Vec<u8> {
+        self.rotors.offsets().to_vec()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), InvalidStateError> {
+        match state {
+            &[s0, s1, r0, r1, r2] => {
+                self.rotors.set_offsets([s0, s1, r0, r1, r2]);
+                Ok(())
+            },
+            _ => Err(InvalidStateError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::utils::RUNE_SET_SIZE;
+
+    fn wiring(s: &str) -> Permutation {
+        Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+    }
+
+    fn runes(s: &str) -> Vec<Rune> {
+        s.chars().map(|ch| Rune::from_char(ch).unwrap()).collect()
+    }
+
+    fn create_machine(reversed: bool) -> Typex {
+        let stators = [
+            rotor(wiring("ekmflgdqvzntowyhxuspaibrcj"), &[], 4, false).unwrap(),
+            rotor(wiring("ajdksiruxblhwtmcqgznpyfvoe"), &[], 19, reversed).unwrap(),
+        ];
+        let rotators = [
+            rotor(wiring("bdfhjlcprtxvznyeiwgakmusqo"), &runes("aeimquy"), 0, false).unwrap(),
+            rotor(wiring("esovpzjayquirhxlnftgkdcmwb"), &runes("bfjnrvz"), 7, reversed).unwrap(),
+            rotor(wiring("vzbrgityupsdnhlxawmjqofeck"), &runes("cgkosw"), 11, false).unwrap(),
+        ];
+        let reflector = Reflector::from_perm(wiring("yruhqsldpxngokmiebfzcwvjat")).unwrap();
+        Typex::new(stators, rotators, reflector)
+    }
+
+    mod rotor_tests {
+        use super::*;
+
+        #[test]
+        fn test_reversed_twice() {
+            let perm = wiring("ekmflgdqvzntowyhxuspaibrcj");
+            let reversed = rotor(perm.clone(), &[], 0, true).unwrap();
+            let perm_reversed = Permutation::from_perm((0..RUNE_SET_SIZE)
+                .map(|i| reversed.map_forward(unsafe { Rune::from_value_unchecked(i) }).value())
+                .collect()).unwrap();
+            assert_ne!(perm_reversed, perm);
+            assert_ne!(perm_reversed, perm.inverse());
+
+            let twice = rotor(perm_reversed, &[], 0, true).unwrap();
+            for i in 0..RUNE_SET_SIZE {
+                let input = Rune::from_value(i).unwrap();
+                assert_eq!(twice.map_forward(input).value(), perm.map(i));
+            }
+        }
+
+        #[test]
+        fn test_reversed_notches() {
+            let mut r = rotor(wiring("ekmflgdqvzntowyhxuspaibrcj"), &runes("bc"), 0, true)
+                .unwrap();
+            let mut notches = Vec::new();
+            for _ in 0..RUNE_SET_SIZE {
+                if r.at_notch() {
+                    notches.push(r.offset());
+                }
+                r.advance();
+            }
+            assert_eq!(notches, vec![24, 25]);
+        }
+
+        #[test]
+        fn test_invalid_size() {
+            assert!(rotor(Permutation::identity(5), &[], 0, true).is_err());
+            assert!(rotor(Permutation::identity(5), &[], 0, false).is_err());
+        }
+    }
+
+    mod typex_tests {
+        use super::*;
+
+        #[test]
+        fn test_reciprocal() {
+            let plaintext = "THETYPEXHASFIVEROTORSANDTWOOFTHEMARESTATORS".repeat(20);
+            for reversed in &[false, true] {
+                let ciphertext = create_machine(*reversed).map_str(&plaintext);
+                assert!(plaintext.chars().zip(ciphertext.chars()).all(|(p, c)| p != c));
+                assert_eq!(create_machine(*reversed).map_str(&ciphertext), plaintext);
+            }
+        }
+
+        #[test]
+        fn test_rotor_machine() {
+            let mut machine = create_machine(true);
+            assert_eq!(machine.state(), vec![4, 19, 0, 7, 11]);
+            crate::machines::tests::check_restore(&mut machine);
+        }
+
+        #[test]
+        fn test_reversed_rotors() {
+            let plaintext = "AAAAAAAAAAAAAAAAAAAA";
+            assert_ne!(create_machine(false).map_str(plaintext),
+                create_machine(true).map_str(plaintext));
+        }
+
+        #[test]
+        fn test_stepping() {
+            let mut machine = create_machine(false);
+            let mut middle_steps = 0;
+            for _ in 0..RUNE_SET_SIZE {
+                let middle = machine.rotators().rotators()[3].offset();
+                machine.advance_rotators();
+                if machine.rotators().rotators()[3].offset() != middle {
+                    middle_steps += 1;
+                }
+            }
+
+            // The stators never step. The 7 notches of the first rotator step the middle rotator 7
+            // times per revolution, and the middle rotator double steps twice at its own notches.
+            let stators: Vec<u8> = machine.stators().iter().map(|s| s.offset()).collect();
+            assert_eq!(stators, vec![4, 19]);
+            assert_eq!(middle_steps, 9);
+        }
+    }
+}
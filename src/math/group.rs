@@ -0,0 +1,292 @@
+//! This module implements utilities for the groups generated by sets of permutations, e.g. to
+//! analyze which permutations the components of a weakened Enigma variant can realize.
+//!
+//! The `generate` function lists all elements of a group, which is only feasible for small
+//! groups, and the `order_of_group` function calculates the number of elements of a group with
+//! the Schreier-Sims algorithm, which is feasible for every group of permutations of runes:
+//!
+//! ```
+//! # use enigma::math::{group, Permutation, PermutationBuilder};
+//! #
+//! let shift = Permutation::first_with_cycle_type(&[26]).unwrap();
+//! assert_eq!(group::generate(&[shift.clone()], 100).unwrap().len(), 26);
+//!
+//! // A cycle of all runes and a transposition of neighbors generate the whole symmetric group.
+//! let swap = PermutationBuilder::new(26).swap(0, 1).build();
+//! assert_eq!(group::order_of_group(&[shift, swap]), (1..=26u128).product());
+//! ```
+//!
+
+use std::collections::{HashSet, VecDeque};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::math::Permutation;
+
+/// Error indicating that a group has more elements than the size cap.
+#[derive(Clone, Copy, Debug)]
+pub struct GroupTooLargeError;
+
+impl Display for GroupTooLargeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("group has more elements than the size cap")
+    }
+}
+
+impl Error for GroupTooLargeError { }
+
+/// Generate all elements of the group generated by the specified permutations, e.g. the closure of
+/// the permutations under composition, starting with the identity. This function fails if the
+/// group has more than `cap` elements.
+///
+/// Returns an empty vector if no permutation is specified, since the size of the identity is
+/// unknown then. This function panics if the permutations have different sizes.
+pub fn generate(generators: &[Permutation], cap: usize)
+    -> Result<Vec<Permutation>, GroupTooLargeError> {
+    let n = match generators.first() {
+        Some(first) => first.n(),
+        None => return Ok(Vec::new()),
+    };
+    assert!(generators.iter().all(|g| g.n() == n), "permutation sizes differ");
+
+    let identity = Permutation::identity(n);
+    let mut seen: HashSet<Vec<u8>> = HashSet::new();
+    seen.insert(identity.perm.to_vec());
+    let mut elements = vec![identity];
+
+    // Every element of a finite group is a product of generators, since the inverse of a
+    // permutation is one of its powers.
+    let mut pending: VecDeque<usize> = VecDeque::from(vec![0]);
+    while let Some(index) = pending.pop_front() {
+        for g in generators {
+            let element = elements[index].compose(g);
+            if seen.insert(element.perm.to_vec()) {
+                if elements.len() == cap {
+                    return Err(GroupTooLargeError);
+                }
+                elements.push(element);
+                pending.push_back(elements.len() - 1);
+            }
+        }
+    }
+
+    Ok(elements)
+}
+
+/// Calculate the number of elements of the group generated by the specified permutations.
+///
+/// This function panics if the permutations have different sizes, or if the order does not fit
+/// into a `u128`, which only happens for permutations of more than 34 elements.
+pub fn order_of_group(generators: &[Permutation]) -> u128 {
+    let chain = StabilizerChain::new(generators);
+    chain.levels.iter()
+        .map(|level| level.orbit().len() as u128)
+        .try_fold(1u128, |order, len| order.checked_mul(len))
+        .expect("group order overflows u128")
+}
+
+/// A base and strong generating set of a group, as built by the Schreier-Sims algorithm. The
+/// order of the group is the product of the lengths of the basic orbits.
+struct StabilizerChain {
+    levels: Vec<Level>,
+}
+
+/// A level of a stabilizer chain. Its generators fix the base points of all previous levels.
+struct Level {
+    /// The base point of this level.
+    base: u8,
+
+    /// The generators of this level.
+    generators: Vec<Permutation>,
+
+    /// For every point within the orbit of the base point, a product of the generators mapping the
+    /// base point to it. Entries are never replaced, so that checked Schreier generators stay
+    /// checked.
+    transversal: Vec<Option<Permutation>>,
+
+    /// The pairs of orbit points and generator indices whose Schreier generators are known to be
+    /// within the group of the next level.
+    checked: HashSet<(u8, usize)>,
+}
+
+impl Level {
+    fn new(n: u8, base: u8) -> Self {
+        let mut transversal = vec![None; n as usize];
+        transversal[base as usize] = Some(Permutation::identity(n));
+        Self { base, generators: Vec::new(), transversal, checked: HashSet::new() }
+    }
+
+    /// Get the points within the orbit of the base point.
+    fn orbit(&self) -> Vec<u8> {
+        (0..self.transversal.len() as u8)
+            .filter(|x| self.transversal[*x as usize].is_some())
+            .collect()
+    }
+
+    /// Add the specified generator and extend the orbit of the base point.
+    fn add_generator(&mut self, g: Permutation) {
+        self.generators.push(g);
+
+        let mut pending = self.orbit();
+        while let Some(x) = pending.pop() {
+            for g in &self.generators {
+                let y = g.map(x);
+                if self.transversal[y as usize].is_none() {
+                    let u = self.transversal[x as usize].as_ref().unwrap().compose(g);
+                    self.transversal[y as usize] = Some(u);
+                    pending.push(y);
+                }
+            }
+        }
+    }
+
+    /// Get the Schreier generator of the specified orbit point and generator index, which fixes
+    /// the base point.
+    fn schreier_generator(&self, x: u8, index: usize) -> Permutation {
+        let s = &self.generators[index];
+        let u = self.transversal[x as usize].as_ref().unwrap();
+        let v = self.transversal[s.map(x) as usize].as_ref().unwrap();
+        u.compose(s).compose(&v.inverse())
+    }
+}
+
+impl StabilizerChain {
+    fn new(generators: &[Permutation]) -> Self {
+        let n = generators.first().map_or(0, |g| g.n());
+        assert!(generators.iter().all(|g| g.n() == n), "permutation sizes differ");
+
+        let generators: Vec<&Permutation> = generators.iter()
+            .filter(|g| !is_identity(g))
+            .collect();
+        let mut chain = Self { levels: Vec::new() };
+        let base = match generators.first() {
+            Some(g) => first_moved_point(g),
+            None => return chain,
+        };
+
+        let mut level = Level::new(n, base);
+        for g in generators {
+            level.add_generator(g.clone());
+        }
+        chain.levels.push(level);
+
+        // Complete the levels from the last one upwards. A level is complete when all its
+        // Schreier generators sift through the levels below it. Whenever one does not, the residue
+        // becomes a generator of the levels between, and these are completed again.
+        let mut i = 0;
+        loop {
+            match chain.find_residue(i) {
+                Some((j, residue)) => {
+                    if j == chain.levels.len() {
+                        chain.levels.push(Level::new(n, first_moved_point(&residue)));
+                    }
+                    for level in &mut chain.levels[i + 1..=j] {
+                        level.add_generator(residue.clone());
+                    }
+                    i = j;
+                },
+                None if i == 0 => break,
+                None => i -= 1,
+            }
+        }
+        chain
+    }
+
+    /// Find a Schreier generator of the specified level that does not sift through the levels
+    /// below it, and return the level at which sifting stopped and the residue.
+    fn find_residue(&mut self, level: usize) -> Option<(usize, Permutation)> {
+        let current = &self.levels[level];
+        let pairs: Vec<(u8, usize)> = current.orbit().into_iter()
+            .flat_map(|x| (0..current.generators.len()).map(move |index| (x, index)))
+            .filter(|pair| !current.checked.contains(pair))
+            .collect();
+
+        for (x, index) in pairs {
+            let schreier_generator = self.levels[level].schreier_generator(x, index);
+            let (j, residue) = self.sift(schreier_generator, level + 1);
+            if !is_identity(&residue) {
+                return Some((j, residue));
+            }
+            self.levels[level].checked.insert((x, index));
+        }
+        None
+    }
+
+    /// Sift the specified permutation through the levels starting from the specified level.
+    /// Returns the level at which sifting stopped and the residue, which is the identity if the
+    /// permutation is within the group of the starting level.
+    fn sift(&self, mut g: Permutation, start: usize) -> (usize, Permutation) {
+        for (i, level) in self.levels.iter().enumerate().skip(start) {
+            match &level.transversal[g.map(level.base) as usize] {
+                Some(u) => g = g.compose(&u.inverse()),
+                None => return (i, g),
+            }
+        }
+        (self.levels.len(), g)
+    }
+}
+
+/// Get the smallest point moved by the specified permutation, which should not be the identity.
+fn first_moved_point(perm: &Permutation) -> u8 {
+    (0..perm.n()).find(|x| perm.map(*x) != *x).unwrap()
+}
+
+fn is_identity(perm: &Permutation) -> bool {
+    perm.perm.iter().enumerate().all(|(i, x)| i == *x as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::math::PermutationBuilder;
+
+    fn perm(images: &[u8]) -> Permutation {
+        Permutation::from_perm(images.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_generate_cyclic() {
+        let shift = Permutation::first_with_cycle_type(&[5]).unwrap();
+        let group = generate(&[shift], 100).unwrap();
+        assert_eq!(group.len(), 5);
+        assert_eq!(group[0], Permutation::identity(5));
+        assert!(generate(&[], 100).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_generate_symmetric() {
+        let group = generate(&[perm(&[1, 2, 3, 0]), perm(&[1, 0, 2, 3])], 100).unwrap();
+        assert_eq!(group.len(), 24);
+        for a in &group {
+            for b in &group {
+                assert!(group.contains(&a.compose(b)));
+            }
+        }
+        assert!(generate(&[perm(&[1, 2, 3, 0]), perm(&[1, 0, 2, 3])], 23).is_err());
+    }
+
+    #[test]
+    fn test_order_of_group() {
+        assert_eq!(order_of_group(&[]), 1);
+        assert_eq!(order_of_group(&[Permutation::identity(26)]), 1);
+        assert_eq!(order_of_group(&[perm(&[1, 2, 3, 0]), perm(&[1, 0, 2, 3])]), 24);
+
+        // The alternating group of 5 elements.
+        assert_eq!(order_of_group(&[perm(&[1, 2, 0, 3, 4]), perm(&[1, 2, 3, 4, 0])]), 60);
+
+        let generators = [perm(&[1, 0, 3, 2, 5, 4]), perm(&[2, 4, 0, 5, 1, 3])];
+        assert_eq!(order_of_group(&generators), generate(&generators, 1000).unwrap().len() as u128);
+
+        let generators = [perm(&[1, 2, 3, 0, 5, 6, 7, 4]), perm(&[4, 1, 2, 3, 0, 5, 6, 7])];
+        assert_eq!(order_of_group(&generators),
+            generate(&generators, 50000).unwrap().len() as u128);
+    }
+
+    #[test]
+    fn test_order_of_symmetric_group() {
+        let shift = Permutation::first_with_cycle_type(&[26]).unwrap();
+        let swap = PermutationBuilder::new(26).swap(0, 1).build();
+        assert_eq!(order_of_group(&[shift, swap]), (1..=26u128).product());
+    }
+}
@@ -0,0 +1,147 @@
+//! This module counts the keys of Enigma machines, e.g. the numbers of rotor orders, plug board
+//! settings and total keys, as big integers.
+//!
+//! ```
+//! # use enigma::math::keyspace::{self, Variant};
+//! #
+//! assert_eq!(keyspace::rotor_orders(5, 3).to_string(), "60");
+//! assert_eq!(keyspace::plug_board_settings(10).to_string(), "150738274937250");
+//!
+//! // The well-known figure for the Enigma I without ring settings.
+//! let variant = Variant { ring_settings: false, ..keyspace::ENIGMA_I };
+//! assert_eq!(variant.total_keys().to_string(), "158962555217826360000");
+//! ```
+//!
+//! This module is available with the `num-bigint` feature.
+//!
+
+use num_bigint::BigUint;
+
+use crate::utils::RUNE_SET_SIZE;
+
+/// A model of an Enigma machine, described by the choices its operators have when setting it up.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Variant {
+    /// The number of rotors available to choose from.
+    pub rotors: u32,
+
+    /// The number of rotor slots within the machine.
+    pub slots: u32,
+
+    /// The number of reflectors available to choose from.
+    pub reflectors: u32,
+
+    /// The number of cables plugged into the plug board.
+    pub plug_cables: u32,
+
+    /// Whether the rings of the rotors can be set.
+    pub ring_settings: bool,
+}
+
+/// The Enigma I of the German Army and Air Force from 1939 on, with 5 rotors, reflector B and 10
+/// plug cables.
+pub const ENIGMA_I: Variant = Variant {
+    rotors: 5,
+    slots: 3,
+    reflectors: 1,
+    plug_cables: 10,
+    ring_settings: true,
+};
+
+/// The Enigma M3 of the German Navy, with 8 rotors, reflectors B and C and 10 plug cables.
+pub const ENIGMA_M3: Variant = Variant {
+    rotors: 8,
+    slots: 3,
+    reflectors: 2,
+    plug_cables: 10,
+    ring_settings: true,
+};
+
+/// The machines of this crate built from 5 rotors, which have no ring settings.
+pub const CRATE_ENIGMA: Variant = Variant {
+    rotors: 5,
+    slots: 3,
+    reflectors: 1,
+    plug_cables: 10,
+    ring_settings: false,
+};
+
+impl Variant {
+    /// Get the number of distinct keys of this variant, e.g. the product of the numbers of rotor
+    /// orders, reflectors, rotor positions, ring settings and plug board settings.
+    ///
+    /// Every ring setting is counted, although the ring of the leftmost rotor never affects the
+    /// stepping and is thus equivalent to its position.
+    pub fn total_keys(&self) -> BigUint {
+        let mut total = rotor_orders(self.rotors, self.slots) * BigUint::from(self.reflectors) *
+            positions(self.slots) * plug_board_settings(self.plug_cables);
+        if self.ring_settings {
+            total *= positions(self.slots);
+        }
+        total
+    }
+}
+
+/// Get `n!`.
+fn factorial(n: u32) -> BigUint {
+    (1..=n).map(BigUint::from).product()
+}
+
+/// Get the number of ordered choices of the specified number of rotors among the available ones.
+/// Returns `0` if there are fewer rotors than slots.
+pub fn rotor_orders(rotors: u32, slots: u32) -> BigUint {
+    if slots > rotors {
+        return BigUint::from(0u32);
+    }
+    factorial(rotors) / factorial(rotors - slots)
+}
+
+/// Get the number of positions of the specified number of rotors.
+pub fn positions(slots: u32) -> BigUint {
+    BigUint::from(RUNE_SET_SIZE).pow(slots)
+}
+
+/// Get the number of ways to plug the specified number of cables into the plug board, e.g.
+/// `26! / ((26 - 2k)! k! 2^k)` for `k` cables. Returns `0` if there are more than 13 cables.
+pub fn plug_board_settings(cables: u32) -> BigUint {
+    let letters = RUNE_SET_SIZE as u32;
+    if 2 * cables > letters {
+        return BigUint::from(0u32);
+    }
+    factorial(letters) /
+        (factorial(letters - 2 * cables) * factorial(cables) * BigUint::from(2u32).pow(cables))
+}
+
+/// Get the number of ways to plug any number of cables, up to the specified number, into the plug
+/// board.
+pub fn plug_board_settings_up_to(max_cables: u32) -> BigUint {
+    (0..=max_cables).map(plug_board_settings).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotor_orders() {
+        assert_eq!(rotor_orders(3, 3), BigUint::from(6u32));
+        assert_eq!(rotor_orders(8, 3), BigUint::from(336u32));
+        assert_eq!(rotor_orders(2, 3), BigUint::from(0u32));
+    }
+
+    #[test]
+    fn test_plug_board_settings() {
+        assert_eq!(plug_board_settings(0), BigUint::from(1u32));
+        assert_eq!(plug_board_settings(1), BigUint::from(325u32));
+        assert_eq!(plug_board_settings(13), BigUint::from(7_905_853_580_625u64));
+        assert_eq!(plug_board_settings(14), BigUint::from(0u32));
+        assert_eq!(plug_board_settings_up_to(13), BigUint::from(532_985_208_200_576u64));
+    }
+
+    #[test]
+    fn test_total_keys() {
+        assert_eq!(CRATE_ENIGMA.total_keys().to_string(), "158962555217826360000");
+        assert_eq!(ENIGMA_I.total_keys(), CRATE_ENIGMA.total_keys() * BigUint::from(17576u32));
+        assert_eq!(ENIGMA_M3.total_keys().to_string(), "31291969749695380357632000");
+    }
+}
@@ -0,0 +1,1119 @@
+//! This module provides math abstractions needed by other modules of this crate.
+//!
+//! Specifically, this module provides the following components:
+//! - Permutations
+//! - Groups generated by permutations, within the [`group`] submodule
+//! - Keyspace sizes, within the [`keyspace`] submodule (with the `num-bigint` feature)
+//!
+//! # Permutations
+//!
+//! A permutation of size `n` is a rearrangement of the array `[0, 1, 2, ..., n-1]`. Compared to the
+//! sorted array, each number is mapped to another number within range and all images of all numbers
+//! are distinct. The sorted array is called identity permutation.
+//!
+//! Permutations are represented by the [`Permutation`] type. To effectively build a permutation,
+//! you can use the [`PermutationBuilder`] type. The [`PermutationBuilder`] type builds a
+//! permutation iteratively by swapping elements within the permutation.
+//!
+//! ## Cycles
+//!
+//! Permutations can be decomposed into
+//! [cycles](https://en.wikipedia.org/wiki/Permutation#Cycle_notation). You can use the
+//! `max_cycle_len` associate function to calculate the length of the longest cycle within a
+//! permutation:
+//!
+//! ```
+//! # use enigma::math::Permutation;
+//! #
+//! let perm = Permutation::from_perm(vec![0u8, 2u8, 3u8, 1u8]).unwrap();
+//! assert_eq!(perm.max_cycle_len(), 3);
+//! ```
+//!
+//! Conversely, permutations with a prescribed cycle type can be created with the
+//! `first_with_cycle_type` and `random_with_cycle_type` associate functions, e.g. the wiring of a
+//! reflector consists of thirteen 2-cycles:
+//!
+//! ```
+//! # use enigma::math::Permutation;
+//! #
+//! let perm = Permutation::first_with_cycle_type(&[2; 13]).unwrap();
+//! assert_eq!(perm.map(0), 1);
+//! assert_eq!(perm.max_cycle_len(), 2);
+//! ```
+//!
+//! ## Conjugation
+//!
+//! The conjugate of a permutation by another permutation has the same cycles with every element
+//! relabeled. Two permutations are conjugate to each other if and only if they have the same cycle
+//! type, which the `are_conjugate` function checks, and the `conjugating_element` function finds a
+//! permutation relabeling one into the other. More generally, conjugation equations, e.g.
+//! equations of the form `a.conjugate_by(&x) == b` for some unknown permutation `x`, are solved by
+//! the `solve_conjugation` function:
+//!
+//! ```
+//! # use enigma::math::{self, Permutation};
+//! #
+//! let a = Permutation::from_perm(vec![1u8, 2u8, 0u8, 3u8]).unwrap();
+//! let b = Permutation::from_perm(vec![0u8, 3u8, 2u8, 1u8]).unwrap();
+//! assert!(!math::are_conjugate(&a, &b));
+//! assert!(math::solve_conjugation(4, &[(&a, &b)], &[], 100).is_empty());
+//!
+//! let c = Permutation::from_perm(vec![2u8, 1u8, 3u8, 0u8]).unwrap();
+//! let g = math::conjugating_element(&a, &c).unwrap();
+//! assert_eq!(a.conjugate_by(&g), c);
+//!
+//! let x = Permutation::from_perm(vec![3u8, 0u8, 1u8, 2u8]).unwrap();
+//! let solutions = math::solve_conjugation(4, &[(&a, &a.conjugate_by(&x))], &[(0, 3)], 100);
+//! assert_eq!(solutions, vec![x]);
+//! ```
+//!
+//! ## Notations
+//!
+//! Permutations can be displayed in one-line notation, in two-line notation or in cycle notation
+//! through the wrappers returned by the `display_one_line`, `display_two_line` and
+//! `display_cycles` associate functions. The elements of permutations of runes can be displayed as
+//! letters, as the literature on the Enigma does:
+//!
+//! ```
+//! # use enigma::math::Permutation;
+//! #
+//! let perm = Permutation::from_perm(vec![1u8, 2u8, 0u8, 3u8]).unwrap();
+//! assert_eq!(perm.display_one_line().to_string(), "1 2 0 3");
+//! assert_eq!(perm.display_cycles().to_string(), "(0 1 2)(3)");
+//! assert_eq!(perm.display_cycles().with_letters().to_string(), "(ABC)(D)");
+//! ```
+//!
+//! [`Permutation`]: struct.Permutation.html
+//! [`PermutationBuilder`]: struct.PermutationBuilder.html
+//! [`group`]: group/index.html
+//! [`keyspace`]: keyspace/index.html
+
+pub mod group;
+#[cfg(feature = "num-bigint")]
+pub mod keyspace;
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Write};
+use std::ops::Index;
+
+#[cfg(feature = "arbitrary")]
+use arbitrary::{Arbitrary, Unstructured};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::utils::RUNE_SET_SIZE;
+
+/// Error that indicates a permutation is invalid.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidPermutationError;
+
+impl Display for InvalidPermutationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid permutation")
+    }
+}
+
+impl Error for InvalidPermutationError { }
+
+/// A permutation.
+///
+/// Permutations are ordered lexicographically by the images of `0, 1, 2, ...`, so that they can be
+/// kept in ordered collections. Permutations of different sizes compare like slices.
+#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(not(feature = "redact-debug"), derive(Debug))]
+pub struct Permutation {
+    perm: Cow<'static, [u8]>,
+}
+
+impl Permutation {
+    /// Create an identity permutation of the specified size.
+    pub fn identity(n: u8) -> Self {
+        let perm = identity_perm(n);
+        unsafe { Self::from_perm_unchecked(perm) }
+    }
+
+    /// Create a new permutation from the specified rearranged array. This function fails if the
+    /// specified array does not form a permutation.
+    pub fn from_perm(perm: Vec<u8>) -> Result<Self, InvalidPermutationError> {
+        if !is_perm(&perm) {
+            return Err(InvalidPermutationError);
+        }
+
+        Ok(Self { perm: Cow::Owned(perm) })
+    }
+
+    /// Create the first permutation with the specified cycle type, e.g. the permutation whose
+    /// cycles are consecutive runs of `0, 1, 2, ..., n-1` with the specified lengths, in order.
+    /// For example, the cycle type `[2; 13]` gives the permutation swapping `0` and `1`, `2` and
+    /// `3` and so on.
+    ///
+    /// This function fails if a cycle length is `0` or the lengths sum up to more than `u8::MAX`.
+    pub fn first_with_cycle_type(cycle_type: &[usize]) -> Result<Self, InvalidPermutationError> {
+        let n = cycle_type_size(cycle_type)?;
+        Ok(from_cycle_runs(&identity_perm(n), cycle_type))
+    }
+
+    /// Create a random permutation with the specified cycle type, drawn uniformly from all
+    /// permutations of that cycle type. The random numbers are drawn from `rng`, which should
+    /// return a uniformly random integer less than the integer passed to it, e.g.
+    /// `|n| rng.gen_range(0..n)` with the `rand` crate.
+    ///
+    /// This function fails if a cycle length is `0` or the lengths sum up to more than `u8::MAX`.
+    pub fn random_with_cycle_type<R>(cycle_type: &[usize], mut rng: R)
+        -> Result<Self, InvalidPermutationError>
+        where R: FnMut(usize) -> usize {
+        let n = cycle_type_size(cycle_type)?;
+
+        // Relabeling the first permutation of the cycle type with a uniformly random shuffle gives
+        // every permutation of the cycle type equally often.
+        let mut elements = identity_perm(n);
+        for i in (1..elements.len()).rev() {
+            elements.swap(i, rng(i + 1));
+        }
+        Ok(from_cycle_runs(&elements, cycle_type))
+    }
+
+    /// Create a permutation that borrows the specified static rearranged array. This function can
+    /// be called in const context, so that permutations can be `const` or `static` items.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the specified array does not form a permutation. In const context, this fails the
+    /// compilation.
+    pub const fn from_static(perm: &'static [u8]) -> Self {
+        assert!(is_perm(perm), "invalid permutation");
+        Self { perm: Cow::Borrowed(perm) }
+    }
+
+    /// Create a new permutation from the specified rearranged array without sanity checks. Usage
+    /// of this function is strongly discouraged and one should use the `from_perm` associate
+    /// function instead.
+    ///
+    /// # Safety
+    ///
+    /// The specified array must be a rearrangement of `[0, 1, 2, ..., n-1]`.
+    pub unsafe fn from_perm_unchecked(perm: Vec<u8>) -> Self {
+        Self { perm: Cow::Owned(perm) }
+    }
+
+    /// Get the size of the permutation, e.g. the number of elements in the permutation.
+    pub fn n(&self) -> u8 {
+        self.perm.len() as u8
+    }
+
+    /// Get the size of the permutation.
+    pub fn len(&self) -> usize {
+        self.perm.len()
+    }
+
+    /// Determine whether this permutation is empty, e.g. its size is 0.
+    pub fn is_empty(&self) -> bool {
+        self.perm.is_empty()
+    }
+
+    /// Get the mapped-to number of the specified element within this permutation.
+    ///
+    /// This function panics if element is greater than or equal to `n()`.
+    pub fn map(&self, element: u8) -> u8 {
+        self.perm[element as usize]
+    }
+
+    /// Calculates the length of the longest cycle in the specified permutation.
+    pub fn max_cycle_len(&self) -> usize {
+        let mut visited: Vec<bool> = vec![false; self.perm.len()];
+        let mut max_len = 0usize;
+
+        for i in 0..self.perm.len() {
+            if visited[i] {
+                continue;
+            }
+
+            let mut current_len = 0usize;
+            let mut j = i;
+            while !visited[j] {
+                visited[j] = true;
+                current_len += 1;
+                j = self.perm[j] as usize;
+            }
+
+            max_len = std::cmp::max(max_len, current_len);
+        }
+
+        max_len
+    }
+
+    /// Calculate the lengths of all cycles within this permutation, sorted in descending order.
+    ///
+    /// Two permutations of the same size have the same cycle type if and only if they are
+    /// conjugate to each other.
+    pub fn cycle_type(&self) -> Vec<usize> {
+        let mut visited: Vec<bool> = vec![false; self.perm.len()];
+        let mut lengths = Vec::new();
+
+        for i in 0..self.perm.len() {
+            if visited[i] {
+                continue;
+            }
+
+            let mut current_len = 0usize;
+            let mut j = i;
+            while !visited[j] {
+                visited[j] = true;
+                current_len += 1;
+                j = self.perm[j] as usize;
+            }
+
+            lengths.push(current_len);
+        }
+
+        lengths.sort_unstable_by(|a, b| b.cmp(a));
+        lengths
+    }
+
+    /// Get the cycles of this permutation, ordered by their smallest elements. Each cycle starts
+    /// with its smallest element and lists the following elements in the order of the mapping.
+    pub fn cycles(&self) -> Vec<Vec<u8>> {
+        let mut visited: Vec<bool> = vec![false; self.perm.len()];
+        let mut cycles = Vec::new();
+
+        for i in 0..self.perm.len() {
+            if visited[i] {
+                continue;
+            }
+
+            let mut cycle = Vec::new();
+            let mut j = i;
+            while !visited[j] {
+                visited[j] = true;
+                cycle.push(j as u8);
+                j = self.perm[j] as usize;
+            }
+            cycles.push(cycle);
+        }
+
+        cycles
+    }
+
+    /// Compose this permutation with the specified permutation. The returned permutation maps each
+    /// element first by this permutation and then by `other`.
+    ///
+    /// This function panics if the two permutations have different sizes.
+    pub fn compose(&self, other: &Permutation) -> Self {
+        assert_eq!(self.perm.len(), other.perm.len(), "permutation sizes differ");
+
+        Self {
+            perm: Cow::Owned(self.perm.iter().map(|x| other.perm[*x as usize]).collect()),
+        }
+    }
+
+    /// Conjugate this permutation by the specified permutation `g`, e.g. compose the inverse of
+    /// `g`, this permutation and `g`. The returned permutation maps `g.map(x)` to
+    /// `g.map(self.map(x))`, thus it has the cycles of this permutation with every element
+    /// relabeled by `g`.
+    ///
+    /// This function panics if the two permutations have different sizes.
+    pub fn conjugate_by(&self, g: &Permutation) -> Self {
+        assert_eq!(self.perm.len(), g.perm.len(), "permutation sizes differ");
+
+        let mut perm = vec![0u8; self.perm.len()];
+        for (x, y) in self.perm.iter().enumerate() {
+            perm[g.perm[x] as usize] = g.perm[*y as usize];
+        }
+
+        Self {
+            perm: Cow::Owned(perm),
+        }
+    }
+
+    /// Calculate the inverse permutation of this permutation.
+    pub fn inverse(&self) -> Self {
+        let mut inv: Vec<u8> = vec![0u8; self.perm.len()];
+
+        for i in 0..self.perm.len() {
+            inv[self.perm[i] as usize] = i as u8;
+        }
+
+        Self {
+            perm: Cow::Owned(inv),
+        }
+    }
+
+    /// Rearrange the elements of the specified slice by this permutation, e.g. move the element at
+    /// index `i` to index `self.map(i)`. For example, permuting the letter counts of a text by the
+    /// permutation of a machine gives the letter counts of its output.
+    ///
+    /// This function uses a buffer of `n()` flags to follow the cycles of this permutation. Use
+    /// `permute_in_place` to avoid the buffer. It panics if the length of the slice is not `n()`.
+    pub fn permute_slice<T>(&self, slice: &mut [T]) {
+        assert_eq!(slice.len(), self.perm.len(), "slice length differs from permutation size");
+
+        let mut visited = vec![false; slice.len()];
+        for start in 0..slice.len() {
+            if !visited[start] {
+                visited[start] = true;
+                let mut j = self.perm[start] as usize;
+                while j != start {
+                    visited[j] = true;
+                    slice.swap(start, j);
+                    j = self.perm[j] as usize;
+                }
+            }
+        }
+    }
+
+    /// Rearrange the elements of the specified slice like `permute_slice`, with `O(1)` extra
+    /// memory. Each cycle is rotated when it is reached at its smallest element, which takes
+    /// quadratic time in the worst case.
+    ///
+    /// This function panics if the length of the slice is not `n()`.
+    pub fn permute_in_place<T>(&self, slice: &mut [T]) {
+        assert_eq!(slice.len(), self.perm.len(), "slice length differs from permutation size");
+
+        for start in 0..slice.len() {
+            let mut j = self.perm[start] as usize;
+            while j > start {
+                j = self.perm[j] as usize;
+            }
+            if j != start {
+                // The cycle has a smaller element and has been rotated already.
+                continue;
+            }
+
+            let mut j = self.perm[start] as usize;
+            while j != start {
+                slice.swap(start, j);
+                j = self.perm[j] as usize;
+            }
+        }
+    }
+
+    /// Get a wrapper that displays this permutation in the specified notation.
+    pub fn display(&self, notation: Notation) -> PermutationDisplay<'_> {
+        PermutationDisplay { perm: self, notation, letters: false }
+    }
+
+    /// Get a wrapper that displays this permutation in one-line notation, e.g. `1 2 0 3`.
+    pub fn display_one_line(&self) -> PermutationDisplay<'_> {
+        self.display(Notation::OneLine)
+    }
+
+    /// Get a wrapper that displays this permutation in two-line notation, e.g. the elements on the
+    /// first line and their images on the second line.
+    pub fn display_two_line(&self) -> PermutationDisplay<'_> {
+        self.display(Notation::TwoLine)
+    }
+
+    /// Get a wrapper that displays this permutation in cycle notation, e.g. `(0 1 2)(3)`.
+    pub fn display_cycles(&self) -> PermutationDisplay<'_> {
+        self.display(Notation::Cycles)
+    }
+}
+
+/// The notations in which permutations can be displayed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Notation {
+    /// The images of all elements in order, e.g. `1 2 0 3`.
+    OneLine,
+
+    /// The elements on the first line and their images below them on the second line.
+    TwoLine,
+
+    /// The cycles of the permutation, including cycles of length 1, e.g. `(0 1 2)(3)`.
+    Cycles,
+}
+
+/// A wrapper that displays a permutation in a selected notation. It is created by the
+/// `Permutation::display` function and its siblings.
+#[derive(Clone, Copy, Debug)]
+pub struct PermutationDisplay<'a> {
+    perm: &'a Permutation,
+    notation: Notation,
+    letters: bool,
+}
+
+impl PermutationDisplay<'_> {
+    /// Display the elements as the letters `A` to `Z` instead of numbers, as the literature on the
+    /// Enigma does, e.g. `(ABC)(D)` instead of `(0 1 2)(3)`. Permutations of more than
+    /// `RUNE_SET_SIZE` elements are still displayed with numbers.
+    pub fn with_letters(mut self) -> Self {
+        self.letters = true;
+        self
+    }
+
+    /// Determine whether elements are displayed as letters.
+    fn uses_letters(&self) -> bool {
+        self.letters && self.perm.len() <= RUNE_SET_SIZE as usize
+    }
+
+    /// Write the specified elements, padded to the width of the largest element.
+    fn write_elements<I>(&self, f: &mut Formatter<'_>, elements: I) -> std::fmt::Result
+        where I: IntoIterator<Item=u8> {
+        if self.uses_letters() {
+            for x in elements {
+                f.write_char((b'A' + x) as char)?;
+            }
+            return Ok(());
+        }
+
+        let width = self.perm.len().saturating_sub(1).to_string().len();
+        for (i, x) in elements.into_iter().enumerate() {
+            if i > 0 {
+                f.write_char(' ')?;
+            }
+            write!(f, "{:>width$}", x, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for PermutationDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.notation {
+            Notation::OneLine => self.write_elements(f, self.perm.perm.iter().copied()),
+            Notation::TwoLine => {
+                self.write_elements(f, 0..self.perm.n())?;
+                f.write_char('\n')?;
+                self.write_elements(f, self.perm.perm.iter().copied())
+            },
+            Notation::Cycles => {
+                // Cycles are not padded, as they are not aligned with each other anyway.
+                for cycle in self.perm.cycles() {
+                    f.write_char('(')?;
+                    if self.uses_letters() {
+                        self.write_elements(f, cycle)?;
+                    } else {
+                        let cycle: Vec<String> = cycle.iter().map(|x| x.to_string()).collect();
+                        f.write_str(&cycle.join(" "))?;
+                    }
+                    f.write_char(')')?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl Index<usize> for Permutation {
+    type Output = u8;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.perm[index]
+    }
+}
+
+impl Index<u8> for Permutation {
+    type Output = u8;
+
+    fn index(&self, index: u8) -> &Self::Output {
+        &self.perm[index as usize]
+    }
+}
+
+impl TryFrom<Vec<u8>> for Permutation {
+    type Error = InvalidPermutationError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        Self::from_perm(value)
+    }
+}
+
+#[cfg(feature = "redact-debug")]
+impl std::fmt::Debug for Permutation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Permutation").field("n", &self.n()).finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for Permutation {
+    fn zeroize(&mut self) {
+        // Static arrays cannot be wiped, but they only hold the wirings of preset components.
+        if let Cow::Owned(perm) = &mut self.perm {
+            perm.zeroize();
+        }
+        self.perm = Cow::Borrowed(&[]);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Permutation {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for Permutation { }
+
+#[cfg(feature = "arbitrary")]
+impl Permutation {
+    /// Generate an arbitrary permutation of the specified size from the specified unstructured
+    /// data, e.g. for property tests of components of a given size.
+    pub fn arbitrary_of_size(u: &mut Unstructured<'_>, n: u8) -> arbitrary::Result<Self> {
+        // Fisher-Yates shuffle of the identity permutation.
+        let mut perm = identity_perm(n);
+        for i in (1..perm.len()).rev() {
+            let j = u.int_in_range(0..=i)?;
+            perm.swap(i, j);
+        }
+        Ok(Self { perm: Cow::Owned(perm) })
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for Permutation {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let n = u.int_in_range(0..=u8::MAX)?;
+        Self::arbitrary_of_size(u, n)
+    }
+}
+
+/// Build permutations with successive swapping operations.
+#[derive(Clone, Debug)]
+pub struct PermutationBuilder {
+    perm: Vec<u8>,
+}
+
+impl PermutationBuilder {
+    /// Create a new permutation builder that builds a permutation of the specified size.
+    pub fn new(n: u8) -> Self {
+        Self {
+            perm: identity_perm(n),
+        }
+    }
+
+    /// Get the size of the permutation under construction.
+    pub fn n(&self) -> u8 {
+        self.perm.len() as u8
+    }
+
+    /// Swap the value at the two specified index in the permutation.
+    pub fn swap(mut self, i: u8, j: u8) -> Self {
+        self.perm.swap(i as usize, j as usize);
+        self
+    }
+
+    /// Get the built permutation.
+    pub fn build(self) -> Permutation {
+        unsafe {
+            Permutation::from_perm_unchecked(self.perm)
+        }
+    }
+}
+
+/// Get the inverse of the specified permutation array. This function can be called in const
+/// context, e.g. to compute the inverse wiring tables of static rotators.
+///
+/// # Panics
+///
+/// Panics if the specified array does not form a permutation.
+pub const fn invert<const N: usize>(perm: &[u8; N]) -> [u8; N] {
+    assert!(is_perm(perm), "invalid permutation");
+    let mut inv = [0u8; N];
+    let mut i = 0;
+    while i < N {
+        inv[perm[i] as usize] = i as u8;
+        i += 1;
+    }
+    inv
+}
+
+/// Determine whether the specified array is a rearrangement of `[0, 1, 2, ..., n-1]` whose size
+/// fits into a `u8`.
+const fn is_perm(perm: &[u8]) -> bool {
+    if perm.len() > u8::MAX as usize {
+        return false;
+    }
+
+    let mut seen = [false; u8::MAX as usize];
+    let mut i = 0;
+    while i < perm.len() {
+        let x = perm[i] as usize;
+        if x >= perm.len() || seen[x] {
+            return false;
+        }
+        seen[x] = true;
+        i += 1;
+    }
+    true
+}
+
+/// Determine whether the two specified permutations are conjugate to each other, e.g. whether
+/// they have the same size and the same cycle type.
+pub fn are_conjugate(a: &Permutation, b: &Permutation) -> bool {
+    a.n() == b.n() && a.cycle_type() == b.cycle_type()
+}
+
+/// Find a permutation `g` such that `a.conjugate_by(&g) == b`. Returns `None` if the two
+/// permutations are not conjugate to each other.
+///
+/// The returned permutation maps the cycles of `a` onto the cycles of `b` of the same lengths.
+/// All such permutations can be found with `solve_conjugation`.
+pub fn conjugating_element(a: &Permutation, b: &Permutation) -> Option<Permutation> {
+    if !are_conjugate(a, b) {
+        return None;
+    }
+
+    let mut a_cycles = a.cycles();
+    let mut b_cycles = b.cycles();
+    a_cycles.sort_by_key(|cycle| cycle.len());
+    b_cycles.sort_by_key(|cycle| cycle.len());
+
+    let mut perm = vec![0u8; a.len()];
+    for (a_cycle, b_cycle) in a_cycles.iter().zip(&b_cycles) {
+        for (x, y) in a_cycle.iter().zip(b_cycle) {
+            perm[*x as usize] = *y;
+        }
+    }
+    Some(unsafe { Permutation::from_perm_unchecked(perm) })
+}
+
+/// Solve a system of conjugation equations, e.g. find the permutations `x` of size `n` such that
+/// `a.conjugate_by(&x) == b` for every pair `(a, b)` of the specified relations and `x.map(i) == j`
+/// for every pair `(i, j)` of the specified hints. At most `limit` solutions are returned.
+///
+/// The solutions of a single relation map each cycle of `a` onto a cycle of `b` of the same
+/// length, so there may be very many of them unless the relations and the hints constrain each
+/// other. This function panics if a permutation of the relations is not of size `n`, or if an
+/// element of the hints is out of range.
+pub fn solve_conjugation(n: u8, relations: &[(&Permutation, &Permutation)], hints: &[(u8, u8)],
+    limit: usize) -> Vec<Permutation> {
+    for (a, b) in relations {
+        assert!(a.n() == n && b.n() == n, "permutation sizes differ");
+    }
+
+    let inverses: Vec<(Permutation, Permutation)> = relations.iter()
+        .map(|(a, b)| (a.inverse(), b.inverse()))
+        .collect();
+    let solver = ConjugationSolver { relations, inverses, limit };
+
+    let mut solutions = Vec::new();
+    let mut map = PartialMap::new(n);
+    if limit > 0 && hints.iter().all(|(i, j)| solver.assign(&mut map, *i, *j)) {
+        solver.search(map, &mut solutions);
+    }
+    solutions
+}
+
+/// A partially determined permutation.
+#[derive(Clone)]
+struct PartialMap {
+    images: Vec<Option<u8>>,
+    preimages: Vec<Option<u8>>,
+}
+
+impl PartialMap {
+    fn new(n: u8) -> Self {
+        Self {
+            images: vec![None; n as usize],
+            preimages: vec![None; n as usize],
+        }
+    }
+}
+
+/// The backtracking search behind `solve_conjugation`.
+struct ConjugationSolver<'a> {
+    relations: &'a [(&'a Permutation, &'a Permutation)],
+    inverses: Vec<(Permutation, Permutation)>,
+    limit: usize,
+}
+
+impl ConjugationSolver<'_> {
+    /// Let the specified partial map send `x` to `y`, together with every assignment implied by
+    /// the relations. Returns `false` if an assignment contradicts the map.
+    fn assign(&self, map: &mut PartialMap, x: u8, y: u8) -> bool {
+        let mut pending = vec![(x, y)];
+        while let Some((x, y)) = pending.pop() {
+            match (map.images[x as usize], map.preimages[y as usize]) {
+                (Some(image), _) if image == y => continue,
+                (None, None) => {
+                    map.images[x as usize] = Some(y);
+                    map.preimages[y as usize] = Some(x);
+                },
+                _ => return false,
+            }
+
+            // If `x` maps `i` to `j`, it maps `a(i)` to `b(j)` for every relation.
+            for ((a, b), (a_inv, b_inv)) in self.relations.iter().zip(&self.inverses) {
+                pending.push((a.map(x), b.map(y)));
+                pending.push((a_inv.map(x), b_inv.map(y)));
+            }
+        }
+        true
+    }
+
+    fn search(&self, map: PartialMap, solutions: &mut Vec<Permutation>) {
+        let x = match map.images.iter().position(|image| image.is_none()) {
+            Some(x) => x as u8,
+            None => {
+                let perm = map.images.into_iter().map(|image| image.unwrap()).collect();
+                solutions.push(unsafe { Permutation::from_perm_unchecked(perm) });
+                return;
+            },
+        };
+
+        for y in 0..map.preimages.len() as u8 {
+            if map.preimages[y as usize].is_some() {
+                continue;
+            }
+
+            let mut next = map.clone();
+            if self.assign(&mut next, x, y) {
+                self.search(next, solutions);
+                if solutions.len() >= self.limit {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Get the size of the permutations of the specified cycle type.
+fn cycle_type_size(cycle_type: &[usize]) -> Result<u8, InvalidPermutationError> {
+    if cycle_type.contains(&0) {
+        return Err(InvalidPermutationError);
+    }
+
+    let n: usize = cycle_type.iter().sum();
+    u8::try_from(n).map_err(|_| InvalidPermutationError)
+}
+
+/// Create the permutation whose cycles are consecutive runs of the specified elements with the
+/// lengths of the specified cycle type.
+fn from_cycle_runs(elements: &[u8], cycle_type: &[usize]) -> Permutation {
+    let mut perm = vec![0u8; elements.len()];
+    let mut start = 0;
+    for len in cycle_type {
+        let run = &elements[start..start + len];
+        for (i, x) in run.iter().enumerate() {
+            perm[*x as usize] = run[(i + 1) % len];
+        }
+        start += len;
+    }
+    unsafe { Permutation::from_perm_unchecked(perm) }
+}
+
+/// Generate an identity permutation of the specified length.
+fn identity_perm(n: u8) -> Vec<u8> {
+    let mut perm = Vec::with_capacity(n as usize);
+    for i in 0..n {
+        perm.push(i);
+    }
+    perm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_perm() {
+        assert_eq!(identity_perm(4), vec![0u8, 1u8, 2u8, 3u8]);
+    }
+
+    mod permutation_tests {
+        use super::*;
+
+        #[test]
+        fn test_from_perm_valid() {
+            let perm = Permutation::from_perm(vec![0u8, 2u8, 1u8, 3u8]).unwrap();
+            assert_eq!(perm, unsafe { Permutation::from_perm_unchecked(vec![0u8, 2u8, 1u8, 3u8]) });
+        }
+
+        #[test]
+        fn test_from_static() {
+            static PERM: Permutation = Permutation::from_static(&[0u8, 2u8, 1u8, 3u8]);
+            assert_eq!(PERM, Permutation::from_perm(vec![0u8, 2u8, 1u8, 3u8]).unwrap());
+            assert_eq!(PERM.inverse(), PERM);
+        }
+
+        #[test]
+        fn test_hash_and_ord() {
+            use std::collections::{BTreeSet, HashSet};
+
+            static PERM: Permutation = Permutation::from_static(&[0u8, 2u8, 1u8, 3u8]);
+            let perms = vec![
+                Permutation::from_perm(vec![1u8, 0u8, 2u8, 3u8]).unwrap(),
+                Permutation::from_perm(vec![0u8, 2u8, 1u8, 3u8]).unwrap(),
+                PERM.clone(),
+                Permutation::identity(4),
+            ];
+
+            // Static and owned permutations of the same images are the same key.
+            let unique: HashSet<Permutation> = perms.iter().cloned().collect();
+            assert_eq!(unique.len(), 3);
+
+            let sorted: Vec<Permutation> = perms.into_iter().collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            assert_eq!(sorted, vec![Permutation::identity(4), PERM.clone(),
+                Permutation::from_perm(vec![1u8, 0u8, 2u8, 3u8]).unwrap()]);
+        }
+
+        #[test]
+        #[should_panic]
+        fn test_from_static_invalid() {
+            Permutation::from_static(&[0u8, 2u8, 3u8, 2u8]);
+        }
+
+        #[test]
+        fn test_invert() {
+            const INV: [u8; 4] = invert(&[2u8, 1u8, 3u8, 0u8]);
+            assert_eq!(INV, [3u8, 1u8, 0u8, 2u8]);
+        }
+
+        #[test]
+        fn test_from_perm_invalid_range_err() {
+            assert!(Permutation::from_perm(vec![0u8, 2u8, 4u8, 3u8]).is_err());
+        }
+
+        #[test]
+        fn test_from_perm_invalid_duplicate() {
+            assert!(Permutation::from_perm(vec![0u8, 2u8, 3u8, 2u8]).is_err());
+        }
+
+        #[test]
+        fn test_map() {
+            let perm = Permutation::from_perm(vec![0u8, 2u8, 1u8, 3u8]).unwrap();
+            assert_eq!(perm.map(0), 0);
+            assert_eq!(perm.map(1), 2);
+            assert_eq!(perm.map(2), 1);
+            assert_eq!(perm.map(3), 3);
+        }
+
+        #[test]
+        fn test_max_cycle_len() {
+            let perm = Permutation::from_perm(vec![0u8, 1u8, 2u8, 3u8]).unwrap();
+            assert_eq!(perm.max_cycle_len(), 1);
+
+            let perm = Permutation::from_perm(vec![0u8, 2u8, 1u8, 3u8]).unwrap();
+            assert_eq!(perm.max_cycle_len(), 2);
+
+            let perm = Permutation::from_perm(vec![1u8, 2u8, 3u8, 0u8]).unwrap();
+            assert_eq!(perm.max_cycle_len(), 4);
+        }
+
+        #[test]
+        fn test_cycle_type() {
+            let perm = Permutation::from_perm(vec![1u8, 2u8, 0u8, 3u8, 5u8, 4u8]).unwrap();
+            assert_eq!(perm.cycle_type(), vec![3, 2, 1]);
+        }
+
+        #[test]
+        fn test_first_with_cycle_type() {
+            let perm = Permutation::first_with_cycle_type(&[2, 3, 1]).unwrap();
+            assert_eq!(perm, Permutation::from_perm(vec![1u8, 0u8, 3u8, 4u8, 2u8, 5u8]).unwrap());
+
+            let perm = Permutation::first_with_cycle_type(&[2; 13]).unwrap();
+            assert_eq!(perm.n(), 26);
+            assert_eq!(perm.cycle_type(), vec![2; 13]);
+            assert_eq!(Permutation::first_with_cycle_type(&[]).unwrap().n(), 0);
+
+            assert!(Permutation::first_with_cycle_type(&[2, 0]).is_err());
+            assert!(Permutation::first_with_cycle_type(&[200, 100]).is_err());
+        }
+
+        #[test]
+        fn test_random_with_cycle_type() {
+            let mut seed = 12345u32;
+            let mut rng = |n: usize| {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                (seed >> 16) as usize % n
+            };
+
+            let perm = Permutation::random_with_cycle_type(&[13, 13], &mut rng).unwrap();
+            assert_eq!(perm.cycle_type(), vec![13, 13]);
+            assert_ne!(perm, Permutation::first_with_cycle_type(&[13, 13]).unwrap());
+
+            let perm = Permutation::random_with_cycle_type(&[1, 4, 2], &mut rng).unwrap();
+            assert_eq!(perm.cycle_type(), vec![4, 2, 1]);
+            assert!(Permutation::random_with_cycle_type(&[0], &mut rng).is_err());
+        }
+
+        #[test]
+        fn test_cycles() {
+            let perm = Permutation::from_perm(vec![1u8, 2u8, 0u8, 3u8, 5u8, 4u8]).unwrap();
+            assert_eq!(perm.cycles(), vec![vec![0u8, 1u8, 2u8], vec![3u8], vec![4u8, 5u8]]);
+            assert!(Permutation::identity(0).cycles().is_empty());
+        }
+
+        #[test]
+        fn test_compose() {
+            let lhs = Permutation::from_perm(vec![1u8, 2u8, 0u8]).unwrap();
+            let rhs = Permutation::from_perm(vec![0u8, 2u8, 1u8]).unwrap();
+            assert_eq!(lhs.compose(&rhs), Permutation::from_perm(vec![2u8, 1u8, 0u8]).unwrap());
+            assert_eq!(rhs.compose(&lhs), Permutation::from_perm(vec![1u8, 0u8, 2u8]).unwrap());
+        }
+
+        #[test]
+        fn test_conjugate() {
+            let perm = Permutation::from_perm(vec![1u8, 2u8, 0u8, 3u8]).unwrap();
+            let by = Permutation::from_perm(vec![3u8, 0u8, 1u8, 2u8]).unwrap();
+            let conjugate = perm.conjugate_by(&by);
+            assert_eq!(conjugate, by.inverse().compose(&perm).compose(&by));
+            assert_eq!(conjugate.map(3), 0);
+            assert_eq!(conjugate.cycle_type(), perm.cycle_type());
+            assert_eq!(perm.conjugate_by(&Permutation::identity(4)), perm);
+        }
+
+        #[test]
+        fn test_inverse() {
+            let perm = Permutation::from_perm(vec![2u8, 1u8, 3u8, 0u8]).unwrap();
+            let inv_perm = perm.inverse();
+            assert_eq!(inv_perm, Permutation::from_perm(vec![3u8, 1u8, 0u8, 2u8]).unwrap());
+        }
+
+        #[test]
+        fn test_permute_slice() {
+            let perm = Permutation::from_perm(vec![1u8, 2u8, 0u8, 4u8, 3u8, 5u8]).unwrap();
+            let mut slice = ['a', 'b', 'c', 'd', 'e', 'f'];
+            perm.permute_slice(&mut slice);
+            assert_eq!(slice, ['c', 'a', 'b', 'e', 'd', 'f']);
+            for (i, x) in ['a', 'b', 'c', 'd', 'e', 'f'].iter().enumerate() {
+                assert_eq!(slice[perm.map(i as u8) as usize], *x);
+            }
+
+            perm.inverse().permute_slice(&mut slice);
+            assert_eq!(slice, ['a', 'b', 'c', 'd', 'e', 'f']);
+        }
+
+        #[test]
+        fn test_permute_in_place() {
+            let perm = Permutation::from_perm(vec![3u8, 0u8, 4u8, 1u8, 2u8, 5u8, 7u8, 6u8])
+                .unwrap();
+            let mut expected: Vec<String> = (0..8).map(|i| i.to_string()).collect();
+            let mut slice = expected.clone();
+            perm.permute_slice(&mut expected);
+            perm.permute_in_place(&mut slice);
+            assert_eq!(slice, expected);
+        }
+
+        #[test]
+        #[should_panic]
+        fn test_permute_slice_len_mismatch() {
+            Permutation::identity(3).permute_slice(&mut [0, 1]);
+        }
+
+        #[test]
+        fn test_display() {
+            let perm = Permutation::from_perm(vec![1u8, 2u8, 0u8, 3u8]).unwrap();
+            assert_eq!(perm.display_one_line().to_string(), "1 2 0 3");
+            assert_eq!(perm.display_two_line().to_string(), "0 1 2 3\n1 2 0 3");
+            assert_eq!(perm.display_cycles().to_string(), "(0 1 2)(3)");
+            assert_eq!(perm.display(Notation::Cycles).with_letters().to_string(), "(ABC)(D)");
+            assert_eq!(perm.display_two_line().with_letters().to_string(), "ABCD\nBCAD");
+            assert_eq!(Permutation::identity(0).display_cycles().to_string(), "");
+        }
+
+        #[test]
+        fn test_display_padding() {
+            let perm = Permutation::first_with_cycle_type(&[12]).unwrap();
+            assert!(perm.display_two_line().to_string().starts_with(" 0  1  2"));
+            assert!(perm.display_one_line().to_string().ends_with("10 11  0"));
+            assert!(perm.display_cycles().to_string().starts_with("(0 1 2"));
+
+            let perm = Permutation::identity(30);
+            assert!(perm.display_one_line().with_letters().to_string().starts_with(" 0  1"));
+        }
+
+        #[cfg(feature = "arbitrary")]
+        #[test]
+        fn test_arbitrary() {
+            let data: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+            let mut u = Unstructured::new(&data);
+            let perm = Permutation::arbitrary(&mut u).unwrap();
+            assert!(Permutation::from_perm(perm.perm.to_vec()).is_ok());
+
+            let perm = Permutation::arbitrary_of_size(&mut u, 26).unwrap();
+            assert_eq!(perm.n(), 26);
+            assert_ne!(perm, Permutation::identity(26));
+            assert!(Permutation::from_perm(perm.perm.to_vec()).is_ok());
+        }
+
+        #[cfg(feature = "zeroize")]
+        #[test]
+        fn test_zeroize() {
+            let mut perm = Permutation::from_perm(vec![2u8, 1u8, 3u8, 0u8]).unwrap();
+            perm.zeroize();
+            assert_eq!(perm.n(), 0);
+        }
+    }
+
+    mod conjugation_tests {
+        use super::*;
+
+        #[test]
+        fn test_solve_conjugation() {
+            let a = Permutation::from_perm(vec![1u8, 2u8, 3u8, 4u8, 0u8]).unwrap();
+            let x = Permutation::from_perm(vec![2u8, 4u8, 1u8, 0u8, 3u8]).unwrap();
+            let b = a.conjugate_by(&x);
+
+            // The solutions of a single relation are `x` composed with the powers of `b`.
+            let solutions = solve_conjugation(5, &[(&a, &b)], &[], 100);
+            assert_eq!(solutions.len(), 5);
+            assert!(solutions.contains(&x));
+            assert!(solutions.iter().all(|s| a.conjugate_by(s) == b));
+
+            let solutions = solve_conjugation(5, &[(&a, &b)], &[(0, x.map(0))], 100);
+            assert_eq!(solutions, vec![x.clone()]);
+            assert_eq!(solve_conjugation(5, &[(&a, &b)], &[], 2).len(), 2);
+        }
+
+        #[test]
+        fn test_are_conjugate() {
+            let a = Permutation::from_perm(vec![1u8, 2u8, 0u8, 3u8]).unwrap();
+            let b = Permutation::from_perm(vec![0u8, 3u8, 1u8, 2u8]).unwrap();
+            assert!(are_conjugate(&a, &b));
+            assert!(!are_conjugate(&a, &Permutation::identity(4)));
+            assert!(!are_conjugate(&Permutation::identity(3), &Permutation::identity(4)));
+        }
+
+        #[test]
+        fn test_conjugating_element() {
+            let a = Permutation::from_perm(vec![1u8, 0u8, 3u8, 4u8, 2u8, 5u8]).unwrap();
+            let b = Permutation::from_perm(vec![3u8, 4u8, 1u8, 0u8, 2u8, 5u8]).unwrap();
+            let g = conjugating_element(&a, &b).unwrap();
+            assert_eq!(a.conjugate_by(&g), b);
+
+            assert_eq!(conjugating_element(&a, &a).map(|g| a.conjugate_by(&g)), Some(a.clone()));
+            assert!(conjugating_element(&a, &Permutation::identity(6)).is_none());
+        }
+
+        #[test]
+        fn test_solve_conjugation_no_solution() {
+            let a = Permutation::from_perm(vec![1u8, 2u8, 0u8, 3u8]).unwrap();
+            let b = Permutation::from_perm(vec![1u8, 0u8, 3u8, 2u8]).unwrap();
+            assert!(solve_conjugation(4, &[(&a, &b)], &[], 100).is_empty());
+            assert!(solve_conjugation(4, &[(&a, &a)], &[(0, 3)], 100).is_empty());
+        }
+
+        #[test]
+        fn test_solve_conjugation_hints_only() {
+            let solutions = solve_conjugation(3, &[], &[(0, 1)], 100);
+            assert_eq!(solutions.len(), 2);
+            assert!(solutions.iter().all(|s| s.map(0) == 1));
+            assert_eq!(solve_conjugation(3, &[], &[], 100).len(), 6);
+        }
+    }
+
+    mod permutation_builder_tests {
+        use super::*;
+
+        #[test]
+        fn test_initial_identity() {
+            let perm = PermutationBuilder::new(4).build();
+            assert_eq!(perm, Permutation::identity(4));
+        }
+
+        #[test]
+        fn test_swap() {
+            let perm = PermutationBuilder::new(4)
+                .swap(1, 2)
+                .swap(2, 3)
+                .build();
+            assert_eq!(perm, Permutation::from_perm(vec![0u8, 2u8, 3u8, 1u8]).unwrap());
+        }
+    }
+}
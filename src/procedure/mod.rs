@@ -0,0 +1,10 @@
+//! This module implements the operating procedures around the Enigma machine, e.g. how messages
+//! were laid out for transmission.
+//!
+//! The following procedures are available:
+//! - The transmission header of a message, in the [`preamble`] submodule.
+//!
+//! [`preamble`]: preamble/index.html
+//!
+
+pub mod preamble;
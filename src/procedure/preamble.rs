@@ -0,0 +1,218 @@
+//! This module formats and parses the transmission header (preamble) of Enigma messages.
+//!
+//! Following the procedure of the German Army from 1940, the preamble of a message is sent in the
+//! clear before the ciphertext and holds the time of origin, the number of letters of the
+//! ciphertext and the indicator groups: the ground setting chosen by the operator, and the message
+//! key enciphered at that ground setting. It may also carry the discriminant (Kenngruppe), which
+//! identifies the key net. The fields are separated by `=`, the break signal:
+//!
+//! ```text
+//! 1510 = 49 = EHZ TBS =
+//! 1510 = 49 = EHZ TBS = FDJ =
+//! ```
+//!
+//! The [`Preamble`] type formats the preamble through its `Display` implementation and parses it
+//! through its `FromStr` implementation:
+//!
+//! ```
+//! # use enigma::procedure::preamble::Preamble;
+//! #
+//! let preamble: Preamble = "1510 = 49 = EHZ TBS =".parse().unwrap();
+//! assert_eq!(preamble.time_of_origin, (15, 10));
+//! assert_eq!(preamble.letter_count, 49);
+//! assert_eq!(preamble.to_string(), "1510 = 49 = EHZ TBS =");
+//! ```
+//!
+//! A complete message blank consists of the preamble on the first line and the ciphertext on the
+//! following lines. Such blanks are split by the [`split_message`] function.
+//!
+//! [`Preamble`]: struct.Preamble.html
+//! [`split_message`]: fn.split_message.html
+//!
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::utils::Rune;
+
+/// The number of letters within an indicator group or the discriminant.
+pub const GROUP_LEN: usize = 3;
+
+/// Error indicating that a preamble is malformed.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidPreambleError;
+
+impl Display for InvalidPreambleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid preamble")
+    }
+}
+
+impl Error for InvalidPreambleError { }
+
+/// The transmission header of a message.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Preamble {
+    /// The time of origin of the message, given by the hour and the minute.
+    pub time_of_origin: (u8, u8),
+
+    /// The number of letters of the ciphertext.
+    pub letter_count: usize,
+
+    /// The indicator groups, e.g. the ground setting and the enciphered message key.
+    pub indicators: [[Rune; GROUP_LEN]; 2],
+
+    /// The discriminant of the key net.
+    pub discriminant: Option<[Rune; GROUP_LEN]>,
+}
+
+impl Preamble {
+    /// Create the preamble of the specified ciphertext. The letter count is the number of letters
+    /// within the ciphertext.
+    pub fn for_message(time_of_origin: (u8, u8), indicators: [[Rune; GROUP_LEN]; 2],
+        ciphertext: &str) -> Self {
+        Self {
+            time_of_origin,
+            letter_count: ciphertext.chars().filter(char::is_ascii_alphabetic).count(),
+            indicators,
+            discriminant: None,
+        }
+    }
+
+    /// Set the discriminant of the key net.
+    pub fn with_discriminant(mut self, discriminant: [Rune; GROUP_LEN]) -> Self {
+        self.discriminant = Some(discriminant);
+        self
+    }
+}
+
+/// Write the runes of a group.
+fn write_group(f: &mut Formatter<'_>, group: &[Rune; GROUP_LEN]) -> std::fmt::Result {
+    for rune in group {
+        write!(f, "{}", rune)?;
+    }
+    Ok(())
+}
+
+/// Parse a group of runes.
+fn parse_group(s: &str) -> Result<[Rune; GROUP_LEN], InvalidPreambleError> {
+    let mut group = [Rune::from_value(0).unwrap(); GROUP_LEN];
+    if s.chars().count() != GROUP_LEN {
+        return Err(InvalidPreambleError);
+    }
+    for (rune, ch) in group.iter_mut().zip(s.chars()) {
+        *rune = Rune::from_char(ch).map_err(|_| InvalidPreambleError)?;
+    }
+    Ok(group)
+}
+
+impl Display for Preamble {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}{:02} = {} = ", self.time_of_origin.0, self.time_of_origin.1,
+            self.letter_count)?;
+        write_group(f, &self.indicators[0])?;
+        f.write_str(" ")?;
+        write_group(f, &self.indicators[1])?;
+        if let Some(discriminant) = &self.discriminant {
+            f.write_str(" = ")?;
+            write_group(f, discriminant)?;
+        }
+        f.write_str(" =")
+    }
+}
+
+impl FromStr for Preamble {
+    type Err = InvalidPreambleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().strip_suffix('=').ok_or(InvalidPreambleError)?;
+        let fields: Vec<&str> = s.split('=').map(str::trim).collect();
+        if fields.len() != 3 && fields.len() != 4 {
+            return Err(InvalidPreambleError);
+        }
+
+        let time = fields[0];
+        if time.len() != 4 || !time.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(InvalidPreambleError);
+        }
+        let hour: u8 = time[..2].parse().unwrap();
+        let minute: u8 = time[2..].parse().unwrap();
+        if hour >= 24 || minute >= 60 {
+            return Err(InvalidPreambleError);
+        }
+
+        let letter_count = fields[1].parse().map_err(|_| InvalidPreambleError)?;
+
+        let groups: Vec<&str> = fields[2].split_whitespace().collect();
+        if groups.len() != 2 {
+            return Err(InvalidPreambleError);
+        }
+        let indicators = [parse_group(groups[0])?, parse_group(groups[1])?];
+
+        let discriminant = match fields.get(3) {
+            Some(field) => Some(parse_group(field)?),
+            None => None,
+        };
+
+        Ok(Self { time_of_origin: (hour, minute), letter_count, indicators, discriminant })
+    }
+}
+
+/// Split the specified message blank into its preamble, given on the first line, and its
+/// ciphertext, given on the following lines.
+pub fn split_message(message: &str) -> Result<(Preamble, &str), InvalidPreambleError> {
+    let message = message.trim_start();
+    let (header, body) = message.split_once('\n').unwrap_or((message, ""));
+    Ok((header.parse()?, body.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(s: &str) -> [Rune; GROUP_LEN] {
+        parse_group(s).unwrap()
+    }
+
+    mod preamble_tests {
+        use super::*;
+
+        #[test]
+        fn test_format() {
+            let preamble = Preamble::for_message((9, 5), [group("ehz"), group("tbs")],
+                "AAAAA BBBBB C");
+            assert_eq!(preamble.to_string(), "0905 = 11 = EHZ TBS =");
+
+            let preamble = preamble.with_discriminant(group("fdj"));
+            assert_eq!(preamble.to_string(), "0905 = 11 = EHZ TBS = FDJ =");
+        }
+
+        #[test]
+        fn test_parse() {
+            let preamble: Preamble = " 2220=204= QWE EWG =FDJ= ".parse().unwrap();
+            assert_eq!(preamble.time_of_origin, (22, 20));
+            assert_eq!(preamble.letter_count, 204);
+            assert_eq!(preamble.indicators, [group("QWE"), group("EWG")]);
+            assert_eq!(preamble.discriminant, Some(group("FDJ")));
+        }
+
+        #[test]
+        fn test_parse_invalid() {
+            assert!("1510 = 49 = EHZ TBS".parse::<Preamble>().is_err());
+            assert!("2510 = 49 = EHZ TBS =".parse::<Preamble>().is_err());
+            assert!("1510 = 4x = EHZ TBS =".parse::<Preamble>().is_err());
+            assert!("1510 = 49 = EHZ TB =".parse::<Preamble>().is_err());
+            assert!("1510 = 49 = EHZ =".parse::<Preamble>().is_err());
+            assert!("1510 = 49 = EHZ TBS = FD1 =".parse::<Preamble>().is_err());
+        }
+    }
+
+    #[test]
+    fn test_split_message() {
+        let (preamble, body) = split_message("1510 = 10 = EHZ TBS =\nABCDE FGHIJ\n").unwrap();
+        assert_eq!(preamble.letter_count, 10);
+        assert_eq!(body, "ABCDE FGHIJ");
+        assert!(split_message("ABCDE FGHIJ").is_err());
+    }
+}
@@ -0,0 +1,329 @@
+//! This module provides the Python bindings of the crate. It is only available with the `python`
+//! feature.
+//!
+//! The bindings form a Python module named `enigma`, which can be built with
+//! [maturin](https://www.maturin.rs/) by running `maturin develop` in the repository. It provides
+//! the following:
+//! - The `Settings` class, whose wirings are given as strings of letters, e.g. the i-th letter is
+//!   the image of the i-th letter of the alphabet, and whose plug pairs are given as strings of 2
+//!   letters;
+//! - The `Enigma` class, created from settings;
+//! - The analysis functions `index_of_coincidence`, `english_score`, `crib_positions`,
+//!   `hill_climb` and `known_plaintext`.
+//!
+//! ```python
+//! import enigma
+//!
+//! settings = enigma.Settings(
+//!     ["ekmflgdqvzntowyhxuspaibrcj", "ajdksiruxblhwtmcqgznpyfvoe", "bdfhjlcprtxvznyeiwgakmusqo"],
+//!     [0, 5, 19],
+//!     "yruhqsldpxngokmiebfzcwvjat",
+//!     ["ap", "qk"])
+//! machine = settings.create_enigma()
+//! ciphertext = machine.map_str("hello world")
+//! ```
+//!
+//! Invalid arguments raise `ValueError`.
+//!
+
+// The code generated by the pyo3 macros for functions returning `PyResult` triggers this lint.
+#![allow(clippy::useless_conversion)]
+
+use std::convert::TryInto;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::{Enigma, SteppingMode};
+use crate::analysis::{crib, fitness, hillclimb, kpa};
+use crate::math::Permutation;
+use crate::settings::Settings;
+use crate::utils::Rune;
+
+fn parse_runes(text: &str) -> Vec<Rune> {
+    text.chars().filter_map(|ch| Rune::from_char(ch).ok()).collect()
+}
+
+fn parse_wiring(s: &str) -> PyResult<Permutation> {
+    let invalid = || PyValueError::new_err(format!("invalid wiring: {}", s));
+    let perm = s.chars()
+        .map(|ch| Rune::from_char(ch).map(|r| r.value()))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| invalid())?;
+    Permutation::from_perm(perm).map_err(|_| invalid())
+}
+
+fn parse_wirings(wirings: &[String]) -> PyResult<Vec<Permutation>> {
+    wirings.iter().map(|w| parse_wiring(w)).collect()
+}
+
+fn format_wiring(perm: &Permutation) -> String {
+    (0..perm.n()).map(|i| (b'a' + perm.map(i)) as char).collect()
+}
+
+/// The settings of an Enigma machine.
+#[pyclass(name = "Settings")]
+#[derive(Clone)]
+pub struct PySettings {
+    inner: Settings,
+}
+
+#[pymethods]
+impl PySettings {
+    #[new]
+    #[pyo3(signature = (rotators, positions, reflector, plug_pairs = Vec::new(), rings = [0; 3]))]
+    fn new(rotators: Vec<String>, positions: [u8; 3], reflector: &str, plug_pairs: Vec<String>,
+        rings: [u8; 3]) -> PyResult<Self> {
+        let wirings = parse_wirings(&rotators)?;
+        let rotator_wirings: [Permutation; 3] = wirings.try_into()
+            .map_err(|_| PyValueError::new_err("there should be 3 rotators"))?;
+        let plug_pairs = plug_pairs.iter()
+            .map(|pair| match parse_runes(pair).as_slice() {
+                &[lhs, rhs] if pair.len() == 2 => Ok((lhs, rhs)),
+                _ => Err(PyValueError::new_err(format!("invalid plug pair: {}", pair))),
+            })
+            .collect::<PyResult<Vec<(Rune, Rune)>>>()?;
+
+        let inner = Settings {
+            rotator_wirings,
+            positions,
+            rings,
+            notches: Default::default(),
+            stepping: SteppingMode::Odometer,
+            reflector: parse_wiring(reflector)?,
+            plug_pairs,
+        };
+        Ok(Self { inner })
+    }
+
+    /// The wirings of the 3 rotators.
+    #[getter]
+    fn rotators(&self) -> Vec<String> {
+        self.inner.rotator_wirings.iter().map(format_wiring).collect()
+    }
+
+    /// The offsets of the 3 rotators.
+    #[getter]
+    fn positions(&self) -> [u8; 3] {
+        self.inner.positions
+    }
+
+    /// The ring settings of the 3 rotators.
+    #[getter]
+    fn rings(&self) -> [u8; 3] {
+        self.inner.rings
+    }
+
+    /// The wiring of the reflector.
+    #[getter]
+    fn reflector(&self) -> String {
+        format_wiring(&self.inner.reflector)
+    }
+
+    /// The pairs of letters connected on the plug board.
+    #[getter]
+    fn plug_pairs(&self) -> Vec<String> {
+        self.inner.plug_pairs.iter()
+            .map(|(lhs, rhs)| format!("{}{}", lhs.into_char(), rhs.into_char()).to_lowercase())
+            .collect()
+    }
+
+    /// Create the Enigma machine described by these settings.
+    fn create_enigma(&self) -> PyResult<PyEnigma> {
+        let inner = self.inner.create_enigma()
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(PyEnigma { inner })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Settings({:?}, {:?}, {:?}, {:?})", self.rotators(), self.positions(),
+            self.reflector(), self.plug_pairs())
+    }
+}
+
+/// An Enigma machine.
+#[pyclass(name = "Enigma")]
+#[derive(Clone)]
+pub struct PyEnigma {
+    inner: Enigma,
+}
+
+#[pymethods]
+impl PyEnigma {
+    #[new]
+    fn new(settings: &PySettings) -> PyResult<Self> {
+        settings.create_enigma()
+    }
+
+    /// Map the letters within the specified string, ignoring other characters.
+    fn map_str(&mut self, s: &str) -> String {
+        self.inner.map_str(s)
+    }
+
+    /// Map the specified letter.
+    fn map_char(&mut self, ch: char) -> PyResult<char> {
+        let rune = Rune::from_char(ch)
+            .map_err(|_| PyValueError::new_err(format!("{} is not a letter", ch)))?;
+        Ok(self.inner.map_rune(rune).into_char())
+    }
+
+    /// The offsets of the 3 rotators.
+    #[getter]
+    fn positions(&self) -> Vec<u8> {
+        self.inner.rotators().rotators().iter().map(|r| r.offset()).collect()
+    }
+
+    #[setter]
+    fn set_positions(&mut self, positions: Vec<u8>) -> PyResult<()> {
+        crate::RotorMachine::restore(&mut self.inner, &positions)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// Advance the rotators by the specified number of steps.
+    #[pyo3(signature = (steps = 1))]
+    fn advance(&mut self, steps: u64) {
+        self.inner.advance_by(steps);
+    }
+
+    /// Step the rotators backwards by the specified number of steps.
+    #[pyo3(signature = (steps = 1))]
+    fn rewind(&mut self, steps: u64) {
+        self.inner.rewind(steps);
+    }
+
+    /// The number of key presses after which the state of the machine repeats.
+    fn period(&self) -> u64 {
+        self.inner.period()
+    }
+
+    /// Precompute the outputs of the machine at every state of its rotators.
+    fn precompute(&mut self) {
+        self.inner.precompute();
+    }
+}
+
+/// Get the index of coincidence of the letters within the specified text.
+#[pyfunction]
+fn index_of_coincidence(text: &str) -> f64 {
+    fitness::index_of_coincidence(&parse_runes(text))
+}
+
+/// Get the mean log-probability of the trigrams of the specified text in English.
+#[pyfunction]
+fn english_score(text: &str) -> f64 {
+    fitness::english_trigrams().score(&parse_runes(text))
+}
+
+/// Get every offset within the ciphertext at which the specified crib can be placed.
+#[pyfunction]
+fn crib_positions(crib: &str, ciphertext: &str) -> Vec<usize> {
+    crib::positions(&parse_runes(crib), &parse_runes(ciphertext))
+}
+
+/// Recover the key of the specified ciphertext by hill climbing. Returns the candidates as tuples
+/// of settings, rotor order and score, the best first.
+#[pyfunction]
+#[pyo3(signature = (ciphertext, rotators, reflector, candidates = 10, max_plug_pairs = 10))]
+fn hill_climb(py: Python<'_>, ciphertext: &str, rotators: Vec<String>, reflector: &str,
+    candidates: usize, max_plug_pairs: usize) -> PyResult<Vec<(PySettings, [usize; 3], f64)>> {
+    let constraints = hillclimb::Constraints::new(parse_wirings(&rotators)?,
+        parse_wiring(reflector)?)
+        .candidates(candidates)
+        .max_plug_pairs(max_plug_pairs);
+    let ciphertext = parse_runes(ciphertext);
+    let candidates = py.allow_threads(|| hillclimb::recover(&ciphertext, &constraints));
+    Ok(candidates.into_iter()
+        .map(|c| (PySettings { inner: c.settings }, c.rotor_order, c.score))
+        .collect())
+}
+
+/// Find all keys that encipher the specified plaintext into the specified ciphertext.
+#[pyfunction]
+fn known_plaintext(py: Python<'_>, plaintext: &str, ciphertext: &str, rotators: Vec<String>,
+    reflector: &str) -> PyResult<Vec<PySettings>> {
+    let known = kpa::KnownParts::new(parse_wirings(&rotators)?, parse_wiring(reflector)?);
+    let plaintext = parse_runes(plaintext);
+    let ciphertext = parse_runes(ciphertext);
+    let keys = py.allow_threads(|| kpa::solve(&plaintext, &ciphertext, &known))
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(keys.into_iter().map(|inner| PySettings { inner }).collect())
+}
+
+/// The `enigma` Python module.
+#[pymodule]
+fn enigma(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PySettings>()?;
+    m.add_class::<PyEnigma>()?;
+    m.add_function(wrap_pyfunction!(index_of_coincidence, m)?)?;
+    m.add_function(wrap_pyfunction!(english_score, m)?)?;
+    m.add_function(wrap_pyfunction!(crib_positions, m)?)?;
+    m.add_function(wrap_pyfunction!(hill_climb, m)?)?;
+    m.add_function(wrap_pyfunction!(known_plaintext, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pyo3::types::PyDict;
+
+    fn run(code: &str) -> PyResult<()> {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new_bound(py, "enigma")?;
+            enigma(&module)?;
+            let globals = PyDict::new_bound(py);
+            globals.set_item("enigma", module)?;
+            py.run_bound(code, Some(&globals), None)
+        })
+    }
+
+    #[test]
+    fn test_enigma() {
+        run(r#"
+settings = enigma.Settings(
+    ["ekmflgdqvzntowyhxuspaibrcj", "ajdksiruxblhwtmcqgznpyfvoe", "bdfhjlcprtxvznyeiwgakmusqo"],
+    [0, 5, 19],
+    "yruhqsldpxngokmiebfzcwvjat",
+    ["ap", "QK"])
+assert settings.positions == [0, 5, 19]
+assert settings.plug_pairs == ["ap", "qk"]
+
+machine = settings.create_enigma()
+ciphertext = machine.map_str("hello world")
+assert machine.positions == [10, 5, 19]
+machine.rewind(10)
+assert machine.map_str(ciphertext) == "HELLOWORLD"
+
+machine = enigma.Enigma(settings)
+assert machine.map_char("h") == ciphertext[0]
+machine.positions = [0, 5, 19]
+assert machine.map_char("h") == ciphertext[0]
+assert machine.period() == 17576
+"#).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        assert!(run(r#"enigma.Settings(["abc"], [0, 0, 0], "yruhqsldpxngokmiebfzcwvjat")"#)
+            .is_err());
+        assert!(run(r#"
+rotators = ["ekmflgdqvzntowyhxuspaibrcj"] * 3
+enigma.Settings(rotators, [0, 0, 0], "yruhqsldpxngokmiebfzcwvjat", ["abc"])
+"#).is_err());
+        assert!(run(r#"
+rotators = ["ekmflgdqvzntowyhxuspaibrcj"] * 3
+enigma.Settings(rotators, [0, 0, 0], "ekmflgdqvzntowyhxuspaibrcj").create_enigma()
+"#).is_err());
+    }
+
+    #[test]
+    fn test_analysis() {
+        run(r#"
+assert enigma.crib_positions("ab", "abba") == [2]
+assert enigma.index_of_coincidence("aaaa") == 1.0
+assert enigma.english_score("theweatherforecast") > enigma.english_score("qzxjkvqzxjkvqzxjkv")
+"#).unwrap();
+    }
+}
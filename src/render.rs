@@ -0,0 +1,256 @@
+//! This module renders the state of Enigma machines for documentation, teaching and debugging.
+//!
+//! The `Enigma::wiring_graph` associate function produces a [Graphviz](https://graphviz.org/)
+//! graph in the DOT language that shows the current wiring of the machine. The signal path is
+//! unrolled from left to right: each column holds the 26 contacts between two consecutive
+//! components along the path, from the keyboard through the plug board, the rotators and the
+//! reflector, back through the rotators and the plug board to the lamps. The path of an input rune
+//! can be highlighted:
+//!
+//! ```
+//! # use enigma::{Rune, Settings, SteppingMode};
+//! # use enigma::math::Permutation;
+//! #
+//! # fn wiring(s: &str) -> Permutation {
+//! #     Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+//! # }
+//! # let settings = Settings {
+//! #     rotator_wirings: [
+//! #         wiring("ekmflgdqvzntowyhxuspaibrcj"),
+//! #         wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+//! #         wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+//! #     ],
+//! #     positions: [0, 5, 19],
+//! #     rings: [0, 0, 0],
+//! #     notches: Default::default(),
+//! #     stepping: SteppingMode::Odometer,
+//! #     reflector: wiring("yruhqsldpxngokmiebfzcwvjat"),
+//! #     plug_pairs: Vec::new(),
+//! # };
+//! let machine = settings.create_enigma().unwrap();
+//! let dot = machine.wiring_graph(Some(Rune::from_char('a').unwrap()));
+//! assert!(dot.starts_with("digraph enigma {"));
+//! ```
+//!
+//! Render the graph with `dot -Tsvg`. With the `svg` feature, the `Enigma::wiring_svg` associate
+//! function renders it to SVG without Graphviz.
+//!
+//! The `Enigma::render_state` associate function gives a compact fixed-width diagram of the
+//! settings an operator can see on the machine, e.g. the rotor windows and the plug pairs:
+//!
+//! ```text
+//!  1   2   3
+//! [A] [F] [T]
+//! Plugs: AP QK TW
+//! ```
+//!
+//! The machines of this crate have no separate ring settings, so the window letters are the rotor
+//! offsets.
+//!
+
+use std::fmt::Write;
+
+use crate::{Enigma, Rune, RuneTrace};
+use crate::utils::RUNE_SET_SIZE;
+
+/// The color of the highlighted signal path.
+const PATH_COLOR: &str = "red";
+
+/// A column of contacts in the unrolled signal path, given by its node ID prefix and its title.
+type Column = (&'static str, &'static str);
+
+const KEYBOARD: Column = ("key", "Keyboard");
+const PLUG_FORWARD: Column = ("plug", "Plug board");
+const ENTRY_FORWARD: Column = ("entry", "Entry wheel");
+const ROTATORS_FORWARD: [Column; 3] = [("r1", "Rotor 1"), ("r2", "Rotor 2"), ("r3", "Rotor 3")];
+const REFLECTOR: Column = ("ukw", "Reflector");
+const ROTATORS_BACKWARD: [Column; 3] = [("r1b", "Rotor 1"), ("r2b", "Rotor 2"), ("r3b", "Rotor 3")];
+const ENTRY_BACKWARD: Column = ("entryb", "Entry wheel");
+const LAMPS: Column = ("lamp", "Lamps");
+
+/// Get the columns of the unrolled signal path and the rune at each of them along the specified
+/// trace.
+fn unroll(trace: &RuneTrace) -> Vec<(Column, Rune)> {
+    let mut path = vec![(KEYBOARD, trace.input), (PLUG_FORWARD, trace.plug_forward)];
+    if let Some(r) = trace.entry_forward {
+        path.push((ENTRY_FORWARD, r));
+    }
+    for (column, r) in ROTATORS_FORWARD.iter().zip(&trace.rotators_forward) {
+        path.push((*column, *r));
+    }
+    path.push((REFLECTOR, trace.reflector));
+    for (column, r) in ROTATORS_BACKWARD.iter().zip(&trace.rotators_backward).rev() {
+        path.push((*column, *r));
+    }
+    if let Some(r) = trace.entry_backward {
+        path.push((ENTRY_BACKWARD, r));
+    }
+    path.push((LAMPS, trace.plug_backward));
+    path
+}
+
+impl Enigma {
+    /// Produce a Graphviz graph in the DOT language showing the wiring of this machine in its
+    /// current state. If an input rune is specified, its signal path is highlighted.
+    pub fn wiring_graph(&self, highlight: Option<Rune>) -> String {
+        let traces: Vec<RuneTrace> = Rune::ALPHABET.iter()
+            .map(|rune| self.trace_rune(*rune))
+            .collect();
+        let columns: Vec<Column> = unroll(&traces[0]).into_iter().map(|(c, _)| c).collect();
+
+        let mut dot = String::new();
+        dot.push_str("digraph enigma {\n");
+        dot.push_str("  rankdir=LR;\n");
+        dot.push_str("  node [shape=circle, fontsize=10];\n");
+        dot.push_str("  edge [color=gray, arrowhead=none];\n");
+
+        // The title nodes keep the columns in order.
+        for (i, (id, title)) in columns.iter().enumerate() {
+            writeln!(dot, "  {} [label=\"{}\", shape=box];", id, title).unwrap();
+            if i > 0 {
+                writeln!(dot, "  {} -> {} [style=invis];", columns[i - 1].0, id).unwrap();
+            }
+        }
+
+        for (id, _) in &columns {
+            for i in 0..RUNE_SET_SIZE {
+                let letter = Rune::from_value(i).unwrap().into_char();
+                writeln!(dot, "  {}_{} [label=\"{}\"];", id, letter, letter).unwrap();
+            }
+        }
+
+        for trace in &traces {
+            let path = unroll(trace);
+            let highlighted = highlight == Some(trace.input);
+            for edge in path.windows(2) {
+                let ((from, from_rune), (to, to_rune)) = (edge[0], edge[1]);
+                write!(dot, "  {}_{} -> {}_{}", from.0, from_rune.into_char(), to.0,
+                    to_rune.into_char()).unwrap();
+                if highlighted {
+                    write!(dot, " [color={}, penwidth=2]", PATH_COLOR).unwrap();
+                }
+                dot.push_str(";\n");
+            }
+        }
+
+        if let Some(input) = highlight {
+            for ((id, _), r) in unroll(&traces[input.value() as usize]) {
+                writeln!(dot, "  {}_{} [color={}];", id, r.into_char(), PATH_COLOR).unwrap();
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Render the rotor windows and the plug pairs of this machine in a fixed-width diagram. The
+    /// rotators are listed in the order of the rotator group.
+    pub fn render_state(&self) -> String {
+        let rotators = self.rotators.rotators();
+        let numbers: Vec<String> = (1..=rotators.len()).map(|i| format!("{:^3}", i)).collect();
+        let windows: Vec<String> = rotators.iter()
+            .map(|r| format!("[{}]", r.window_letter()))
+            .collect();
+
+        let pairs: Vec<String> = Rune::ALPHABET.iter()
+            .copied()
+            .filter(|r| self.plug.map(*r).value() > r.value())
+            .map(|r| format!("{}{}", r, self.plug.map(r)))
+            .collect();
+        let pairs = if pairs.is_empty() { String::from("none") } else { pairs.join(" ") };
+
+        format!("{}\n{}\nPlugs: {}\n", numbers.join(" ").trim_end(), windows.join(" "), pairs)
+    }
+
+    /// Render the graph produced by `wiring_graph` to SVG.
+    #[cfg(feature = "svg")]
+    pub fn wiring_svg(&self, highlight: Option<Rune>) -> String {
+        use layout::backends::svg::SVGWriter;
+        use layout::gv::{DotParser, GraphBuilder};
+
+        let dot = self.wiring_graph(highlight);
+        let graph = DotParser::new(&dot).process().unwrap();
+        let mut builder = GraphBuilder::new();
+        builder.visit_graph(&graph);
+
+        let mut svg = SVGWriter::new();
+        builder.get().do_it(false, false, false, &mut svg);
+        svg.finalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{Settings, SteppingMode};
+    use crate::math::Permutation;
+
+    fn wiring(s: &str) -> Permutation {
+        Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+    }
+
+    fn create_enigma() -> Enigma {
+        let settings = Settings {
+            rotator_wirings: [
+                wiring("ekmflgdqvzntowyhxuspaibrcj"),
+                wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+                wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+            ],
+            positions: [0, 5, 19],
+            rings: [0, 0, 0],
+            notches: Default::default(),
+            stepping: SteppingMode::Odometer,
+            reflector: wiring("yruhqsldpxngokmiebfzcwvjat"),
+            plug_pairs: vec![(Rune::from_char('a').unwrap(), Rune::from_char('p').unwrap())],
+        };
+        settings.create_enigma().unwrap()
+    }
+
+    mod render_tests {
+        use super::*;
+
+        #[test]
+        fn test_wiring_graph() {
+            let machine = create_enigma();
+            let dot = machine.wiring_graph(None);
+            assert!(dot.starts_with("digraph enigma {\n"));
+            assert!(dot.ends_with("}\n"));
+            assert!(dot.contains("  key_A -> plug_P;\n"));
+            assert!(!dot.contains(PATH_COLOR));
+
+            // 26 contacts in each of 10 columns, fully connected between adjacent columns.
+            let edges = dot.lines().filter(|l| l.contains("->") && !l.contains("invis")).count();
+            assert_eq!(edges, 26 * 9);
+        }
+
+        #[test]
+        fn test_wiring_graph_highlight() {
+            let machine = create_enigma();
+            let input = Rune::from_char('a').unwrap();
+            let trace = machine.trace_rune(input);
+            let dot = machine.wiring_graph(Some(input));
+
+            let highlighted: Vec<&str> = dot.lines().filter(|l| l.contains("penwidth")).collect();
+            assert_eq!(highlighted.len(), 9);
+            assert!(highlighted[0].starts_with("  key_A -> plug_P "));
+            assert!(highlighted[8].ends_with(&format!("-> lamp_{} [color=red, penwidth=2];",
+                trace.output())));
+        }
+
+        #[test]
+        fn test_render_state() {
+            let mut machine = create_enigma();
+            assert_eq!(machine.render_state(), " 1   2   3\n[A] [F] [T]\nPlugs: AP\n");
+            machine.advance_by(27);
+            assert_eq!(machine.render_state().lines().nth(1), Some("[B] [G] [T]"));
+        }
+
+        #[cfg(feature = "svg")]
+        #[test]
+        fn test_wiring_svg() {
+            let svg = create_enigma().wiring_svg(Some(Rune::from_char('a').unwrap()));
+            assert!(svg.contains("<svg"));
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! This module records transcripts of the sessions of Enigma machines, e.g. for museums and
+//! reenactors who want verifiable records of interactive sessions.
+//!
+//! Logging is started by the `Enigma::start_logging` associate function. While a machine is
+//! logging, every rune it maps is recorded in a [`SessionLog`] together with the time of the key
+//! press and the window letters at which the rune is mapped. The log can be exported as CSV or as
+//! JSON:
+//!
+//! ```
+//! # use enigma::testvectors::TEST_VECTORS;
+//! #
+//! let mut machine = TEST_VECTORS[0].create_enigma();
+//! machine.start_logging();
+//! let ciphertext = machine.map_str("Hello");
+//! let log = machine.stop_logging().unwrap();
+//!
+//! assert_eq!(log.len(), 5);
+//! assert_eq!(log.input(), "HELLO");
+//! assert_eq!(log.output(), ciphertext);
+//! assert_eq!(log.entries()[0].positions, "ABM");
+//!
+//! let mut csv = Vec::new();
+//! log.write_csv(&mut csv).unwrap();
+//! assert!(csv.starts_with(b"timestamp_ms,input,output,positions\n"));
+//! ```
+//!
+//! [`SessionLog`]: struct.SessionLog.html
+//!
+
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Enigma;
+use crate::utils::Rune;
+
+/// A rune mapped by an Enigma machine, as recorded in a session log.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LogEntry {
+    /// The time at which the rune was mapped.
+    pub timestamp: SystemTime,
+
+    /// The input rune.
+    pub input: Rune,
+
+    /// The output rune.
+    pub output: Rune,
+
+    /// The window letters at which the rune was mapped, from left to right, as returned by
+    /// `Enigma::window`.
+    pub positions: String,
+}
+
+impl LogEntry {
+    /// Get the number of milliseconds between the UNIX epoch and the timestamp of this entry.
+    /// Returns `0` if the timestamp is before the epoch.
+    pub fn timestamp_millis(&self) -> u128 {
+        self.timestamp.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis())
+    }
+}
+
+/// A transcript of the runes mapped by an Enigma machine, in the order they were mapped.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SessionLog {
+    entries: Vec<LogEntry>,
+}
+
+impl SessionLog {
+    /// Create an empty session log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the entries of this log, in the order the runes were mapped.
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    /// Get the number of entries of this log.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Determine whether this log has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the input runes of this log as a string.
+    pub fn input(&self) -> String {
+        self.entries.iter().map(|entry| entry.input.into_char()).collect()
+    }
+
+    /// Get the output runes of this log as a string.
+    pub fn output(&self) -> String {
+        self.entries.iter().map(|entry| entry.output.into_char()).collect()
+    }
+
+    /// Write the entries of this log as CSV with a header row, one row per entry. The timestamps
+    /// are written in milliseconds since the UNIX epoch.
+    pub fn write_csv<W>(&self, mut writer: W) -> std::io::Result<()>
+        where W: Write {
+        writeln!(writer, "timestamp_ms,input,output,positions")?;
+        for entry in &self.entries {
+            writeln!(writer, "{},{},{},{}", entry.timestamp_millis(), entry.input, entry.output,
+                entry.positions)?;
+        }
+        Ok(())
+    }
+
+    /// Get the entries of this log as a JSON array with one object per entry. The timestamps are
+    /// given in milliseconds since the UNIX epoch.
+    pub fn to_json(&self) -> String {
+        let entries: Vec<String> = self.entries.iter()
+            .map(|entry| {
+                format!("  {{\"timestamp_ms\": {}, \"input\": \"{}\", \"output\": \"{}\", \
+                    \"positions\": \"{}\"}}", entry.timestamp_millis(), entry.input, entry.output,
+                    entry.positions)
+            })
+            .collect();
+
+        if entries.is_empty() {
+            String::from("[]\n")
+        } else {
+            format!("[\n{}\n]\n", entries.join(",\n"))
+        }
+    }
+
+    fn record(&mut self, input: Rune, output: Rune, positions: String) {
+        self.entries.push(LogEntry { timestamp: SystemTime::now(), input, output, positions });
+    }
+}
+
+impl Enigma {
+    /// Start recording the runes mapped by this machine in a session log. If this machine is
+    /// already logging, the entries recorded so far are kept.
+    ///
+    /// Only the runes mapped by the functions that advance the rotators, e.g. `map_rune` and
+    /// `map_str`, are recorded. Clones of a logging machine log separately.
+    pub fn start_logging(&mut self) {
+        if self.log.is_none() {
+            self.log = Some(SessionLog::new());
+        }
+    }
+
+    /// Stop recording the runes mapped by this machine, and return the session log. Returns `None`
+    /// if this machine is not logging.
+    pub fn stop_logging(&mut self) -> Option<SessionLog> {
+        self.log.take()
+    }
+
+    /// Get the session log of this machine, or `None` if this machine is not logging.
+    pub fn session_log(&self) -> Option<&SessionLog> {
+        self.log.as_ref()
+    }
+
+    /// Record the specified rune mapped at the current window letters, if this machine is logging.
+    pub(crate) fn log_rune(&mut self, input: Rune, output: Rune) {
+        if self.log.is_some() {
+            let positions = self.window();
+            if let Some(log) = &mut self.log {
+                log.record(input, output, positions);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testvectors::TEST_VECTORS;
+
+    #[test]
+    fn test_logging() {
+        let mut machine = TEST_VECTORS[1].create_enigma();
+        machine.map_str("unlogged");
+        assert!(machine.session_log().is_none());
+
+        machine.start_logging();
+        let positions = machine.window();
+        let ciphertext = machine.map_str("Enigma");
+        machine.start_logging();
+        machine.map_str("x");
+        let log = machine.session_log().unwrap();
+        assert_eq!(log.len(), 7);
+        assert_eq!(log.input(), "ENIGMAX");
+        assert_eq!(&log.output()[..6], ciphertext);
+        assert_eq!(log.entries()[0].positions, positions);
+        assert!(log.entries().windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+
+        // A logging machine maps like any other machine.
+        let log = machine.stop_logging().unwrap();
+        assert_eq!(TEST_VECTORS[1].create_enigma().map_str("unloggedEnigmax"),
+            format!("{}{}", &TEST_VECTORS[1].create_enigma().map_str("unlogged"), log.output()));
+        assert!(machine.stop_logging().is_none());
+    }
+
+    #[test]
+    fn test_export() {
+        let mut machine = TEST_VECTORS[0].create_enigma();
+        machine.start_logging();
+        machine.map_str("ab");
+        let log = machine.stop_logging().unwrap();
+        let millis = log.entries()[0].timestamp_millis();
+        assert!(millis > 0);
+
+        let mut csv = Vec::new();
+        log.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], format!("{},A,{},ABM", millis, log.entries()[0].output));
+
+        let json = log.to_json();
+        assert!(json.starts_with("[\n  {\"timestamp_ms\": "));
+        assert!(json.contains("\"input\": \"B\", "));
+        assert!(json.contains("\"positions\": \"ABN\"}\n]\n"));
+        assert_eq!(SessionLog::new().to_json(), "[]\n");
+    }
+}
@@ -0,0 +1,1082 @@
+//! This module provides the [`Settings`] type, which describes a complete key of an Enigma
+//! machine: the rotator wirings in their order, the positions, the ring settings and the notches
+//! of the rotators, their stepping mode, the reflector and the plug board connections.
+//!
+//! Unlike the components themselves, settings are plain data that can be freely inspected,
+//! modified and compared. An Enigma machine is created from settings with the `create_enigma`
+//! associate function:
+//!
+//! ```
+//! # use enigma::{Rune, SteppingMode};
+//! # use enigma::math::{Permutation, PermutationBuilder};
+//! # use enigma::settings::Settings;
+//! # use enigma::utils::RUNE_SET_SIZE;
+//! #
+//! let wiring = |s: &str| Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap();
+//! let settings = Settings {
+//!     rotator_wirings: [
+//!         wiring("ekmflgdqvzntowyhxuspaibrcj"),
+//!         wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+//!         wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+//!     ],
+//!     positions: [0, 5, 19],
+//!     rings: [0, 0, 0],
+//!     notches: Default::default(),
+//!     stepping: SteppingMode::Odometer,
+//!     reflector: PermutationBuilder::new(RUNE_SET_SIZE)
+//!         .swap(0, 1).swap(2, 3).swap(4, 5).swap(6, 7).swap(8, 9)
+//!         .swap(10, 11).swap(12, 13).swap(14, 15).swap(16, 17).swap(18, 19)
+//!         .swap(20, 21).swap(22, 23).swap(24, 25)
+//!         .build(),
+//!     plug_pairs: vec![(Rune::from_char('a').unwrap(), Rune::from_char('p').unwrap())],
+//! };
+//!
+//! let mut machine = settings.create_enigma().unwrap();
+//! let ciphertext = machine.map_str("hello");
+//! ```
+//!
+//! The `diff` associate function lists the differences between two settings, e.g. between a key
+//! recovered by a search and the expected key, as a [`SettingsDiff`].
+//!
+//! The [`Keyspace`] type ranks the rotor orders and positions of the machines built from a set of
+//! available rotators, e.g. maps them to consecutive integers and back, so that a brute-force
+//! search can be split into disjoint slices deterministically:
+//!
+//! ```
+//! # use enigma::settings::Keyspace;
+//! #
+//! let keyspace = Keyspace::new(5);
+//! assert_eq!(keyspace.len(), 60 * 17576);
+//!
+//! let rank = keyspace.rank([4, 0, 2], [3, 7, 11]).unwrap();
+//! assert_eq!(keyspace.unrank(rank), Some(([4, 0, 2], [3, 7, 11])));
+//! assert!(keyspace.shard(3, 4).contains(&rank));
+//! ```
+//!
+//! [`Settings`]: struct.Settings.html
+//! [`SettingsDiff`]: struct.SettingsDiff.html
+//! [`Keyspace`]: struct.Keyspace.html
+//!
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+#[cfg(feature = "arbitrary")]
+use arbitrary::{Arbitrary, Unstructured};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::{Enigma, PlugBoard, Reflector, Rotator, RotatorGroup, SteppingMode};
+use crate::math::{Permutation, PermutationBuilder};
+use crate::utils::{Rune, RUNE_SET_SIZE};
+#[cfg(feature = "arbitrary")]
+use crate::utils::arbitrary_pairs;
+
+/// Error indicating that settings do not describe a valid Enigma machine.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InvalidSettingsError {
+    /// The plug pairs do not form a valid plug board, e.g. a letter occurs in two pairs.
+    PlugBoard,
+
+    /// The wiring of a rotator is invalid.
+    Rotator {
+        /// The index of the rotator within the rotator group.
+        index: usize,
+    },
+
+    /// The reflector wiring is invalid.
+    Reflector,
+}
+
+impl Display for InvalidSettingsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidSettingsError::PlugBoard => f.write_str("invalid plug board settings"),
+            InvalidSettingsError::Rotator { index } =>
+                write!(f, "invalid settings of rotator {}", index + 1),
+            InvalidSettingsError::Reflector => f.write_str("invalid reflector settings"),
+        }
+    }
+}
+
+impl Error for InvalidSettingsError { }
+
+/// The complete key of an Enigma machine.
+///
+/// Settings are ordered by their rotator wirings, then by their positions, their ring settings,
+/// their notches, their stepping mode, their reflector and their plug pairs. Settings listing the
+/// same plug pairs in different orders are distinct.
+#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(not(feature = "redact-debug"), derive(Debug))]
+pub struct Settings {
+    /// The wirings of the 3 rotators, in the order of the rotator group.
+    pub rotator_wirings: [Permutation; 3],
+
+    /// The offsets of the 3 rotators.
+    pub positions: [u8; 3],
+
+    /// The ring settings of the 3 rotators, in the order of the rotator group. The letter shown in
+    /// the window of a rotator is its offset shifted forward by its ring setting.
+    pub rings: [u8; 3],
+
+    /// The notches of the 3 rotators, in the order of the rotator group. A notch is given by the
+    /// letter shown in the window of its rotator when the rotator is at the notch, so notches do
+    /// not depend on the ring settings.
+    pub notches: [Vec<Rune>; 3],
+
+    /// The stepping mode of the rotator group. The notches are ignored with the odometer stepping
+    /// mode.
+    pub stepping: SteppingMode,
+
+    /// The wiring of the reflector.
+    pub reflector: Permutation,
+
+    /// The pairs of letters connected on the plug board.
+    pub plug_pairs: Vec<(Rune, Rune)>,
+}
+
+impl Settings {
+    /// Replace the plug pairs of these settings.
+    pub fn with_plug_pairs(mut self, plug_pairs: Vec<(Rune, Rune)>) -> Self {
+        self.plug_pairs = plug_pairs;
+        self
+    }
+
+    /// Create the plug board described by these settings.
+    pub fn create_plug_board(&self) -> Result<PlugBoard, InvalidSettingsError> {
+        PlugBoard::from_perm(plug_pairs_to_perm(&self.plug_pairs)?)
+            .map_err(|_| InvalidSettingsError::PlugBoard)
+    }
+
+    /// Create the rotator group described by these settings.
+    pub fn create_rotator_group(&self) -> Result<RotatorGroup, InvalidSettingsError> {
+        let create_rotator = |index: usize| {
+            // Rotators have no rings, so their notches are shifted back by the ring settings like
+            // their offsets.
+            let notches: Vec<Rune> = self.notches[index].iter()
+                .map(|notch| shift_rune(*notch, RUNE_SET_SIZE - self.rings[index] % RUNE_SET_SIZE))
+                .collect();
+            Rotator::new(self.rotator_wirings[index].clone(), self.positions[index])
+                .map(|rotator| rotator.with_notches(&notches))
+                .map_err(|_| InvalidSettingsError::Rotator { index })
+        };
+
+        let rotators = [create_rotator(0)?, create_rotator(1)?, create_rotator(2)?];
+        Ok(RotatorGroup::new(rotators).with_stepping_mode(self.stepping))
+    }
+
+    /// Create the reflector described by these settings.
+    pub fn create_reflector(&self) -> Result<Reflector, InvalidSettingsError> {
+        Reflector::from_perm(self.reflector.clone())
+            .map_err(|_| InvalidSettingsError::Reflector)
+    }
+
+    /// Create the Enigma machine described by these settings.
+    pub fn create_enigma(&self) -> Result<Enigma, InvalidSettingsError> {
+        let machine = Enigma::new(
+            self.create_plug_board()?,
+            self.create_rotator_group()?,
+            self.create_reflector()?,
+        );
+
+        // Machines list their ring settings from left to right.
+        let [right, middle, left] = self.rings;
+        Ok(machine.with_ring_settings([left, middle, right]))
+    }
+
+    /// Get the canonical representative of the keys equivalent to these settings, so that
+    /// equivalent keys found by a search can be deduplicated by comparing their canonical forms.
+    ///
+    /// The following equivalences are removed:
+    /// - The order of the plug pairs and of the letters within each pair, and pairs connecting a
+    ///   letter to itself;
+    /// - The position of the third rotator. It never steps another rotator, so its offset acts
+    ///   like a ring setting and is folded into its wiring, leaving its offset at `0`;
+    /// - The ring settings, which are folded into the notches, leaving them at `0`;
+    /// - The order of the notches and repeated notches, the notches of all rotators with the
+    ///   odometer stepping mode and the notches of the third rotator, which never steps another
+    ///   rotator.
+    ///
+    /// Settings with equal canonical forms create machines that encrypt every message alike.
+    /// Equivalent settings may still have different canonical forms, e.g. when different wirings
+    /// of the third rotator and the reflector compose to the same mapping.
+    pub fn canonicalize(&self) -> Settings {
+        let mut canonical = self.clone();
+
+        let offset = self.positions[2] % RUNE_SET_SIZE;
+        let wiring = &self.rotator_wirings[2];
+        if wiring.n() == RUNE_SET_SIZE {
+            // The wiring seen by the signal at the offset of the rotator.
+            let perm = (0..RUNE_SET_SIZE)
+                .map(|i| (wiring.map((i + offset) % RUNE_SET_SIZE) + RUNE_SET_SIZE - offset) %
+                    RUNE_SET_SIZE)
+                .collect();
+            canonical.rotator_wirings[2] = Permutation::from_perm(perm).unwrap();
+            canonical.positions[2] = 0;
+        }
+
+        for index in 0..3 {
+            if self.stepping == SteppingMode::Odometer || index == 2 {
+                canonical.notches[index].clear();
+                continue;
+            }
+
+            // The notches of the rotators, which are the notches of the settings shifted back by
+            // the ring settings.
+            let ring = self.rings[index] % RUNE_SET_SIZE;
+            let notches: Vec<Rune> = self.notches[index].iter()
+                .map(|notch| shift_rune(*notch, RUNE_SET_SIZE - ring))
+                .collect();
+            canonical.notches[index] = normalize_notches(&notches);
+        }
+        canonical.rings = [0; 3];
+        canonical.plug_pairs = normalize_plug_pairs(&self.plug_pairs);
+
+        canonical
+    }
+
+    /// Compare these settings against the specified other settings, e.g. a key recovered by a
+    /// search against the expected key.
+    ///
+    /// Notches are compared regardless of their order, and plug pairs regardless of their order and
+    /// the order of the letters within each pair. Positions and ring settings are compared apart,
+    /// so a changed ring setting does not show up as a changed position.
+    pub fn diff(&self, other: &Settings) -> SettingsDiff {
+        let mut diff = SettingsDiff::default();
+
+        if self.rotator_wirings != other.rotator_wirings {
+            diff.rotor_order = find_rotor_order(&self.rotator_wirings, &other.rotator_wirings);
+            if diff.rotor_order.is_none() {
+                diff.rotator_wirings = (0..3)
+                    .filter(|i| self.rotator_wirings[*i] != other.rotator_wirings[*i])
+                    .collect();
+            }
+        }
+
+        diff.positions = (0..3)
+            .filter(|i| self.positions[*i] != other.positions[*i])
+            .map(|i| (i, self.positions[i], other.positions[i]))
+            .collect();
+        diff.rings = (0..3)
+            .filter(|i| self.rings[*i] != other.rings[*i])
+            .map(|i| (i, self.rings[i], other.rings[i]))
+            .collect();
+        diff.notches = (0..3)
+            .filter(|i| {
+                normalize_notches(&self.notches[*i]) != normalize_notches(&other.notches[*i])
+            })
+            .collect();
+        diff.stepping = self.stepping != other.stepping;
+        diff.reflector = self.reflector != other.reflector;
+
+        let pairs = normalize_plug_pairs(&self.plug_pairs);
+        let other_pairs = normalize_plug_pairs(&other.plug_pairs);
+        diff.added_plug_pairs = other_pairs.iter()
+            .filter(|pair| !pairs.contains(pair))
+            .copied()
+            .collect();
+        diff.removed_plug_pairs = pairs.iter()
+            .filter(|pair| !other_pairs.contains(pair))
+            .copied()
+            .collect();
+
+        diff
+    }
+
+    /// Write these settings as a configuration file in the JSON format read by `enigma-cli`, which
+    /// is described in `docs/Configuration.md`.
+    ///
+    /// The rotators with notches are written as custom rotors named `rotor-1` to `rotor-3`, and the
+    /// other rotators by their wirings. The reflector is written as the pairs of letters it swaps,
+    /// so a reflector wiring with fixed points does not survive the conversion.
+    pub fn to_json(&self) -> String {
+        let mut rotators = Vec::new();
+        let mut custom_rotors = Vec::new();
+        for (index, wiring) in self.rotator_wirings.iter().enumerate() {
+            let wiring: String = (0..wiring.n()).map(|i| (b'a' + wiring.map(i)) as char)
+                .collect();
+            let notches = &self.notches[index];
+            if notches.is_empty() {
+                rotators.push(format!("    [\"{}\", {}]", wiring, self.positions[index]));
+                continue;
+            }
+
+            let name = format!("rotor-{}", index + 1);
+            let notches: String = notches.iter().map(|notch| notch.into_char().to_ascii_lowercase())
+                .collect();
+            rotators.push(format!("    [\"{}\", {}]", name, self.positions[index]));
+            custom_rotors.push(format!("    \"{}\": [\"{}\", \"{}\"]", name, wiring, notches));
+        }
+        let reflector: Vec<(u8, u8)> = (0..self.reflector.n())
+            .map(|i| (i, self.reflector.map(i)))
+            .filter(|(i, j)| i < j)
+            .collect();
+        let plug_pairs: Vec<(u8, u8)> = self.plug_pairs.iter()
+            .map(|(a, b)| (a.value(), b.value()))
+            .collect();
+
+        let mut json = String::from("{\n");
+        json.push_str(&format!("  \"plug_board\": {},\n", pairs_to_json(&plug_pairs)));
+        json.push_str(&format!("  \"rotators\": [\n{}\n  ],\n", rotators.join(",\n")));
+        json.push_str(&format!("  \"rings\": [{}, {}, {}],\n",
+            self.rings[0], self.rings[1], self.rings[2]));
+        json.push_str(&format!("  \"stepping\": \"{}\",\n", match self.stepping {
+            SteppingMode::Odometer => "odometer",
+            SteppingMode::WehrmachtLever => "wehrmacht_lever",
+        }));
+        if !custom_rotors.is_empty() {
+            json.push_str(&format!("  \"custom_rotors\": {{\n{}\n  }},\n",
+                custom_rotors.join(",\n")));
+        }
+        json.push_str(&format!("  \"reflector\": {}\n}}\n", pairs_to_json(&reflector)));
+        json
+    }
+}
+
+#[cfg(feature = "redact-debug")]
+impl std::fmt::Debug for Settings {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Settings")
+            .field("rotator_wirings", &self.rotator_wirings)
+            .field("positions", &self.positions)
+            .field("rings", &self.rings)
+            .field("notches", &self.notches)
+            .field("stepping", &self.stepping)
+            .field("reflector", &self.reflector)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for Settings {
+    fn zeroize(&mut self) {
+        self.rotator_wirings.zeroize();
+        self.positions.zeroize();
+        self.rings.zeroize();
+        self.notches.zeroize();
+        self.stepping = SteppingMode::default();
+        self.reflector.zeroize();
+        self.plug_pairs.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Settings {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for Settings { }
+
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for Settings {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut arbitrary_wiring = || Permutation::arbitrary_of_size(u, RUNE_SET_SIZE);
+        let rotator_wirings = [arbitrary_wiring()?, arbitrary_wiring()?, arbitrary_wiring()?];
+
+        let mut positions = [0u8; 3];
+        let mut rings = [0u8; 3];
+        for value in positions.iter_mut().chain(rings.iter_mut()) {
+            *value = u.int_in_range(0..=RUNE_SET_SIZE - 1)?;
+        }
+        let mut notches: [Vec<Rune>; 3] = Default::default();
+        for rotator_notches in &mut notches {
+            rotator_notches.extend(Option::<Rune>::arbitrary(u)?);
+        }
+        let stepping = if u.arbitrary()? {
+            SteppingMode::WehrmachtLever
+        } else {
+            SteppingMode::Odometer
+        };
+
+        let max_pairs = RUNE_SET_SIZE as usize / 2;
+        let reflector = plug_pairs_to_perm(&arbitrary_pairs(u, max_pairs)?).unwrap();
+        let num_plug_pairs = u.int_in_range(0..=max_pairs)?;
+        let plug_pairs = arbitrary_pairs(u, num_plug_pairs)?;
+
+        Ok(Self { rotator_wirings, positions, rings, notches, stepping, reflector, plug_pairs })
+    }
+}
+
+/// The differences between two settings, as returned by `Settings::diff`.
+///
+/// The `Display` implementation lists one difference per line, numbering the rotators from `1`:
+///
+/// ```
+/// # use enigma::{Rune, SteppingMode};
+/// # use enigma::math::Permutation;
+/// # use enigma::settings::Settings;
+/// #
+/// # let wiring = |s: &str| Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap();
+/// # let rune = |ch: char| Rune::from_char(ch).unwrap();
+/// let expected = Settings {
+///     rotator_wirings: [
+///         wiring("ekmflgdqvzntowyhxuspaibrcj"),
+///         wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+///         wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+///     ],
+///     positions: [0, 5, 19],
+///     rings: [0, 0, 0],
+///     notches: Default::default(),
+///     stepping: SteppingMode::Odometer,
+///     reflector: wiring("yruhqsldpxngokmiebfzcwvjat"),
+///     plug_pairs: vec![(rune('a'), rune('p')), (rune('c'), rune('x'))],
+/// };
+/// let mut found = expected.clone();
+/// found.rotator_wirings.swap(0, 2);
+/// found.positions[1] = 7;
+/// found.rings[0] = 2;
+/// found.plug_pairs = vec![(rune('x'), rune('c')), (rune('b'), rune('q'))];
+///
+/// assert_eq!(expected.diff(&found).to_string(), "\
+///     rotor order: 3 2 1\n\
+///     position of rotator 2: F -> H\n\
+///     ring of rotator 1: A -> C\n\
+///     plug pairs added: BQ\n\
+///     plug pairs removed: AP");
+/// ```
+#[derive(Clone, Default, Eq, PartialEq)]
+#[cfg_attr(not(feature = "redact-debug"), derive(Debug))]
+pub struct SettingsDiff {
+    /// The rotor order of the other settings if their rotators are the rotators of these settings
+    /// placed in a different order, as indices into the rotators of these settings. For example,
+    /// `[2, 0, 1]` means the first rotator of the other settings is the third rotator of these
+    /// settings.
+    pub rotor_order: Option<[usize; 3]>,
+
+    /// The indices of the rotators whose wirings differ. Empty if the rotators are only reordered.
+    pub rotator_wirings: Vec<usize>,
+
+    /// The indices of the rotators whose positions differ, together with their positions within
+    /// these settings and the other settings.
+    pub positions: Vec<(usize, u8, u8)>,
+
+    /// The indices of the rotators whose ring settings differ, together with their ring settings
+    /// within these settings and the other settings.
+    pub rings: Vec<(usize, u8, u8)>,
+
+    /// The indices of the rotators whose notches differ.
+    pub notches: Vec<usize>,
+
+    /// Whether the stepping modes differ.
+    pub stepping: bool,
+
+    /// Whether the reflector wirings differ.
+    pub reflector: bool,
+
+    /// The plug pairs only within the other settings, sorted.
+    pub added_plug_pairs: Vec<(Rune, Rune)>,
+
+    /// The plug pairs only within these settings, sorted.
+    pub removed_plug_pairs: Vec<(Rune, Rune)>,
+}
+
+impl SettingsDiff {
+    /// Determine whether the compared settings are the same.
+    pub fn is_empty(&self) -> bool {
+        *self == SettingsDiff::default()
+    }
+}
+
+impl Display for SettingsDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return f.write_str("no differences");
+        }
+
+        let mut lines = Vec::new();
+        if let Some(order) = self.rotor_order {
+            lines.push(format!("rotor order: {} {} {}", order[0] + 1, order[1] + 1, order[2] + 1));
+        }
+        for index in &self.rotator_wirings {
+            lines.push(format!("wiring of rotator {} differs", index + 1));
+        }
+        for (index, position, other) in &self.positions {
+            lines.push(format!("position of rotator {}: {} -> {}", index + 1,
+                format_position(*position), format_position(*other)));
+        }
+        for (index, ring, other) in &self.rings {
+            lines.push(format!("ring of rotator {}: {} -> {}", index + 1,
+                format_position(*ring), format_position(*other)));
+        }
+        for index in &self.notches {
+            lines.push(format!("notches of rotator {} differ", index + 1));
+        }
+        if self.stepping {
+            lines.push(String::from("stepping mode differs"));
+        }
+        if self.reflector {
+            lines.push(String::from("wiring of reflector differs"));
+        }
+        if !self.added_plug_pairs.is_empty() {
+            lines.push(format!("plug pairs added: {}", format_pairs(&self.added_plug_pairs)));
+        }
+        if !self.removed_plug_pairs.is_empty() {
+            lines.push(format!("plug pairs removed: {}", format_pairs(&self.removed_plug_pairs)));
+        }
+
+        f.write_str(&lines.join("\n"))
+    }
+}
+
+#[cfg(feature = "redact-debug")]
+impl std::fmt::Debug for SettingsDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SettingsDiff")
+            .field("rotor_order", &self.rotor_order)
+            .field("rotator_wirings", &self.rotator_wirings)
+            .field("positions", &self.positions)
+            .field("rings", &self.rings)
+            .field("notches", &self.notches)
+            .field("stepping", &self.stepping)
+            .field("reflector", &self.reflector)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The keyspace of the rotor orders and positions of the machines built from a set of available
+/// rotators.
+///
+/// The keys are ranked first by their rotor orders, then by their positions. A rotor order is
+/// given by the indices of the rotators placed into the machine, in the order of the rotator
+/// group, and the rotor orders are ranked lexicographically. The positions are ranked in the
+/// stepping order of the rotator group starting from offsets `[0, 0, 0]`, e.g. the first rotator
+/// changes fastest.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Keyspace {
+    num_rotators: usize,
+}
+
+impl Keyspace {
+    /// The number of positions of a rotator group.
+    pub const NUM_POSITIONS: u64 = (RUNE_SET_SIZE as u64) * (RUNE_SET_SIZE as u64)
+        * (RUNE_SET_SIZE as u64);
+
+    /// Create the keyspace of the specified number of available rotators.
+    pub fn new(num_rotators: usize) -> Self {
+        Self { num_rotators }
+    }
+
+    /// Get the number of available rotators.
+    pub fn num_rotators(&self) -> usize {
+        self.num_rotators
+    }
+
+    /// Get the number of rotor orders, e.g. the number of ordered choices of 3 distinct rotators.
+    pub fn num_rotor_orders(&self) -> u64 {
+        let n = self.num_rotators as u64;
+        if n < 3 {
+            return 0;
+        }
+        n * (n - 1) * (n - 2)
+    }
+
+    /// Get the number of keys within the keyspace.
+    pub fn len(&self) -> u64 {
+        self.num_rotor_orders() * Self::NUM_POSITIONS
+    }
+
+    /// Determine whether the keyspace is empty, e.g. less than 3 rotators are available.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get all rotor orders, ranked.
+    pub fn rotor_orders(&self) -> Vec<[usize; 3]> {
+        (0..self.num_rotor_orders())
+            .map(|rank| self.unrank_rotor_order(rank))
+            .collect()
+    }
+
+    /// Get the rank of the specified key. Returns `None` if the rotor order contains an invalid or
+    /// repeated rotator index, or a position is not a valid offset.
+    pub fn rank(&self, rotor_order: [usize; 3], positions: [u8; 3]) -> Option<u64> {
+        let [i, j, k] = rotor_order;
+        if i == j || j == k || i == k || rotor_order.iter().any(|x| *x >= self.num_rotators) {
+            return None;
+        }
+        if positions.iter().any(|x| *x >= RUNE_SET_SIZE) {
+            return None;
+        }
+
+        // Each index is ranked among the indices not used by the preceding rotators.
+        let n = self.num_rotators as u64;
+        let j_rank = j - (j > i) as usize;
+        let k_rank = k - (k > i) as usize - (k > j) as usize;
+        let order_rank = (i as u64 * (n - 1) + j_rank as u64) * (n - 2) + k_rank as u64;
+
+        let size = RUNE_SET_SIZE as u64;
+        let position_rank = positions[0] as u64 + size * (positions[1] as u64
+            + size * positions[2] as u64);
+
+        Some(order_rank * Self::NUM_POSITIONS + position_rank)
+    }
+
+    /// Get the key of the specified rank. Returns `None` if the rank is out of range.
+    pub fn unrank(&self, rank: u64) -> Option<([usize; 3], [u8; 3])> {
+        if rank >= self.len() {
+            return None;
+        }
+
+        let rotor_order = self.unrank_rotor_order(rank / Self::NUM_POSITIONS);
+
+        let size = RUNE_SET_SIZE as u64;
+        let position_rank = rank % Self::NUM_POSITIONS;
+        let positions = [
+            (position_rank % size) as u8,
+            (position_rank / size % size) as u8,
+            (position_rank / size / size) as u8,
+        ];
+
+        Some((rotor_order, positions))
+    }
+
+    /// Get the range of ranks within the `index`-th of `count` disjoint slices of the keyspace.
+    /// The slices are contiguous, cover the whole keyspace and differ in size by at most 1.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `count` is 0 or `index` is not less than `count`.
+    pub fn shard(&self, index: u64, count: u64) -> Range<u64> {
+        assert!(index < count, "shard index {} out of range for {} shards", index, count);
+
+        let len = self.len() as u128;
+        let start = len * index as u128 / count as u128;
+        let end = len * (index + 1) as u128 / count as u128;
+        (start as u64)..(end as u64)
+    }
+
+    fn unrank_rotor_order(&self, rank: u64) -> [usize; 3] {
+        let n = self.num_rotators as u64;
+        let i = (rank / ((n - 1) * (n - 2))) as usize;
+        let j_rank = (rank / (n - 2) % (n - 1)) as usize;
+        let k_rank = (rank % (n - 2)) as usize;
+
+        let j = (0..self.num_rotators).filter(|x| *x != i).nth(j_rank).unwrap();
+        let k = (0..self.num_rotators).filter(|x| *x != i && *x != j).nth(k_rank).unwrap();
+        [i, j, k]
+    }
+}
+
+/// Sort the specified plug pairs and the letters within each pair, dropping repeated pairs and
+/// pairs connecting a letter to itself.
+fn normalize_plug_pairs(pairs: &[(Rune, Rune)]) -> Vec<(Rune, Rune)> {
+    let mut pairs: Vec<(Rune, Rune)> = pairs.iter()
+        .filter(|(a, b)| a != b)
+        .map(|(a, b)| (*a.min(b), *a.max(b)))
+        .collect();
+    pairs.sort();
+    pairs.dedup();
+    pairs
+}
+
+/// Sort the specified notches, dropping repeated notches.
+fn normalize_notches(notches: &[Rune]) -> Vec<Rune> {
+    let mut notches = notches.to_vec();
+    notches.sort();
+    notches.dedup();
+    notches
+}
+
+/// Shift the specified rune forward by the specified number of letters, wrapping around.
+fn shift_rune(rune: Rune, shift: u8) -> Rune {
+    Rune::from_value((rune.value() + shift) % RUNE_SET_SIZE).unwrap()
+}
+
+/// Find the indices into `wirings` of the rotators listed by `other`. Returns `None` if `other` is
+/// not a reordering of `wirings`.
+fn find_rotor_order(wirings: &[Permutation; 3], other: &[Permutation; 3]) -> Option<[usize; 3]> {
+    let mut used = [false; 3];
+    let mut order = [0; 3];
+    for (index, wiring) in other.iter().enumerate() {
+        let i = (0..3).find(|i| !used[*i] && wirings[*i] == *wiring)?;
+        used[i] = true;
+        order[index] = i;
+    }
+    Some(order)
+}
+
+/// Format the specified position as its window letter, or as a number if it is out of range.
+fn format_position(position: u8) -> String {
+    match Rune::from_value(position) {
+        Ok(rune) => rune.to_string(),
+        Err(_) => position.to_string(),
+    }
+}
+
+fn format_pairs(pairs: &[(Rune, Rune)]) -> String {
+    let pairs: Vec<String> = pairs.iter().map(|(a, b)| format!("{}{}", a, b)).collect();
+    pairs.join(" ")
+}
+
+/// Write the specified pairs of letter values as a JSON array of pairs of lower case letters.
+fn pairs_to_json(pairs: &[(u8, u8)]) -> String {
+    let pairs: Vec<String> = pairs.iter()
+        .map(|(a, b)| format!("[\"{}\", \"{}\"]", (b'a' + a) as char, (b'a' + b) as char))
+        .collect();
+    format!("[{}]", pairs.join(", "))
+}
+
+/// Create the plug board permutation of the specified plug pairs. Fails if a letter occurs twice.
+pub(crate) fn plug_pairs_to_perm(pairs: &[(Rune, Rune)])
+    -> Result<Permutation, InvalidSettingsError> {
+    let mut used = [false; RUNE_SET_SIZE as usize];
+    let mut builder = PermutationBuilder::new(RUNE_SET_SIZE);
+    for (a, b) in pairs {
+        for rune in &[*a, *b] {
+            if used[rune.value() as usize] {
+                return Err(InvalidSettingsError::PlugBoard);
+            }
+            used[rune.value() as usize] = true;
+        }
+        builder = builder.swap(a.value(), b.value());
+    }
+
+    Ok(builder.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wiring(s: &str) -> Permutation {
+        Permutation::from_perm(s.bytes().map(|b| b - b'a').collect()).unwrap()
+    }
+
+    fn rune(ch: char) -> Rune {
+        Rune::from_char(ch).unwrap()
+    }
+
+    fn create_test_settings() -> Settings {
+        Settings {
+            rotator_wirings: [
+                wiring("ekmflgdqvzntowyhxuspaibrcj"),
+                wiring("ajdksiruxblhwtmcqgznpyfvoe"),
+                wiring("bdfhjlcprtxvznyeiwgakmusqo"),
+            ],
+            positions: [3, 7, 11],
+            rings: [0, 0, 0],
+            notches: Default::default(),
+            stepping: SteppingMode::Odometer,
+            reflector: wiring("yruhqsldpxngokmiebfzcwvjat"),
+            plug_pairs: vec![(rune('a'), rune('b')), (rune('c'), rune('d'))],
+        }
+    }
+
+    #[test]
+    fn test_create_enigma_valid() {
+        let settings = create_test_settings();
+        let machine = settings.create_enigma().unwrap();
+
+        let rotators = machine.rotators().rotators();
+        assert_eq!(rotators[0].offset(), 3);
+        assert_eq!(rotators[1].offset(), 7);
+        assert_eq!(rotators[2].offset(), 11);
+    }
+
+    #[test]
+    fn test_create_enigma_reciprocal() {
+        let settings = create_test_settings();
+        let ciphertext = settings.create_enigma().unwrap().map_str("attackatdawn");
+        let plaintext = settings.create_enigma().unwrap().map_str(&ciphertext);
+        assert_eq!(plaintext, "ATTACKATDAWN");
+    }
+
+    #[test]
+    fn test_create_enigma_invalid_plug_pairs() {
+        let mut settings = create_test_settings();
+        settings.plug_pairs.push((rune('a'), rune('z')));
+        assert_eq!(settings.create_enigma().unwrap_err(), InvalidSettingsError::PlugBoard);
+    }
+
+    #[test]
+    fn test_create_enigma_invalid_rotator() {
+        let mut settings = create_test_settings();
+        settings.rotator_wirings[1] = Permutation::identity(4);
+        assert_eq!(settings.create_enigma().unwrap_err(),
+            InvalidSettingsError::Rotator { index: 1 });
+    }
+
+    #[test]
+    fn test_create_enigma_invalid_reflector() {
+        let mut settings = create_test_settings();
+        settings.reflector = Permutation::identity(RUNE_SET_SIZE);
+        assert_eq!(settings.create_enigma().unwrap_err(), InvalidSettingsError::Reflector);
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let settings = create_test_settings();
+        let canonical = settings.canonicalize();
+        assert_eq!(canonical.positions, [3, 7, 0]);
+        assert_eq!(canonical.canonicalize(), canonical);
+
+        let machine = settings.create_enigma().unwrap();
+        assert!(machine.is_equivalent(&canonical.create_enigma().unwrap(), 20000));
+
+        let mut other = settings.clone();
+        other.plug_pairs = vec![
+            (rune('d'), rune('c')), (rune('e'), rune('e')), (rune('b'), rune('a')),
+        ];
+        assert_eq!(other.canonicalize(), canonical);
+
+        other.rings = [4, 0, 9];
+        assert_eq!(other.canonicalize(), canonical);
+
+        other.positions[2] = 12;
+        assert_ne!(other.canonicalize(), canonical);
+    }
+
+    #[test]
+    fn test_canonicalize_lever() {
+        let mut settings = create_test_settings();
+        settings.rings = [4, 2, 9];
+        settings.notches = [vec![rune('q'), rune('q')], vec![rune('e')], vec![rune('v')]];
+        settings.stepping = SteppingMode::WehrmachtLever;
+
+        let canonical = settings.canonicalize();
+        assert_eq!(canonical.rings, [0; 3]);
+        assert_eq!(canonical.notches, [vec![rune('m')], vec![rune('c')], Vec::new()]);
+        assert_eq!(canonical.canonicalize(), canonical);
+
+        let machine = settings.create_enigma().unwrap();
+        assert!(machine.is_equivalent(&canonical.create_enigma().unwrap(), 20000));
+
+        settings.stepping = SteppingMode::Odometer;
+        assert!(settings.canonicalize().notches.iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut settings = create_test_settings();
+        let json = settings.to_json();
+        assert!(json.starts_with("{\n  \"plug_board\": [[\"a\", \"b\"], [\"c\", \"d\"]],\n"));
+        assert!(json.contains("\n    [\"ekmflgdqvzntowyhxuspaibrcj\", 3],\n"));
+        assert!(json.contains("\n  \"rings\": [0, 0, 0],\n  \"stepping\": \"odometer\",\n"));
+        assert!(!json.contains("custom_rotors"));
+
+        settings.notches[1] = vec![rune('e'), rune('q')];
+        settings.stepping = SteppingMode::WehrmachtLever;
+        let json = settings.to_json();
+        assert!(json.contains("\n    [\"rotor-2\", 7],\n"));
+        assert!(json.contains("\n  \"stepping\": \"wehrmacht_lever\",\n"));
+        assert!(json.contains("\n  \"custom_rotors\": {\n    \"rotor-2\": \
+            [\"ajdksiruxblhwtmcqgznpyfvoe\", \"eq\"]\n  },\n"));
+        assert!(json.contains("\"reflector\": [[\"a\", \"y\"], [\"b\", \"r\"], [\"c\", \"u\"], "));
+    }
+
+    #[test]
+    fn test_to_config() {
+        let settings = create_test_settings();
+        let mut machine = settings.create_enigma().unwrap();
+        assert_eq!(machine.to_config().unwrap(), settings);
+
+        machine.map_str("hello");
+        let config = machine.to_config().unwrap();
+        assert_eq!(config.positions, [8, 7, 11]);
+        assert!(machine.is_equivalent(&config.create_enigma().unwrap(), 1000));
+
+        let machine = machine.with_ring_settings([1, 2, 3]);
+        assert_eq!(machine.to_config().unwrap().rings, [3, 2, 1]);
+
+        let machine = machine.with_entry_wheel(crate::EntryWheel::qwertz());
+        assert!(machine.to_config().is_none());
+    }
+
+    #[test]
+    fn test_to_config_lever() {
+        // Rotors II, I and III from left to right with the ring settings XMV.
+        let machine = crate::testvectors::TEST_VECTORS[0].create_enigma();
+        let settings = machine.to_config().unwrap();
+        assert_eq!(settings.rings, [21, 12, 23]);
+        assert_eq!(settings.notches, [vec![rune('v')], vec![rune('q')], vec![rune('e')]]);
+        assert_eq!(settings.stepping, SteppingMode::WehrmachtLever);
+
+        let copy = settings.create_enigma().unwrap();
+        assert_eq!(copy.window(), machine.window());
+        assert!(machine.is_equivalent(&copy, 20000));
+
+        let rotators = settings.create_rotator_group().unwrap()
+            .with_stepping(crate::components::stepping::Gear);
+        let machine = Enigma::new(settings.create_plug_board().unwrap(), rotators,
+            settings.create_reflector().unwrap());
+        assert!(machine.to_config().is_none());
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary() {
+        let data: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+        let mut u = Unstructured::new(&data);
+        for _ in 0..4 {
+            assert!(Settings::arbitrary(&mut u).unwrap().create_enigma().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_dedup_settings() {
+        use std::collections::{BTreeSet, HashSet};
+
+        let settings = create_test_settings();
+        let mut other = settings.clone();
+        other.positions = [0, 7, 11];
+        let candidates = vec![settings.clone(), other.clone(), settings.clone()];
+
+        let unique: HashSet<Settings> = candidates.iter().cloned().collect();
+        assert_eq!(unique.len(), 2);
+
+        let sorted: BTreeSet<Settings> = candidates.into_iter().collect();
+        assert_eq!(sorted.into_iter().collect::<Vec<_>>(), vec![other, settings]);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_zeroize() {
+        let mut settings = create_test_settings();
+        settings.zeroize();
+        assert!(settings.rotator_wirings.iter().all(|wiring| wiring.n() == 0));
+        assert_eq!(settings.positions, [0; 3]);
+        assert_eq!(settings.reflector.n(), 0);
+        assert!(settings.plug_pairs.is_empty());
+    }
+
+    #[cfg(feature = "redact-debug")]
+    #[test]
+    fn test_debug_redacted() {
+        let debug = format!("{:?}", create_test_settings());
+        assert!(debug.contains("positions"));
+        assert!(!debug.contains("plug_pairs"));
+        assert!(!debug.contains("perm"));
+    }
+
+    #[test]
+    fn test_diff_same() {
+        let settings = create_test_settings();
+        let mut other = settings.clone();
+        other.plug_pairs = vec![(rune('d'), rune('c')), (rune('a'), rune('b'))];
+
+        let diff = settings.diff(&other);
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "no differences");
+    }
+
+    #[test]
+    fn test_diff_rotor_order() {
+        let settings = create_test_settings();
+        let mut other = settings.clone();
+        other.rotator_wirings.rotate_left(1);
+
+        let diff = settings.diff(&other);
+        assert_eq!(diff.rotor_order, Some([1, 2, 0]));
+        assert!(diff.rotator_wirings.is_empty());
+        assert_eq!(diff.to_string(), "rotor order: 2 3 1");
+    }
+
+    #[test]
+    fn test_diff() {
+        let settings = create_test_settings();
+        let mut other = settings.clone();
+        other.rotator_wirings[1] = wiring("esovpzjayquirhxlnftgkdcmwb");
+        other.positions[2] = 0;
+        other.rings[1] = 25;
+        other.notches[0] = vec![rune('q')];
+        other.stepping = SteppingMode::WehrmachtLever;
+        other.reflector = wiring("fvpjiaoyedrzxwgctkuqsbnmhl");
+        other.plug_pairs = vec![(rune('b'), rune('a')), (rune('z'), rune('e'))];
+
+        let diff = settings.diff(&other);
+        assert_eq!(diff.rotor_order, None);
+        assert_eq!(diff.rotator_wirings, vec![1]);
+        assert_eq!(diff.positions, vec![(2, 11, 0)]);
+        assert_eq!(diff.rings, vec![(1, 0, 25)]);
+        assert_eq!(diff.notches, vec![0]);
+        assert!(diff.stepping);
+        assert!(diff.reflector);
+        assert_eq!(diff.added_plug_pairs, vec![(rune('e'), rune('z'))]);
+        assert_eq!(diff.removed_plug_pairs, vec![(rune('c'), rune('d'))]);
+        assert_eq!(diff.to_string(), "\
+            wiring of rotator 2 differs\n\
+            position of rotator 3: L -> A\n\
+            ring of rotator 2: A -> Z\n\
+            notches of rotator 1 differ\n\
+            stepping mode differs\n\
+            wiring of reflector differs\n\
+            plug pairs added: EZ\n\
+            plug pairs removed: CD");
+    }
+
+    mod keyspace_tests {
+        use super::*;
+
+        #[test]
+        fn test_len() {
+            assert_eq!(Keyspace::new(3).num_rotor_orders(), 6);
+            assert_eq!(Keyspace::new(5).num_rotor_orders(), 60);
+            assert_eq!(Keyspace::new(8).len(), 336 * 17576);
+            assert!(Keyspace::new(2).is_empty());
+        }
+
+        #[test]
+        fn test_rotor_orders() {
+            let orders = Keyspace::new(4).rotor_orders();
+            assert_eq!(orders.len(), 24);
+            assert_eq!(orders[0], [0, 1, 2]);
+            assert_eq!(orders[1], [0, 1, 3]);
+            assert_eq!(orders[2], [0, 2, 1]);
+            assert_eq!(orders[23], [3, 2, 1]);
+
+            let mut sorted = orders.clone();
+            sorted.sort();
+            sorted.dedup();
+            assert_eq!(sorted, orders);
+        }
+
+        #[test]
+        fn test_rank_unrank() {
+            let keyspace = Keyspace::new(5);
+            assert_eq!(keyspace.rank([0, 1, 2], [0, 0, 0]), Some(0));
+            assert_eq!(keyspace.rank([0, 1, 2], [1, 0, 0]), Some(1));
+            assert_eq!(keyspace.rank([0, 1, 2], [0, 1, 0]), Some(26));
+            assert_eq!(keyspace.rank([4, 3, 2], [25, 25, 25]), Some(keyspace.len() - 1));
+
+            for rank in (0..keyspace.len()).step_by(9973) {
+                let (order, positions) = keyspace.unrank(rank).unwrap();
+                assert_eq!(keyspace.rank(order, positions), Some(rank));
+            }
+            assert_eq!(keyspace.unrank(keyspace.len()), None);
+        }
+
+        #[test]
+        fn test_rank_invalid() {
+            let keyspace = Keyspace::new(5);
+            assert_eq!(keyspace.rank([0, 0, 1], [0, 0, 0]), None);
+            assert_eq!(keyspace.rank([0, 1, 5], [0, 0, 0]), None);
+            assert_eq!(keyspace.rank([0, 1, 2], [0, 26, 0]), None);
+        }
+
+        #[test]
+        fn test_shard() {
+            let keyspace = Keyspace::new(3);
+            let shards: Vec<Range<u64>> = (0..7).map(|i| keyspace.shard(i, 7)).collect();
+
+            assert_eq!(shards[0].start, 0);
+            assert_eq!(shards[6].end, keyspace.len());
+            for pair in shards.windows(2) {
+                assert_eq!(pair[0].end, pair[1].start);
+            }
+            assert!(shards.iter().all(|shard| {
+                let len = shard.end - shard.start;
+                len == keyspace.len() / 7 || len == keyspace.len() / 7 + 1
+            }));
+        }
+
+        #[test]
+        #[should_panic]
+        fn test_shard_out_of_range() {
+            Keyspace::new(3).shard(2, 2);
+        }
+    }
+}
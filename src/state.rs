@@ -0,0 +1,315 @@
+//! This module persists the state of Wehrmacht Enigma machines in a small versioned binary format,
+//! so that long-lived services can save their machines and restore them after being restarted,
+//! even by a newer version of this crate.
+//!
+//! An [`EnigmaState`] refers to the rotators and reflectors of the Wehrmacht Enigma by their
+//! indices within `testvectors::ROTATORS` and `testvectors::REFLECTORS`, and holds the window
+//! letters, the ring settings and the plug pairs of the machine:
+//!
+//! ```
+//! # use enigma::state::EnigmaState;
+//! #
+//! let mut state = EnigmaState {
+//!     rotor_order: [1, 3, 4],
+//!     reflector: 1,
+//!     offsets: [1, 11, 0],
+//!     rings: [1, 20, 11],
+//!     plug_pairs: Vec::new(),
+//! };
+//!
+//! let mut machine = state.create_enigma().unwrap();
+//! machine.map_str("HELLO");
+//! state.update_offsets(&machine);
+//!
+//! let loaded = EnigmaState::from_bytes(&state.to_bytes()).unwrap();
+//! assert_eq!(loaded, state);
+//! assert_eq!(loaded.offsets, [1, 11, 5]);
+//! ```
+//!
+//! The binary format consists of the magic bytes `ENST`, a format version byte, the indices of the
+//! 3 rotators, the index of the reflector, the 3 window letters and the 3 ring settings, a byte
+//! holding the number of plug pairs and the 2 letters of each pair. The rotators are listed from
+//! left to right and letters are stored as their values, e.g. `0` for `A`. Files written by older
+//! versions of the format are still loaded.
+//!
+//! [`EnigmaState`]: struct.EnigmaState.html
+//!
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use crate::{Enigma, PlugBoard};
+use crate::machines::InvalidStateError;
+use crate::settings::plug_pairs_to_perm;
+use crate::testvectors::{self, REFLECTORS, ROTATORS};
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// The magic bytes at the start of a state file.
+const MAGIC: &[u8; 4] = b"ENST";
+
+/// The version of the binary format written by `to_bytes`.
+const FORMAT_VERSION: u8 = 1;
+
+/// The length of the fixed part of a state file, up to and including the number of plug pairs.
+const HEADER_LEN: usize = 16;
+
+/// The maximal number of plug pairs.
+const MAX_PLUG_PAIRS: usize = RUNE_SET_SIZE as usize / 2;
+
+/// Error indicating that a state file cannot be saved or loaded.
+#[derive(Debug)]
+pub enum StateFileError {
+    /// The state file is malformed.
+    Format,
+
+    /// The state file was written by a newer version of the format.
+    Version {
+        /// The version of the state file.
+        version: u8,
+    },
+
+    /// The state file cannot be read or written.
+    Io(std::io::Error),
+}
+
+impl Display for StateFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateFileError::Format => f.write_str("malformed state file"),
+            StateFileError::Version { version } =>
+                write!(f, "unsupported state file version {}", version),
+            StateFileError::Io(e) => write!(f, "cannot access state file: {}", e),
+        }
+    }
+}
+
+impl Error for StateFileError { }
+
+impl From<std::io::Error> for StateFileError {
+    fn from(e: std::io::Error) -> Self {
+        StateFileError::Io(e)
+    }
+}
+
+/// The state of a Wehrmacht Enigma machine.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct EnigmaState {
+    /// The indices of the rotators within `testvectors::ROTATORS`, from left to right.
+    pub rotor_order: [u8; 3],
+
+    /// The index of the reflector within `testvectors::REFLECTORS`.
+    pub reflector: u8,
+
+    /// The values of the window letters, from left to right.
+    pub offsets: [u8; 3],
+
+    /// The values of the ring settings, from left to right.
+    pub rings: [u8; 3],
+
+    /// The pairs of letters connected on the plug board.
+    pub plug_pairs: Vec<(Rune, Rune)>,
+}
+
+impl EnigmaState {
+    /// Create the machine in this state.
+    ///
+    /// The machine maps its next rune at the current window letters and steps afterwards, as all
+    /// machines of this crate do. This function fails if an index, window letter or ring setting
+    /// is out of range, or if the plug pairs do not form a valid plug board.
+    pub fn create_enigma(&self) -> Result<Enigma, InvalidStateError> {
+        if !self.is_in_range() {
+            return Err(InvalidStateError);
+        }
+
+        let perm = plug_pairs_to_perm(&self.plug_pairs).map_err(|_| InvalidStateError)?;
+        let plug = PlugBoard::from_perm(perm).map_err(|_| InvalidStateError)?;
+
+        testvectors::create_enigma(self.rotor_order.map(usize::from), self.reflector as usize,
+            self.rings, self.offsets, plug)
+            .ok_or(InvalidStateError)
+    }
+
+    /// Set the window letters of this state to the current window letters of the specified
+    /// machine, which should have been created by `create_enigma`.
+    pub fn update_offsets(&mut self, machine: &Enigma) {
+        // The rotator group lists the rotators from right to left, and holds the window letters
+        // shifted back by the ring settings.
+        let offsets = machine.rotators.offsets();
+        for i in 0..3 {
+            self.offsets[i] = (offsets[2 - i] + self.rings[i]) % RUNE_SET_SIZE;
+        }
+    }
+
+    /// Determine whether the indices, window letters and ring settings of this state are in range.
+    fn is_in_range(&self) -> bool {
+        self.rotor_order.iter().all(|i| (*i as usize) < ROTATORS.len()) &&
+            (self.reflector as usize) < REFLECTORS.len() &&
+            self.offsets.iter().chain(self.rings.iter()).all(|x| *x < RUNE_SET_SIZE)
+    }
+
+    /// Write this state in the binary format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + 2 * self.plug_pairs.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&self.rotor_order);
+        bytes.push(self.reflector);
+        bytes.extend_from_slice(&self.offsets);
+        bytes.extend_from_slice(&self.rings);
+        bytes.push(self.plug_pairs.len() as u8);
+        for (a, b) in &self.plug_pairs {
+            bytes.extend_from_slice(&[a.value(), b.value()]);
+        }
+        bytes
+    }
+
+    /// Read a state written in the binary format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StateFileError> {
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return Err(StateFileError::Format);
+        }
+        if bytes[4] == 0 || bytes[4] > FORMAT_VERSION {
+            return Err(StateFileError::Version { version: bytes[4] });
+        }
+
+        let num_plug_pairs = bytes[15] as usize;
+        if num_plug_pairs > MAX_PLUG_PAIRS || bytes.len() != HEADER_LEN + 2 * num_plug_pairs {
+            return Err(StateFileError::Format);
+        }
+
+        let plug_pairs = bytes[HEADER_LEN..].chunks_exact(2)
+            .map(|pair| match (Rune::from_value(pair[0]), Rune::from_value(pair[1])) {
+                (Ok(a), Ok(b)) => Ok((a, b)),
+                _ => Err(StateFileError::Format),
+            })
+            .collect::<Result<_, _>>()?;
+
+        let state = Self {
+            rotor_order: [bytes[5], bytes[6], bytes[7]],
+            reflector: bytes[8],
+            offsets: [bytes[9], bytes[10], bytes[11]],
+            rings: [bytes[12], bytes[13], bytes[14]],
+            plug_pairs,
+        };
+        if !state.is_in_range() {
+            return Err(StateFileError::Format);
+        }
+        Ok(state)
+    }
+
+    /// Save this state into the file at the specified path, replacing its content.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), StateFileError> {
+        std::fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Load a state from the file at the specified path.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, StateFileError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testvectors::TEST_VECTORS;
+
+    fn rune(ch: char) -> Rune {
+        Rune::from_char(ch).unwrap()
+    }
+
+    fn create_test_state() -> EnigmaState {
+        EnigmaState {
+            rotor_order: [1, 3, 4],
+            reflector: 1,
+            offsets: [11, 18, 3],
+            rings: [1, 20, 11],
+            plug_pairs: vec![(rune('a'), rune('v')), (rune('b'), rune('s'))],
+        }
+    }
+
+    #[test]
+    fn test_create_enigma() {
+        // Barbarossa, part 2: rotors II IV V, rings BUL, message key LSD. The machine of the test
+        // vector steps before mapping, so it is one step ahead of the state.
+        let mut state = create_test_state();
+        state.plug_pairs = "AV BS CG DL FU HZ IN KM OW RX".split_whitespace()
+            .map(|pair| (rune(pair.chars().next().unwrap()), rune(pair.chars().nth(1).unwrap())))
+            .collect();
+        state.offsets[2] += 1;
+
+        let vector = &TEST_VECTORS[2];
+        let mut machine = state.create_enigma().unwrap();
+        assert!(machine.is_equivalent(&vector.create_enigma(), 1000));
+
+        machine.map_str("ABCDE");
+        state.update_offsets(&machine);
+        assert_eq!(state.offsets, [11, 18, 9]);
+    }
+
+    #[test]
+    fn test_create_enigma_invalid() {
+        let mut state = create_test_state();
+        state.rotor_order[0] = 5;
+        assert!(state.create_enigma().is_err());
+
+        let mut state = create_test_state();
+        state.rings[1] = 26;
+        assert!(state.create_enigma().is_err());
+
+        let mut state = create_test_state();
+        state.plug_pairs.push((rune('v'), rune('c')));
+        assert!(state.create_enigma().is_err());
+    }
+
+    mod binary_tests {
+        use super::*;
+
+        #[test]
+        fn test_round_trip() {
+            let state = create_test_state();
+            let bytes = state.to_bytes();
+            assert_eq!(bytes.len(), HEADER_LEN + 4);
+            assert_eq!(EnigmaState::from_bytes(&bytes).unwrap(), state);
+        }
+
+        #[test]
+        fn test_from_bytes_invalid() {
+            let bytes = create_test_state().to_bytes();
+            assert!(matches!(EnigmaState::from_bytes(&bytes[..bytes.len() - 1]),
+                Err(StateFileError::Format)));
+
+            let mut corrupted = bytes.clone();
+            corrupted[0] = b'X';
+            assert!(matches!(EnigmaState::from_bytes(&corrupted), Err(StateFileError::Format)));
+
+            let mut corrupted = bytes.clone();
+            corrupted[4] = FORMAT_VERSION + 1;
+            assert!(matches!(EnigmaState::from_bytes(&corrupted),
+                Err(StateFileError::Version { .. })));
+
+            let mut corrupted = bytes.clone();
+            corrupted[8] = 2;
+            assert!(matches!(EnigmaState::from_bytes(&corrupted), Err(StateFileError::Format)));
+
+            let mut corrupted = bytes;
+            corrupted[HEADER_LEN] = 26;
+            assert!(matches!(EnigmaState::from_bytes(&corrupted), Err(StateFileError::Format)));
+        }
+
+        #[test]
+        fn test_save_load() {
+            let path = std::env::temp_dir()
+                .join(format!("enigma-state-{}.bin", std::process::id()));
+            let state = create_test_state();
+            state.save(&path).unwrap();
+            let loaded = EnigmaState::load(&path);
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(loaded.unwrap(), state);
+
+            assert!(matches!(EnigmaState::load(&path), Err(StateFileError::Io(_))));
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! This module implements the `StreamCipher` trait of the RustCrypto `cipher` crate for the
+//! reciprocal rotor machines, so that they can be used by code written against the RustCrypto
+//! traits. It is only available with the `cipher` feature.
+//!
+//! The machines work on bytes as follows:
+//! - Each ASCII letter is mapped as a rune by the machine, which then advances its rotors. The
+//!   letter case is preserved.
+//! - Other bytes are copied unchanged, and the rotors do not advance.
+//!
+//! The keystream never ends, so the fallible functions of the trait never fail. Since the machines
+//! are reciprocal, applying the keystream twice from the same state restores the input:
+//!
+//! ```
+//! use cipher::StreamCipher;
+//! # use enigma::variants::EnigmaG;
+//!
+//! let mut buf = *b"Attack at dawn!";
+//! EnigmaG::historical([2, 0, 1], [4, 17, 9], 12).apply_keystream(&mut buf);
+//! assert_ne!(&buf, b"Attack at dawn!");
+//!
+//! EnigmaG::historical([2, 0, 1], [4, 17, 9], 12).apply_keystream(&mut buf);
+//! assert_eq!(&buf, b"Attack at dawn!");
+//! ```
+//!
+//! The SIGABA is not reciprocal, so it does not implement the trait.
+//!
+
+use ::cipher::inout::InOutBuf;
+use ::cipher::{StreamCipher, StreamCipherError};
+
+use crate::Enigma;
+use crate::machines::{RotorMachine, Typex};
+use crate::variants::{CommercialEnigma, EnigmaG};
+
+fn apply_keystream<M: RotorMachine>(machine: &mut M, mut buf: InOutBuf<'_, '_, u8>) {
+    for i in 0..buf.len() {
+        let mut byte = buf.get(i);
+        *byte.get_out() = machine.map_byte(*byte.get_in());
+    }
+}
+
+macro_rules! impl_stream_cipher {
+    ($($machine:ty),*) => {
+        $(
+            impl StreamCipher for $machine {
+                fn try_apply_keystream_inout(&mut self, buf: InOutBuf<'_, '_, u8>)
+                    -> Result<(), StreamCipherError> {
+                    apply_keystream(self, buf);
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_stream_cipher!(Enigma, EnigmaG, CommercialEnigma, Typex);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::variants::Model;
+
+    #[test]
+    fn test_apply_keystream() {
+        let plaintext = b"Meet me at 10:30, by the river.";
+        let mut buf = *plaintext;
+        CommercialEnigma::historical(Model::K, [2, 1, 0], [3, 14, 25], 7).apply_keystream(&mut buf);
+
+        let mut expected = CommercialEnigma::historical(Model::K, [2, 1, 0], [3, 14, 25], 7);
+        let expected = expected.map_str("MEETMEATBYTHERIVER");
+        let letters: String = buf.iter().filter(|b| b.is_ascii_alphabetic())
+            .map(|b| b.to_ascii_uppercase() as char)
+            .collect();
+        assert_eq!(letters, expected);
+
+        for (p, c) in plaintext.iter().zip(&buf) {
+            assert_eq!(p.is_ascii_lowercase(), c.is_ascii_lowercase());
+            if !p.is_ascii_alphabetic() {
+                assert_eq!(p, c);
+            }
+        }
+
+        CommercialEnigma::historical(Model::K, [2, 1, 0], [3, 14, 25], 7).apply_keystream(&mut buf);
+        assert_eq!(&buf, plaintext);
+    }
+
+    #[test]
+    fn test_apply_keystream_b2b() {
+        let mut output = [0u8; 5];
+        let mut machine = EnigmaG::historical([0, 1, 2], [0, 0, 0], 0);
+        assert!(machine.apply_keystream_b2b(b"HELLO", &mut output).is_ok());
+        assert_eq!(&output[..], EnigmaG::historical([0, 1, 2], [0, 0, 0], 0).map_str("HELLO")
+            .as_bytes());
+        assert!(machine.apply_keystream_b2b(b"HELLO", &mut output[..4]).is_err());
+    }
+}
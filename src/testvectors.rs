@@ -0,0 +1,229 @@
+//! This module bundles published Enigma messages with their keys, so that the historical accuracy
+//! of the machine can be asserted.
+//!
+//! Each [`TestVector`] holds the key of a message as it was written on the key sheet, e.g. the
+//! rotor order from left to right, the ring settings, the plug pairs and the message key, and the
+//! ciphertext and the plaintext of the message. The `verify_all` function decrypts all bundled
+//! messages and reports the first one that does not decrypt to its plaintext:
+//!
+//! ```
+//! # use enigma::testvectors;
+//! #
+//! testvectors::verify_all().unwrap();
+//! ```
+//!
+//! The machines of this crate have no separate ring settings. A ring setting is emulated by
+//! shifting the offset and the notch of a rotator back by the ring setting, which leaves the
+//! mapping of the rotator at every window letter unchanged. The messages of the four-rotor naval
+//! Enigma M4, e.g. the message of Karl Dönitz of 1 May 1945, are not included since the machines
+//! of this crate have 3 rotators.
+//!
+//! [`TestVector`]: struct.TestVector.html
+//!
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::{Enigma, PlugBoard, Reflector, Rotator, RotatorGroup, Rune};
+use crate::components::stepping::Lever;
+use crate::math::{Permutation, PermutationBuilder};
+use crate::utils::RUNE_SET_SIZE;
+
+/// The names, wirings and notches of the rotators I to V of the Wehrmacht Enigma.
+pub const ROTATORS: [(&str, &str, char); 5] = [
+    ("I", "ekmflgdqvzntowyhxuspaibrcj", 'q'),
+    ("II", "ajdksiruxblhwtmcqgznpyfvoe", 'e'),
+    ("III", "bdfhjlcprtxvznyeiwgakmusqo", 'v'),
+    ("IV", "esovpzjayquirhxlnftgkdcmwb", 'j'),
+    ("V", "vzbrgityupsdnhlxawmjqofeck", 'z'),
+];
+
+/// The names and wirings of the reflectors A and B of the Wehrmacht Enigma.
+pub const REFLECTORS: [(&str, &str); 2] = [
+    ("A", "ejmzalyxvbwfcrquontspikhgd"),
+    ("B", "yruhqsldpxngokmiebfzcwvjat"),
+];
+
+/// Error indicating that a test vector does not decrypt to its plaintext.
+#[derive(Clone, Copy, Debug)]
+pub struct TestVectorMismatchError {
+    /// The name of the test vector.
+    pub name: &'static str,
+}
+
+impl Display for TestVectorMismatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "test vector \"{}\" does not decrypt to its plaintext", self.name)
+    }
+}
+
+impl Error for TestVectorMismatchError { }
+
+/// A published message and its key. The discriminant and the indicator groups of the message are
+/// not part of the ciphertext.
+#[derive(Clone, Copy, Debug)]
+pub struct TestVector {
+    /// The name of the message.
+    pub name: &'static str,
+
+    /// The names of the rotators within `ROTATORS`, from left to right.
+    pub rotor_order: [&'static str; 3],
+
+    /// The name of the reflector within `REFLECTORS`.
+    pub reflector: &'static str,
+
+    /// The ring settings, from left to right.
+    pub ring_settings: &'static str,
+
+    /// The pairs of letters connected on the plug board, separated by spaces.
+    pub plug_pairs: &'static str,
+
+    /// The window letters at which the message is enciphered, from left to right.
+    pub message_key: &'static str,
+
+    /// The ciphertext in groups of 5 letters.
+    pub ciphertext: &'static str,
+
+    /// The plaintext in groups of 5 letters.
+    pub plaintext: &'static str,
+}
+
+/// The bundled test vectors.
+pub const TEST_VECTORS: [TestVector; 3] = [
+    TestVector {
+        name: "Enigma instruction manual, 1930",
+        rotor_order: ["II", "I", "III"],
+        reflector: "A",
+        ring_settings: "XMV",
+        plug_pairs: "AM FI NV PS TU WZ",
+        message_key: "ABL",
+        ciphertext: "GCDSE AHUGW TQGRK VLFGX UCALX VYMIG MMNMF DXTGN VHVRM MEVOU YFZSL RHDRR XFJWC \
+            FHUHM UNZEF RDISI KBGPM YVXUZ",
+        plaintext: "FEIND LIQEI NFANT ERIEK OLONN EBEOB AQTET XANFA NGSUE DAUSG ANGBA ERWAL DEXEN \
+            DEDRE IKMOS TWAER TSNEU STADT",
+    },
+    TestVector {
+        name: "Operation Barbarossa, part 1, 1941",
+        rotor_order: ["II", "IV", "V"],
+        reflector: "B",
+        ring_settings: "BUL",
+        plug_pairs: "AV BS CG DL FU HZ IN KM OW RX",
+        message_key: "BLA",
+        ciphertext: "EDPUD NRGYS ZRCXN UYTPO MRMBO FKTBZ REZKM LXLVE FGUEY SIOZV EQMIK UBPMM YLKLT \
+            TDEIS MDICA GYKUA CTCDO MOHWX MUUIA UBSTS LRNBZ SZWNR FXWFY SSXJZ VIJHI DISHP RKLKA \
+            YUPAD TXQSP INQMA TLPIF SVKDA SCTAC DPBOP VHJK",
+        plaintext: "AUFKL XABTE ILUNG XVONX KURTI NOWAX KURTI NOWAX NORDW ESTLX SEBEZ XSEBE ZXUAF \
+            FLIEG ERSTR ASZER IQTUN GXDUB ROWKI XDUBR OWKIX OPOTS CHKAX OPOTS CHKAX UMXEI NSAQT \
+            DREIN ULLXU HRANG ETRET ENXAN GRIFF XINFX RGTX",
+    },
+    TestVector {
+        name: "Operation Barbarossa, part 2, 1941",
+        rotor_order: ["II", "IV", "V"],
+        reflector: "B",
+        ring_settings: "BUL",
+        plug_pairs: "AV BS CG DL FU HZ IN KM OW RX",
+        message_key: "LSD",
+        ciphertext: "SFBWD NJUSE GQOBH KRTAR EEZMW KPPRB XOHDR OEQGB BGTQV PGVKB VVGBI MHUSZ YDAJQ \
+            IROAX SSSNR EHYGG RPISE ZBOVM QIEMM ZCYSG QDGRE RVBIL EKXYQ IRGIR QNRDN VRXCY YTNJR",
+        plaintext: "DREIG EHTLA NGSAM ABERS IQERV ORWAE RTSXE INSSI EBENN ULLSE QSXUH RXROE MXEIN \
+            SXINF RGTXD REIXA UFFLI EGERS TRASZ EMITA NFANG XEINS SEQSX KMXKM XOSTW XKAME NECXK",
+    },
+];
+
+/// Get the value of the specified letter.
+fn value(ch: char) -> u8 {
+    Rune::from_char(ch).unwrap().value()
+}
+
+/// Create the permutation that maps `i` to the i-th of the specified letters.
+fn wiring(letters: &str) -> Permutation {
+    Permutation::from_perm(letters.chars().map(value).collect()).unwrap()
+}
+
+/// Create a Wehrmacht Enigma from the specified indices of rotators within `ROTATORS` and ring
+/// settings and window letters, all from left to right, the specified index of the reflector within
+/// `REFLECTORS` and the specified plug board. Returns `None` if an index is out of range. The
+/// ring settings and window letters should be less than `RUNE_SET_SIZE`.
+pub(crate) fn create_enigma(rotor_order: [usize; 3], reflector: usize, rings: [u8; 3],
+    positions: [u8; 3], plug: PlugBoard) -> Option<Enigma> {
+    if rotor_order.iter().any(|i| *i >= ROTATORS.len()) || reflector >= REFLECTORS.len() {
+        return None;
+    }
+
+    // The rotator group lists the rotators from right to left.
+    let rotators = [2, 1, 0].map(|i| {
+        let (_, perm, notch) = ROTATORS[rotor_order[i]];
+        let shift = |x: u8| (x + RUNE_SET_SIZE - rings[i]) % RUNE_SET_SIZE;
+        let notch = Rune::from_value(shift(value(notch))).unwrap();
+        Rotator::new(wiring(perm), shift(positions[i])).unwrap().with_notches(&[notch])
+    });
+
+    Some(Enigma::new(plug, RotatorGroup::new(rotators).with_stepping(Lever),
+        Reflector::from_perm(wiring(REFLECTORS[reflector].1)).unwrap())
+        .with_ring_settings(rings))
+}
+
+impl TestVector {
+    /// Create the machine described by this test vector, set to the message key.
+    ///
+    /// This function panics if the test vector refers to an unknown rotator or reflector.
+    pub fn create_enigma(&self) -> Enigma {
+        let rings: Vec<u8> = self.ring_settings.chars().map(value).collect();
+        let positions: Vec<u8> = self.message_key.chars().map(value).collect();
+        let rotor_order = [0, 1, 2].map(|i| {
+            ROTATORS.iter().position(|(name, _, _)| *name == self.rotor_order[i]).unwrap()
+        });
+        let reflector = REFLECTORS.iter().position(|(name, _)| *name == self.reflector).unwrap();
+
+        let mut plug = PermutationBuilder::new(RUNE_SET_SIZE);
+        for pair in self.plug_pairs.split_whitespace() {
+            let mut letters = pair.chars().map(value);
+            plug = plug.swap(letters.next().unwrap(), letters.next().unwrap());
+        }
+
+        let mut machine = create_enigma(rotor_order, reflector, [rings[0], rings[1], rings[2]],
+            [positions[0], positions[1], positions[2]], PlugBoard::from_perm(plug.build()).unwrap())
+            .unwrap();
+
+        // The rotators of the Wehrmacht Enigma step before the signal passes through them.
+        machine.advance_rotators();
+        machine.reset_stats();
+        machine
+    }
+
+    /// Determine whether the ciphertext of this test vector decrypts to its plaintext.
+    pub fn verify(&self) -> bool {
+        let plaintext: String = self.plaintext.split_whitespace().collect();
+        self.create_enigma().map_str(self.ciphertext).eq_ignore_ascii_case(&plaintext)
+    }
+}
+
+/// Verify all bundled test vectors, and return the first one that does not decrypt to its
+/// plaintext.
+pub fn verify_all() -> Result<(), TestVectorMismatchError> {
+    match TEST_VECTORS.iter().find(|vector| !vector.verify()) {
+        Some(vector) => Err(TestVectorMismatchError { name: vector.name }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_all() {
+        for vector in &TEST_VECTORS {
+            assert!(vector.verify(), "{}", vector.name);
+        }
+        assert!(verify_all().is_ok());
+    }
+
+    #[test]
+    fn test_encrypt() {
+        let vector = &TEST_VECTORS[0];
+        let plaintext: String = vector.plaintext.split_whitespace().collect();
+        let ciphertext: String = vector.ciphertext.split_whitespace().collect();
+        assert_eq!(vector.create_enigma().map_str(&plaintext).to_ascii_uppercase(), ciphertext);
+    }
+}
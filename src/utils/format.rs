@@ -0,0 +1,189 @@
+//! This module provides utilities for shaping the text produced by the Enigma machine.
+//!
+//! Enigma messages were traditionally transmitted in groups of letters of equal length, e.g. 5
+//! letters per group, with a fixed number of groups on each line. The [`TextFormatter`] type
+//! arranges text in this shape:
+//!
+//! ```
+//! # use enigma::utils::format::{LetterCase, TextFormatter};
+//! #
+//! let formatter = TextFormatter::new()
+//!     .group_size(5)
+//!     .line_width(11)
+//!     .case(LetterCase::Lower);
+//! assert_eq!(formatter.format("HELLOWORLDFOO"), "hello world\nfoo");
+//! ```
+//!
+//! The inverse operation, which removes the grouping from a text, is provided by the [`ungroup`]
+//! function:
+//!
+//! ```
+//! # use enigma::utils::format::ungroup;
+//! #
+//! assert_eq!(ungroup("HELLO WORLD\nFOO"), "HELLOWORLDFOO");
+//! ```
+//!
+//! [`TextFormatter`]: struct.TextFormatter.html
+//! [`ungroup`]: fn.ungroup.html
+//!
+
+/// The letter case of the formatted text.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LetterCase {
+    /// Keep the letter case of the input text.
+    Keep,
+
+    /// Convert all letters to upper case.
+    Upper,
+
+    /// Convert all letters to lower case.
+    Lower,
+}
+
+/// Arrange text in groups and lines.
+///
+/// By default, a formatter keeps the letter case and does not group letters or break lines.
+#[derive(Clone, Debug)]
+pub struct TextFormatter {
+    group_size: usize,
+    line_width: usize,
+    case: LetterCase,
+}
+
+impl TextFormatter {
+    /// Create a new formatter with the default options.
+    pub fn new() -> Self {
+        Self {
+            group_size: 0,
+            line_width: 0,
+            case: LetterCase::Keep,
+        }
+    }
+
+    /// Set the number of letters within each group. Groups are separated by a space. A group size
+    /// of `0` disables grouping.
+    pub fn group_size(mut self, group_size: usize) -> Self {
+        self.group_size = group_size;
+        self
+    }
+
+    /// Set the maximal number of characters on each line, including the spaces between groups.
+    /// When grouping is enabled, lines are only broken between groups, so a line may be longer
+    /// than the width if a single group does not fit. A line width of `0` disables line breaking.
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// Set the letter case of the formatted text.
+    pub fn case(mut self, case: LetterCase) -> Self {
+        self.case = case;
+        self
+    }
+
+    /// Format the specified text. All whitespace characters within the text are discarded before
+    /// the text is arranged.
+    pub fn format(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars()
+            .filter(|ch| !ch.is_whitespace())
+            .map(|ch| match self.case {
+                LetterCase::Keep => ch,
+                LetterCase::Upper => ch.to_ascii_uppercase(),
+                LetterCase::Lower => ch.to_ascii_lowercase(),
+            })
+            .collect();
+
+        let chunk_size = if self.group_size == 0 {
+            if self.line_width == 0 { chars.len().max(1) } else { self.line_width }
+        } else {
+            self.group_size
+        };
+
+        let mut output = String::with_capacity(chars.len() * 2);
+        let mut current_line_len = 0usize;
+        for chunk in chars.chunks(chunk_size) {
+            if current_line_len > 0 {
+                let separator_len = if self.group_size == 0 { 0 } else { 1 };
+                if self.line_width == 0 ||
+                    current_line_len + separator_len + chunk.len() <= self.line_width {
+                    if separator_len > 0 {
+                        output.push(' ');
+                    }
+                    current_line_len += separator_len;
+                } else {
+                    output.push('\n');
+                    current_line_len = 0;
+                }
+            }
+
+            output.extend(chunk);
+            current_line_len += chunk.len();
+        }
+
+        output
+    }
+}
+
+impl Default for TextFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Remove all whitespace characters, which separate groups and lines, from the specified text.
+pub fn ungroup(text: &str) -> String {
+    text.chars().filter(|ch| !ch.is_whitespace()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod text_formatter_tests {
+        use super::*;
+
+        #[test]
+        fn test_default() {
+            let formatter = TextFormatter::new();
+            assert_eq!(formatter.format("Hello World"), "HelloWorld");
+        }
+
+        #[test]
+        fn test_group_size() {
+            let formatter = TextFormatter::new().group_size(4);
+            assert_eq!(formatter.format("ABCDEFGHIJ"), "ABCD EFGH IJ");
+        }
+
+        #[test]
+        fn test_line_width_with_groups() {
+            let formatter = TextFormatter::new().group_size(2).line_width(5);
+            assert_eq!(formatter.format("ABCDEFGHIJ"), "AB CD\nEF GH\nIJ");
+        }
+
+        #[test]
+        fn test_line_width_without_groups() {
+            let formatter = TextFormatter::new().line_width(4);
+            assert_eq!(formatter.format("ABCDEFGHIJ"), "ABCD\nEFGH\nIJ");
+        }
+
+        #[test]
+        fn test_case() {
+            let formatter = TextFormatter::new().case(LetterCase::Upper);
+            assert_eq!(formatter.format("abc"), "ABC");
+
+            let formatter = TextFormatter::new().case(LetterCase::Lower);
+            assert_eq!(formatter.format("ABC"), "abc");
+        }
+
+        #[test]
+        fn test_empty() {
+            let formatter = TextFormatter::new().group_size(5).line_width(10);
+            assert_eq!(formatter.format(""), "");
+        }
+    }
+
+    #[test]
+    fn test_ungroup() {
+        assert_eq!(ungroup("AB CD\nEF"), "ABCDEF");
+    }
+}
@@ -2,6 +2,10 @@
 //!
 //! Specifically, this module provides the following components:
 //! - Runes
+//! - Text formatting, in the [`format`] submodule
+//! - Text preparation following historical conventions, in the [`preprocess`] submodule
+//! - Word segmentation of texts without spaces, in the [`segment`] submodule
+//! - Morse code, in the [`morse`] submodule
 //!
 //! # Runes
 //!
@@ -62,15 +66,42 @@
 //!
 //! ## Rune Operations
 //!
-//! [`Rune`] implements `Copy`, `Eq` and `Ord`.
+//! [`Rune`] implements `Copy`, `Eq` and `Ord`. `Rune::ALPHABET` lists all runes in order, so that
+//! loops over the alphabet need not convert letter indices:
+//!
+//! ```
+//! # use enigma::utils::Rune;
+//! #
+//! let letters: String = Rune::ALPHABET.iter().map(|rune| rune.into_char()).collect();
+//! assert_eq!(letters, "ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+//! assert_eq!(Rune::ALPHABET[0], Rune::MIN);
+//! assert_eq!(Rune::ALPHABET[25], Rune::MAX);
+//! ```
 //!
 //! [`Rune`]: struct.Rune.html
+//! [`format`]: format/index.html
+//! [`morse`]: morse/index.html
+//! [`preprocess`]: preprocess/index.html
+//! [`segment`]: segment/index.html
 //!
 
+pub mod format;
+pub mod morse;
+pub mod preprocess;
+pub mod segment;
+
 use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt::{Display, Formatter, Write};
 
+#[cfg(feature = "arbitrary")]
+use arbitrary::{Arbitrary, Unstructured};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+#[cfg(feature = "arbitrary")]
+use crate::math::Permutation;
+
 /// Error indicating that the value of a rune is out of range.
 #[derive(Clone, Copy, Debug)]
 pub struct RuneOutOfRangeError;
@@ -92,14 +123,31 @@ pub const RUNE_SET_SIZE: u8 = 26;
 /// A rune.
 ///
 /// Runes are individual characters that can be processed by the Enigma machine.
-#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Rune {
     value: u8,
 }
 
 impl Rune {
+    /// The rune of the smallest value, e.g. `A`.
+    pub const MIN: Rune = Rune { value: 0 };
+
+    /// The rune of the largest value, e.g. `Z`.
+    pub const MAX: Rune = Rune { value: RUNE_VALUE_MAX };
+
+    /// All runes in the order of their values, e.g. from `A` to `Z`.
+    pub const ALPHABET: [Rune; RUNE_SET_SIZE as usize] = {
+        let mut runes = [Rune::MIN; RUNE_SET_SIZE as usize];
+        let mut value = 0;
+        while value < RUNE_SET_SIZE {
+            runes[value as usize] = Rune { value };
+            value += 1;
+        }
+        runes
+    };
+
     /// Create a rune from the specified English letter index.
-    pub fn from_value(value: u8) -> Result<Self, RuneOutOfRangeError> {
+    pub const fn from_value(value: u8) -> Result<Self, RuneOutOfRangeError> {
         if value > RUNE_VALUE_MAX {
             return Err(RuneOutOfRangeError)
         }
@@ -111,12 +159,16 @@ impl Rune {
     ///
     /// Usage of this function is strongly discouraged. Please use the `from_value` function
     /// instead.
-    pub unsafe fn from_value_unchecked(value: u8) -> Self {
+    ///
+    /// # Safety
+    ///
+    /// The specified value must not be greater than `RUNE_VALUE_MAX`.
+    pub const unsafe fn from_value_unchecked(value: u8) -> Self {
         Self { value }
     }
 
     /// Get the index of the English letter represented by this rune.
-    pub fn value(&self) -> u8 {
+    pub const fn value(&self) -> u8 {
         self.value
     }
 
@@ -155,6 +207,31 @@ impl Rune {
     }
 }
 
+#[cfg(feature = "zeroize")]
+impl Zeroize for Rune {
+    fn zeroize(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for Rune {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self { value: u.int_in_range(0..=RUNE_VALUE_MAX)? })
+    }
+}
+
+/// Generate the specified number of arbitrary pairs of runes, where each rune appears in at most
+/// one pair, from the specified unstructured data. At most `RUNE_SET_SIZE / 2` pairs are
+/// generated.
+#[cfg(feature = "arbitrary")]
+pub(crate) fn arbitrary_pairs(u: &mut Unstructured<'_>, count: usize)
+    -> arbitrary::Result<Vec<(Rune, Rune)>> {
+    let order = Permutation::arbitrary_of_size(u, RUNE_SET_SIZE)?;
+    let rune = |i: usize| Rune { value: order.map(i as u8) };
+    Ok((0..count.min(RUNE_SET_SIZE as usize / 2)).map(|i| (rune(2 * i), rune(2 * i + 1))).collect())
+}
+
 impl Display for Rune {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_char((*self).into())
@@ -173,15 +250,15 @@ impl PartialEq<Rune> for char {
     }
 }
 
-impl Into<char> for Rune {
-    fn into(self) -> char {
-        self.into_ascii() as char
+impl From<Rune> for char {
+    fn from(rune: Rune) -> Self {
+        rune.into_ascii() as char
     }
 }
 
-impl Into<String> for Rune {
-    fn into(self) -> String {
-        self.into_string()
+impl From<Rune> for String {
+    fn from(rune: Rune) -> Self {
+        rune.into_string()
     }
 }
 
@@ -193,6 +270,68 @@ impl TryFrom<char> for Rune {
     }
 }
 
+/// Runes are serialized as uppercase letters, e.g. `"A"` in JSON. Letters of either case and the
+/// values of runes, e.g. `0` for `A`, are accepted when deserializing from human-readable formats.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Rune {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: serde::Serializer {
+        serializer.serialize_char(self.into_char())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Rune {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: serde::Deserializer<'de> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(RuneVisitor)
+        } else {
+            deserializer.deserialize_char(RuneVisitor)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+struct RuneVisitor;
+
+#[cfg(feature = "serde")]
+impl serde::de::Visitor<'_> for RuneVisitor {
+    type Value = Rune;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("an ASCII letter or a rune value from 0 to 25")
+    }
+
+    fn visit_char<E>(self, v: char) -> Result<Self::Value, E>
+        where E: serde::de::Error {
+        Rune::from_char(v).map_err(|_| E::invalid_value(serde::de::Unexpected::Char(v), &self))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where E: serde::de::Error {
+        let mut chars = v.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => self.visit_char(ch),
+            _ => Err(E::invalid_value(serde::de::Unexpected::Str(v), &self)),
+        }
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+        where E: serde::de::Error {
+        u8::try_from(v).ok()
+            .and_then(|value| Rune::from_value(value).ok())
+            .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+        where E: serde::de::Error {
+        u64::try_from(v)
+            .map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(v), &self))
+            .and_then(|v| self.visit_u64(v))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -212,6 +351,31 @@ mod tests {
             assert!(Rune::from_value(b'c').is_err());
         }
 
+        #[test]
+        fn test_constants() {
+            assert_eq!(Rune::MIN, 'a');
+            assert_eq!(Rune::MAX, 'z');
+            assert_eq!(Rune::MAX.value(), RUNE_VALUE_MAX);
+            for (i, rune) in Rune::ALPHABET.iter().enumerate() {
+                assert_eq!(rune.value() as usize, i);
+            }
+        }
+
+        #[cfg(feature = "serde_json")]
+        #[test]
+        fn test_serde() {
+            let rune = Rune::from_char('q').unwrap();
+            assert_eq!(serde_json::to_string(&rune).unwrap(), "\"Q\"");
+            assert_eq!(serde_json::to_string(&Rune::ALPHABET[..2]).unwrap(), "[\"A\",\"B\"]");
+
+            for json in &["\"Q\"", "\"q\"", "16"] {
+                assert_eq!(serde_json::from_str::<Rune>(json).unwrap(), rune);
+            }
+            for json in &["\"QQ\"", "\"\"", "\"1\"", "26", "-1", "null"] {
+                assert!(serde_json::from_str::<Rune>(json).is_err(), "{}", json);
+            }
+        }
+
         #[test]
         fn test_value() {
             let rune = Rune::from_value(2).unwrap();
@@ -0,0 +1,227 @@
+//! This module converts texts to and from International Morse code, in which Enigma messages were
+//! transmitted by radio.
+//!
+//! Morse code is written with `.` for dots and `-` for dashes. The codes of the letters of a word
+//! are separated by a space and words are separated by ` / `:
+//!
+//! ```
+//! # use enigma::utils::morse;
+//! #
+//! let code = morse::encode("SOS 73").unwrap();
+//! assert_eq!(code, "... --- ... / --... ...--");
+//! assert_eq!(morse::decode(&code).unwrap(), "SOS 73");
+//! ```
+//!
+//! Procedural signals (prosigns), which are sent as a single character, are written as their
+//! letters in angle brackets, e.g. `<AR>` for the end of a message. The supported prosigns are
+//! `<KA>` (start of message), `<BT>` (break), `<AR>` (end of message), `<AS>` (wait), `<SK>` (end
+//! of contact) and `<HH>` (error).
+//!
+//! The `keying` function gives the on-off keying of a Morse code in units of the length of a dot,
+//! following the standard spacing: a dash is 3 units long, the elements of a character are
+//! separated by 1 unit, characters by 3 units and words by 7 units. The `unit_duration` function
+//! gives the length of a unit at a given speed.
+//!
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// Error indicating that a character cannot be encoded in Morse code.
+#[derive(Clone, Copy, Debug)]
+pub struct UnencodableCharacterError;
+
+impl Display for UnencodableCharacterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("character cannot be encoded in Morse code")
+    }
+}
+
+impl Error for UnencodableCharacterError { }
+
+/// Error indicating that a Morse code is invalid.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidMorseCodeError;
+
+impl Display for InvalidMorseCodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid Morse code")
+    }
+}
+
+impl Error for InvalidMorseCodeError { }
+
+/// The codes of the letters, indexed by their values.
+const LETTER_CODES: [&str; 26] = [
+    ".-", "-...", "-.-.", "-..", ".", "..-.", "--.", "....", "..", ".---", "-.-", ".-..", "--",
+    "-.", "---", ".--.", "--.-", ".-.", "...", "-", "..-", "...-", ".--", "-..-", "-.--", "--..",
+];
+
+/// The codes of the digits, indexed by their values.
+const DIGIT_CODES: [&str; 10] = [
+    "-----", ".----", "..---", "...--", "....-", ".....", "-....", "--...", "---..", "----.",
+];
+
+/// The supported prosigns and their codes.
+const PROSIGNS: [(&str, &str); 6] = [
+    ("<KA>", "-.-.-"),
+    ("<BT>", "-...-"),
+    ("<AR>", ".-.-."),
+    ("<AS>", ".-..."),
+    ("<SK>", "...-.-"),
+    ("<HH>", "........"),
+];
+
+/// The separator between the words of a Morse code.
+const WORD_SEPARATOR: &str = " / ";
+
+/// The length of the standard word `PARIS` in units, including the gap to the next word.
+const PARIS_UNITS_PER_MINUTE: u64 = 50;
+
+/// Encode a single word into Morse code.
+fn encode_word(word: &str) -> Result<String, UnencodableCharacterError> {
+    let mut codes = Vec::new();
+    let mut rest = word;
+    while let Some(ch) = rest.chars().next() {
+        if ch == '<' {
+            let (prosign, code) = PROSIGNS.iter()
+                .find(|(prosign, _)| rest.get(..prosign.len())
+                    .is_some_and(|s| s.eq_ignore_ascii_case(prosign)))
+                .ok_or(UnencodableCharacterError)?;
+            codes.push(*code);
+            rest = &rest[prosign.len()..];
+            continue;
+        }
+
+        let code = if ch.is_ascii_alphabetic() {
+            LETTER_CODES[(ch.to_ascii_uppercase() as u8 - b'A') as usize]
+        } else if ch.is_ascii_digit() {
+            DIGIT_CODES[(ch as u8 - b'0') as usize]
+        } else {
+            return Err(UnencodableCharacterError);
+        };
+        codes.push(code);
+        rest = &rest[ch.len_utf8()..];
+    }
+
+    Ok(codes.join(" "))
+}
+
+/// Encode the specified text into Morse code. Whitespace characters separate words. The text may
+/// only contain letters, digits and prosigns.
+pub fn encode(text: &str) -> Result<String, UnencodableCharacterError> {
+    let words = text.split_whitespace()
+        .map(encode_word)
+        .collect::<Result<Vec<String>, UnencodableCharacterError>>()?;
+    Ok(words.join(WORD_SEPARATOR))
+}
+
+/// Decode a single character from Morse code.
+fn decode_character(code: &str) -> Result<&'static str, InvalidMorseCodeError> {
+    const LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    const DIGITS: &str = "0123456789";
+
+    if let Some(i) = LETTER_CODES.iter().position(|c| *c == code) {
+        return Ok(&LETTERS[i..=i]);
+    }
+    if let Some(i) = DIGIT_CODES.iter().position(|c| *c == code) {
+        return Ok(&DIGITS[i..=i]);
+    }
+    PROSIGNS.iter()
+        .find(|(_, c)| *c == code)
+        .map(|(prosign, _)| *prosign)
+        .ok_or(InvalidMorseCodeError)
+}
+
+/// Decode the specified Morse code into upper case letters, digits and prosigns. Words are
+/// separated by `/` and the characters within a word by whitespace characters. Decoded words are
+/// separated by a space.
+pub fn decode(code: &str) -> Result<String, InvalidMorseCodeError> {
+    let mut words = Vec::new();
+    for word in code.split('/') {
+        let mut decoded = String::new();
+        for character in word.split_whitespace() {
+            decoded.push_str(decode_character(character)?);
+        }
+        if !decoded.is_empty() {
+            words.push(decoded);
+        }
+    }
+
+    Ok(words.join(" "))
+}
+
+/// Get the on-off keying of the specified Morse code, one element per unit of time. `true` means
+/// the key is down.
+pub fn keying(code: &str) -> Result<Vec<bool>, InvalidMorseCodeError> {
+    let mut units = Vec::new();
+    for (i, word) in code.split('/').map(str::trim).filter(|w| !w.is_empty()).enumerate() {
+        if i > 0 {
+            units.extend_from_slice(&[false; 7]);
+        }
+
+        for (j, character) in word.split_whitespace().enumerate() {
+            if j > 0 {
+                units.extend_from_slice(&[false; 3]);
+            }
+
+            for (k, element) in character.chars().enumerate() {
+                if k > 0 {
+                    units.push(false);
+                }
+                match element {
+                    '.' => units.push(true),
+                    '-' => units.extend_from_slice(&[true; 3]),
+                    _ => return Err(InvalidMorseCodeError),
+                }
+            }
+        }
+    }
+
+    Ok(units)
+}
+
+/// Get the length of a unit of time when sending at the specified speed in words per minute, as
+/// measured with the standard word `PARIS`.
+pub fn unit_duration(wpm: u32) -> Duration {
+    Duration::from_secs(60) / (PARIS_UNITS_PER_MINUTE * u64::from(wpm.max(1))) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("Enigma").unwrap(), ". -. .. --. -- .-");
+        assert_eq!(encode("  AB\nC ").unwrap(), ".- -... / -.-.");
+        assert_eq!(encode("<ka> QRV <AR>").unwrap(), "-.-.- / --.- .-. ...- / .-.-.");
+        assert!(encode("A.").is_err());
+        assert!(encode("<XY>").is_err());
+        assert_eq!(encode("").unwrap(), "");
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(". -. .. --. -- .-").unwrap(), "ENIGMA");
+        assert_eq!(decode("-.-.- / .- -... /\n-.-. / .-.-.").unwrap(), "<KA> AB C <AR>");
+        assert!(decode(".-.-.-.-").is_err());
+        assert!(decode(".x").is_err());
+    }
+
+    #[test]
+    fn test_keying() {
+        let units: String = keying(".- / -").unwrap().into_iter()
+            .map(|on| if on { '=' } else { '_' })
+            .collect();
+        assert_eq!(units, "=_===_______===");
+
+        let paris = keying(&encode("PARIS").unwrap()).unwrap();
+        assert_eq!(paris.len() + 7, PARIS_UNITS_PER_MINUTE as usize);
+    }
+
+    #[test]
+    fn test_unit_duration() {
+        assert_eq!(unit_duration(20), Duration::from_millis(60));
+    }
+}
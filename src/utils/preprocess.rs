@@ -0,0 +1,206 @@
+//! This module prepares plain texts for the Enigma machine following the conventions of German
+//! operators, and cleans up decrypted texts.
+//!
+//! The Enigma machine only has keys for the 26 Latin letters, so operators spelled out everything
+//! else before a message was enciphered. The [`prepare`] function applies these conventions:
+//!
+//! | Plain text                  | Prepared text                         |
+//! |-----------------------------|---------------------------------------|
+//! | Letters                     | Upper case letters                    |
+//! | `Ä`, `Ö`, `Ü`, `ß`          | `AE`, `OE`, `UE`, `SS`                |
+//! | `CH`                        | `Q`                                   |
+//! | Whitespace                  | `X`                                   |
+//! | `.`, `!`, `:`               | `X`                                   |
+//! | `,`, `;`                    | `Y`                                   |
+//! | `?`                         | `FRAQ`                                |
+//! | Quotation marks             | `J`                                   |
+//! | `(`, `)`                    | `KK`                                  |
+//! | Digits                      | Spelled out between two `Y`s          |
+//!
+//! Digits are spelled out one by one as `NULL`, `EINS`, `ZWO`, `DREI`, `VIER`, `FUENF`, `SEQS`,
+//! `SIEBEN`, `AQT` and `NEUN`. All other characters are dropped:
+//!
+//! ```
+//! # use enigma::utils::preprocess::prepare;
+//! #
+//! assert_eq!(prepare("Nachricht 1940, Ende."), "NAQRIQTXYEINSNEUNVIERNULLYYXENDEX");
+//! ```
+//!
+//! The [`restore`] function reverses the conventions on a decrypted text as far as possible:
+//!
+//! ```
+//! # use enigma::utils::preprocess::restore;
+//! #
+//! assert_eq!(restore("NAQRIQTXYEINSNEUNVIERNULLYYXENDEX"), "NACHRICHT 1940, ENDE.");
+//! ```
+//!
+//! The conventions are ambiguous, so restoring is lossy: `XX` is read as a full stop followed by a
+//! space, a single `X` as a space, or as a full stop at the end of the text, and `Q` as `CH` unless
+//! it is followed by `U`. Umlauts and quotation marks are not restored, since `AE` and `J` also
+//! occur in plain words.
+//!
+//! [`prepare`]: fn.prepare.html
+//! [`restore`]: fn.restore.html
+//!
+
+/// The spelled out digits, indexed by their values.
+const DIGIT_WORDS: [&str; 10] = [
+    "NULL", "EINS", "ZWO", "DREI", "VIER", "FUENF", "SEQS", "SIEBEN", "AQT", "NEUN",
+];
+
+/// Prepare the specified plain text for encryption following the conventions of German operators.
+/// The prepared text only consists of upper case letters.
+pub fn prepare(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.trim().chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            'c' | 'C' if chars.peek().is_some_and(|next| next.eq_ignore_ascii_case(&'h')) => {
+                chars.next();
+                output.push('Q');
+            }
+            'a'..='z' | 'A'..='Z' => output.push(ch.to_ascii_uppercase()),
+            'ä' | 'Ä' => output.push_str("AE"),
+            'ö' | 'Ö' => output.push_str("OE"),
+            'ü' | 'Ü' => output.push_str("UE"),
+            'ß' => output.push_str("SS"),
+            '0'..='9' => {
+                output.push('Y');
+                output.push_str(DIGIT_WORDS[ch.to_digit(10).unwrap() as usize]);
+                while let Some(digit) = chars.peek().and_then(|next| next.to_digit(10)) {
+                    chars.next();
+                    output.push_str(DIGIT_WORDS[digit as usize]);
+                }
+                output.push('Y');
+            }
+            '.' | '!' | ':' => output.push('X'),
+            ',' | ';' => output.push('Y'),
+            '?' => output.push_str("FRAQ"),
+            '"' | '„' | '“' | '”' => output.push('J'),
+            '(' | ')' => output.push_str("KK"),
+            _ if ch.is_whitespace() => {
+                while chars.peek().is_some_and(|next| next.is_whitespace()) {
+                    chars.next();
+                }
+                output.push('X');
+            }
+            _ => { }
+        }
+    }
+
+    output
+}
+
+/// Parse the spelled out digits at the beginning of the specified text, up to the closing `Y`.
+/// Returns the digits and the length of the spelled out digits, without the closing `Y`.
+fn parse_number(text: &[u8]) -> Option<(String, usize)> {
+    let mut digits = String::new();
+    let mut len = 0;
+    while text.get(len) != Some(&b'Y') {
+        let (digit, word) = DIGIT_WORDS.iter().enumerate()
+            .find(|(_, word)| text[len..].starts_with(word.as_bytes()))?;
+        digits.push((b'0' + digit as u8) as char);
+        len += word.len();
+    }
+
+    if digits.is_empty() { None } else { Some((digits, len)) }
+}
+
+/// Reverse the conventions applied by `prepare` on the specified decrypted text as far as
+/// possible. Whitespace characters within the text, e.g. between groups, are discarded.
+pub fn restore(text: &str) -> String {
+    let text: Vec<u8> = text.bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .map(|b| b.to_ascii_uppercase())
+        .collect();
+
+    let mut output = String::with_capacity(text.len());
+    let mut in_parentheses = false;
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let consumed = match rest {
+            [b'X', b'X', ..] => {
+                output.push_str(". ");
+                2
+            }
+            [b'X'] => {
+                output.push('.');
+                1
+            }
+            [b'X', ..] => {
+                output.push(' ');
+                1
+            }
+            [b'Y', number @ ..] => match parse_number(number) {
+                Some((digits, len)) => {
+                    output.push_str(&digits);
+                    len + 2
+                }
+                None => {
+                    output.push(',');
+                    1
+                }
+            },
+            [b'F', b'R', b'A', b'Q'] | [b'F', b'R', b'A', b'Q', b'X', ..] |
+            [b'F', b'R', b'A', b'Q', b'Y', ..] => {
+                output.push('?');
+                4
+            }
+            [b'K', b'K', ..] => {
+                output.push(if in_parentheses { ')' } else { '(' });
+                in_parentheses = !in_parentheses;
+                2
+            }
+            [b'Q', b'U', ..] => {
+                output.push_str("QU");
+                2
+            }
+            [b'Q', ..] => {
+                output.push_str("CH");
+                1
+            }
+            [b, ..] => {
+                output.push(*b as char);
+                1
+            }
+            [] => unreachable!(),
+        };
+        i += consumed;
+    }
+
+    output.truncate(output.trim_end().len());
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare() {
+        assert_eq!(prepare("Hello,  world!"), "HELLOYXWORLDX");
+        assert_eq!(prepare("Wer? Ich."), "WERFRAQXIQX");
+        assert_eq!(prepare("Größe: \"Bär\""), "GROESSEXXJBAERJ");
+        assert_eq!(prepare("(Kurs 270)"), "KKKURSXYZWOSIEBENNULLYKK");
+        assert_eq!(prepare("a-b/c"), "ABC");
+        assert_eq!(prepare(""), "");
+    }
+
+    #[test]
+    fn test_restore() {
+        assert_eq!(restore("HELLOYXWORLDX"), "HELLO, WORLD.");
+        assert_eq!(restore("WERFRAQXIQX"), "WER? ICH.");
+        assert_eq!(restore("FRAQT QUELLE"), "FRACHTQUELLE");
+        assert_eq!(restore("KKKURSXYZWOSIEBENNULLYKK"), "(KURS 270)");
+        assert_eq!(restore("AYYEINSY"), "A,1");
+        assert_eq!(restore("YZWOX"), ",ZWO.");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text = "Funkspruch 12: Angriff um 0600. Ziel (Brücke) halten, Bestätigung?";
+        assert_eq!(restore(&prepare(text)),
+            "FUNKSPRUCH 12. ANGRIFF UM 0600. ZIEL (BRUECKE) HALTEN, BESTAETIGUNG?");
+    }
+}
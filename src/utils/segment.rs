@@ -0,0 +1,218 @@
+//! This module reinserts word boundaries into texts whose spaces have been stripped, e.g. the
+//! decryptions of messages transmitted in groups of 5 letters.
+//!
+//! The [`segment`] function splits a text into the most probable sequence of words according to
+//! the word frequencies of a [`Dictionary`]. Letters that do not form any word of the dictionary
+//! are kept together as unknown words:
+//!
+//! ```
+//! # use enigma::utils::segment::{segment, Dictionary};
+//! #
+//! let dictionary = Dictionary::from_words(vec!["attack", "at", "dawn"]);
+//! assert_eq!(segment("ATTAC KATDA WN", &dictionary), "ATTACK AT DAWN");
+//! assert_eq!(segment("ATTACKXQZATDAWN", &dictionary), "ATTACK XQZ AT DAWN");
+//! ```
+//!
+//! With the `wordlist` feature, small built-in dictionaries of common English and German words,
+//! including military terms, are available through the `Dictionary::english` and
+//! `Dictionary::german` associate functions. The German words are transliterated the way Enigma
+//! operators wrote them, e.g. `ü` becomes `UE`.
+//!
+//! [`segment`]: fn.segment.html
+//! [`Dictionary`]: struct.Dictionary.html
+//!
+
+use std::collections::HashMap;
+
+/// The built-in English words, ordered by descending frequency.
+#[cfg(feature = "wordlist")]
+const ENGLISH_WORDS: &str = include_str!("data/english-words.txt");
+
+/// The built-in German words, ordered by descending frequency.
+#[cfg(feature = "wordlist")]
+const GERMAN_WORDS: &str = include_str!("data/german-words.txt");
+
+/// The occurrence count of the most frequent word of a built-in word list.
+#[cfg(feature = "wordlist")]
+const BUILTIN_TOP_COUNT: u64 = 1_000_000;
+
+/// The maximal length of the unknown words produced by `segment`. Longer runs of unknown letters
+/// are split.
+const MAX_UNKNOWN_LEN: usize = 20;
+
+/// A dictionary of words and their occurrence counts.
+#[derive(Clone, Debug, Default)]
+pub struct Dictionary {
+    counts: HashMap<String, u64>,
+    total: u64,
+    max_len: usize,
+}
+
+impl Dictionary {
+    /// Create an empty dictionary.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a dictionary from the specified words. Each occurrence of a word within the
+    /// iterator counts once.
+    pub fn from_words<'a, I>(words: I) -> Self
+        where I: IntoIterator<Item = &'a str> {
+        let mut dictionary = Self::new();
+        for word in words {
+            dictionary.insert(word, 1);
+        }
+        dictionary
+    }
+
+    /// Create a dictionary of common English words.
+    #[cfg(feature = "wordlist")]
+    pub fn english() -> Self {
+        Self::from_ranked_words(ENGLISH_WORDS)
+    }
+
+    /// Create a dictionary of common German words.
+    #[cfg(feature = "wordlist")]
+    pub fn german() -> Self {
+        Self::from_ranked_words(GERMAN_WORDS)
+    }
+
+    /// Create a dictionary from the specified lines of words ordered by descending frequency. The
+    /// occurrence counts are estimated by Zipf's law.
+    #[cfg(feature = "wordlist")]
+    fn from_ranked_words(words: &str) -> Self {
+        let mut dictionary = Self::new();
+        for (rank, word) in words.lines().enumerate() {
+            dictionary.insert(word, BUILTIN_TOP_COUNT / (rank as u64 + 1));
+        }
+        dictionary
+    }
+
+    /// Add the specified number of occurrences of a word to this dictionary. Letters are
+    /// case-insensitive and all other characters within the word are ignored.
+    pub fn insert(&mut self, word: &str, count: u64) {
+        let word = normalize(word);
+        if word.is_empty() || count == 0 {
+            return;
+        }
+
+        self.max_len = self.max_len.max(word.len());
+        self.total += count;
+        *self.counts.entry(word).or_insert(0) += count;
+    }
+
+    /// Get the number of occurrences of the specified word.
+    pub fn count(&self, word: &str) -> u64 {
+        self.counts.get(&normalize(word)).copied().unwrap_or(0)
+    }
+
+    /// Determine whether the specified word is within this dictionary.
+    pub fn contains(&self, word: &str) -> bool {
+        self.count(word) > 0
+    }
+
+    /// Get the number of distinct words within this dictionary.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Determine whether this dictionary is empty.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Get the cost of the specified normalized word, e.g. its negative log-probability. Words that
+    /// are not within the dictionary get a cost that grows with their length, but is lower than
+    /// the cost of splitting them into shorter unknown words.
+    fn cost(&self, word: &str) -> f64 {
+        let total = (self.total.max(1) as f64).ln();
+        match self.counts.get(word) {
+            Some(count) => total - (*count as f64).ln(),
+            None => total + word.len() as f64 * 10f64.ln(),
+        }
+    }
+}
+
+/// Convert the specified word to upper case and remove all characters other than letters.
+fn normalize(word: &str) -> String {
+    word.chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|ch| ch.to_ascii_uppercase())
+        .collect()
+}
+
+/// Split the specified text into the most probable sequence of words according to the specified
+/// dictionary, and join the words with single spaces. Only the letters of the text are kept, in
+/// their original case.
+pub fn segment(text: &str, dictionary: &Dictionary) -> String {
+    let letters: Vec<char> = text.chars().filter(char::is_ascii_alphabetic).collect();
+    let normalized: String = letters.iter().map(|ch| ch.to_ascii_uppercase()).collect();
+    let max_len = dictionary.max_len.max(MAX_UNKNOWN_LEN);
+
+    // best[i] holds the minimal cost of segmenting the first i letters, and the start of the last
+    // word of that segmentation.
+    let mut best = vec![(0.0f64, 0usize); letters.len() + 1];
+    for end in 1..=letters.len() {
+        best[end] = (end.saturating_sub(max_len)..end)
+            .map(|start| (best[start].0 + dictionary.cost(&normalized[start..end]), start))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap();
+    }
+
+    let mut words = Vec::new();
+    let mut end = letters.len();
+    while end > 0 {
+        let start = best[end].1;
+        words.push(letters[start..end].iter().collect::<String>());
+        end = start;
+    }
+    words.reverse();
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod dictionary_tests {
+        use super::*;
+
+        #[test]
+        fn test_insert() {
+            let mut dictionary = Dictionary::new();
+            assert!(dictionary.is_empty());
+
+            dictionary.insert("Brücke", 2);
+            dictionary.insert("bruecke", 1);
+            dictionary.insert("", 1);
+            assert_eq!(dictionary.len(), 2);
+            assert_eq!(dictionary.count("BRCKE"), 2);
+            assert_eq!(dictionary.count("Bruecke"), 1);
+            assert!(!dictionary.contains("bruce"));
+        }
+
+        #[cfg(feature = "wordlist")]
+        #[test]
+        fn test_builtin() {
+            assert!(Dictionary::english().count("the") > Dictionary::english().count("dawn"));
+            assert!(Dictionary::german().contains("zerstoerer"));
+        }
+    }
+
+    #[test]
+    fn test_segment() {
+        let dictionary = Dictionary::from_words(vec!["the", "them", "enemy", "my", "attacks"]);
+        assert_eq!(segment("theenemyattacks", &dictionary), "the enemy attacks");
+        assert_eq!(segment("THEMXX", &dictionary), "THEM XX");
+        assert_eq!(segment("", &dictionary), "");
+        assert_eq!(segment("ABC", &Dictionary::new()), "ABC");
+    }
+
+    #[cfg(feature = "wordlist")]
+    #[test]
+    fn test_segment_builtin() {
+        let dictionary = Dictionary::german();
+        assert_eq!(segment("FEIND LICHE PANZE RGESI CHTET", &dictionary),
+            "FEINDLICHE PANZER GESICHTET");
+    }
+}
@@ -0,0 +1,240 @@
+//! This module implements the commercial Enigma machines sold by Chiffriermaschinen AG: the
+//! Enigma D, the Enigma K, and the Enigma K used by the Swiss army.
+//!
+//! The commercial machines differ from the Wehrmacht Enigma in the following ways:
+//! - There is no plug board, and the keyboard is connected to the first rotator through an entry
+//!   wheel wired in the order of the keyboard keys.
+//! - The reflector can be set to any position by hand, but it does not step.
+//!
+//! The rotators are stepped by levers like the Wehrmacht Enigma, so the middle rotator double
+//! steps. The published wirings of the rotators and the reflector of each [`Model`] are provided
+//! by its associate functions, and the `CommercialEnigma::historical` associate function creates a
+//! machine from them:
+//!
+//! ```
+//! # use enigma::variants::{CommercialEnigma, Model};
+//! #
+//! let mut machine = CommercialEnigma::historical(Model::SwissK, [0, 2, 1], [5, 11, 23], 17);
+//! let ciphertext = machine.map_str("Neutral");
+//!
+//! let mut machine = CommercialEnigma::historical(Model::SwissK, [0, 2, 1], [5, 11, 23], 17);
+//! assert_eq!(machine.map_str(&ciphertext), "NEUTRAL");
+//! ```
+//!
+//! [`Model`]: enum.Model.html
+//!
+
+use crate::components::{EntryWheel, Rotator, RotatorGroup};
+use crate::components::stepping::Lever;
+use crate::machines::{InvalidStateError, RotorMachine};
+use crate::utils::Rune;
+use crate::variants::{create_rotators, wiring};
+
+/// The wirings and the notches of the rotators I, II and III of the Enigma D and K.
+pub const ROTATORS_K: [(&str, &str); 3] = [
+    ("lpgszmhaeoqkvxrfybutnicjdw", "y"),
+    ("slvgbtfxjqohewirzyamkpcndu", "e"),
+    ("cjgdpshkturawzxfmynqobvlie", "n"),
+];
+
+/// The wirings and the notches of the rotators I, II and III of the Swiss Enigma K.
+pub const ROTATORS_SWISS_K: [(&str, &str); 3] = [
+    ("pezuohxscvfmtbglrinqjwaydk", "y"),
+    ("zouesydkfwpciqxhmvblgnjrat", "e"),
+    ("ehrvxgaobqusimzflynwktpdjc", "n"),
+];
+
+/// The wiring of the reflector of the Enigma D and K, which is also used by the Swiss Enigma K.
+pub const REFLECTOR_K: &str = "imetcgfraysqbzxwlhkdvupojn";
+
+/// A commercial Enigma model.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Model {
+    /// The Enigma D.
+    D,
+
+    /// The Enigma K.
+    K,
+
+    /// The Enigma K used by the Swiss army.
+    SwissK,
+}
+
+impl Model {
+    /// Get the wirings and the notches of the rotators of this model.
+    pub fn rotators(&self) -> &'static [(&'static str, &'static str); 3] {
+        match self {
+            Model::D | Model::K => &ROTATORS_K,
+            Model::SwissK => &ROTATORS_SWISS_K,
+        }
+    }
+
+    /// Get the wiring of the reflector of this model.
+    pub fn reflector(&self) -> &'static str {
+        REFLECTOR_K
+    }
+}
+
+/// A commercial Enigma machine.
+#[derive(Clone, Debug)]
+pub struct CommercialEnigma {
+    entry_wheel: EntryWheel,
+    rotators: RotatorGroup,
+    reflector: Rotator,
+}
+
+impl CommercialEnigma {
+    /// Create a new commercial Enigma machine with the specified rotators and reflector. The first
+    /// rotator is the one nearest to the entry wheel. The rotators are stepped by levers. The
+    /// reflector is given as a rotator, whose offset is the position of the reflector and whose
+    /// wiring should be an involution without fixed points.
+    pub fn new(rotators: [Rotator; 3], reflector: Rotator) -> Self {
+        let entry_wheel = EntryWheel::qwertz();
+        let rotators = RotatorGroup::new(rotators).with_stepping(Lever);
+        Self { entry_wheel, rotators, reflector }
+    }
+
+    /// Create a new machine of the specified model with its published wirings. The rotator order
+    /// gives the indices of the rotators within `Model::rotators`, starting from the rotator
+    /// nearest to the entry wheel, and the positions give their offsets.
+    ///
+    /// This function panics if a rotator index is out of range.
+    pub fn historical(model: Model, rotator_order: [usize; 3], positions: [u8; 3],
+        reflector_position: u8) -> Self {
+        let reflector = Rotator::new(wiring(model.reflector()), reflector_position)
+            .unwrap();
+        Self::new(create_rotators(model.rotators(), rotator_order, positions), reflector)
+    }
+
+    /// Get the rotator group of this machine.
+    pub fn rotators(&self) -> &RotatorGroup {
+        &self.rotators
+    }
+
+    /// Get the entry wheel of this machine.
+    pub fn entry_wheel(&self) -> &EntryWheel {
+        &self.entry_wheel
+    }
+
+    /// Get the reflector of this machine.
+    pub fn reflector(&self) -> &Rotator {
+        &self.reflector
+    }
+
+    /// Map the specified input rune to output rune, but do not advance the rotators.
+    pub fn map_rune_static(&self, input: Rune) -> Rune {
+        let mut current = self.entry_wheel.map_forward(input);
+        current = self.rotators.map_forward(current);
+        current = self.reflector.map_forward(current);
+        current = self.rotators.map_backward(current);
+        self.entry_wheel.map_backward(current)
+    }
+
+    /// Map the specified input rune to output rune.
+    pub fn map_rune(&mut self, input: Rune) -> Rune {
+        let ret = self.map_rune_static(input);
+        self.advance_rotators();
+        ret
+    }
+
+    /// Map all runes within the specified string to output rune and returns all output runes as a
+    /// string.
+    pub fn map_str(&mut self, s: &str) -> String {
+        s.chars()
+            .filter_map(|ch| Rune::from_char(ch).ok())
+            .map(|rune| self.map_rune(rune).into_char())
+            .collect()
+    }
+
+    /// Advance the rotators by one step. The reflector never steps.
+    pub fn advance_rotators(&mut self) {
+        self.rotators.advance();
+    }
+}
+
+impl RotorMachine for CommercialEnigma {
+    fn map_rune_static(&self, input: Rune) -> Rune {
+        CommercialEnigma::map_rune_static(self, input)
+    }
+
+    fn advance_rotators(&mut self) {
+        CommercialEnigma::advance_rotators(self)
+    }
+
+    fn state(&self) -> Vec<u8> {
+        self.rotators.rotators().iter().chain(std::iter::once(&self.reflector))
+            .map(|r| r.offset())
+            .collect()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), InvalidStateError> {
+        match state {
+            &[r0, r1, r2, reflector] => {
+                self.rotators.set_offsets([r0, r1, r2]);
+                self.reflector.set_offset(reflector);
+                Ok(())
+            },
+            _ => Err(InvalidStateError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offsets(machine: &CommercialEnigma) -> Vec<u8> {
+        machine.rotators.rotators().iter().map(|r| r.offset())
+            .chain(std::iter::once(machine.reflector.offset()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rotor_machine() {
+        let mut machine = CommercialEnigma::historical(Model::K, [2, 1, 0], [3, 14, 25], 7);
+        assert_eq!(machine.state(), vec![3, 14, 25, 7]);
+        crate::machines::tests::check_restore(&mut machine);
+    }
+
+    #[test]
+    fn test_reciprocal() {
+        let plaintext = "DIESERTEXTWURDEMITEINERHANDELSMASCHINEVERSCHLUESSELT".repeat(10);
+        for model in &[Model::D, Model::K, Model::SwissK] {
+            let mut machine = CommercialEnigma::historical(*model, [2, 1, 0], [3, 14, 25], 7);
+            let ciphertext = machine.map_str(&plaintext);
+            assert!(plaintext.chars().zip(ciphertext.chars()).all(|(p, c)| p != c));
+
+            let mut machine = CommercialEnigma::historical(*model, [2, 1, 0], [3, 14, 25], 7);
+            assert_eq!(machine.map_str(&ciphertext), plaintext);
+        }
+    }
+
+    #[test]
+    fn test_models() {
+        let mut k = CommercialEnigma::historical(Model::K, [0, 1, 2], [0, 0, 0], 0);
+        let mut d = CommercialEnigma::historical(Model::D, [0, 1, 2], [0, 0, 0], 0);
+        let mut swiss = CommercialEnigma::historical(Model::SwissK, [0, 1, 2], [0, 0, 0], 0);
+        let k = k.map_str("AAAAAAAAAAAAAAAAAAAA");
+        assert_eq!(d.map_str("AAAAAAAAAAAAAAAAAAAA"), k);
+        assert_ne!(swiss.map_str("AAAAAAAAAAAAAAAAAAAA"), k);
+    }
+
+    #[test]
+    fn test_double_stepping() {
+        // Rotator III has a notch at N and rotator II at E.
+        let mut machine = CommercialEnigma::historical(Model::K, [2, 1, 0], [12, 3, 0], 9);
+        machine.advance_rotators();
+        assert_eq!(offsets(&machine), vec![13, 3, 0, 9]);
+        machine.advance_rotators();
+        assert_eq!(offsets(&machine), vec![14, 4, 0, 9]);
+        machine.advance_rotators();
+        assert_eq!(offsets(&machine), vec![15, 5, 1, 9]);
+    }
+
+    #[test]
+    fn test_reflector_position() {
+        let mut first = CommercialEnigma::historical(Model::K, [0, 1, 2], [0, 0, 0], 0);
+        let mut second = CommercialEnigma::historical(Model::K, [0, 1, 2], [0, 0, 0], 1);
+        assert_ne!(first.map_str("AAAAAAAAAAAAAAAAAAAA"), second.map_str("AAAAAAAAAAAAAAAAAAAA"));
+    }
+}
@@ -0,0 +1,197 @@
+//! This module implements the Enigma G used by the Abwehr, the German military intelligence.
+//!
+//! The Enigma G differs from the Wehrmacht Enigma in the following ways:
+//! - There is no plug board. The keyboard is connected to the first rotator through an entry wheel
+//!   wired in the order of the keyboard keys, `QWERTZUIOASDFGHJKPYXCVBNML`.
+//! - The rotators are driven by cog wheels instead of levers, so there is no double stepping, and
+//!   the rotators have many notches each.
+//! - The reflector can be set to any position, and it is stepped by the third rotator like a
+//!   fourth rotator.
+//!
+//! The wirings and the notches of the rotators and the reflector of the G-312 machine are provided
+//! as the `ROTATORS` and `REFLECTOR` constants, and the `EnigmaG::historical` associate function
+//! creates a machine from them:
+//!
+//! ```
+//! # use enigma::variants::EnigmaG;
+//! #
+//! let mut machine = EnigmaG::historical([2, 0, 1], [4, 17, 9], 12);
+//! let ciphertext = machine.map_str("Abwehr");
+//!
+//! let mut machine = EnigmaG::historical([2, 0, 1], [4, 17, 9], 12);
+//! assert_eq!(machine.map_str(&ciphertext), "ABWEHR");
+//! ```
+//!
+
+use crate::components::{EntryWheel, Rotator};
+use crate::components::stepping::{Gear, SteppingMechanism};
+use crate::machines::{InvalidStateError, RotorMachine};
+use crate::utils::Rune;
+use crate::variants::{create_rotators, wiring};
+
+/// The wirings and the notches of the rotators I, II and III of the G-312 machine.
+pub const ROTATORS: [(&str, &str); 3] = [
+    ("dmtwsilruyqnkfejcazbpgxohv", "suvwzabcefgiklopq"),
+    ("hqzgpjtmoblncifdyawveusrkx", "stvyzacdfghkmnq"),
+    ("uqntlszfmrehdpxkibvygjcwoa", "uwxaefhkmnr"),
+];
+
+/// The wiring of the reflector of the G-312 machine.
+pub const REFLECTOR: &str = "rulqmzjsygocetkwdahnbxpvif";
+
+/// An Enigma G machine.
+#[derive(Clone, Debug)]
+pub struct EnigmaG {
+    entry_wheel: EntryWheel,
+    wheels: [Rotator; 4],
+}
+
+impl EnigmaG {
+    /// Create a new Enigma G machine with the specified rotators and reflector. The first rotator
+    /// is the one nearest to the entry wheel. The reflector is given as a rotator, whose offset is
+    /// the position of the reflector and whose wiring should be an involution without fixed points.
+    pub fn new(rotators: [Rotator; 3], reflector: Rotator) -> Self {
+        let [r0, r1, r2] = rotators;
+        let entry_wheel = EntryWheel::qwertz();
+        Self { entry_wheel, wheels: [r0, r1, r2, reflector] }
+    }
+
+    /// Create a new Enigma G machine with the rotators and the reflector of the G-312 machine.
+    /// The rotator order gives the indices of the rotators within `ROTATORS`, starting from the
+    /// rotator nearest to the entry wheel, and the positions give their offsets.
+    ///
+    /// This function panics if a rotator index is out of range.
+    pub fn historical(rotator_order: [usize; 3], positions: [u8; 3], reflector_position: u8)
+        -> Self {
+        let reflector = Rotator::new(wiring(REFLECTOR), reflector_position).unwrap();
+        Self::new(create_rotators(&ROTATORS, rotator_order, positions), reflector)
+    }
+
+    /// Get the 3 rotators of this machine.
+    pub fn rotators(&self) -> &[Rotator] {
+        &self.wheels[..3]
+    }
+
+    /// Get the entry wheel of this machine.
+    pub fn entry_wheel(&self) -> &EntryWheel {
+        &self.entry_wheel
+    }
+
+    /// Get the reflector of this machine.
+    pub fn reflector(&self) -> &Rotator {
+        &self.wheels[3]
+    }
+
+    /// Map the specified input rune to output rune, but do not advance the rotators.
+    pub fn map_rune_static(&self, input: Rune) -> Rune {
+        let mut current = self.entry_wheel.map_forward(input);
+        for r in self.rotators() {
+            current = r.map_forward(current);
+        }
+        current = self.reflector().map_forward(current);
+        for r in self.rotators().iter().rev() {
+            current = r.map_backward(current);
+        }
+        self.entry_wheel.map_backward(current)
+    }
+
+    /// Map the specified input rune to output rune.
+    pub fn map_rune(&mut self, input: Rune) -> Rune {
+        let ret = self.map_rune_static(input);
+        self.advance_rotators();
+        ret
+    }
+
+    /// Map all runes within the specified string to output rune and returns all output runes as a
+    /// string.
+    pub fn map_str(&mut self, s: &str) -> String {
+        s.chars()
+            .filter_map(|ch| Rune::from_char(ch).ok())
+            .map(|rune| self.map_rune(rune).into_char())
+            .collect()
+    }
+
+    /// Advance the rotators and the reflector by one step.
+    pub fn advance_rotators(&mut self) {
+        Gear.advance(&mut self.wheels);
+    }
+}
+
+impl RotorMachine for EnigmaG {
+    fn map_rune_static(&self, input: Rune) -> Rune {
+        EnigmaG::map_rune_static(self, input)
+    }
+
+    fn advance_rotators(&mut self) {
+        EnigmaG::advance_rotators(self)
+    }
+
+    fn state(&self) -> Vec<u8> {
+        self.wheels.iter().map(|r| r.offset()).collect()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), InvalidStateError> {
+        if state.len() != self.wheels.len() {
+            return Err(InvalidStateError);
+        }
+
+        for (r, offset) in self.wheels.iter_mut().zip(state) {
+            r.set_offset(*offset);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offsets(machine: &EnigmaG) -> Vec<u8> {
+        machine.wheels.iter().map(|r| r.offset()).collect()
+    }
+
+    #[test]
+    fn test_rotor_machine() {
+        let mut machine = EnigmaG::historical([1, 2, 0], [3, 14, 25], 7);
+        assert_eq!(machine.state(), vec![3, 14, 25, 7]);
+        crate::machines::tests::check_restore(&mut machine);
+    }
+
+    #[test]
+    fn test_entry_wheel() {
+        let machine = EnigmaG::historical([0, 1, 2], [0, 0, 0], 0);
+        assert_eq!(machine.entry_wheel(), &EntryWheel::qwertz());
+    }
+
+    #[test]
+    fn test_reciprocal() {
+        let plaintext = "THEREISNOPLUGBOARDONTHEABWEHRMACHINE".repeat(20);
+        let mut machine = EnigmaG::historical([1, 2, 0], [3, 14, 25], 7);
+        let ciphertext = machine.map_str(&plaintext);
+        assert!(plaintext.chars().zip(ciphertext.chars()).all(|(p, c)| p != c));
+
+        let mut machine = EnigmaG::historical([1, 2, 0], [3, 14, 25], 7);
+        assert_eq!(machine.map_str(&ciphertext), plaintext);
+    }
+
+    #[test]
+    fn test_stepping() {
+        // Rotator III has a notch at U, rotator II at S and rotator I at Q.
+        let mut machine = EnigmaG::historical([2, 1, 0], [19, 17, 15], 3);
+        machine.advance_rotators();
+        assert_eq!(offsets(&machine), vec![20, 17, 15, 3]);
+        machine.advance_rotators();
+        assert_eq!(offsets(&machine), vec![21, 18, 15, 3]);
+
+        let mut machine = EnigmaG::historical([2, 1, 0], [20, 18, 16], 3);
+        machine.advance_rotators();
+        assert_eq!(offsets(&machine), vec![21, 19, 17, 4]);
+    }
+
+    #[test]
+    fn test_reflector_position() {
+        let mut first = EnigmaG::historical([0, 1, 2], [0, 0, 0], 0);
+        let mut second = EnigmaG::historical([0, 1, 2], [0, 0, 0], 1);
+        assert_ne!(first.map_str("AAAAAAAAAAAAAAAAAAAA"), second.map_str("AAAAAAAAAAAAAAAAAAAA"));
+    }
+}
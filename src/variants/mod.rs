@@ -0,0 +1,44 @@
+//! This module implements variants of the Enigma machine whose construction differs from the
+//! Wehrmacht Enigma modeled by the [`Enigma`] type.
+//!
+//! The following variants are available:
+//! - The Abwehr Enigma G, in the [`g`] submodule;
+//! - The commercial Enigma D and K and the Swiss Enigma K, in the [`commercial`] submodule.
+//!
+//! None of these variants has a plug board. The keyboard is connected to the first rotator through
+//! an entry wheel wired in the order of the keyboard keys, as given by `EntryWheel::qwertz`.
+//!
+//! [`Enigma`]: ../struct.Enigma.html
+//! [`g`]: g/index.html
+//! [`commercial`]: commercial/index.html
+//!
+
+pub mod commercial;
+pub mod g;
+
+pub use commercial::{CommercialEnigma, Model};
+pub use g::EnigmaG;
+
+use crate::components::Rotator;
+use crate::math::Permutation;
+use crate::utils::{Rune, RUNE_SET_SIZE};
+
+/// Create the permutation that maps `i` to the i-th of the specified letters.
+fn wiring(letters: &str) -> Permutation {
+    let perm: Vec<u8> = letters.chars().map(|ch| Rune::from_char(ch).unwrap().value()).collect();
+    let perm = Permutation::from_perm(perm).unwrap();
+    debug_assert_eq!(perm.n(), RUNE_SET_SIZE);
+    perm
+}
+
+/// Create the rotators described by the specified wirings and notches, in the specified order and
+/// at the specified positions.
+fn create_rotators(wirings: &[(&str, &str)], rotator_order: [usize; 3], positions: [u8; 3])
+    -> [Rotator; 3] {
+    let create_rotator = |i: usize| {
+        let (perm, notches) = wirings[rotator_order[i]];
+        let notches: Vec<Rune> = notches.chars().map(|ch| Rune::from_char(ch).unwrap()).collect();
+        Rotator::new(wiring(perm), positions[i]).unwrap().with_notches(&notches)
+    };
+    [create_rotator(0), create_rotator(1), create_rotator(2)]
+}